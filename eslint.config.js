@@ -22,6 +22,7 @@ export default [
       'codex-acp',
       'public',
       'docs',
+      'src/types/codexEvents.generated.ts',
     ],
   },
   js.configs.recommended,
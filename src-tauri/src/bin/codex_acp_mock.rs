@@ -0,0 +1,23 @@
+//! Scriptable stand-in for codex-acp used by offline end-to-end tests.
+//!
+//! Usage: `codex-acp-mock <scenario.json>` (or set `CODEX_ACP_MOCK_SCENARIO`).
+//! Select it from the desktop backend with `CODEX_DESKTOP_ACP_MODE=mock`.
+
+use anyhow::{Context, Result};
+use codex_desktop_lib::codex_dev::mock_agent::{serve_stdio, Scenario};
+use std::path::PathBuf;
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<()> {
+    let path = std::env::args_os()
+        .nth(1)
+        .or_else(|| std::env::var_os("CODEX_ACP_MOCK_SCENARIO"))
+        .map(PathBuf::from)
+        .context("usage: codex-acp-mock <scenario.json>")?;
+    let scenario = Scenario::load(&path)?;
+    eprintln!("INFO codex_acp_mock: replaying {}", path.display());
+
+    tokio::task::LocalSet::new()
+        .run_until(serve_stdio(scenario))
+        .await
+}
@@ -0,0 +1,438 @@
+//! Headless front-end for scripted Codex runs.
+//!
+//! Drives the same `CodexService` the desktop app uses on a mock Tauri runtime, streams
+//! session updates to stdout as text or JSONL and exits with a code derived from the
+//! turn's `StopReason`.
+
+use anyhow::{anyhow, bail, Context, Result};
+use codex_desktop_lib::codex::{
+    events::{
+        EVENT_AGENT_LOG, EVENT_APPROVAL_AUTO_DECIDED, EVENT_APPROVAL_REQUEST,
+        EVENT_CONNECTION_STATE, EVENT_ERROR, EVENT_MESSAGE_CHUNK, EVENT_PLAN, EVENT_THOUGHT_CHUNK,
+        EVENT_TOOL_CALL, EVENT_TOOL_CALL_UPDATE, EVENT_USAGE,
+    },
+    service::CodexService,
+    types::{ApprovalDecision, PromptDelivery, PromptInput},
+};
+use serde_json::{json, Value};
+use std::{
+    io::{Read, Write},
+    path::PathBuf,
+    process::ExitCode,
+};
+use tauri::{test::mock_app, Listener};
+
+/// Bundle identifier of the desktop app; release builds keep Codex home under it.
+const APP_IDENTIFIER: &str = "com.zp.codex-desktop";
+
+const EXIT_ERROR: u8 = 1;
+const EXIT_USAGE: u8 = 2;
+const EXIT_MAX_TOKENS: u8 = 3;
+const EXIT_MAX_TURN_REQUESTS: u8 = 4;
+const EXIT_REFUSAL: u8 = 5;
+const EXIT_CANCELLED: u8 = 130;
+
+const USAGE: &str = "usage: codex-desktop-cli [OPTIONS] [PROMPT...]
+
+Reads the prompt from stdin when no PROMPT or --prompt-file is given.
+
+  --cwd <dir>                          session working directory (default: current dir)
+  --prompt-file <path>                 read the prompt from a file
+  --format <text|jsonl>                output format (default: text)
+  --auto-approve <none|read-only|all>  answer approval requests (default: none, rejects)
+  --model <id>                         select a model for the session
+  --profile <id>                       launch the agent with an environment profile
+  --verbose                            also print agent stderr and connection states
+
+exit codes: 0 end_turn, 1 error, 2 usage, 3 max_tokens, 4 max_turn_requests,
+            5 refusal, 130 cancelled";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Text,
+    Jsonl,
+}
+
+/// Which approval requests the CLI allows without a user at the keyboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AutoApprove {
+    /// Reject every request that no approval rule decided.
+    None,
+    /// Allow tools that only read (read, search, fetch, think); reject the rest.
+    ReadOnly,
+    /// Allow every request.
+    All,
+}
+
+impl AutoApprove {
+    fn parse(value: &str) -> Result<Self> {
+        match value {
+            "none" => Ok(Self::None),
+            "read-only" => Ok(Self::ReadOnly),
+            "all" => Ok(Self::All),
+            other => bail!("unknown --auto-approve value: {other}"),
+        }
+    }
+
+    /// Decide an `approval-request` payload from the tool kind of its tool call.
+    fn decide(self, tool_call: &Value) -> ApprovalDecision {
+        let kind = tool_call.get("kind").and_then(Value::as_str);
+        let allow = match self {
+            Self::None => false,
+            Self::ReadOnly => matches!(kind, Some("read" | "search" | "fetch" | "think")),
+            Self::All => true,
+        };
+        if allow {
+            ApprovalDecision::AllowOnce
+        } else {
+            ApprovalDecision::RejectOnce
+        }
+    }
+}
+
+struct Args {
+    cwd: PathBuf,
+    prompt: String,
+    format: OutputFormat,
+    auto_approve: AutoApprove,
+    model: Option<String>,
+    profile: Option<String>,
+    verbose: bool,
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(err) => {
+            eprintln!("error: {err:#}\n\n{USAGE}");
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+
+    let runtime = match tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(runtime) => runtime,
+        Err(err) => {
+            eprintln!("error: failed to start runtime: {err}");
+            return ExitCode::from(EXIT_ERROR);
+        }
+    };
+
+    match runtime.block_on(run(args)) {
+        Ok(code) => ExitCode::from(code),
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::from(EXIT_ERROR)
+        }
+    }
+}
+
+async fn run(args: Args) -> Result<u8> {
+    use_desktop_codex_home();
+
+    let app = mock_app();
+    codex_desktop_lib::manage_state(&app);
+
+    let handle = app.handle().clone();
+    let service = CodexService::new(handle.clone());
+    listen(&handle, &service, &args);
+
+    service.initialize().await?;
+    let session = service
+        .create_session(args.cwd.clone(), args.profile.clone())
+        .await
+        .with_context(|| format!("failed to create session in {}", args.cwd.display()))?;
+    if let Some(model) = args.model.clone() {
+        service
+            .set_config_option(session.session_id.clone(), "model".to_string(), model)
+            .await?;
+    }
+
+    let result = service
+        .send_prompt(
+            session.session_id.clone(),
+            PromptInput::Text(args.prompt),
+            PromptDelivery::Queue,
+        )
+        .await?;
+    let stop_reason = result.stop_reason.as_str().unwrap_or("unknown");
+
+    match args.format {
+        OutputFormat::Text => {
+            println!();
+            if let Some(usage) = result.usage.as_ref() {
+                eprintln!("[usage] {}", json!(usage));
+            }
+            eprintln!("[done] {stop_reason}");
+        }
+        OutputFormat::Jsonl => print_json(json!({
+            "event": "result",
+            "payload": {
+                "sessionId": session.session_id,
+                "result": result,
+            },
+        })),
+    }
+    Ok(exit_code(stop_reason))
+}
+
+/// Release builds keep Codex home under the desktop app's data dir; the mock runtime
+/// has a different identifier, so point the service at the desktop location explicitly.
+fn use_desktop_codex_home() {
+    if cfg!(debug_assertions)
+        || std::env::var_os("CODEX_DESKTOP_CODEX_HOME").is_some()
+        || std::env::var_os("CODEX_HOME").is_some()
+    {
+        return;
+    }
+    if let Some(dir) = dirs::data_dir() {
+        std::env::set_var(
+            "CODEX_DESKTOP_CODEX_HOME",
+            dir.join(APP_IDENTIFIER).join("codex"),
+        );
+    }
+}
+
+/// Subscribe to service events, print them and answer approval requests.
+fn listen(app: &tauri::AppHandle<tauri::test::MockRuntime>, service: &CodexService, args: &Args) {
+    let format = args.format;
+    let mut events = vec![
+        EVENT_MESSAGE_CHUNK,
+        EVENT_THOUGHT_CHUNK,
+        EVENT_TOOL_CALL,
+        EVENT_TOOL_CALL_UPDATE,
+        EVENT_PLAN,
+        EVENT_APPROVAL_AUTO_DECIDED,
+        EVENT_ERROR,
+    ];
+    if format == OutputFormat::Jsonl {
+        events.push(EVENT_USAGE);
+    }
+    if args.verbose || format == OutputFormat::Jsonl {
+        events.extend([EVENT_CONNECTION_STATE, EVENT_AGENT_LOG]);
+    }
+    for event in events {
+        app.listen_any(event, move |msg| {
+            let payload = serde_json::from_str(msg.payload()).unwrap_or(Value::Null);
+            match format {
+                OutputFormat::Text => print_text(event, &payload),
+                OutputFormat::Jsonl => print_json(json!({ "event": event, "payload": payload })),
+            }
+        });
+    }
+
+    let service = service.clone();
+    let auto_approve = args.auto_approve;
+    app.listen_any(EVENT_APPROVAL_REQUEST, move |msg| {
+        let payload: Value = serde_json::from_str(msg.payload()).unwrap_or(Value::Null);
+        let tool_call = payload.get("toolCall").cloned().unwrap_or(Value::Null);
+        let decision = auto_approve.decide(&tool_call);
+        match format {
+            OutputFormat::Text => eprintln!(
+                "[approval] {} -> {}",
+                tool_call
+                    .get("title")
+                    .and_then(Value::as_str)
+                    .unwrap_or("tool call"),
+                json!(decision).as_str().unwrap_or_default()
+            ),
+            OutputFormat::Jsonl => print_json(json!({
+                "event": EVENT_APPROVAL_REQUEST,
+                "payload": payload,
+                "decision": decision,
+            })),
+        }
+        let session_id = payload.get("sessionId").and_then(Value::as_str);
+        let request_id = payload.get("requestId").and_then(Value::as_str);
+        if let (Some(session_id), Some(request_id)) = (session_id, request_id) {
+            if let Err(err) = service.respond_permission(
+                session_id.to_string(),
+                request_id.to_string(),
+                Some(decision),
+                None,
+            ) {
+                eprintln!("[approval] failed to respond: {err:#}");
+            }
+        }
+    });
+}
+
+fn print_text(event: &str, payload: &Value) {
+    let field = |name: &str| {
+        payload
+            .get(name)
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+    };
+    match event {
+        EVENT_MESSAGE_CHUNK => {
+            let mut stdout = std::io::stdout().lock();
+            let _ = stdout.write_all(field("text").as_bytes());
+            let _ = stdout.flush();
+        }
+        EVENT_THOUGHT_CHUNK => eprint!("{}", field("text")),
+        EVENT_TOOL_CALL => {
+            let tool_call = &payload["toolCall"];
+            eprintln!(
+                "\n[tool] {} ({})",
+                tool_call["title"].as_str().unwrap_or_default(),
+                tool_call["kind"].as_str().unwrap_or("other")
+            );
+        }
+        EVENT_TOOL_CALL_UPDATE => {
+            let update = &payload["update"];
+            if let Some(status @ ("completed" | "failed")) = update["status"].as_str() {
+                eprintln!(
+                    "[tool] {} {status}",
+                    update["toolCallId"].as_str().unwrap_or_default()
+                );
+            }
+        }
+        EVENT_PLAN => {
+            eprintln!("[plan]");
+            for entry in payload["plan"]["entries"].as_array().into_iter().flatten() {
+                eprintln!(
+                    "  [{}] {}",
+                    entry["status"].as_str().unwrap_or_default(),
+                    entry["content"].as_str().unwrap_or_default()
+                );
+            }
+        }
+        EVENT_APPROVAL_AUTO_DECIDED => eprintln!("[approval] decided by rule: {payload}"),
+        EVENT_ERROR => eprintln!("[error] {}", field("error")),
+        EVENT_CONNECTION_STATE => eprintln!("[connection] {}", field("state")),
+        EVENT_AGENT_LOG => eprintln!("[agent] {}", field("message")),
+        _ => {}
+    }
+}
+
+fn print_json(value: Value) {
+    let mut stdout = std::io::stdout().lock();
+    let _ = writeln!(stdout, "{value}");
+    let _ = stdout.flush();
+}
+
+/// Map an ACP stop reason to the process exit code.
+fn exit_code(stop_reason: &str) -> u8 {
+    match stop_reason {
+        "end_turn" => 0,
+        "max_tokens" => EXIT_MAX_TOKENS,
+        "max_turn_requests" => EXIT_MAX_TURN_REQUESTS,
+        "refusal" => EXIT_REFUSAL,
+        "cancelled" => EXIT_CANCELLED,
+        _ => EXIT_ERROR,
+    }
+}
+
+/// Parse command-line arguments; `None` means help was requested.
+fn parse_args() -> Result<Option<Args>> {
+    let mut cwd: Option<PathBuf> = None;
+    let mut prompt: Option<String> = None;
+    let mut prompt_file: Option<PathBuf> = None;
+    let mut format = OutputFormat::Text;
+    let mut auto_approve = AutoApprove::None;
+    let mut model: Option<String> = None;
+    let mut profile: Option<String> = None;
+    let mut verbose = false;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value)),
+            _ => (arg.clone(), None),
+        };
+        let mut value = |name: &str| -> Result<String> {
+            match inline {
+                Some(value) => Ok(value.to_string()),
+                None => args
+                    .next()
+                    .ok_or_else(|| anyhow!("{name} requires a value")),
+            }
+        };
+        match flag.as_str() {
+            "--cwd" => cwd = Some(PathBuf::from(value("--cwd")?)),
+            "--prompt-file" => prompt_file = Some(PathBuf::from(value("--prompt-file")?)),
+            "--format" => {
+                format = match value("--format")?.as_str() {
+                    "text" => OutputFormat::Text,
+                    "jsonl" => OutputFormat::Jsonl,
+                    other => bail!("unknown --format value: {other}"),
+                }
+            }
+            "--auto-approve" => auto_approve = AutoApprove::parse(&value("--auto-approve")?)?,
+            "--model" => model = Some(value("--model")?),
+            "--profile" => profile = Some(value("--profile")?),
+            "--verbose" | "-v" => verbose = true,
+            "--help" | "-h" => return Ok(None),
+            other if other.starts_with("--") => bail!("unknown option: {other}"),
+            other => {
+                prompt = Some(match prompt {
+                    Some(existing) => format!("{existing} {other}"),
+                    None => other.to_string(),
+                })
+            }
+        }
+    }
+
+    let prompt = match (prompt, prompt_file) {
+        (Some(_), Some(_)) => bail!("pass either a PROMPT or --prompt-file, not both"),
+        (Some(prompt), None) => prompt,
+        (None, Some(path)) => std::fs::read_to_string(&path)
+            .with_context(|| format!("failed to read prompt file: {}", path.display()))?,
+        (None, None) => {
+            let mut buf = String::new();
+            std::io::stdin()
+                .read_to_string(&mut buf)
+                .context("failed to read prompt from stdin")?;
+            buf
+        }
+    };
+    if prompt.trim().is_empty() {
+        bail!("prompt is empty");
+    }
+
+    let cwd = match cwd {
+        Some(cwd) => cwd,
+        None => std::env::current_dir().context("failed to get current_dir")?,
+    };
+    let cwd = cwd
+        .canonicalize()
+        .with_context(|| format!("working directory not found: {}", cwd.display()))?;
+
+    Ok(Some(Args {
+        cwd,
+        prompt,
+        format,
+        auto_approve,
+        model,
+        profile,
+        verbose,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auto_approve_read_only_and_exit_codes() {
+        let read = json!({ "kind": "read", "title": "Read a.rs" });
+        let edit = json!({ "kind": "edit", "title": "Edit a.rs" });
+        let is_allow = |decision| matches!(decision, ApprovalDecision::AllowOnce);
+        assert!(is_allow(AutoApprove::ReadOnly.decide(&read)));
+        assert!(!is_allow(AutoApprove::ReadOnly.decide(&edit)));
+        assert!(!is_allow(AutoApprove::ReadOnly.decide(&Value::Null)));
+        assert!(is_allow(AutoApprove::All.decide(&edit)));
+        assert!(!is_allow(AutoApprove::None.decide(&read)));
+
+        assert_eq!(exit_code("end_turn"), 0);
+        assert_eq!(exit_code("cancelled"), EXIT_CANCELLED);
+        assert_eq!(exit_code("something_new"), EXIT_ERROR);
+    }
+}
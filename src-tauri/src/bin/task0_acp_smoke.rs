@@ -249,7 +249,7 @@ async fn run_smoke(
     prompt: String,
     out_log: OutputLog,
 ) -> Result<()> {
-    let binary = CodexAcpBinary::resolve(None)?;
+    let binary = CodexAcpBinary::resolve(None::<&tauri::AppHandle>)?;
     let diagnostics = binary.diagnostics_line();
     out_log.write_json(json!({ "type": "codex_acp_spawn", "diagnostics": diagnostics }));
     eprintln!("{diagnostics}");
@@ -0,0 +1,324 @@
+//! Capture of codex-acp stderr into per-connection ring buffers.
+//!
+//! Each line is parsed for a `RUST_LOG`-style level and target, kept in a bounded
+//! buffer keyed by connection target (local or remote server) and emitted to the UI.
+
+use crate::codex::events::CodexEvent;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex, MutexGuard},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tauri::{AppHandle, Runtime};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    task::JoinHandle,
+};
+
+/// Lines kept per connection target.
+const LOG_CAPACITY: usize = 2000;
+
+/// Severity parsed from an agent log line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    fn parse(word: &str) -> Option<Self> {
+        match word {
+            "ERROR" => Some(Self::Error),
+            "WARN" => Some(Self::Warn),
+            "INFO" => Some(Self::Info),
+            "DEBUG" => Some(Self::Debug),
+            "TRACE" => Some(Self::Trace),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Error => "ERROR",
+            Self::Warn => "WARN",
+            Self::Info => "INFO",
+            Self::Debug => "DEBUG",
+            Self::Trace => "TRACE",
+        }
+    }
+}
+
+/// One stderr line from an agent process.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentLogLine {
+    /// Remote server the agent runs on, or `None` for the local agent.
+    pub server_id: Option<String>,
+    /// Connection that produced the line; changes when the agent is restarted.
+    pub connection_id: u64,
+    /// Capture time in milliseconds since the Unix epoch.
+    pub ts: u64,
+    /// Parsed level, if the line looked like a log record.
+    pub level: Option<LogLevel>,
+    /// Parsed module target, if present.
+    pub target: Option<String>,
+    /// Message text with the level/target prefix and ANSI colors removed.
+    pub message: String,
+}
+
+/// Ring buffer of stderr lines for one connection target.
+pub struct AgentLog {
+    server_id: Option<String>,
+    lines: Mutex<VecDeque<AgentLogLine>>,
+}
+
+impl AgentLog {
+    fn new(server_id: Option<String>) -> Self {
+        Self {
+            server_id,
+            lines: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    fn lock_lines(&self) -> MutexGuard<'_, VecDeque<AgentLogLine>> {
+        self.lines
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn push(&self, connection_id: u64, raw: &str) -> AgentLogLine {
+        let (level, target, message) = parse_log_line(raw);
+        let line = AgentLogLine {
+            server_id: self.server_id.clone(),
+            connection_id,
+            ts: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            level,
+            target,
+            message,
+        };
+        let mut lines = self.lock_lines();
+        if lines.len() == LOG_CAPACITY {
+            lines.pop_front();
+        }
+        lines.push_back(line.clone());
+        line
+    }
+
+    /// Remote server the agent runs on, or `None` for the local agent.
+    pub fn server_id(&self) -> Option<&str> {
+        self.server_id.as_deref()
+    }
+
+    /// Return the last `count` captured lines, oldest first.
+    pub fn tail(&self, count: usize) -> Vec<AgentLogLine> {
+        let lines = self.lock_lines();
+        lines
+            .iter()
+            .skip(lines.len().saturating_sub(count))
+            .cloned()
+            .collect()
+    }
+
+    /// Return the last `count` lines of one connection as plain text.
+    pub fn tail_text(&self, connection_id: u64, count: usize) -> String {
+        let lines = self.lock_lines();
+        let matching: Vec<&AgentLogLine> = lines
+            .iter()
+            .filter(|line| line.connection_id == connection_id)
+            .collect();
+        matching[matching.len().saturating_sub(count)..]
+            .iter()
+            .map(|line| match (line.level, &line.target) {
+                (Some(level), Some(target)) => {
+                    format!("{} {target}: {}", level.as_str(), line.message)
+                }
+                (Some(level), None) => format!("{} {}", level.as_str(), line.message),
+                _ => line.message.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Read `stderr` line by line into the buffer, emitting each line to the UI.
+    pub fn capture<R, S>(
+        self: &Arc<Self>,
+        app: AppHandle<R>,
+        connection_id: u64,
+        stderr: S,
+    ) -> JoinHandle<()>
+    where
+        R: Runtime,
+        S: AsyncRead + Unpin + 'static,
+    {
+        let log = self.clone();
+        tokio::task::spawn_local(async move {
+            let mut reader = BufReader::new(stderr);
+            let mut buf = Vec::new();
+            loop {
+                buf.clear();
+                match reader.read_until(b'\n', &mut buf).await {
+                    Ok(0) => break,
+                    Ok(_) => {
+                        let raw = String::from_utf8_lossy(&buf);
+                        let raw = raw.trim_end_matches(['\r', '\n']);
+                        if raw.trim().is_empty() {
+                            continue;
+                        }
+                        let line = log.push(connection_id, raw);
+                        CodexEvent::AgentLog(&line).emit(&app);
+                    }
+                    Err(err) => {
+                        tracing::debug!(error = %err, "agent stderr read failed");
+                        break;
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// Stderr buffers for all connection targets, shared with the service.
+#[derive(Default)]
+pub struct AgentLogs {
+    logs: Mutex<HashMap<Option<String>, Arc<AgentLog>>>,
+}
+
+impl AgentLogs {
+    /// Buffer for the local agent (`None`) or a remote server; kept across restarts.
+    pub fn for_target(&self, server_id: Option<&str>) -> Arc<AgentLog> {
+        let mut logs = self
+            .logs
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        logs.entry(server_id.map(str::to_string))
+            .or_insert_with(|| Arc::new(AgentLog::new(server_id.map(str::to_string))))
+            .clone()
+    }
+
+    /// Last `count` lines captured for a connection target.
+    pub fn tail(&self, server_id: Option<&str>, count: usize) -> Vec<AgentLogLine> {
+        self.for_target(server_id).tail(count)
+    }
+}
+
+/// Split a log line into level, target and message.
+///
+/// Understands `tracing` output (`2024-05-01T10:00:00Z  WARN codex_acp::x: msg`),
+/// `env_logger` output (`[2024-05-01T10:00:00Z WARN codex_acp::x] msg`) and bare
+/// `WARN target: msg` lines. Anything else is returned as an unleveled message.
+pub fn parse_log_line(raw: &str) -> (Option<LogLevel>, Option<String>, String) {
+    let line = strip_ansi(raw);
+    let text = line.trim();
+
+    if let Some((header, message)) = text.strip_prefix('[').and_then(|s| s.split_once(']')) {
+        let words: Vec<&str> = header.split_whitespace().collect();
+        if let Some(pos) = words.iter().position(|w| LogLevel::parse(w).is_some()) {
+            return (
+                LogLevel::parse(words[pos]),
+                words.get(pos + 1).map(|target| target.to_string()),
+                message.trim().to_string(),
+            );
+        }
+    }
+
+    // The level is the first or second word (after an optional timestamp).
+    let mut rest = text;
+    for _ in 0..2 {
+        let (word, after) = split_word(rest);
+        if let Some(level) = LogLevel::parse(word) {
+            let after = after.trim_start();
+            let (target, message) = match split_word(after) {
+                (target, message) if target.len() > 1 && target.ends_with(':') => (
+                    Some(target.trim_end_matches(':').to_string()),
+                    message.trim_start(),
+                ),
+                _ => (None, after),
+            };
+            return (Some(level), target, message.to_string());
+        }
+        rest = after;
+    }
+    (None, None, text.to_string())
+}
+
+fn split_word(text: &str) -> (&str, &str) {
+    let text = text.trim_start();
+    text.split_once(char::is_whitespace).unwrap_or((text, ""))
+}
+
+/// Remove ANSI CSI escape sequences (colors) from a line.
+fn strip_ansi(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\u{1b}' {
+            out.push(c);
+            continue;
+        }
+        if chars.next() == Some('[') {
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_log_line_formats() {
+        let (level, target, message) = parse_log_line(
+            "\u{1b}[2m2024-05-01T10:00:00.123Z\u{1b}[0m \u{1b}[33m WARN\u{1b}[0m codex_acp::agent: model not found",
+        );
+        assert_eq!(level, Some(LogLevel::Warn));
+        assert_eq!(target.as_deref(), Some("codex_acp::agent"));
+        assert_eq!(message, "model not found");
+
+        let (level, target, message) =
+            parse_log_line("[2024-05-01T10:00:00Z ERROR codex_core::auth] token expired");
+        assert_eq!(level, Some(LogLevel::Error));
+        assert_eq!(target.as_deref(), Some("codex_core::auth"));
+        assert_eq!(message, "token expired");
+
+        let (level, target, message) = parse_log_line("INFO starting up");
+        assert_eq!(level, Some(LogLevel::Info));
+        assert_eq!(target, None);
+        assert_eq!(message, "starting up");
+
+        let (level, _, message) = parse_log_line("npm ERR! code E404");
+        assert_eq!(level, None);
+        assert_eq!(message, "npm ERR! code E404");
+    }
+
+    #[test]
+    fn test_agent_log_ring_buffer() {
+        let log = AgentLog::new(None);
+        for i in 0..LOG_CAPACITY + 5 {
+            log.push(1, &format!("WARN x: line {i}"));
+        }
+        log.push(2, "ERROR y: boom");
+        let tail = log.tail(2);
+        assert_eq!(tail.len(), 2);
+        assert_eq!(tail[0].message, format!("line {}", LOG_CAPACITY + 4));
+        assert_eq!(log.lock_lines().len(), LOG_CAPACITY);
+        assert_eq!(log.tail_text(2, 10), "ERROR y: boom");
+        assert_eq!(
+            log.tail_text(1, 1),
+            format!("WARN x: line {}", LOG_CAPACITY + 4)
+        );
+    }
+}
@@ -1,21 +1,25 @@
 //! Resolve and spawn the codex-acp binary.
 
 use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
 use std::{
     ffi::OsString,
     path::{Path, PathBuf},
     process::Stdio,
 };
-use tauri::Manager;
+use tauri::{AppHandle, Manager, Runtime};
 use tokio::process::Command;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 /// Launch strategy for the codex-acp binary.
 pub enum CodexAcpLaunchMode {
     /// Use `npx` to resolve and run codex-acp.
     Npx,
     /// Use a bundled sidecar binary.
     Sidecar,
+    /// Use the scriptable `codex-acp-mock` agent replaying a scenario file.
+    Mock,
 }
 
 impl CodexAcpLaunchMode {
@@ -27,6 +31,22 @@ impl CodexAcpLaunchMode {
             Some(Self::Npx)
         } else if trimmed.eq_ignore_ascii_case("sidecar") {
             Some(Self::Sidecar)
+        } else if trimmed.eq_ignore_ascii_case("mock") {
+            Some(Self::Mock)
+        } else {
+            None
+        }
+    }
+
+    /// `from_env`, honoured only in debug builds, the CLI and mock agent builds, so a
+    /// release app cannot be pointed at another agent through its environment.
+    pub fn from_dev_env() -> Option<Self> {
+        if cfg!(any(
+            debug_assertions,
+            feature = "cli",
+            feature = "mock-agent"
+        )) {
+            Self::from_env()
         } else {
             None
         }
@@ -81,21 +101,32 @@ pub struct CodexAcpBinary {
 
 impl CodexAcpBinary {
     /// Resolve the codex-acp binary using environment overrides.
-    pub fn resolve(app: Option<&tauri::AppHandle>) -> Result<Self> {
+    pub fn resolve<R: Runtime>(app: Option<&AppHandle<R>>) -> Result<Self> {
         let mode =
             CodexAcpLaunchMode::from_env().unwrap_or_else(CodexAcpLaunchMode::default_for_build);
         Self::resolve_with_mode(mode, app)
     }
 
     /// Resolve the codex-acp binary using an explicit launch mode.
-    pub fn resolve_with_mode(
+    pub fn resolve_with_mode<R: Runtime>(
         mode: CodexAcpLaunchMode,
-        app: Option<&tauri::AppHandle>,
+        app: Option<&AppHandle<R>>,
     ) -> Result<Self> {
         match mode {
             CodexAcpLaunchMode::Npx => Ok(Self::npx()),
             CodexAcpLaunchMode::Sidecar => Self::sidecar(app),
+            CodexAcpLaunchMode::Mock => Self::mock(),
+        }
+    }
+
+    /// Run `spec` instead of the default npx package; other launch modes are unchanged.
+    pub fn with_npx_spec(mut self, spec: impl Into<OsString>) -> Self {
+        if self.mode == CodexAcpLaunchMode::Npx {
+            if let Some(last) = self.args.last_mut() {
+                *last = spec.into();
+            }
         }
+        self
     }
 
     /// Format a human-readable diagnostics line for logging.
@@ -145,7 +176,31 @@ impl CodexAcpBinary {
         }
     }
 
-    fn sidecar(app: Option<&tauri::AppHandle>) -> Result<Self> {
+    fn mock() -> Result<Self> {
+        // 1. Explicit path (integration tests pass `CARGO_BIN_EXE_codex-acp-mock`)
+        // 2. Next to the running executable, as built by cargo
+        let program = match std::env::var_os("CODEX_DESKTOP_ACP_MOCK_BIN") {
+            Some(explicit) => explicit,
+            None => {
+                let exe = std::env::current_exe().context("failed to resolve current_exe")?;
+                let dir = exe
+                    .parent()
+                    .context("current_exe has no parent directory")?;
+                dir.join(format!("codex-acp-mock{}", std::env::consts::EXE_SUFFIX))
+                    .into_os_string()
+            }
+        };
+        let scenario = std::env::var_os("CODEX_DESKTOP_ACP_MOCK_SCENARIO").context(
+            "mock mode requires CODEX_DESKTOP_ACP_MOCK_SCENARIO pointing at a scenario file",
+        )?;
+        Ok(Self {
+            mode: CodexAcpLaunchMode::Mock,
+            program,
+            args: vec![scenario],
+        })
+    }
+
+    fn sidecar<R: Runtime>(app: Option<&AppHandle<R>>) -> Result<Self> {
         // 1. Check explicit path override
         if let Some(explicit) = std::env::var_os("CODEX_DESKTOP_ACP_PATH") {
             return Ok(Self {
@@ -215,7 +270,7 @@ impl CodexAcpBinary {
     }
 
     /// Resolve the default Codex home directory based on environment and app data.
-    pub fn default_codex_home(app: Option<&tauri::AppHandle>) -> Result<PathBuf> {
+    pub fn default_codex_home<R: Runtime>(app: Option<&AppHandle<R>>) -> Result<PathBuf> {
         if let Some(explicit) =
             std::env::var_os("CODEX_DESKTOP_CODEX_HOME").or_else(|| std::env::var_os("CODEX_HOME"))
         {
@@ -0,0 +1,336 @@
+//! Coalescing of agent message and thought chunks before they reach the webview.
+//!
+//! Long answers arrive as many tiny `AgentMessageChunk` updates; emitting each one as
+//! a Tauri event floods the IPC bridge. When a `ChunkCoalescer` is managed by the app,
+//! consecutive chunks of the same kind are concatenated per session and emitted once
+//! the window elapses or the batch reaches the size limit. Any other update of the
+//! session, a permission request and turn completion flush the batch first, so the
+//! frontend sees the same ordering as without coalescing.
+
+use crate::codex::events::{CodexEvent, TextChunkPayload};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, MutexGuard,
+    },
+    time::Duration,
+};
+use tauri::{AppHandle, Manager, Runtime};
+
+/// Environment override for the coalescing window in milliseconds (`0` disables it).
+pub const CHUNK_WINDOW_ENV: &str = "CODEX_DESKTOP_CHUNK_WINDOW_MS";
+/// Environment override for the batch size limit in bytes.
+pub const CHUNK_MAX_BYTES_ENV: &str = "CODEX_DESKTOP_CHUNK_MAX_BYTES";
+
+const DEFAULT_WINDOW_MS: u64 = 16;
+const DEFAULT_MAX_BYTES: usize = 8 * 1024;
+
+/// Coalescing window and batch size limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoalesceConfig {
+    /// How long the first chunk of a batch may wait for more; `0` emits every chunk.
+    pub window_ms: u64,
+    /// Batches reaching this many bytes are emitted immediately.
+    pub max_bytes: usize,
+}
+
+impl Default for CoalesceConfig {
+    fn default() -> Self {
+        Self {
+            window_ms: DEFAULT_WINDOW_MS,
+            max_bytes: DEFAULT_MAX_BYTES,
+        }
+    }
+}
+
+impl CoalesceConfig {
+    /// Defaults with the `CODEX_DESKTOP_CHUNK_*` environment overrides applied.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let parse = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .and_then(|value| value.trim().parse::<u64>().ok())
+        };
+        Self {
+            window_ms: parse(CHUNK_WINDOW_ENV).unwrap_or(defaults.window_ms),
+            max_bytes: parse(CHUNK_MAX_BYTES_ENV)
+                .map(|value| value.max(1) as usize)
+                .unwrap_or(defaults.max_bytes),
+        }
+    }
+}
+
+/// Kind of text chunk being coalesced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkKind {
+    Message,
+    Thought,
+}
+
+struct PendingChunks {
+    kind: ChunkKind,
+    replay: bool,
+    text: String,
+    batch: u64,
+}
+
+/// Per-session batches of message and thought chunks awaiting emission.
+///
+/// Batches are emitted while the lock is held so a window timer firing concurrently
+/// with a new chunk cannot reorder them.
+#[derive(Default)]
+pub struct ChunkCoalescer {
+    config: Mutex<CoalesceConfig>,
+    pending: Mutex<HashMap<String, PendingChunks>>,
+    next_batch: AtomicU64,
+}
+
+impl ChunkCoalescer {
+    /// Create a coalescer with the given window and size limit.
+    pub fn new(config: CoalesceConfig) -> Self {
+        Self {
+            config: Mutex::new(config),
+            ..Self::default()
+        }
+    }
+
+    /// Current window and size limit.
+    pub fn config(&self) -> CoalesceConfig {
+        *self
+            .config
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Replace the window and size limit; applies to batches started afterwards.
+    pub fn set_config(&self, config: CoalesceConfig) {
+        *self
+            .config
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = config;
+    }
+
+    fn lock_pending(&self) -> MutexGuard<'_, HashMap<String, PendingChunks>> {
+        self.pending
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Queue a chunk for `session_id`, emitting it right away when coalescing is off.
+    pub fn push<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        session_id: &str,
+        kind: ChunkKind,
+        text: &str,
+        replay: bool,
+    ) {
+        let config = self.config();
+        if config.window_ms == 0 {
+            self.flush(app, session_id);
+            emit_chunk(app, session_id, kind, text, replay);
+            return;
+        }
+
+        let mut pending = self.lock_pending();
+        if pending
+            .get(session_id)
+            .is_some_and(|batch| batch.kind != kind || batch.replay != replay)
+        {
+            if let Some(batch) = pending.remove(session_id) {
+                emit_chunk(app, session_id, batch.kind, &batch.text, batch.replay);
+            }
+        }
+
+        let mut scheduled = None;
+        let batch = pending.entry(session_id.to_string()).or_insert_with(|| {
+            let batch = self.next_batch.fetch_add(1, Ordering::Relaxed);
+            scheduled = Some(batch);
+            PendingChunks {
+                kind,
+                replay,
+                text: String::new(),
+                batch,
+            }
+        });
+        batch.text.push_str(text);
+        if batch.text.len() >= config.max_bytes {
+            if let Some(batch) = pending.remove(session_id) {
+                emit_chunk(app, session_id, batch.kind, &batch.text, batch.replay);
+            }
+            return;
+        }
+        drop(pending);
+
+        if let Some(batch) = scheduled {
+            let app = app.clone();
+            let session_id = session_id.to_string();
+            let window = Duration::from_millis(config.window_ms);
+            tauri::async_runtime::spawn(async move {
+                tokio::time::sleep(window).await;
+                if let Some(coalescer) = app.try_state::<ChunkCoalescer>() {
+                    coalescer.flush_batch(&app, &session_id, batch);
+                }
+            });
+        }
+    }
+
+    /// Emit the pending batch of `session_id`, if any.
+    pub fn flush<R: Runtime>(&self, app: &AppHandle<R>, session_id: &str) {
+        let mut pending = self.lock_pending();
+        if let Some(batch) = pending.remove(session_id) {
+            emit_chunk(app, session_id, batch.kind, &batch.text, batch.replay);
+        }
+    }
+
+    /// Emit every pending batch.
+    pub fn flush_all<R: Runtime>(&self, app: &AppHandle<R>) {
+        let mut pending = self.lock_pending();
+        for (session_id, batch) in pending.drain() {
+            emit_chunk(app, &session_id, batch.kind, &batch.text, batch.replay);
+        }
+    }
+
+    /// Emit the batch of `session_id` if it is still the one the window timer was set for.
+    fn flush_batch<R: Runtime>(&self, app: &AppHandle<R>, session_id: &str, batch: u64) {
+        let mut pending = self.lock_pending();
+        if pending
+            .get(session_id)
+            .is_some_and(|pending| pending.batch == batch)
+        {
+            if let Some(batch) = pending.remove(session_id) {
+                emit_chunk(app, session_id, batch.kind, &batch.text, batch.replay);
+            }
+        }
+    }
+}
+
+fn emit_chunk<R: Runtime>(
+    app: &AppHandle<R>,
+    session_id: &str,
+    kind: ChunkKind,
+    text: &str,
+    replay: bool,
+) {
+    let payload = TextChunkPayload {
+        session_id,
+        text,
+        replay,
+    };
+    match kind {
+        ChunkKind::Message => CodexEvent::MessageChunk(payload),
+        ChunkKind::Thought => CodexEvent::ThoughtChunk(payload),
+    }
+    .emit(app);
+}
+
+/// Emit a chunk through the managed coalescer, or directly when there is none.
+pub fn emit_text_chunk<R: Runtime>(
+    app: &AppHandle<R>,
+    session_id: &str,
+    kind: ChunkKind,
+    text: &str,
+    replay: bool,
+) {
+    match app.try_state::<ChunkCoalescer>() {
+        Some(coalescer) => coalescer.push(app, session_id, kind, text, replay),
+        None => emit_chunk(app, session_id, kind, text, replay),
+    }
+}
+
+/// Emit the pending chunks of `session_id` before an event that must follow them.
+pub fn flush_chunks<R: Runtime>(app: &AppHandle<R>, session_id: &str) {
+    if let Some(coalescer) = app.try_state::<ChunkCoalescer>() {
+        coalescer.flush(app, session_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codex::events::{EVENT_MESSAGE_CHUNK, EVENT_THOUGHT_CHUNK};
+    use serde_json::Value;
+    use std::sync::Arc;
+    use tauri::{test::mock_app, Listener};
+
+    type Seen = Arc<Mutex<Vec<(&'static str, String)>>>;
+
+    fn record(app: &tauri::App<tauri::test::MockRuntime>) -> Seen {
+        let seen: Seen = Arc::default();
+        for name in [EVENT_MESSAGE_CHUNK, EVENT_THOUGHT_CHUNK] {
+            let seen = seen.clone();
+            app.listen_any(name, move |event| {
+                let payload: Value = serde_json::from_str(event.payload()).unwrap();
+                let text = payload["text"].as_str().unwrap_or_default().to_string();
+                seen.lock().unwrap().push((name, text));
+            });
+        }
+        seen
+    }
+
+    #[test]
+    fn test_coalesces_until_flush_or_size_limit() {
+        let app = mock_app();
+        let seen = record(&app);
+        let handle = app.handle();
+        app.manage(ChunkCoalescer::new(CoalesceConfig {
+            window_ms: 60_000,
+            max_bytes: 8,
+        }));
+
+        for text in ["Hel", "lo ", "wor"] {
+            emit_text_chunk(handle, "s1", ChunkKind::Message, text, false);
+        }
+        emit_text_chunk(handle, "s1", ChunkKind::Message, "ld", false);
+        emit_text_chunk(handle, "s1", ChunkKind::Thought, "hm", false);
+        emit_text_chunk(handle, "s1", ChunkKind::Message, "!", false);
+        assert_eq!(seen.lock().unwrap().len(), 3);
+
+        flush_chunks(handle, "s1");
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                (EVENT_MESSAGE_CHUNK, "Hello wor".to_string()),
+                (EVENT_MESSAGE_CHUNK, "ld".to_string()),
+                (EVENT_THOUGHT_CHUNK, "hm".to_string()),
+                (EVENT_MESSAGE_CHUNK, "!".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_window_elapses_and_zero_window_passes_through() {
+        let app = mock_app();
+        let seen = record(&app);
+        let handle = app.handle();
+        app.manage(ChunkCoalescer::new(CoalesceConfig {
+            window_ms: 10,
+            max_bytes: 1024,
+        }));
+
+        emit_text_chunk(handle, "s1", ChunkKind::Message, "a", false);
+        emit_text_chunk(handle, "s1", ChunkKind::Message, "b", false);
+        for _ in 0..200 {
+            if !seen.lock().unwrap().is_empty() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![(EVENT_MESSAGE_CHUNK, "ab".to_string())]
+        );
+
+        let coalescer = app.state::<ChunkCoalescer>();
+        coalescer.set_config(CoalesceConfig {
+            window_ms: 0,
+            max_bytes: 1024,
+        });
+        emit_text_chunk(handle, "s1", ChunkKind::Message, "c", false);
+        assert_eq!(seen.lock().unwrap().len(), 2);
+    }
+}
@@ -1,11 +1,31 @@
 //! Tauri command handlers for Codex interactions.
 
+use crate::codex::agent_log::AgentLogLine;
+use crate::codex::coalesce::{ChunkCoalescer, CoalesceConfig};
+use crate::codex::history::{
+    SessionSearchHit, SessionStore, SessionSummary, SessionTranscript, SessionWorktree,
+};
+use crate::codex::policy::{ApprovalAuditEntry, ApprovalPolicy, ApprovalRule};
+use crate::codex::profiles::{EnvProfile, ProfileStore};
+use crate::codex::protocol::PendingApprovalInfo;
+use crate::codex::recorder::{load_recording, replay_recording, ReplaySummary};
+use crate::codex::remote_session::parse_remote_path;
+use crate::codex::scheduler::QueuedPromptInfo;
 use crate::codex::service::CodexService;
+use crate::codex::timeline::{DebugTraceExport, TraceFormat};
 use crate::codex::types::{
-    ApprovalDecision, CodexCliConfigInfo, InitializeResult, NewSessionResult, PromptResult,
+    ApprovalDecision, ApprovalTimeout, CodexCliConfigInfo, InitializeResult, NewSessionResult,
+    PromptDelivery, PromptInput, PromptResult,
+};
+use crate::codex::usage::{UsageReport, UsageSettings, UsageStore};
+use crate::codex::worktrees::{
+    check_removable, merge_worktree, remove_session_worktree, worktree_info,
+    NewWorktreeSessionResult, WorktreeCleanup, WorktreeSessionInfo, WorktreeSessions,
 };
 use crate::codex_dev::config::load_codex_cli_config;
-use std::path::PathBuf;
+use crate::git::checkpoints::delete_checkpoints;
+use crate::git::types::GitCommit;
+use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
 use tauri::{AppHandle, State};
 
@@ -89,31 +109,103 @@ pub async fn codex_set_env(
     svc.set_env(key, value).await.map_err(|e| e.to_string())
 }
 
-/// Create a new ACP session rooted at the provided working directory.
+/// Create a new ACP session rooted at the provided working directory, optionally
+/// launching the agent with an environment profile.
 #[tauri::command]
 pub async fn codex_new_session(
     state: State<'_, CodexManager>,
     cwd: String,
+    profile: Option<String>,
 ) -> Result<NewSessionResult, String> {
     let svc = state
         .get()
         .ok_or_else(|| "codex service not initialized; call codex_init first".to_string())?;
-    svc.create_session(PathBuf::from(cwd))
+    svc.create_session(PathBuf::from(cwd), profile)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Create a git worktree on a new branch from `base` (HEAD by default) in the
+/// repository containing `cwd`, and start a session in it. The worktree belongs to
+/// the stored session until it is cleaned up.
+#[tauri::command]
+pub async fn codex_new_worktree_session(
+    state: State<'_, CodexManager>,
+    store: State<'_, SessionStore>,
+    worktrees: State<'_, WorktreeSessions>,
+    cwd: String,
+    base: Option<String>,
+    branch: Option<String>,
+    profile: Option<String>,
+) -> Result<NewWorktreeSessionResult, String> {
+    let svc = state
+        .get()
+        .ok_or_else(|| "codex service not initialized; call codex_init first".to_string())?;
+    let worktrees = worktrees.inner().clone();
+    let worktree = tauri::async_runtime::spawn_blocking(move || {
+        worktrees.create(&cwd, base.as_deref(), branch.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+
+    let session = match svc
+        .create_session(PathBuf::from(&worktree.path), profile)
+        .await
+    {
+        Ok(session) => session,
+        Err(err) => {
+            // Nothing has happened in the new worktree yet.
+            if let Err(cleanup) = remove_session_worktree(&worktree, true) {
+                tracing::warn!(error = %cleanup, path = %worktree.path, "failed to remove worktree");
+            }
+            return Err(err.to_string());
+        }
+    };
+    if let Err(err) = store.set_worktree(&session.session_id, Some(worktree.clone())) {
+        // Unrecorded, the worktree would never be cleaned up.
+        if let Err(err) = svc.close_session(session.session_id.clone()).await {
+            tracing::warn!(error = %err, "failed to close session");
+        }
+        if let Err(cleanup) = remove_session_worktree(&worktree, true) {
+            tracing::warn!(error = %cleanup, path = %worktree.path, "failed to remove worktree");
+        }
+        return Err(err.to_string());
+    }
+    Ok(NewWorktreeSessionResult { session, worktree })
+}
+
+/// Load an existing ACP session and replay its history. Without a profile, the one
+/// the session last ran with is used.
+#[tauri::command]
+pub async fn codex_load_session(
+    state: State<'_, CodexManager>,
+    session_id: String,
+    cwd: String,
+    profile: Option<String>,
+) -> Result<NewSessionResult, String> {
+    let svc = state
+        .get()
+        .ok_or_else(|| "codex service not initialized; call codex_init first".to_string())?;
+    svc.load_session(session_id, PathBuf::from(cwd), profile)
         .await
         .map_err(|e| e.to_string())
 }
 
 /// Send a prompt to the ACP session.
+///
+/// `content` is either plain text or a list of text, image and resource parts.
 #[tauri::command]
 pub async fn codex_prompt(
     state: State<'_, CodexManager>,
     session_id: String,
-    content: String,
+    content: PromptInput,
+    delivery: Option<PromptDelivery>,
 ) -> Result<PromptResult, String> {
     let svc = state
         .get()
         .ok_or_else(|| "codex service not initialized; call codex_init first".to_string())?;
-    svc.send_prompt(session_id, content)
+    svc.send_prompt(session_id, content, delivery.unwrap_or_default())
         .await
         .map_err(|e| e.to_string())
 }
@@ -146,6 +238,122 @@ pub async fn codex_approve(
         .map_err(|e| e.to_string())
 }
 
+/// List approval requests still waiting for an answer, e.g. after a UI reload.
+#[tauri::command]
+pub async fn codex_list_pending_approvals(
+    state: State<'_, CodexManager>,
+    session_id: Option<String>,
+) -> Result<Vec<PendingApprovalInfo>, String> {
+    Ok(state
+        .get()
+        .map(|svc| svc.pending_approvals(session_id.as_deref()))
+        .unwrap_or_default())
+}
+
+/// List prompts waiting behind a running turn, optionally for one session.
+#[tauri::command]
+pub async fn codex_list_queued_prompts(
+    state: State<'_, CodexManager>,
+    session_id: Option<String>,
+) -> Result<Vec<QueuedPromptInfo>, String> {
+    Ok(state
+        .get()
+        .map(|svc| svc.queued_prompts(session_id.as_deref()))
+        .unwrap_or_default())
+}
+
+/// Remove a queued prompt before it runs; returns false if it already started or is unknown.
+#[tauri::command]
+pub async fn codex_remove_queued_prompt(
+    app: AppHandle,
+    state: State<'_, CodexManager>,
+    id: String,
+) -> Result<bool, String> {
+    Ok(state
+        .get()
+        .is_some_and(|svc| svc.remove_queued_prompt(&app, &id)))
+}
+
+/// Return the last captured stderr lines of the local agent or of a remote server's agent.
+#[tauri::command]
+pub async fn codex_agent_log(
+    state: State<'_, CodexManager>,
+    server_id: Option<String>,
+    lines: Option<usize>,
+) -> Result<Vec<AgentLogLine>, String> {
+    Ok(state
+        .get()
+        .map(|svc| svc.agent_log(server_id.as_deref(), lines.unwrap_or(200)))
+        .unwrap_or_default())
+}
+
+/// Start or stop recording raw ACP traffic; returns the recording directory when enabled.
+#[tauri::command]
+pub async fn codex_set_acp_recording(
+    app: AppHandle,
+    state: State<'_, CodexManager>,
+    enabled: bool,
+) -> Result<Option<String>, String> {
+    let svc = state.get_or_create(app);
+    if !enabled {
+        svc.set_recording_dir(None);
+        return Ok(None);
+    }
+    let dir = svc.recording_dir().unwrap_or_else(|| {
+        dirs::data_dir()
+            .unwrap_or_default()
+            .join("codex-desktop")
+            .join("recordings")
+    });
+    svc.set_recording_dir(Some(dir.clone()));
+    Ok(Some(dir.display().to_string()))
+}
+
+/// Replay the `session/update` notifications of a recording into the UI.
+#[tauri::command]
+pub async fn codex_replay_acp_recording(
+    app: AppHandle,
+    path: String,
+    speed: Option<f64>,
+    session_id: Option<String>,
+) -> Result<ReplaySummary, String> {
+    let speed = speed.unwrap_or(1.0);
+    if !speed.is_finite() || speed < 0.0 {
+        return Err(format!("Invalid replay speed: {speed}"));
+    }
+    let frames = load_recording(&PathBuf::from(path)).map_err(|e| format!("{e:#}"))?;
+    Ok(replay_recording(app, &frames, speed, session_id))
+}
+
+/// Export the debug timeline of a session (or all sessions) as Chrome trace JSON or CSV,
+/// with per-turn timing summaries.
+#[tauri::command]
+pub async fn codex_export_debug_trace(
+    state: State<'_, CodexManager>,
+    session_id: Option<String>,
+    format: TraceFormat,
+    path: Option<String>,
+) -> Result<DebugTraceExport, String> {
+    let svc = state
+        .get()
+        .ok_or_else(|| "codex service not initialized; call codex_init first".to_string())?;
+    svc.export_debug_trace(session_id.as_deref(), format, path.map(PathBuf::from))
+        .map_err(|e| format!("{e:#}"))
+}
+
+/// Configure how long approval requests wait before the default outcome applies.
+#[tauri::command]
+pub async fn codex_set_approval_timeout(
+    state: State<'_, CodexManager>,
+    timeout: Option<ApprovalTimeout>,
+) -> Result<(), String> {
+    let svc = state
+        .get()
+        .ok_or_else(|| "codex service not initialized; call codex_init first".to_string())?;
+    svc.set_approval_timeout(timeout);
+    Ok(())
+}
+
 /// Update a session config option by id.
 #[tauri::command]
 pub async fn codex_set_config_option(
@@ -182,6 +390,33 @@ pub async fn codex_set_model(
     codex_set_config_option(state, session_id, "model".to_string(), model_id).await
 }
 
+/// Share unsaved editor contents for a file with the agent.
+#[tauri::command]
+pub async fn codex_set_file_buffer(
+    state: State<'_, CodexManager>,
+    path: String,
+    content: String,
+) -> Result<(), String> {
+    let svc = state
+        .get()
+        .ok_or_else(|| "codex service not initialized; call codex_init first".to_string())?;
+    svc.set_file_buffer(PathBuf::from(path), content);
+    Ok(())
+}
+
+/// Stop sharing unsaved editor contents for a file with the agent.
+#[tauri::command]
+pub async fn codex_clear_file_buffer(
+    state: State<'_, CodexManager>,
+    path: String,
+) -> Result<(), String> {
+    let svc = state
+        .get()
+        .ok_or_else(|| "codex service not initialized; call codex_init first".to_string())?;
+    svc.clear_file_buffer(PathBuf::from(path));
+    Ok(())
+}
+
 /// Warmup the Codex ACP connection.
 /// This pre-spawns the codex-acp process and initializes the protocol,
 /// reducing latency for the first actual session creation.
@@ -277,3 +512,230 @@ pub async fn list_local_directory(path: String) -> Result<LocalDirectoryListing,
 
     Ok(LocalDirectoryListing { path, entries })
 }
+
+/// List sessions stored in the local journal, most recent first.
+#[tauri::command]
+pub fn codex_list_sessions(store: State<'_, SessionStore>) -> Vec<SessionSummary> {
+    store.list()
+}
+
+/// Search stored session transcripts.
+#[tauri::command]
+pub fn codex_search_sessions(
+    store: State<'_, SessionStore>,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<SessionSearchHit>, String> {
+    store
+        .search(&query, limit.unwrap_or(50))
+        .map_err(|e| e.to_string())
+}
+
+/// Load the stored transcript of a session.
+#[tauri::command]
+pub fn codex_open_session(
+    store: State<'_, SessionStore>,
+    session_id: String,
+) -> Result<SessionTranscript, String> {
+    store.open(&session_id).map_err(|e| e.to_string())
+}
+
+/// Close a live session and delete its stored transcript, its checkpoints and its
+/// worktree, if it has one. A worktree with uncommitted changes or an unmerged branch
+/// has to be cleaned up with `force` first.
+#[tauri::command]
+pub async fn codex_delete_session(
+    state: State<'_, CodexManager>,
+    store: State<'_, SessionStore>,
+    session_id: String,
+) -> Result<(), String> {
+    let worktree = store.worktree(&session_id);
+    // Refuse before anything is closed or removed.
+    if let Some(worktree) = &worktree {
+        check_removable(worktree).map_err(|e| e.to_string())?;
+    }
+    let svc = state.get();
+    if let Some(svc) = &svc {
+        svc.close_session(session_id.clone())
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    // Checkpoint refs are shared by all worktrees of a repository.
+    let repo = match &worktree {
+        Some(worktree) => Some(worktree.repo_root.clone()),
+        None => store
+            .cwd(&session_id)
+            .filter(|cwd| matches!(parse_remote_path(cwd), Ok((false, _, _)))),
+    };
+    if let Some(worktree) = &worktree {
+        let cleanup = remove_session_worktree(worktree, false).map_err(|e| e.to_string())?;
+        if !cleanup.branch_deleted {
+            return Err(format!(
+                "branch {} is not merged; clean up the worktree with force first",
+                worktree.branch
+            ));
+        }
+    }
+    store.delete(&session_id).map_err(|e| e.to_string())?;
+    if let Some(repo) = repo {
+        if let Err(err) = delete_checkpoints(Path::new(&repo), &session_id) {
+            tracing::warn!(error = %err, session_id, "failed to delete session checkpoints");
+        }
+    }
+    if let Some(svc) = svc {
+        svc.clear_debug_timeline(&session_id);
+    }
+    Ok(())
+}
+
+/// List stored sessions that run in a worktree, with the state of each worktree.
+#[tauri::command]
+pub fn codex_list_worktree_sessions(store: State<'_, SessionStore>) -> Vec<WorktreeSessionInfo> {
+    store.list().into_iter().filter_map(worktree_info).collect()
+}
+
+/// Merge a session's worktree branch into the branch checked out in the main
+/// working tree, either as a merge commit or squashed into one commit.
+#[tauri::command]
+pub fn codex_merge_worktree_session(
+    store: State<'_, SessionStore>,
+    session_id: String,
+    squash: Option<bool>,
+    message: Option<String>,
+) -> Result<GitCommit, String> {
+    let mut worktree = session_worktree(&store, &session_id)?;
+    let commit = merge_worktree(&mut worktree, squash.unwrap_or(false), message.as_deref())
+        .map_err(|e| e.to_string())?;
+    store
+        .set_worktree(&session_id, Some(worktree))
+        .map_err(|e| e.to_string())?;
+    Ok(commit)
+}
+
+/// Remove a session's worktree and detach it from the stored session once the branch
+/// is deleted too. A branch with unmerged commits stays recorded; `force` discards
+/// both.
+#[tauri::command]
+pub fn codex_cleanup_worktree_session(
+    store: State<'_, SessionStore>,
+    session_id: String,
+    force: Option<bool>,
+) -> Result<WorktreeCleanup, String> {
+    let worktree = session_worktree(&store, &session_id)?;
+    let cleanup =
+        remove_session_worktree(&worktree, force.unwrap_or(false)).map_err(|e| e.to_string())?;
+    if cleanup.branch_deleted {
+        store
+            .set_worktree(&session_id, None)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(cleanup)
+}
+
+fn session_worktree(store: &SessionStore, session_id: &str) -> Result<SessionWorktree, String> {
+    store
+        .worktree(session_id)
+        .ok_or_else(|| format!("session {session_id} has no worktree"))
+}
+
+/// List approval rules in evaluation order.
+#[tauri::command]
+pub fn codex_list_approval_rules(policy: State<'_, ApprovalPolicy>) -> Vec<ApprovalRule> {
+    policy.list()
+}
+
+/// Create or update an approval rule, optionally moving it to `index`.
+#[tauri::command]
+pub fn codex_save_approval_rule(
+    policy: State<'_, ApprovalPolicy>,
+    rule: ApprovalRule,
+    index: Option<usize>,
+) -> Result<ApprovalRule, String> {
+    policy.upsert(rule, index).map_err(|e| e.to_string())
+}
+
+/// Delete an approval rule.
+#[tauri::command]
+pub fn codex_delete_approval_rule(
+    policy: State<'_, ApprovalPolicy>,
+    id: String,
+) -> Result<(), String> {
+    policy.remove(&id).map_err(|e| e.to_string())
+}
+
+/// List environment profiles with secret values redacted.
+#[tauri::command]
+pub fn codex_list_profiles(profiles: State<'_, ProfileStore>) -> Vec<EnvProfile> {
+    profiles.list()
+}
+
+/// Create or update an environment profile. Sessions already running keep the
+/// settings they were started with.
+#[tauri::command]
+pub fn codex_save_profile(
+    profiles: State<'_, ProfileStore>,
+    profile: EnvProfile,
+) -> Result<EnvProfile, String> {
+    profiles.upsert(profile).map_err(|e| e.to_string())
+}
+
+/// Delete an environment profile and its secrets.
+#[tauri::command]
+pub fn codex_delete_profile(profiles: State<'_, ProfileStore>, id: String) -> Result<(), String> {
+    profiles.remove(&id).map_err(|e| e.to_string())
+}
+
+/// Read the most recent automatic approval decisions.
+#[tauri::command]
+pub fn codex_approval_audit(
+    policy: State<'_, ApprovalPolicy>,
+    limit: Option<usize>,
+) -> Result<Vec<ApprovalAuditEntry>, String> {
+    policy
+        .read_audit(limit.unwrap_or(200))
+        .map_err(|e| e.to_string())
+}
+
+/// Summarize token usage and cost per day and session, with any reached budgets.
+#[tauri::command]
+pub fn codex_usage_report(
+    usage: State<'_, UsageStore>,
+    session_id: Option<String>,
+    days: Option<usize>,
+) -> UsageReport {
+    usage.report(session_id.as_deref(), days.unwrap_or(30))
+}
+
+/// Return the model price table and usage budget.
+#[tauri::command]
+pub fn codex_usage_settings(usage: State<'_, UsageStore>) -> UsageSettings {
+    usage.settings()
+}
+
+/// Replace the model price table and usage budget.
+#[tauri::command]
+pub fn codex_set_usage_settings(
+    usage: State<'_, UsageStore>,
+    settings: UsageSettings,
+) -> Result<(), String> {
+    usage.set_settings(settings).map_err(|e| e.to_string())
+}
+
+/// Return the message/thought chunk coalescing window and size limit.
+#[tauri::command]
+pub fn codex_chunk_coalescing(coalescer: State<'_, ChunkCoalescer>) -> CoalesceConfig {
+    coalescer.config()
+}
+
+/// Change the chunk coalescing window (`0` disables coalescing) and size limit.
+#[tauri::command]
+pub fn codex_set_chunk_coalescing(
+    coalescer: State<'_, ChunkCoalescer>,
+    config: CoalesceConfig,
+) -> Result<(), String> {
+    if config.max_bytes == 0 {
+        return Err("maxBytes must be greater than zero".to_string());
+    }
+    coalescer.set_config(config);
+    Ok(())
+}
@@ -1,15 +1,18 @@
 //! Debug timing helpers for Codex event tracing.
 
-use serde::Serialize;
 use serde_json::{json, Value};
 use std::{
     collections::HashMap,
     sync::Mutex,
     time::{Instant, SystemTime, UNIX_EPOCH},
 };
-use tauri::{AppHandle, Emitter};
+use tauri::AppHandle;
 
-use super::events::EVENT_DEBUG;
+use super::{
+    events::{CodexEvent, DebugPayload},
+    recorder::TrafficRecorder,
+    timeline::{DebugTimeline, TimelineEvent},
+};
 
 #[derive(Debug, Clone)]
 /// Timing metrics emitted for debug instrumentation.
@@ -24,18 +27,6 @@ pub struct DebugTiming {
     pub since_last_event_ms: Option<u64>,
 }
 
-#[derive(Clone, Serialize)]
-#[serde(rename_all = "camelCase")]
-struct DebugPayload<'a> {
-    label: &'a str,
-    session_id: Option<&'a str>,
-    ts_ms: u64,
-    dt_ms: u64,
-    since_prompt_ms: Option<u64>,
-    since_last_event_ms: Option<u64>,
-    extra: &'a Value,
-}
-
 #[derive(Debug)]
 /// Stateful timing tracker for Codex backend events.
 pub struct DebugState {
@@ -44,6 +35,10 @@ pub struct DebugState {
     last_event_by_session: Mutex<HashMap<String, Instant>>,
     last_prompt_by_session: Mutex<HashMap<String, Instant>>,
     emit_to_stderr: bool,
+    /// Opt-in recorder of ACP traffic for bug reports.
+    pub recorder: TrafficRecorder,
+    /// Bounded per-session history of emitted events.
+    pub timeline: DebugTimeline,
 }
 
 impl Default for DebugState {
@@ -69,6 +64,8 @@ impl DebugState {
             last_event_by_session: Mutex::new(HashMap::new()),
             last_prompt_by_session: Mutex::new(HashMap::new()),
             emit_to_stderr,
+            recorder: TrafficRecorder::new(),
+            timeline: DebugTimeline::default(),
         }
     }
 
@@ -122,7 +119,8 @@ impl DebugState {
         }
     }
 
-    /// Emit a debug timing payload to the frontend and optional stderr.
+    /// Emit a debug timing payload to the frontend and optional stderr, keeping it in
+    /// the timeline.
     pub fn emit<R: tauri::Runtime>(
         &self,
         app: &AppHandle<R>,
@@ -153,7 +151,17 @@ impl DebugState {
             since_last_event_ms: timing.since_last_event_ms,
             extra: &extra,
         };
-        let _ = app.emit(EVENT_DEBUG, payload);
+        CodexEvent::Debug(payload).emit(app);
+
+        self.timeline.push(TimelineEvent {
+            session_id: session_id.map(str::to_string),
+            label: label.to_string(),
+            ts_ms: timing.ts_ms,
+            dt_ms: timing.dt_ms,
+            since_prompt_ms: timing.since_prompt_ms,
+            since_last_event_ms: timing.since_last_event_ms,
+            extra,
+        });
     }
 
     fn update_last_event(&self, session_id: &str, now: Instant) -> Option<u64> {
@@ -1,7 +1,32 @@
-//! Event names emitted from the Codex backend.
+//! Event names and payloads emitted from the Codex backend.
+//!
+//! Every event sent to the webview is a variant of [`CodexEvent`]. Payloads are emitted
+//! with a `schemaVersion` field, and the TypeScript declarations in
+//! `src/types/codexEvents.generated.ts` are generated from these types; regenerate them
+//! with `CODEX_UPDATE_EVENT_TYPES=1 cargo test generated_event_types`.
+
+use crate::codex::{
+    agent_log::AgentLogLine,
+    mentions::MentionAttachment,
+    policy::ApprovalAuditEntry,
+    scheduler::QueuedPromptInfo,
+    typescript,
+    usage::{BudgetAlert, UsageEvent},
+};
+use crate::git::types::{GitCheckpoint, GitProgressEvent};
+use agent_client_protocol::{
+    AvailableCommandsUpdate, ConfigOptionUpdate, CurrentModeUpdate, PermissionOption, Plan,
+    StopReason, ToolCall, ToolCallUpdate,
+};
+use schemars::{generate::SchemaSettings, JsonSchema, Schema, SchemaGenerator};
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Runtime};
 
 /// Event emitted when a chat message chunk arrives.
 pub const EVENT_MESSAGE_CHUNK: &str = "codex:message";
+/// Event emitted when a user message chunk is replayed from session history.
+pub const EVENT_USER_MESSAGE_CHUNK: &str = "codex:user-message";
 /// Event emitted when a thought chunk arrives.
 pub const EVENT_THOUGHT_CHUNK: &str = "codex:thought";
 /// Event emitted when a tool call is received.
@@ -10,6 +35,10 @@ pub const EVENT_TOOL_CALL: &str = "codex:tool-call";
 pub const EVENT_TOOL_CALL_UPDATE: &str = "codex:tool-call-update";
 /// Event emitted when a permission request is needed.
 pub const EVENT_APPROVAL_REQUEST: &str = "codex:approval-request";
+/// Event emitted when a pending approval is resolved by timeout, cancel or disconnect.
+pub const EVENT_APPROVAL_CLEARED: &str = "codex:approval-cleared";
+/// Event emitted when an approval rule decided a permission request without asking.
+pub const EVENT_APPROVAL_AUTO_DECIDED: &str = "codex:approval-auto-decided";
 /// Event emitted when a plan update arrives.
 pub const EVENT_PLAN: &str = "codex:plan";
 /// Event emitted when available slash commands are updated.
@@ -18,11 +47,400 @@ pub const EVENT_AVAILABLE_COMMANDS: &str = "codex:available-commands";
 pub const EVENT_CURRENT_MODE: &str = "codex:current-mode";
 /// Event emitted when config options are updated.
 pub const EVENT_CONFIG_OPTION_UPDATE: &str = "codex:config-option-update";
+/// Event emitted when `@` mentions in a prompt have been resolved into attachments.
+pub const EVENT_PROMPT_ATTACHMENTS: &str = "codex:prompt-attachments";
+/// Event emitted when a session's running turn or prompt queue changes.
+pub const EVENT_PROMPT_QUEUE: &str = "codex:prompt-queue";
 /// Event emitted when a prompt turn completes.
 pub const EVENT_TURN_COMPLETE: &str = "codex:turn-complete";
+/// Event emitted when an agent connection changes state (connecting, ready, crashed, restarting).
+pub const EVENT_CONNECTION_STATE: &str = "codex:connection-state";
+/// Event emitted for each stderr line written by an agent process.
+pub const EVENT_AGENT_LOG: &str = "codex:agent-log";
 /// Event emitted when a backend error occurs.
 pub const EVENT_ERROR: &str = "codex:error";
 /// Event emitted with debug timing payloads.
 pub const EVENT_DEBUG: &str = "codex:debug";
 /// Event emitted for token usage updates.
 pub const EVENT_TOKEN_USAGE: &str = "codex:token-usage";
+/// Event emitted with typed per-turn, per-session and daily usage after each update.
+pub const EVENT_USAGE: &str = "codex:usage";
+/// Event emitted when a usage budget is reached.
+pub const EVENT_USAGE_ALERT: &str = "codex:usage-alert";
+/// Event emitted when the agent writes a file through the client.
+pub const EVENT_FILE_WRITE: &str = "codex:file-write";
+/// Event emitted when a tool call embeds an agent terminal.
+pub const EVENT_TERMINAL_ATTACHED: &str = "codex:terminal-attached";
+/// Event emitted when the working tree was snapshotted before a turn.
+pub const EVENT_CHECKPOINT: &str = "codex:checkpoint";
+/// Event carrying `--progress` output of fetch, pull and push.
+pub const EVENT_GIT_PROGRESS: &str = "git:progress";
+
+/// Version of the event payload schema, sent as `schemaVersion` with every event.
+///
+/// Bump it when a payload changes incompatibly (a field is removed, renamed or retyped).
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+
+fn is_live(replay: &bool) -> bool {
+    !*replay
+}
+
+/// A message, thought or replayed user message chunk.
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TextChunkPayload<'a> {
+    pub session_id: &'a str,
+    pub text: &'a str,
+    /// Set when the chunk is replayed from session history.
+    #[serde(skip_serializing_if = "is_live")]
+    pub replay: bool,
+}
+
+/// A new tool call.
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolCallPayload<'a> {
+    pub session_id: &'a str,
+    pub tool_call: &'a ToolCall,
+    #[serde(skip_serializing_if = "is_live")]
+    pub replay: bool,
+}
+
+/// An update of an earlier tool call.
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolCallUpdatePayload<'a> {
+    pub session_id: &'a str,
+    pub update: &'a ToolCallUpdate,
+    #[serde(skip_serializing_if = "is_live")]
+    pub replay: bool,
+}
+
+/// The agent's current plan.
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PlanPayload<'a> {
+    pub session_id: &'a str,
+    pub plan: &'a Plan,
+    #[serde(skip_serializing_if = "is_live")]
+    pub replay: bool,
+}
+
+/// Slash commands the agent offers.
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AvailableCommandsPayload<'a> {
+    pub session_id: &'a str,
+    pub update: &'a AvailableCommandsUpdate,
+    #[serde(skip_serializing_if = "is_live")]
+    pub replay: bool,
+}
+
+/// A session mode change.
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CurrentModePayload<'a> {
+    pub session_id: &'a str,
+    pub update: &'a CurrentModeUpdate,
+    #[serde(skip_serializing_if = "is_live")]
+    pub replay: bool,
+}
+
+/// Changed session configuration options.
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigOptionPayload<'a> {
+    pub session_id: &'a str,
+    pub update: &'a ConfigOptionUpdate,
+    #[serde(skip_serializing_if = "is_live")]
+    pub replay: bool,
+}
+
+/// A permission request waiting for the user.
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ApprovalRequestPayload<'a> {
+    pub session_id: &'a str,
+    /// Tool call id; pass it back to `codex_approve`.
+    pub request_id: &'a str,
+    pub tool_call: &'a ToolCallUpdate,
+    pub options: &'a [PermissionOption],
+}
+
+/// A pending approval resolved without the user.
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ApprovalClearedPayload<'a> {
+    pub session_id: &'a str,
+    pub request_id: &'a str,
+    /// `timeout`, `cancelled` or `disconnected`.
+    pub reason: &'a str,
+    /// Option selected on the user's behalf, if any.
+    pub option_id: Option<&'a str>,
+}
+
+/// Files attached to a prompt from its `@` mentions.
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptAttachmentsPayload<'a> {
+    pub session_id: &'a str,
+    pub attachments: &'a [MentionAttachment],
+}
+
+/// Running turn and queued prompts of a session.
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptQueuePayload<'a> {
+    pub session_id: &'a str,
+    pub running: bool,
+    pub queue: Vec<QueuedPromptInfo>,
+}
+
+/// A finished prompt turn.
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TurnCompletePayload<'a> {
+    pub session_id: &'a str,
+    pub stop_reason: StopReason,
+}
+
+/// Lifecycle state of an agent connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ConnectionState {
+    Connecting,
+    Ready,
+    Crashed,
+    Restarting,
+}
+
+/// An agent connection lifecycle change.
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionStatePayload<'a> {
+    pub state: ConnectionState,
+    /// Remote server of the connection, or `None` for the local agent.
+    pub server_id: Option<&'a str>,
+    /// Restart attempt, while restarting.
+    pub attempt: Option<u32>,
+    pub error: Option<&'a str>,
+    /// Sessions affected by a crash, or reloaded after a restart.
+    pub sessions: &'a [String],
+}
+
+/// Parameters of a `codex/token-usage` notification, passed on as the agent sent them.
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenUsagePayload<'a> {
+    pub usage: &'a Value,
+}
+
+/// A backend error not tied to a request.
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorPayload<'a> {
+    pub error: &'a str,
+}
+
+/// Debug timing of a backend event.
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DebugPayload<'a> {
+    pub label: &'a str,
+    pub session_id: Option<&'a str>,
+    pub ts_ms: u64,
+    pub dt_ms: u64,
+    pub since_prompt_ms: Option<u64>,
+    pub since_last_event_ms: Option<u64>,
+    pub extra: &'a Value,
+}
+
+/// A file written by the agent through the client.
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FileWritePayload<'a> {
+    pub session_id: &'a str,
+    pub path: &'a str,
+    pub created: bool,
+    pub bytes: usize,
+}
+
+/// An agent terminal embedded in a tool call.
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TerminalAttachedPayload<'a> {
+    pub session_id: &'a str,
+    pub tool_call_id: &'a str,
+    pub terminal_id: &'a str,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Versioned<'a, T> {
+    schema_version: u32,
+    #[serde(flatten)]
+    payload: &'a T,
+}
+
+macro_rules! codex_events {
+    ($($(#[$doc:meta])* $variant:ident($payload:ty) => $name:ident,)*) => {
+        /// Every event the backend emits to the webview, with its payload.
+        pub enum CodexEvent<'a> {
+            $($(#[$doc])* $variant($payload),)*
+        }
+
+        impl CodexEvent<'_> {
+            /// Tauri event name.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(Self::$variant(_) => $name,)*
+                }
+            }
+
+            /// Emit the event with its payload and the schema version.
+            pub fn emit<R: Runtime>(&self, app: &AppHandle<R>) {
+                let result = match self {
+                    $(Self::$variant(payload) => app.emit(
+                        $name,
+                        Versioned {
+                            schema_version: EVENT_SCHEMA_VERSION,
+                            payload,
+                        },
+                    ),)*
+                };
+                if let Err(err) = result {
+                    tracing::debug!(error = %err, event = self.name(), "failed to emit event");
+                }
+            }
+        }
+
+        impl<'a> CodexEvent<'a> {
+            /// Event names with their payload schemas, in declaration order.
+            fn schemas(generator: &mut SchemaGenerator) -> Vec<(&'static str, Schema)> {
+                vec![$(($name, generator.subschema_for::<$payload>()),)*]
+            }
+        }
+    };
+}
+
+codex_events! {
+    /// A chunk of the agent's answer.
+    MessageChunk(TextChunkPayload<'a>) => EVENT_MESSAGE_CHUNK,
+    /// A user message chunk replayed from history.
+    UserMessageChunk(TextChunkPayload<'a>) => EVENT_USER_MESSAGE_CHUNK,
+    /// A chunk of the agent's reasoning.
+    ThoughtChunk(TextChunkPayload<'a>) => EVENT_THOUGHT_CHUNK,
+    ToolCall(ToolCallPayload<'a>) => EVENT_TOOL_CALL,
+    ToolCallUpdate(ToolCallUpdatePayload<'a>) => EVENT_TOOL_CALL_UPDATE,
+    ApprovalRequest(ApprovalRequestPayload<'a>) => EVENT_APPROVAL_REQUEST,
+    ApprovalCleared(ApprovalClearedPayload<'a>) => EVENT_APPROVAL_CLEARED,
+    ApprovalAutoDecided(&'a ApprovalAuditEntry) => EVENT_APPROVAL_AUTO_DECIDED,
+    Plan(PlanPayload<'a>) => EVENT_PLAN,
+    AvailableCommands(AvailableCommandsPayload<'a>) => EVENT_AVAILABLE_COMMANDS,
+    CurrentMode(CurrentModePayload<'a>) => EVENT_CURRENT_MODE,
+    ConfigOptionUpdate(ConfigOptionPayload<'a>) => EVENT_CONFIG_OPTION_UPDATE,
+    PromptAttachments(PromptAttachmentsPayload<'a>) => EVENT_PROMPT_ATTACHMENTS,
+    PromptQueue(PromptQueuePayload<'a>) => EVENT_PROMPT_QUEUE,
+    TurnComplete(TurnCompletePayload<'a>) => EVENT_TURN_COMPLETE,
+    ConnectionState(ConnectionStatePayload<'a>) => EVENT_CONNECTION_STATE,
+    AgentLog(&'a AgentLogLine) => EVENT_AGENT_LOG,
+    Error(ErrorPayload<'a>) => EVENT_ERROR,
+    Debug(DebugPayload<'a>) => EVENT_DEBUG,
+    TokenUsage(TokenUsagePayload<'a>) => EVENT_TOKEN_USAGE,
+    Usage(&'a UsageEvent) => EVENT_USAGE,
+    UsageAlert(&'a BudgetAlert) => EVENT_USAGE_ALERT,
+    FileWrite(FileWritePayload<'a>) => EVENT_FILE_WRITE,
+    TerminalAttached(TerminalAttachedPayload<'a>) => EVENT_TERMINAL_ATTACHED,
+    Checkpoint(&'a GitCheckpoint) => EVENT_CHECKPOINT,
+    GitProgress(GitProgressEvent<'a>) => EVENT_GIT_PROGRESS,
+}
+
+/// Render the TypeScript declarations of all event payloads.
+pub fn typescript_declarations() -> String {
+    let mut generator = SchemaSettings::draft2020_12()
+        .for_serialize()
+        .into_generator();
+    let events = CodexEvent::schemas(&mut generator);
+    let definitions = generator.take_definitions(true);
+
+    let mut out = String::from(
+        "// Generated from src-tauri/src/codex/events.rs. Do not edit by hand.\n\
+         // Regenerate with `CODEX_UPDATE_EVENT_TYPES=1 cargo test generated_event_types`\n\
+         // in src-tauri.\n\n",
+    );
+    out.push_str(&format!(
+        "export const CODEX_EVENT_SCHEMA_VERSION = {EVENT_SCHEMA_VERSION};\n\n\
+         export type Versioned<T> = T & {{ schemaVersion: number }};\n\n\
+         export interface CodexEventMap {{\n"
+    ));
+    for (name, schema) in &events {
+        out.push_str(&format!(
+            "  '{name}': Versioned<{}>;\n",
+            typescript::type_expr(schema.as_value())
+        ));
+    }
+    out.push_str("}\n\nexport type CodexEventName = keyof CodexEventMap;\n");
+    for (name, schema) in &definitions {
+        out.push('\n');
+        out.push_str(&typescript::declaration(name, schema));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_generated_event_types() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("..")
+            .join("src")
+            .join("types")
+            .join("codexEvents.generated.ts");
+        let expected = typescript_declarations();
+        if std::env::var_os("CODEX_UPDATE_EVENT_TYPES").is_some() {
+            std::fs::write(&path, &expected).expect("write generated event types");
+            return;
+        }
+        let actual = std::fs::read_to_string(&path).unwrap_or_default();
+        assert!(
+            actual == expected,
+            "{} is stale; regenerate it with `CODEX_UPDATE_EVENT_TYPES=1 cargo test generated_event_types`",
+            path.display()
+        );
+    }
+
+    #[test]
+    fn test_emitted_payloads_carry_schema_version() {
+        let payload = Versioned {
+            schema_version: EVENT_SCHEMA_VERSION,
+            payload: &TextChunkPayload {
+                session_id: "s1",
+                text: "hi",
+                replay: false,
+            },
+        };
+        assert_eq!(
+            serde_json::to_value(&payload).unwrap(),
+            serde_json::json!({ "schemaVersion": 1, "sessionId": "s1", "text": "hi" })
+        );
+        let event = CodexEvent::TurnComplete(TurnCompletePayload {
+            session_id: "s1",
+            stop_reason: StopReason::EndTurn,
+        });
+        assert_eq!(event.name(), EVENT_TURN_COMPLETE);
+
+        // Payloads the agent controls are nested, so any JSON value can be flattened.
+        let usage = serde_json::json!([1, 2]);
+        let payload = Versioned {
+            schema_version: EVENT_SCHEMA_VERSION,
+            payload: &TokenUsagePayload { usage: &usage },
+        };
+        assert_eq!(
+            serde_json::to_value(&payload).unwrap(),
+            serde_json::json!({ "schemaVersion": 1, "usage": [1, 2] })
+        );
+    }
+}
@@ -0,0 +1,281 @@
+//! Client-side file system capability served to the ACP agent.
+
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Component, Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Outcome of a successful agent file write.
+#[derive(Debug, Clone)]
+pub struct FileWriteOutcome {
+    /// Absolute path that was written.
+    pub path: PathBuf,
+    /// True if the file did not exist before the write.
+    pub created: bool,
+    /// Number of bytes written.
+    pub bytes: usize,
+}
+
+#[derive(Default)]
+/// Shared state for `fs/read_text_file` and `fs/write_text_file`.
+pub struct FsState {
+    session_roots: Mutex<HashMap<String, PathBuf>>,
+    buffers: Mutex<HashMap<PathBuf, String>>,
+}
+
+impl FsState {
+    /// Register the working directory that confines file access for a session.
+    pub fn register_session(&self, session_id: &str, cwd: &Path) {
+        let root = fs::canonicalize(cwd).unwrap_or_else(|_| normalize_path(cwd));
+        let mut guard = self.lock_roots();
+        guard.insert(session_id.to_string(), root);
+    }
+
+    /// Return the working directory registered for a session.
+    pub fn session_root(&self, session_id: &str) -> Option<PathBuf> {
+        self.lock_roots().get(session_id).cloned()
+    }
+
+    /// Forget the working directory registered for a session.
+    pub fn unregister_session(&self, session_id: &str) {
+        let mut guard = self.lock_roots();
+        guard.remove(session_id);
+    }
+
+    /// Store the unsaved editor contents for a file so agent reads see them.
+    pub fn set_buffer(&self, path: &Path, content: String) {
+        let mut guard = self.lock_buffers();
+        guard.insert(resolve_existing_prefix(&normalize_path(path)), content);
+    }
+
+    /// Drop the unsaved editor contents for a file.
+    pub fn clear_buffer(&self, path: &Path) {
+        let mut guard = self.lock_buffers();
+        guard.remove(&resolve_existing_prefix(&normalize_path(path)));
+    }
+
+    /// Read a text file for the agent, preferring unsaved editor buffers.
+    pub fn read_text_file(
+        &self,
+        session_id: &str,
+        path: &Path,
+        line: Option<u32>,
+        limit: Option<u32>,
+    ) -> agent_client_protocol::Result<String> {
+        let path = self.confine(session_id, path)?;
+
+        let buffered = self.lock_buffers().get(&path).cloned();
+        let content = match buffered {
+            Some(content) => content,
+            None => fs::read_to_string(&path).map_err(|err| io_error(&path, err))?,
+        };
+
+        Ok(slice_lines(&content, line, limit))
+    }
+
+    /// Write a text file on behalf of the agent.
+    pub fn write_text_file(
+        &self,
+        session_id: &str,
+        path: &Path,
+        content: &str,
+    ) -> agent_client_protocol::Result<FileWriteOutcome> {
+        let path = self.confine(session_id, path)?;
+        let created = !path.exists();
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|err| io_error(parent, err))?;
+        }
+        fs::write(&path, content).map_err(|err| io_error(&path, err))?;
+
+        // The file on disk is now authoritative; a stale buffer would shadow the write.
+        self.lock_buffers().remove(&path);
+
+        Ok(FileWriteOutcome {
+            path,
+            created,
+            bytes: content.len(),
+        })
+    }
+
+    /// Working directory for an agent terminal: the session cwd, or a directory inside
+    /// it.
+    pub fn terminal_cwd(
+        &self,
+        session_id: &str,
+        cwd: Option<&Path>,
+    ) -> agent_client_protocol::Result<PathBuf> {
+        match cwd {
+            Some(cwd) => self.confine(session_id, cwd),
+            None => self.session_root(session_id).ok_or_else(|| {
+                agent_client_protocol::Error::invalid_params()
+                    .data(json!(format!("unknown session: {session_id}")))
+            }),
+        }
+    }
+
+    /// Resolve a requested path and ensure it stays inside the session cwd.
+    fn confine(&self, session_id: &str, path: &Path) -> agent_client_protocol::Result<PathBuf> {
+        let root = self.lock_roots().get(session_id).cloned().ok_or_else(|| {
+            agent_client_protocol::Error::invalid_params()
+                .data(json!(format!("unknown session: {session_id}")))
+        })?;
+
+        if !path.is_absolute() {
+            return Err(agent_client_protocol::Error::invalid_params()
+                .data(json!(format!("path must be absolute: {}", path.display()))));
+        }
+
+        let resolved = resolve_existing_prefix(&normalize_path(path));
+        if !resolved.starts_with(&root) {
+            return Err(
+                agent_client_protocol::Error::invalid_params().data(json!(format!(
+                    "path is outside the session working directory: {}",
+                    path.display()
+                ))),
+            );
+        }
+        Ok(resolved)
+    }
+
+    fn lock_roots(&self) -> std::sync::MutexGuard<'_, HashMap<String, PathBuf>> {
+        self.session_roots
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn lock_buffers(&self) -> std::sync::MutexGuard<'_, HashMap<PathBuf, String>> {
+        self.buffers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn io_error(path: &Path, err: std::io::Error) -> agent_client_protocol::Error {
+    if err.kind() == std::io::ErrorKind::NotFound {
+        return agent_client_protocol::Error::resource_not_found(Some(path.display().to_string()));
+    }
+    agent_client_protocol::Error::internal_error().data(json!(format!(
+        "{}: {}",
+        path.display(),
+        err
+    )))
+}
+
+/// Lexically normalize a path, resolving `.` and `..` without touching the file system.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other.as_os_str()),
+        }
+    }
+    out
+}
+
+/// Canonicalize the longest existing ancestor so symlinks cannot escape the root.
+fn resolve_existing_prefix(path: &Path) -> PathBuf {
+    let mut existing = path.to_path_buf();
+    let mut rest = Vec::new();
+    while !existing.exists() {
+        match (existing.file_name(), existing.parent()) {
+            (Some(name), Some(parent)) => {
+                rest.push(name.to_os_string());
+                existing = parent.to_path_buf();
+            }
+            _ => return path.to_path_buf(),
+        }
+    }
+
+    let mut resolved = fs::canonicalize(&existing).unwrap_or(existing);
+    for name in rest.into_iter().rev() {
+        resolved.push(name);
+    }
+    resolved
+}
+
+/// Return `limit` lines starting at the 1-based `line`, keeping line endings intact.
+pub fn slice_lines(content: &str, line: Option<u32>, limit: Option<u32>) -> String {
+    if line.is_none() && limit.is_none() {
+        return content.to_string();
+    }
+
+    let skip = line.unwrap_or(1).saturating_sub(1) as usize;
+    let take = limit.map(|value| value as usize).unwrap_or(usize::MAX);
+    content
+        .split_inclusive('\n')
+        .skip(skip)
+        .take(take)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slice_lines() {
+        let content = "one\ntwo\nthree\nfour";
+        assert_eq!(slice_lines(content, None, None), content);
+        assert_eq!(slice_lines(content, Some(2), Some(2)), "two\nthree\n");
+        assert_eq!(slice_lines(content, Some(3), None), "three\nfour");
+        assert_eq!(slice_lines(content, None, Some(1)), "one\n");
+        assert_eq!(slice_lines(content, Some(10), Some(1)), "");
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(
+            normalize_path(Path::new("/repo/src/../lib/./mod.rs")),
+            PathBuf::from("/repo/lib/mod.rs")
+        );
+        assert_eq!(
+            normalize_path(Path::new("/repo/../../etc")),
+            PathBuf::from("/etc")
+        );
+    }
+
+    #[test]
+    fn test_confine_rejects_escape() {
+        let root = std::env::temp_dir().join(format!("codex-fs-test-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let state = FsState::default();
+        state.register_session("s1", &root);
+
+        let inside = root.join("notes.txt");
+        let outcome = state.write_text_file("s1", &inside, "hello\n").unwrap();
+        assert!(outcome.created);
+        assert_eq!(
+            state.read_text_file("s1", &inside, None, None).unwrap(),
+            "hello\n"
+        );
+
+        state.set_buffer(&inside, "unsaved\n".to_string());
+        assert_eq!(
+            state.read_text_file("s1", &inside, None, None).unwrap(),
+            "unsaved\n"
+        );
+
+        let escape = root.join("..").join("outside.txt");
+        assert!(state.read_text_file("s1", &escape, None, None).is_err());
+        assert!(state.write_text_file("s1", &escape, "x").is_err());
+        assert!(state.read_text_file("s2", &inside, None, None).is_err());
+
+        assert_eq!(
+            state.terminal_cwd("s1", None).unwrap(),
+            state.session_root("s1").unwrap()
+        );
+        assert!(state.terminal_cwd("s1", Some(&root.join("sub"))).is_ok());
+        assert!(state.terminal_cwd("s1", Some(&root.join(".."))).is_err());
+        assert!(state.terminal_cwd("s2", None).is_err());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
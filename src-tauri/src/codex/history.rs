@@ -0,0 +1,773 @@
+//! Persistent per-session transcript journal.
+//!
+//! Every session started or resumed through the service gets an append-only
+//! JSONL file under `<data dir>/codex-desktop/sessions`, plus a summary entry in
+//! `index.json` so history can be listed without reading every journal.
+
+use crate::codex::util::{content_block_text, now_millis};
+use agent_client_protocol::{ContentBlock, PermissionOptionKind, SessionUpdate, StopReason};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+const INDEX_FILE: &str = "index.json";
+const JOURNAL_EXT: &str = "jsonl";
+const TITLE_MAX_CHARS: usize = 80;
+const SNIPPET_CONTEXT_CHARS: usize = 40;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+/// A single journaled event.
+pub enum JournalRecord {
+    /// A new session was created.
+    Started {
+        /// Working directory as requested by the frontend (may be `remote://`).
+        cwd: String,
+        /// Environment profile the agent was launched with.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        profile: Option<String>,
+    },
+    /// An existing session was resumed via `session/load`.
+    Resumed {
+        /// Working directory as requested by the frontend (may be `remote://`).
+        cwd: String,
+        /// Environment profile the agent was launched with.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        profile: Option<String>,
+    },
+    /// A prompt sent to the agent.
+    Prompt {
+        /// Content blocks sent with the prompt.
+        content: Vec<ContentBlock>,
+    },
+    /// A session update received from the agent.
+    Update {
+        /// Raw ACP session update.
+        update: Box<SessionUpdate>,
+    },
+    /// The outcome of a permission request.
+    #[serde(rename_all = "camelCase")]
+    Approval {
+        /// Tool call the permission was requested for.
+        tool_call_id: String,
+        /// Selected option id, or `None` if the request was cancelled.
+        option_id: Option<String>,
+        /// Kind of the selected option when known.
+        option_kind: Option<PermissionOptionKind>,
+    },
+    /// A prompt turn finished.
+    #[serde(rename_all = "camelCase")]
+    Stop {
+        /// Stop reason reported by the agent.
+        stop_reason: StopReason,
+    },
+    /// The session's git worktree was created, or removed when `None`.
+    Worktree {
+        /// The worktree the session runs in.
+        worktree: Option<SessionWorktree>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// A git worktree created for a single session.
+pub struct SessionWorktree {
+    /// Main working tree of the repository the worktree belongs to.
+    pub repo_root: String,
+    /// Directory of the worktree, which is the session's working directory.
+    pub path: String,
+    /// Branch checked out in the worktree.
+    pub branch: String,
+    /// Commit the branch started from.
+    pub base: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// Branch commit last merged into the main checkout. A squashed branch does not
+    /// look merged to git, so cleanup deletes it only while it still points here.
+    pub merged: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// Timestamped journal line.
+pub struct JournalEntry {
+    /// Milliseconds since the Unix epoch.
+    pub ts: u64,
+    #[serde(flatten)]
+    /// Journaled event.
+    pub record: JournalRecord,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// Summary of a stored session.
+pub struct SessionSummary {
+    /// ACP session id.
+    pub session_id: String,
+    /// Working directory the session was started in.
+    pub cwd: String,
+    /// First prompt text, truncated.
+    pub title: Option<String>,
+    /// Creation time in milliseconds since the Unix epoch.
+    pub created_at: u64,
+    /// Last prompt or stop time in milliseconds since the Unix epoch.
+    pub updated_at: u64,
+    /// Number of prompts sent.
+    pub prompt_count: u32,
+    /// Stop reason of the most recent turn.
+    pub last_stop_reason: Option<StopReason>,
+    /// Environment profile the session last ran with.
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Git worktree the session runs in, until it is cleaned up.
+    #[serde(default)]
+    pub worktree: Option<SessionWorktree>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+/// Full stored transcript for a session.
+pub struct SessionTranscript {
+    /// Session summary.
+    pub summary: SessionSummary,
+    /// Journal entries in recording order.
+    pub entries: Vec<JournalEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+/// A search match inside a stored session.
+pub struct SessionSearchHit {
+    /// ACP session id.
+    pub session_id: String,
+    /// Session title when known.
+    pub title: Option<String>,
+    /// `prompt`, `message` or `toolCall`.
+    pub source: &'static str,
+    /// Text surrounding the match.
+    pub snippet: String,
+    /// Timestamp of the matching entry.
+    pub ts: u64,
+}
+
+/// On-disk session journal shared by the service and history commands.
+pub struct SessionStore {
+    dir: PathBuf,
+    index: Mutex<HashMap<String, SessionSummary>>,
+    files: Mutex<HashMap<String, File>>,
+}
+
+impl SessionStore {
+    /// Open the store rooted at `dir`, rebuilding the index from journals if needed.
+    pub fn new(dir: PathBuf) -> Self {
+        let index = load_index(&dir).unwrap_or_else(|err| {
+            tracing::warn!(error = %err, "session index unreadable; rebuilding from journals");
+            rebuild_index(&dir)
+        });
+        Self {
+            dir,
+            index: Mutex::new(index),
+            files: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Start journaling a newly created session.
+    pub fn start_session(&self, session_id: &str, cwd: &str, profile: Option<&str>) {
+        let now = now_millis();
+        {
+            let mut guard = self.lock_index();
+            guard.insert(
+                session_id.to_string(),
+                SessionSummary {
+                    session_id: session_id.to_string(),
+                    cwd: cwd.to_string(),
+                    title: None,
+                    created_at: now,
+                    updated_at: now,
+                    prompt_count: 0,
+                    last_stop_reason: None,
+                    profile: profile.map(str::to_string),
+                    worktree: None,
+                },
+            );
+        }
+        self.append(
+            session_id,
+            JournalRecord::Started {
+                cwd: cwd.to_string(),
+                profile: profile.map(str::to_string),
+            },
+        );
+        self.persist_index();
+    }
+
+    /// Continue journaling a session resumed via `session/load`.
+    pub fn resume_session(&self, session_id: &str, cwd: &str, profile: Option<&str>) {
+        let now = now_millis();
+        {
+            let mut guard = self.lock_index();
+            let summary = guard
+                .entry(session_id.to_string())
+                .or_insert_with(|| SessionSummary {
+                    session_id: session_id.to_string(),
+                    cwd: cwd.to_string(),
+                    title: None,
+                    created_at: now,
+                    updated_at: now,
+                    prompt_count: 0,
+                    last_stop_reason: None,
+                    profile: None,
+                    worktree: None,
+                });
+            summary.cwd = cwd.to_string();
+            summary.profile = profile.map(str::to_string);
+            summary.updated_at = now;
+        }
+        self.append(
+            session_id,
+            JournalRecord::Resumed {
+                cwd: cwd.to_string(),
+                profile: profile.map(str::to_string),
+            },
+        );
+        self.persist_index();
+    }
+
+    /// Record a prompt sent to the agent.
+    pub fn record_prompt(&self, session_id: &str, content: &[ContentBlock]) {
+        if !self.update_summary(session_id, |summary| {
+            summary.prompt_count += 1;
+            if summary.title.is_none() {
+                summary.title = prompt_title(content);
+            }
+        }) {
+            return;
+        }
+        self.append(
+            session_id,
+            JournalRecord::Prompt {
+                content: content.to_vec(),
+            },
+        );
+        self.persist_index();
+    }
+
+    /// Record a session update received from the agent.
+    pub fn record_update(&self, session_id: &str, update: &SessionUpdate) {
+        if !self.contains(session_id) {
+            return;
+        }
+        self.append(
+            session_id,
+            JournalRecord::Update {
+                update: Box::new(update.clone()),
+            },
+        );
+    }
+
+    /// Record the outcome of a permission request.
+    pub fn record_approval(
+        &self,
+        session_id: &str,
+        tool_call_id: &str,
+        option_id: Option<&str>,
+        option_kind: Option<PermissionOptionKind>,
+    ) {
+        if !self.contains(session_id) {
+            return;
+        }
+        self.append(
+            session_id,
+            JournalRecord::Approval {
+                tool_call_id: tool_call_id.to_string(),
+                option_id: option_id.map(str::to_string),
+                option_kind,
+            },
+        );
+    }
+
+    /// Record the stop reason of a finished turn.
+    pub fn record_stop(&self, session_id: &str, stop_reason: StopReason) {
+        if !self.update_summary(session_id, |summary| {
+            summary.last_stop_reason = Some(stop_reason);
+        }) {
+            return;
+        }
+        self.append(session_id, JournalRecord::Stop { stop_reason });
+        self.persist_index();
+    }
+
+    /// Attach a git worktree to a session, or detach it with `None` once removed.
+    pub fn set_worktree(&self, session_id: &str, worktree: Option<SessionWorktree>) -> Result<()> {
+        {
+            let mut guard = self.lock_index();
+            let summary = guard
+                .get_mut(session_id)
+                .ok_or_else(|| anyhow!("unknown session: {session_id}"))?;
+            summary.worktree = worktree.clone();
+        }
+        self.append(session_id, JournalRecord::Worktree { worktree });
+        self.persist_index();
+        Ok(())
+    }
+
+    /// Git worktree a stored session runs in.
+    pub fn worktree(&self, session_id: &str) -> Option<SessionWorktree> {
+        self.lock_index()
+            .get(session_id)
+            .and_then(|summary| summary.worktree.clone())
+    }
+
+    /// List stored sessions, most recently updated first.
+    pub fn list(&self) -> Vec<SessionSummary> {
+        let mut sessions: Vec<_> = self.lock_index().values().cloned().collect();
+        sessions.sort_by_key(|summary| std::cmp::Reverse(summary.updated_at));
+        sessions
+    }
+
+    /// Working directory a stored session was started in.
+    pub fn cwd(&self, session_id: &str) -> Option<String> {
+        self.lock_index()
+            .get(session_id)
+            .map(|summary| summary.cwd.clone())
+    }
+
+    /// Environment profile a stored session last ran with.
+    pub fn profile(&self, session_id: &str) -> Option<String> {
+        self.lock_index()
+            .get(session_id)
+            .and_then(|summary| summary.profile.clone())
+    }
+
+    /// Number of prompts recorded for a session, including the one in progress.
+    pub fn prompt_count(&self, session_id: &str) -> Option<u32> {
+        self.lock_index()
+            .get(session_id)
+            .map(|summary| summary.prompt_count)
+    }
+
+    /// Load the full transcript of a stored session.
+    pub fn open(&self, session_id: &str) -> Result<SessionTranscript> {
+        let summary = self
+            .lock_index()
+            .get(session_id)
+            .cloned()
+            .ok_or_else(|| anyhow!("unknown session: {session_id}"))?;
+        let entries = read_journal(&journal_path(&self.dir, session_id)?)?;
+        Ok(SessionTranscript { summary, entries })
+    }
+
+    /// Search prompts, agent messages and tool call titles across stored sessions.
+    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<SessionSearchHit>> {
+        let needle = query.trim().to_lowercase();
+        if needle.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut hits = Vec::new();
+        for summary in self.list() {
+            let path = journal_path(&self.dir, &summary.session_id)?;
+            let entries = match read_journal(&path) {
+                Ok(entries) => entries,
+                Err(err) => {
+                    tracing::warn!(error = %err, path = %path.display(), "skipping unreadable journal");
+                    continue;
+                }
+            };
+            for (source, ts, text) in searchable_segments(&entries) {
+                if let Some(snippet) = match_snippet(&text, &needle) {
+                    hits.push(SessionSearchHit {
+                        session_id: summary.session_id.clone(),
+                        title: summary.title.clone(),
+                        source,
+                        snippet,
+                        ts,
+                    });
+                    if hits.len() >= limit {
+                        return Ok(hits);
+                    }
+                }
+            }
+        }
+        Ok(hits)
+    }
+
+    /// Delete a stored session and its journal.
+    pub fn delete(&self, session_id: &str) -> Result<()> {
+        let path = journal_path(&self.dir, session_id)?;
+        let removed = self.lock_index().remove(session_id).is_some();
+        self.lock_files().remove(session_id);
+        match fs::remove_file(&path) {
+            Ok(()) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound && removed => {}
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Err(anyhow!("unknown session: {session_id}"));
+            }
+            Err(err) => {
+                return Err(err).with_context(|| format!("failed to delete {}", path.display()))
+            }
+        }
+        self.persist_index();
+        Ok(())
+    }
+
+    fn contains(&self, session_id: &str) -> bool {
+        self.lock_index().contains_key(session_id)
+    }
+
+    fn update_summary(&self, session_id: &str, apply: impl FnOnce(&mut SessionSummary)) -> bool {
+        let mut guard = self.lock_index();
+        match guard.get_mut(session_id) {
+            Some(summary) => {
+                apply(summary);
+                summary.updated_at = now_millis();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn append(&self, session_id: &str, record: JournalRecord) {
+        if let Err(err) = self.try_append(session_id, record) {
+            tracing::warn!(error = %err, session_id, "failed to append session journal");
+        }
+    }
+
+    fn try_append(&self, session_id: &str, record: JournalRecord) -> Result<()> {
+        let entry = JournalEntry {
+            ts: now_millis(),
+            record,
+        };
+        let mut line = serde_json::to_string(&entry)?;
+        line.push('\n');
+
+        let mut files = self.lock_files();
+        if !files.contains_key(session_id) {
+            let path = journal_path(&self.dir, session_id)?;
+            fs::create_dir_all(&self.dir)?;
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .with_context(|| format!("failed to open {}", path.display()))?;
+            files.insert(session_id.to_string(), file);
+        }
+        let file = files
+            .get_mut(session_id)
+            .ok_or_else(|| anyhow!("journal handle missing"))?;
+        // One write per line keeps each entry intact if the app dies mid-turn.
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    fn persist_index(&self) {
+        let sessions = self.list();
+        if let Err(err) = write_index(&self.dir, &sessions) {
+            tracing::warn!(error = %err, "failed to write session index");
+        }
+    }
+
+    fn lock_index(&self) -> std::sync::MutexGuard<'_, HashMap<String, SessionSummary>> {
+        self.index
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn lock_files(&self) -> std::sync::MutexGuard<'_, HashMap<String, File>> {
+        self.files
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Map a session id to its journal path, rejecting ids that could escape the store.
+fn journal_path(dir: &Path, session_id: &str) -> Result<PathBuf> {
+    let valid = !session_id.is_empty()
+        && !session_id.starts_with('.')
+        && session_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        return Err(anyhow!("invalid session id: {session_id}"));
+    }
+    Ok(dir.join(format!("{session_id}.{JOURNAL_EXT}")))
+}
+
+fn load_index(dir: &Path) -> Result<HashMap<String, SessionSummary>> {
+    let path = dir.join(INDEX_FILE);
+    if !path.exists() {
+        return Ok(rebuild_index(dir));
+    }
+    let raw = fs::read_to_string(&path)?;
+    let sessions: Vec<SessionSummary> = serde_json::from_str(&raw)?;
+    Ok(sessions
+        .into_iter()
+        .map(|summary| (summary.session_id.clone(), summary))
+        .collect())
+}
+
+fn write_index(dir: &Path, sessions: &[SessionSummary]) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(INDEX_FILE);
+    let tmp = dir.join(format!("{INDEX_FILE}.tmp"));
+    fs::write(&tmp, serde_json::to_vec_pretty(sessions)?)?;
+    fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+fn rebuild_index(dir: &Path) -> HashMap<String, SessionSummary> {
+    let mut index = HashMap::new();
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return index;
+    };
+    for entry in read_dir.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(JOURNAL_EXT) {
+            continue;
+        }
+        let Some(session_id) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        if let Ok(entries) = read_journal(&path) {
+            if let Some(summary) = summarize(session_id, &entries) {
+                index.insert(session_id.to_string(), summary);
+            }
+        }
+    }
+    index
+}
+
+/// Read a journal, skipping a truncated trailing line left by a crash.
+fn read_journal(path: &Path) -> Result<Vec<JournalEntry>> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let mut entries = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(entry) => entries.push(entry),
+            Err(err) => {
+                tracing::warn!(error = %err, path = %path.display(), "skipping malformed journal line")
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// Rebuild a session summary from its journal entries.
+fn summarize(session_id: &str, entries: &[JournalEntry]) -> Option<SessionSummary> {
+    let first = entries.first()?;
+    let mut summary = SessionSummary {
+        session_id: session_id.to_string(),
+        cwd: String::new(),
+        title: None,
+        created_at: first.ts,
+        updated_at: first.ts,
+        prompt_count: 0,
+        last_stop_reason: None,
+        profile: None,
+        worktree: None,
+    };
+    for entry in entries {
+        match &entry.record {
+            JournalRecord::Started { cwd, profile } | JournalRecord::Resumed { cwd, profile } => {
+                summary.cwd = cwd.clone();
+                summary.profile = profile.clone();
+                summary.updated_at = entry.ts;
+            }
+            JournalRecord::Prompt { content } => {
+                summary.prompt_count += 1;
+                if summary.title.is_none() {
+                    summary.title = prompt_title(content);
+                }
+                summary.updated_at = entry.ts;
+            }
+            JournalRecord::Stop { stop_reason } => {
+                summary.last_stop_reason = Some(*stop_reason);
+                summary.updated_at = entry.ts;
+            }
+            JournalRecord::Worktree { worktree } => {
+                summary.worktree = worktree.clone();
+            }
+            JournalRecord::Update { .. } | JournalRecord::Approval { .. } => {}
+        }
+    }
+    Some(summary)
+}
+
+/// First line of the first non-empty text block, truncated for display.
+pub fn prompt_title(content: &[ContentBlock]) -> Option<String> {
+    let text = content
+        .iter()
+        .filter_map(content_block_text)
+        .map(str::trim)
+        .find(|text| !text.is_empty())?;
+    let line = text.lines().next().unwrap_or(text);
+    let mut title: String = line.chars().take(TITLE_MAX_CHARS).collect();
+    if line.chars().count() > TITLE_MAX_CHARS {
+        title.push('…');
+    }
+    Some(title)
+}
+
+/// Collapse journal entries into searchable text segments.
+///
+/// Consecutive agent message chunks are joined so a query can match across chunk boundaries.
+fn searchable_segments(entries: &[JournalEntry]) -> Vec<(&'static str, u64, String)> {
+    let mut segments = Vec::new();
+    let mut message: Option<(u64, String)> = None;
+
+    for entry in entries {
+        let update = match &entry.record {
+            JournalRecord::Update { update } => Some(update.as_ref()),
+            _ => None,
+        };
+        if let Some(SessionUpdate::AgentMessageChunk(chunk)) = update {
+            if let Some(text) = content_block_text(&chunk.content) {
+                message
+                    .get_or_insert_with(|| (entry.ts, String::new()))
+                    .1
+                    .push_str(text);
+            }
+            continue;
+        }
+
+        if let Some((ts, text)) = message.take() {
+            segments.push(("message", ts, text));
+        }
+        if let JournalRecord::Prompt { content } = &entry.record {
+            let text: Vec<&str> = content.iter().filter_map(content_block_text).collect();
+            segments.push(("prompt", entry.ts, text.join("\n")));
+        } else if let Some(SessionUpdate::ToolCall(tool_call)) = update {
+            segments.push(("toolCall", entry.ts, tool_call.title.clone()));
+        }
+    }
+    if let Some((ts, text)) = message.take() {
+        segments.push(("message", ts, text));
+    }
+    segments
+}
+
+/// Return a snippet around the first case-insensitive match of `needle` (already lowercase).
+fn match_snippet(text: &str, needle: &str) -> Option<String> {
+    let lower = text.to_lowercase();
+    let byte_pos = lower.find(needle)?;
+    let start_char = lower[..byte_pos].chars().count();
+    let needle_chars = needle.chars().count();
+
+    let chars: Vec<char> = text.chars().collect();
+    let from = start_char.saturating_sub(SNIPPET_CONTEXT_CHARS);
+    let to = (start_char + needle_chars + SNIPPET_CONTEXT_CHARS).min(chars.len());
+    let from = from.min(to);
+
+    let mut snippet = String::new();
+    if from > 0 {
+        snippet.push('…');
+    }
+    snippet.extend(
+        chars[from..to]
+            .iter()
+            .map(|c| if *c == '\n' { ' ' } else { *c }),
+    );
+    if to < chars.len() {
+        snippet.push('…');
+    }
+    Some(snippet)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agent_client_protocol::{ContentChunk, TextContent};
+
+    fn text(value: &str) -> ContentBlock {
+        ContentBlock::Text(TextContent::new(value))
+    }
+
+    #[test]
+    fn test_match_snippet() {
+        assert_eq!(
+            match_snippet("Hello World", "world"),
+            Some("Hello World".into())
+        );
+        assert_eq!(match_snippet("Hello World", "moon"), None);
+
+        let long = format!("{}needle{}", "a".repeat(60), "b".repeat(60));
+        let snippet = match_snippet(&long, "needle").unwrap();
+        assert!(snippet.starts_with('…') && snippet.ends_with('…'));
+        assert!(snippet.contains("needle"));
+    }
+
+    #[test]
+    fn test_journal_path_rejects_traversal() {
+        let dir = Path::new("/tmp/store");
+        assert!(journal_path(dir, "0199-abc_def").is_ok());
+        assert!(journal_path(dir, "../escape").is_err());
+        assert!(journal_path(dir, "a/b").is_err());
+        assert!(journal_path(dir, "").is_err());
+    }
+
+    #[test]
+    fn test_store_round_trip() {
+        let dir = std::env::temp_dir().join(format!("codex-history-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let store = SessionStore::new(dir.clone());
+        store.start_session("s1", "/repo", None);
+        store.record_prompt("s1", &[text("Fix the flaky parser test")]);
+        for chunk in ["The parser ", "is fixed."] {
+            store.record_update(
+                "s1",
+                &SessionUpdate::AgentMessageChunk(ContentChunk::new(text(chunk))),
+            );
+        }
+        store.record_stop("s1", StopReason::EndTurn);
+        let worktree = SessionWorktree {
+            repo_root: "/repo".into(),
+            path: "/worktrees/repo-codex".into(),
+            branch: "codex/s1".into(),
+            base: "abc123".into(),
+            merged: None,
+        };
+        store.set_worktree("s1", Some(worktree.clone())).unwrap();
+        assert!(store.set_worktree("other", None).is_err());
+        // Sessions that were never started are not journaled.
+        store.record_prompt("other", &[text("ignored")]);
+
+        let transcript = store.open("s1").unwrap();
+        assert_eq!(transcript.entries.len(), 6);
+        assert_eq!(transcript.summary.prompt_count, 1);
+        assert_eq!(
+            transcript.summary.title.as_deref(),
+            Some("Fix the flaky parser test")
+        );
+
+        let hits = store.search("parser is", 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].source, "message");
+
+        // A fresh store rebuilds the same summaries from disk.
+        drop(store);
+        fs::remove_file(dir.join(INDEX_FILE)).unwrap();
+        let reopened = SessionStore::new(dir.clone());
+        let sessions = reopened.list();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].last_stop_reason, Some(StopReason::EndTurn));
+        assert_eq!(reopened.worktree("s1"), Some(worktree));
+        reopened.set_worktree("s1", None).unwrap();
+        assert_eq!(reopened.worktree("s1"), None);
+
+        reopened.delete("s1").unwrap();
+        assert!(reopened.list().is_empty());
+        assert!(reopened.open("s1").is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,419 @@
+//! `@path` and `@path:10-40` file mentions in prompt text.
+//!
+//! Mentions are resolved against the session cwd (locally or over SSH for remote
+//! sessions) and must stay inside it. They are attached to the prompt as embedded
+//! resources, or as resource links when the agent cannot take embedded context or
+//! the size budget is exhausted.
+
+use crate::codex::{
+    fs::{normalize_path, slice_lines},
+    prompt::file_uri,
+    util::content_block_text,
+};
+use crate::remote::{ssh_process::read_remote_file_head, RemoteServerConfig};
+use agent_client_protocol::{
+    ContentBlock, EmbeddedResource, EmbeddedResourceResource, ResourceLink, TextResourceContents,
+};
+use anyhow::{anyhow, Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+/// Total bytes of file content embedded into a single prompt.
+const MENTION_BUDGET_BYTES: usize = 256 * 1024;
+/// Largest prefix of a mentioned file that is read to extract its content.
+const MENTION_READ_LIMIT: u64 = 2 * 1024 * 1024;
+/// Bytes inspected for NUL when detecting binary files.
+const BINARY_SNIFF_BYTES: usize = 8 * 1024;
+
+/// A file mention parsed from prompt text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mention {
+    /// Token as typed, including the leading `@`.
+    pub token: String,
+    /// Mentioned path, absolute or relative to the session cwd.
+    pub path: String,
+    /// Optional inclusive 1-based line range.
+    pub lines: Option<(u32, u32)>,
+}
+
+/// How a mentioned file was attached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum AttachmentStatus {
+    /// File content was embedded in the prompt.
+    Embedded,
+    /// Only a resource link was attached.
+    Linked,
+    /// The file looked binary and was skipped.
+    Binary,
+    /// The file could not be read and was skipped.
+    Missing,
+}
+
+/// Result of resolving one mention, reported back to the UI.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MentionAttachment {
+    /// Token as typed, including the leading `@`.
+    pub token: String,
+    /// Resolved agent-side path.
+    pub path: String,
+    /// Inclusive 1-based line range, if one was requested.
+    pub lines: Option<(u32, u32)>,
+    /// How the file was attached.
+    pub status: AttachmentStatus,
+    /// Embedded content size in bytes.
+    pub bytes: usize,
+    /// Why the file was linked or skipped instead of embedded.
+    pub reason: Option<String>,
+}
+
+/// Where mentioned files are read from.
+#[derive(Debug, Clone)]
+pub struct MentionContext {
+    /// Agent-side session working directory.
+    pub cwd: PathBuf,
+    /// Remote server hosting the session, if any.
+    pub remote: Option<RemoteServerConfig>,
+    /// Whether the agent accepts embedded resources.
+    pub embed: bool,
+}
+
+/// Parse `@path` and `@path:start-end` tokens out of prompt text.
+pub fn parse_mentions(text: &str) -> Vec<Mention> {
+    let mut mentions: Vec<Mention> = Vec::new();
+    for word in text.split_whitespace() {
+        let Some(raw) = word.strip_prefix('@') else {
+            continue;
+        };
+        let raw = raw.trim_end_matches([',', ';', '.', '!', '?', ')', '"', '\'']);
+        let (path, lines) = match raw.rsplit_once(':') {
+            Some((path, range)) => match parse_line_range(range) {
+                Some(lines) => (path, Some(lines)),
+                None => (raw, None),
+            },
+            None => (raw, None),
+        };
+        if path.is_empty() || path.ends_with(':') {
+            continue;
+        }
+        let mention = Mention {
+            token: format!("@{raw}"),
+            path: path.to_string(),
+            lines,
+        };
+        if !mentions.contains(&mention) {
+            mentions.push(mention);
+        }
+    }
+    mentions
+}
+
+fn parse_line_range(range: &str) -> Option<(u32, u32)> {
+    let (start, end) = match range.split_once('-') {
+        Some((start, end)) => (start.parse().ok()?, end.parse().ok()?),
+        None => {
+            let line = range.parse().ok()?;
+            (line, line)
+        }
+    };
+    (start >= 1 && end >= start).then_some((start, end))
+}
+
+/// Resolve mentions found in the prompt's text blocks and append them as attachments.
+pub async fn attach_mentions(
+    mut blocks: Vec<ContentBlock>,
+    ctx: &MentionContext,
+) -> (Vec<ContentBlock>, Vec<MentionAttachment>) {
+    let mut mentions = Vec::new();
+    for block in &blocks {
+        if let Some(text) = content_block_text(block) {
+            for mention in parse_mentions(text) {
+                if !mentions.contains(&mention) {
+                    mentions.push(mention);
+                }
+            }
+        }
+    }
+
+    let mut budget = MENTION_BUDGET_BYTES;
+    let mut attachments = Vec::with_capacity(mentions.len());
+    for mention in mentions {
+        let (block, attachment) = resolve_mention(mention, ctx, &mut budget).await;
+        if let Some(block) = block {
+            blocks.push(block);
+        }
+        attachments.push(attachment);
+    }
+    (blocks, attachments)
+}
+
+async fn resolve_mention(
+    mention: Mention,
+    ctx: &MentionContext,
+    budget: &mut usize,
+) -> (Option<ContentBlock>, MentionAttachment) {
+    let path = match resolve_path(&ctx.cwd, &mention.path) {
+        Ok(path) => path,
+        Err(err) => {
+            let attachment = MentionAttachment {
+                token: mention.token,
+                path: mention.path,
+                lines: mention.lines,
+                status: AttachmentStatus::Missing,
+                bytes: 0,
+                reason: Some(err.to_string()),
+            };
+            return (None, attachment);
+        }
+    };
+    let mut uri = file_uri(&path);
+    if let Some((start, end)) = mention.lines {
+        uri.push_str(&format!("#L{start}-{end}"));
+    }
+    let mut attachment = MentionAttachment {
+        token: mention.token,
+        path: path.display().to_string(),
+        lines: mention.lines,
+        status: AttachmentStatus::Linked,
+        bytes: 0,
+        reason: None,
+    };
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| attachment.path.clone());
+    let link = ContentBlock::ResourceLink(ResourceLink::new(name, uri.clone()));
+
+    let (bytes, complete) = match read_head(ctx, &path).await {
+        Ok(read) => read,
+        Err(err) => {
+            attachment.status = AttachmentStatus::Missing;
+            attachment.reason = Some(err.to_string());
+            return (None, attachment);
+        }
+    };
+
+    let text = match decode_text(bytes) {
+        Some(text) => text,
+        None => {
+            attachment.status = AttachmentStatus::Binary;
+            return (None, attachment);
+        }
+    };
+
+    if !ctx.embed {
+        attachment.reason = Some("agent does not accept embedded context".to_string());
+        return (Some(link), attachment);
+    }
+
+    let content = match mention.lines {
+        Some((start, end)) => slice_lines(&text, Some(start), Some(end - start + 1)),
+        None if !complete => {
+            attachment.reason = Some("file is too large to embed".to_string());
+            return (Some(link), attachment);
+        }
+        None => text,
+    };
+    if content.len() > *budget {
+        attachment.reason = Some("attachment size budget exhausted".to_string());
+        return (Some(link), attachment);
+    }
+
+    *budget -= content.len();
+    attachment.status = AttachmentStatus::Embedded;
+    attachment.bytes = content.len();
+    let resource =
+        EmbeddedResourceResource::TextResourceContents(TextResourceContents::new(content, uri));
+    (
+        Some(ContentBlock::Resource(EmbeddedResource::new(resource))),
+        attachment,
+    )
+}
+
+/// Resolve a mentioned path against the session cwd, rejecting paths outside it.
+fn resolve_path(cwd: &Path, path: &str) -> Result<PathBuf> {
+    let resolved = normalize_path(&cwd.join(path));
+    if !resolved.starts_with(normalize_path(cwd)) {
+        return Err(outside_cwd(Path::new(path)));
+    }
+    Ok(resolved)
+}
+
+fn outside_cwd(path: &Path) -> anyhow::Error {
+    anyhow!(
+        "path is outside the session working directory: {}",
+        path.display()
+    )
+}
+
+/// Read up to `MENTION_READ_LIMIT` bytes; the flag reports whether the whole file was read.
+async fn read_head(ctx: &MentionContext, path: &Path) -> Result<(Vec<u8>, bool)> {
+    let mut bytes = match &ctx.remote {
+        Some(server) => {
+            read_remote_file_head(server, &path.to_string_lossy(), MENTION_READ_LIMIT + 1).await?
+        }
+        None => {
+            let cwd = ctx.cwd.clone();
+            let path = path.to_path_buf();
+            tokio::task::spawn_blocking(move || read_local_head(&cwd, &path))
+                .await
+                .context("file read task failed")??
+        }
+    };
+    let complete = bytes.len() as u64 <= MENTION_READ_LIMIT;
+    bytes.truncate(MENTION_READ_LIMIT as usize);
+    Ok((bytes, complete))
+}
+
+fn read_local_head(cwd: &Path, path: &Path) -> Result<Vec<u8>> {
+    // Follow symlinks before checking, so a link in the cwd cannot point outside it.
+    let root = std::fs::canonicalize(cwd).unwrap_or_else(|_| cwd.to_path_buf());
+    let resolved =
+        std::fs::canonicalize(path).with_context(|| format!("cannot read {}", path.display()))?;
+    if !resolved.starts_with(&root) {
+        return Err(outside_cwd(path));
+    }
+    if !resolved.is_file() {
+        return Err(anyhow!("not a file: {}", path.display()));
+    }
+    let file = std::fs::File::open(&resolved)
+        .with_context(|| format!("cannot read {}", path.display()))?;
+    let mut bytes = Vec::new();
+    file.take(MENTION_READ_LIMIT + 1)
+        .read_to_end(&mut bytes)
+        .with_context(|| format!("cannot read {}", path.display()))?;
+    Ok(bytes)
+}
+
+/// Decode file bytes as text, returning `None` for binary content.
+fn decode_text(bytes: Vec<u8>) -> Option<String> {
+    let sniff = &bytes[..bytes.len().min(BINARY_SNIFF_BYTES)];
+    if sniff.contains(&0) {
+        return None;
+    }
+    match String::from_utf8(bytes) {
+        Ok(text) => Some(text),
+        // A read cut off mid-character is still text; keep the valid prefix.
+        Err(err) if err.utf8_error().error_len().is_none() => {
+            let valid = err.utf8_error().valid_up_to();
+            let mut bytes = err.into_bytes();
+            bytes.truncate(valid);
+            String::from_utf8(bytes).ok()
+        }
+        Err(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mentions() {
+        let mentions =
+            parse_mentions("see @src/main.rs:10-40, and @README.md. mail me@example.com @ @:3");
+        assert_eq!(
+            mentions,
+            vec![
+                Mention {
+                    token: "@src/main.rs:10-40".to_string(),
+                    path: "src/main.rs".to_string(),
+                    lines: Some((10, 40)),
+                },
+                Mention {
+                    token: "@README.md".to_string(),
+                    path: "README.md".to_string(),
+                    lines: None,
+                },
+            ]
+        );
+
+        let single = parse_mentions("@lib.rs:7 @lib.rs:9-3");
+        assert_eq!(single[0].lines, Some((7, 7)));
+        assert_eq!(single[1].path, "lib.rs:9-3");
+    }
+
+    #[test]
+    fn test_decode_text_skips_binary() {
+        assert_eq!(decode_text(b"hello".to_vec()).as_deref(), Some("hello"));
+        assert_eq!(decode_text(vec![0x89, b'P', 0, 0]), None);
+        // Truncated multi-byte sequence at the end of a partial read.
+        assert_eq!(decode_text(vec![b'a', 0xE2, 0x82]).as_deref(), Some("a"));
+    }
+
+    #[tokio::test]
+    async fn test_attach_mentions_embeds_and_links() {
+        let root = std::env::temp_dir().join(format!("codex-mentions-test-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("notes.txt"), "one\ntwo\nthree\n").unwrap();
+        std::fs::write(root.join("logo.png"), [0x89, b'P', b'N', b'G', 0, 0]).unwrap();
+
+        let ctx = MentionContext {
+            cwd: root.clone(),
+            remote: None,
+            embed: true,
+        };
+        let prompt = vec![ContentBlock::from(
+            "explain @notes.txt:2-3 and @logo.png and @gone.rs",
+        )];
+        let (blocks, attachments) = attach_mentions(prompt, &ctx).await;
+
+        assert_eq!(blocks.len(), 2);
+        match &blocks[1] {
+            ContentBlock::Resource(resource) => match &resource.resource {
+                EmbeddedResourceResource::TextResourceContents(text) => {
+                    assert_eq!(text.text, "two\nthree\n");
+                    assert!(text.uri.ends_with("notes.txt#L2-3"));
+                }
+                other => panic!("unexpected resource: {other:?}"),
+            },
+            other => panic!("unexpected block: {other:?}"),
+        }
+        let statuses: Vec<_> = attachments.iter().map(|a| a.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                AttachmentStatus::Embedded,
+                AttachmentStatus::Binary,
+                AttachmentStatus::Missing
+            ]
+        );
+
+        let ctx = MentionContext {
+            embed: false,
+            ..ctx
+        };
+        let (blocks, attachments) =
+            attach_mentions(vec![ContentBlock::from("@notes.txt")], &ctx).await;
+        assert!(matches!(blocks[1], ContentBlock::ResourceLink(_)));
+        assert_eq!(attachments[0].status, AttachmentStatus::Linked);
+
+        // Nothing outside the cwd is read, whether named directly or through a link.
+        let outside = root.with_extension("outside");
+        std::fs::write(&outside, "secret").unwrap();
+        let name = outside.file_name().unwrap().to_string_lossy();
+        let prompt = format!("@{} @../{name}", outside.display());
+        #[cfg(unix)]
+        let prompt = {
+            std::os::unix::fs::symlink(&outside, root.join("link.txt")).unwrap();
+            prompt + " @link.txt"
+        };
+        let expected = if cfg!(unix) { 3 } else { 2 };
+        let (blocks, attachments) = attach_mentions(vec![ContentBlock::from(prompt)], &ctx).await;
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(attachments.len(), expected);
+        for attachment in &attachments {
+            assert_eq!(attachment.status, AttachmentStatus::Missing);
+            let reason = attachment.reason.as_deref().unwrap();
+            assert!(reason.contains("outside the session"), "{reason}");
+        }
+
+        let _ = std::fs::remove_file(&outside);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -1,14 +1,28 @@
 //! Core Codex backend modules used by the Tauri application.
 
+pub mod agent_log;
 pub mod binary;
+pub mod coalesce;
 pub mod commands;
 pub mod debug;
 pub mod events;
+pub mod fs;
+pub mod history;
+pub mod mentions;
+pub mod policy;
 pub mod process;
+pub mod profiles;
+pub mod prompt;
 pub mod protocol;
+pub mod recorder;
 pub mod remote_session;
+pub mod scheduler;
 pub mod service;
 pub mod thoughts;
+pub mod timeline;
 pub mod types;
+pub mod typescript;
 pub mod unified_process;
+pub mod usage;
 pub mod util;
+pub mod worktrees;
@@ -0,0 +1,636 @@
+//! User-defined approval rules evaluated before permission requests reach the UI.
+//!
+//! Rules are kept in `approval-rules.json` and checked in order; the first enabled
+//! rule that matches decides. Every automatic decision is appended to an audit log.
+
+use crate::codex::fs::normalize_path;
+use crate::codex::util::{load_json, now_millis, save_json, unique_id};
+use agent_client_protocol::{PermissionOption, PermissionOptionKind, ToolCallUpdate, ToolKind};
+use anyhow::{anyhow, Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+/// What to do with a permission request matched by a rule.
+pub enum RuleAction {
+    /// Select an allow option without asking.
+    Allow,
+    /// Select a reject option without asking.
+    Reject,
+    /// Forward the request to the UI, skipping later rules.
+    Ask,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// A persisted approval rule. Empty criteria match everything.
+pub struct ApprovalRule {
+    /// Stable rule identifier.
+    #[serde(default)]
+    pub id: String,
+    /// Human-readable label.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Disabled rules are kept but never matched.
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Tool kinds the rule applies to.
+    #[serde(default)]
+    pub tool_kinds: Vec<ToolKind>,
+    /// Regex matched against the tool call title.
+    #[serde(default)]
+    pub title_regex: Option<String>,
+    /// Regex matched against the command line of execute tools.
+    #[serde(default)]
+    pub command_regex: Option<String>,
+    /// Globs every touched path must match; relative globs are rooted at the session cwd.
+    #[serde(default)]
+    pub path_globs: Vec<String>,
+    /// Restrict the rule to a single session.
+    #[serde(default)]
+    pub session_id: Option<String>,
+    /// Action taken when the rule matches.
+    pub action: RuleAction,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Clone)]
+/// Rule with its patterns compiled.
+struct CompiledRule {
+    rule: ApprovalRule,
+    title: Option<regex::Regex>,
+    command: Option<regex::Regex>,
+    paths: Vec<glob::Pattern>,
+}
+
+impl CompiledRule {
+    fn compile(rule: ApprovalRule) -> Result<Self> {
+        let regex = |pattern: &Option<String>| {
+            pattern
+                .as_deref()
+                .map(regex::Regex::new)
+                .transpose()
+                .with_context(|| format!("invalid regex in rule {}", rule.id))
+        };
+        let title = regex(&rule.title_regex)?;
+        let command = regex(&rule.command_regex)?;
+        let paths = rule
+            .path_globs
+            .iter()
+            .map(|pattern| glob::Pattern::new(pattern))
+            .collect::<Result<_, _>>()
+            .with_context(|| format!("invalid path glob in rule {}", rule.id))?;
+        Ok(Self {
+            rule,
+            title,
+            command,
+            paths,
+        })
+    }
+
+    fn matches(&self, request: &PermissionSubject<'_>) -> bool {
+        let rule = &self.rule;
+        if !rule.enabled {
+            return false;
+        }
+        if rule
+            .session_id
+            .as_deref()
+            .is_some_and(|id| id != request.session_id)
+        {
+            return false;
+        }
+        if !rule.tool_kinds.is_empty()
+            && !request
+                .kind
+                .is_some_and(|kind| rule.tool_kinds.contains(&kind))
+        {
+            return false;
+        }
+        if let Some(title) = &self.title {
+            if !request.title.is_some_and(|text| title.is_match(text)) {
+                return false;
+            }
+        }
+        if let Some(command) = &self.command {
+            if !request
+                .command
+                .as_deref()
+                .is_some_and(|text| command.is_match(text))
+            {
+                return false;
+            }
+        }
+        if !self.paths.is_empty() {
+            if request.paths.is_empty() {
+                return false;
+            }
+            return request
+                .paths
+                .iter()
+                .all(|path| self.path_matches(path, request.cwd));
+        }
+        true
+    }
+
+    fn path_matches(&self, path: &Path, cwd: Option<&Path>) -> bool {
+        let options = glob::MatchOptions {
+            case_sensitive: true,
+            require_literal_separator: true,
+            require_literal_leading_dot: false,
+        };
+        let relative = cwd.and_then(|cwd| path.strip_prefix(cwd).ok());
+        self.paths.iter().any(|pattern| {
+            if Path::new(pattern.as_str()).is_absolute() {
+                pattern.matches_path_with(path, options)
+            } else {
+                relative.is_some_and(|relative| pattern.matches_path_with(relative, options))
+            }
+        })
+    }
+}
+
+/// The parts of a permission request that rules match against.
+#[derive(Debug)]
+pub struct PermissionSubject<'a> {
+    /// ACP session id.
+    pub session_id: &'a str,
+    /// Session working directory, when known locally.
+    pub cwd: Option<&'a Path>,
+    /// Tool kind reported by the agent.
+    pub kind: Option<ToolKind>,
+    /// Tool call title.
+    pub title: Option<&'a str>,
+    /// Command line for execute tools.
+    pub command: Option<String>,
+    /// Paths the tool call touches.
+    pub paths: Vec<PathBuf>,
+}
+
+impl<'a> PermissionSubject<'a> {
+    /// Extract the matchable fields from a permission request's tool call.
+    pub fn from_tool_call(
+        session_id: &'a str,
+        cwd: Option<&'a Path>,
+        tool_call: &'a ToolCallUpdate,
+    ) -> Self {
+        let fields = &tool_call.fields;
+        let mut paths: Vec<PathBuf> = fields
+            .locations
+            .iter()
+            .flatten()
+            .map(|location| location.path.clone())
+            .collect();
+        if paths.is_empty() {
+            if let Some(path) = fields
+                .raw_input
+                .as_ref()
+                .and_then(|input| input.get("path"))
+                .and_then(|path| path.as_str())
+            {
+                paths.push(PathBuf::from(path));
+            }
+        }
+        // Normalize so `..` segments cannot satisfy a glob rooted inside the cwd.
+        let paths = paths
+            .into_iter()
+            .map(|path| match cwd {
+                Some(cwd) if path.is_relative() => normalize_path(&cwd.join(path)),
+                _ => normalize_path(&path),
+            })
+            .collect();
+
+        Self {
+            session_id,
+            cwd,
+            kind: fields.kind,
+            title: fields.title.as_deref(),
+            command: fields.raw_input.as_ref().and_then(command_line),
+            paths,
+        }
+    }
+}
+
+/// Shells whose `-c` style wrapper is unwrapped to match the script itself.
+const SHELLS: [&str; 7] = ["sh", "bash", "zsh", "dash", "fish", "pwsh", "powershell"];
+/// Flags that make a shell run the next argument as a script.
+const SCRIPT_FLAGS: [&str; 3] = ["-c", "-lc", "-Command"];
+
+/// Extract a command line from a tool call's raw input (`command` as string or argv).
+fn command_line(raw_input: &serde_json::Value) -> Option<String> {
+    match raw_input.get("command")? {
+        serde_json::Value::String(command) => Some(command.clone()),
+        serde_json::Value::Array(argv) => {
+            let parts: Vec<&str> = argv.iter().filter_map(|arg| arg.as_str()).collect();
+            // `bash -lc "<script>"` is how agents wrap shell commands; match the script itself.
+            match parts.as_slice() {
+                [shell, flag, script] if is_shell(shell) && SCRIPT_FLAGS.contains(flag) => {
+                    Some(script.to_string())
+                }
+                _ => Some(parts.join(" ")),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Whether `program` (a name or path, optionally with `.exe`) is a known shell.
+fn is_shell(program: &str) -> bool {
+    let name = program.rsplit(['/', '\\']).next().unwrap_or(program);
+    let name = name.to_ascii_lowercase();
+    let name = name.strip_suffix(".exe").unwrap_or(&name);
+    SHELLS.contains(&name)
+}
+
+/// An automatic decision made by a rule.
+#[derive(Debug, Clone)]
+pub struct AutoDecision {
+    /// Rule that matched.
+    pub rule_id: String,
+    /// Action the rule requested.
+    pub action: RuleAction,
+    /// Option selected on the agent's behalf.
+    pub option: PermissionOption,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+/// One line of the approval audit log.
+pub struct ApprovalAuditEntry {
+    /// Milliseconds since the Unix epoch.
+    pub ts: u64,
+    /// ACP session id.
+    pub session_id: String,
+    /// Tool call id.
+    pub tool_call_id: String,
+    /// Rule that decided.
+    pub rule_id: String,
+    /// Action taken.
+    pub action: RuleAction,
+    /// Option selected.
+    pub option_id: String,
+    /// Kind of the selected option.
+    pub option_kind: PermissionOptionKind,
+    /// Tool kind of the request.
+    pub tool_kind: Option<ToolKind>,
+    /// Tool call title.
+    pub title: Option<String>,
+    /// Command line, for execute tools.
+    pub command: Option<String>,
+    /// Paths touched by the tool call.
+    pub paths: Vec<String>,
+}
+
+/// Persistent approval rules and audit log.
+pub struct ApprovalPolicy {
+    rules_path: PathBuf,
+    audit_path: PathBuf,
+    rules: Mutex<Vec<CompiledRule>>,
+}
+
+impl ApprovalPolicy {
+    /// Load rules from `rules_path`; invalid rules are skipped with a warning.
+    pub fn new(rules_path: PathBuf, audit_path: PathBuf) -> Self {
+        let rules = match load_rules(&rules_path) {
+            Ok(rules) => rules
+                .into_iter()
+                .filter_map(|rule| match CompiledRule::compile(rule) {
+                    Ok(rule) => Some(rule),
+                    Err(err) => {
+                        tracing::warn!(error = %err, "skipping invalid approval rule");
+                        None
+                    }
+                })
+                .collect(),
+            Err(err) => {
+                tracing::warn!(error = %err, "failed to load approval rules");
+                Vec::new()
+            }
+        };
+        Self {
+            rules_path,
+            audit_path,
+            rules: Mutex::new(rules),
+        }
+    }
+
+    /// Return all rules in evaluation order.
+    pub fn list(&self) -> Vec<ApprovalRule> {
+        snapshot(&self.lock_rules())
+    }
+
+    /// Insert or replace a rule by id. New rules are appended unless `index` is given.
+    pub fn upsert(&self, mut rule: ApprovalRule, index: Option<usize>) -> Result<ApprovalRule> {
+        let mut guard = self.lock_rules();
+        if rule.id.trim().is_empty() {
+            rule.id = unique_id("rule", |id| {
+                guard.iter().any(|existing| existing.rule.id == id)
+            });
+        }
+        let compiled = CompiledRule::compile(rule.clone())?;
+
+        // An unsaved rule must not start approving anything.
+        let mut rules = guard.clone();
+        match rules
+            .iter()
+            .position(|existing| existing.rule.id == rule.id)
+        {
+            Some(pos) if index.is_none() => rules[pos] = compiled,
+            existing => {
+                if let Some(pos) = existing {
+                    rules.remove(pos);
+                }
+                let index = index.unwrap_or(rules.len()).min(rules.len());
+                rules.insert(index, compiled);
+            }
+        }
+        save_rules(&self.rules_path, &snapshot(&rules))?;
+        *guard = rules;
+        Ok(rule)
+    }
+
+    /// Delete a rule by id.
+    pub fn remove(&self, id: &str) -> Result<()> {
+        let mut guard = self.lock_rules();
+        let mut rules = guard.clone();
+        rules.retain(|compiled| compiled.rule.id != id);
+        if rules.len() == guard.len() {
+            return Err(anyhow!("unknown approval rule: {id}"));
+        }
+        save_rules(&self.rules_path, &snapshot(&rules))?;
+        *guard = rules;
+        Ok(())
+    }
+
+    /// Decide a permission request, or return `None` to ask the user.
+    pub fn evaluate(
+        &self,
+        subject: &PermissionSubject<'_>,
+        options: &[PermissionOption],
+    ) -> Option<AutoDecision> {
+        let guard = self.lock_rules();
+        let rule = guard.iter().find(|compiled| compiled.matches(subject))?;
+        let preferred: &[PermissionOptionKind] = match rule.rule.action {
+            RuleAction::Ask => return None,
+            RuleAction::Allow => &[
+                PermissionOptionKind::AllowOnce,
+                PermissionOptionKind::AllowAlways,
+            ],
+            RuleAction::Reject => &[
+                PermissionOptionKind::RejectOnce,
+                PermissionOptionKind::RejectAlways,
+            ],
+        };
+        let option = preferred
+            .iter()
+            .find_map(|kind| options.iter().find(|option| option.kind == *kind))?;
+        Some(AutoDecision {
+            rule_id: rule.rule.id.clone(),
+            action: rule.rule.action,
+            option: option.clone(),
+        })
+    }
+
+    /// Append an automatic decision to the audit log.
+    pub fn audit(
+        &self,
+        subject: &PermissionSubject<'_>,
+        tool_call_id: &str,
+        decision: &AutoDecision,
+    ) -> ApprovalAuditEntry {
+        let entry = ApprovalAuditEntry {
+            ts: now_millis(),
+            session_id: subject.session_id.to_string(),
+            tool_call_id: tool_call_id.to_string(),
+            rule_id: decision.rule_id.clone(),
+            action: decision.action,
+            option_id: decision.option.option_id.0.to_string(),
+            option_kind: decision.option.kind,
+            tool_kind: subject.kind,
+            title: subject.title.map(str::to_string),
+            command: subject.command.clone(),
+            paths: subject
+                .paths
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect(),
+        };
+        if let Err(err) = append_audit(&self.audit_path, &entry) {
+            tracing::warn!(error = %err, "failed to write approval audit log");
+        }
+        entry
+    }
+
+    /// Read the most recent `limit` audit entries, oldest first.
+    pub fn read_audit(&self, limit: usize) -> Result<Vec<ApprovalAuditEntry>> {
+        let file = match fs::File::open(&self.audit_path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+        let mut entries = std::collections::VecDeque::with_capacity(limit.min(1024));
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            let Ok(entry) = serde_json::from_str(&line) else {
+                continue;
+            };
+            if entries.len() == limit {
+                entries.pop_front();
+            }
+            if limit > 0 {
+                entries.push_back(entry);
+            }
+        }
+        Ok(entries.into())
+    }
+
+    fn lock_rules(&self) -> std::sync::MutexGuard<'_, Vec<CompiledRule>> {
+        self.rules
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn snapshot(rules: &[CompiledRule]) -> Vec<ApprovalRule> {
+    rules.iter().map(|compiled| compiled.rule.clone()).collect()
+}
+
+fn load_rules(path: &Path) -> Result<Vec<ApprovalRule>> {
+    Ok(load_json(path)?.unwrap_or_default())
+}
+
+fn save_rules(path: &Path, rules: &[ApprovalRule]) -> Result<()> {
+    save_json(path, &rules, false)
+}
+
+fn append_audit(path: &Path, entry: &ApprovalAuditEntry) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agent_client_protocol::{ToolCallLocation, ToolCallUpdateFields};
+
+    fn rule(action: RuleAction) -> ApprovalRule {
+        ApprovalRule {
+            id: "r1".to_string(),
+            name: None,
+            enabled: true,
+            tool_kinds: Vec::new(),
+            title_regex: None,
+            command_regex: None,
+            path_globs: Vec::new(),
+            session_id: None,
+            action,
+        }
+    }
+
+    fn subject<'a>(cwd: &'a Path, command: Option<&str>, paths: &[&str]) -> PermissionSubject<'a> {
+        PermissionSubject {
+            session_id: "s1",
+            cwd: Some(cwd),
+            kind: Some(ToolKind::Execute),
+            title: Some("Run tests"),
+            command: command.map(str::to_string),
+            paths: paths.iter().map(PathBuf::from).collect(),
+        }
+    }
+
+    #[test]
+    fn test_rule_matching() {
+        let cwd = Path::new("/repo");
+
+        let mut by_command = rule(RuleAction::Allow);
+        by_command.tool_kinds = vec![ToolKind::Execute];
+        by_command.command_regex = Some(r"^cargo (test|check)\b".to_string());
+        let compiled = CompiledRule::compile(by_command).unwrap();
+        assert!(compiled.matches(&subject(cwd, Some("cargo test -p core"), &[])));
+        assert!(!compiled.matches(&subject(cwd, Some("rm -rf target"), &[])));
+        assert!(!compiled.matches(&subject(cwd, None, &[])));
+
+        let mut by_path = rule(RuleAction::Allow);
+        by_path.path_globs = vec!["src/**/*.rs".to_string()];
+        let compiled = CompiledRule::compile(by_path).unwrap();
+        assert!(compiled.matches(&subject(cwd, None, &["/repo/src/codex/mod.rs"])));
+        assert!(!compiled.matches(&subject(cwd, None, &["/repo/src/main.rs", "/etc/passwd"])));
+        assert!(!compiled.matches(&subject(cwd, None, &[])));
+
+        let mut other_session = rule(RuleAction::Reject);
+        other_session.session_id = Some("s2".to_string());
+        let compiled = CompiledRule::compile(other_session).unwrap();
+        assert!(!compiled.matches(&subject(cwd, None, &[])));
+
+        let mut invalid = rule(RuleAction::Allow);
+        invalid.title_regex = Some("(".to_string());
+        assert!(CompiledRule::compile(invalid).is_err());
+    }
+
+    #[test]
+    fn test_subject_extracts_command_and_paths() {
+        let cwd = Path::new("/repo");
+        let tool_call = ToolCallUpdate::new(
+            "call-1",
+            ToolCallUpdateFields::new()
+                .kind(ToolKind::Execute)
+                .raw_input(serde_json::json!({ "command": ["bash", "-lc", "cargo test"] }))
+                .locations(vec![ToolCallLocation::new("src/lib.rs")]),
+        );
+        let subject = PermissionSubject::from_tool_call("s1", Some(cwd), &tool_call);
+        assert_eq!(subject.command.as_deref(), Some("cargo test"));
+        assert_eq!(subject.paths, vec![PathBuf::from("/repo/src/lib.rs")]);
+
+        let command =
+            |argv: serde_json::Value| command_line(&serde_json::json!({ "command": argv }));
+        assert_eq!(
+            command(serde_json::json!(["/bin/zsh", "-c", "ls"])).as_deref(),
+            Some("ls")
+        );
+        assert_eq!(
+            command(serde_json::json!(["pwsh.exe", "-Command", "dir"])).as_deref(),
+            Some("dir")
+        );
+        assert_eq!(
+            command(serde_json::json!(["grep", "-c", "foo"])).as_deref(),
+            Some("grep -c foo")
+        );
+        assert_eq!(
+            command(serde_json::json!(["bash", "-xc", "ls"])).as_deref(),
+            Some("bash -xc ls")
+        );
+    }
+
+    #[test]
+    fn test_evaluate_picks_option_and_audits() {
+        let dir = std::env::temp_dir().join(format!("codex-policy-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let policy = ApprovalPolicy::new(dir.join("rules.json"), dir.join("audit.jsonl"));
+
+        let mut ask = rule(RuleAction::Ask);
+        ask.id = "ask-rm".to_string();
+        ask.command_regex = Some(r"\brm\b".to_string());
+        policy.upsert(ask, None).unwrap();
+        policy.upsert(rule(RuleAction::Allow), None).unwrap();
+        assert_eq!(policy.list().len(), 2);
+
+        let options = vec![
+            PermissionOption::new("allow", "Allow", PermissionOptionKind::AllowOnce),
+            PermissionOption::new("reject", "Reject", PermissionOptionKind::RejectOnce),
+        ];
+        let cwd = Path::new("/repo");
+        assert!(policy
+            .evaluate(&subject(cwd, Some("rm -rf /"), &[]), &options)
+            .is_none());
+
+        let request = subject(cwd, Some("ls"), &[]);
+        let decision = policy.evaluate(&request, &options).unwrap();
+        assert_eq!(decision.option.option_id.0.as_ref(), "allow");
+        policy.audit(&request, "call-1", &decision);
+
+        let reloaded = ApprovalPolicy::new(dir.join("rules.json"), dir.join("audit.jsonl"));
+        assert_eq!(reloaded.list()[0].id, "ask-rm");
+        let audit = reloaded.read_audit(10).unwrap();
+        assert_eq!(audit.len(), 1);
+        assert_eq!(audit[0].rule_id, "r1");
+
+        reloaded.remove("r1").unwrap();
+        assert!(reloaded.remove("r1").is_err());
+
+        // Rules saved back to back get distinct ids, even within one millisecond.
+        let mut unnamed = rule(RuleAction::Allow);
+        unnamed.id = String::new();
+        let first = reloaded.upsert(unnamed.clone(), None).unwrap();
+        let second = reloaded.upsert(unnamed, None).unwrap();
+        assert_ne!(first.id, second.id);
+        assert_eq!(reloaded.list().len(), 3);
+
+        // A rule that cannot be saved does not take effect.
+        fs::write(dir.join("blocked"), "").unwrap();
+        let blocked = ApprovalPolicy::new(
+            dir.join("blocked").join("rules.json"),
+            dir.join("audit.jsonl"),
+        );
+        assert!(blocked.upsert(rule(RuleAction::Allow), None).is_err());
+        assert!(blocked.list().is_empty());
+        assert!(blocked.evaluate(&request, &options).is_none());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -6,9 +6,10 @@ use std::{
     collections::BTreeMap,
     ffi::{OsStr, OsString},
     path::{Path, PathBuf},
+    process::Stdio,
 };
-use tauri::AppHandle;
-use tokio::process::{Child, ChildStdin, ChildStdout};
+use tauri::{AppHandle, Runtime};
+use tokio::process::{Child, ChildStderr, ChildStdin, ChildStdout};
 
 #[derive(Debug, Clone, Default)]
 /// Configuration for spawning a codex-acp process.
@@ -17,6 +18,8 @@ pub struct CodexProcessConfig {
     pub mode: Option<CodexAcpLaunchMode>,
     /// Optional explicit Codex home directory.
     pub codex_home: Option<PathBuf>,
+    /// Optional npx package spec, used when launching through npx.
+    pub npx_spec: Option<String>,
     /// Optional working directory for the child process.
     pub cwd: Option<PathBuf>,
     /// Extra environment variables to set on the child process.
@@ -25,7 +28,7 @@ pub struct CodexProcessConfig {
 
 impl CodexProcessConfig {
     /// Resolve the codex home directory for this config.
-    pub fn codex_home_or_default(&self, app: Option<&AppHandle>) -> Result<PathBuf> {
+    pub fn codex_home_or_default<R: Runtime>(&self, app: Option<&AppHandle<R>>) -> Result<PathBuf> {
         self.codex_home
             .clone()
             .map(Ok)
@@ -50,16 +53,24 @@ pub struct CodexProcess {
     child: Child,
     stdin: Option<ChildStdin>,
     stdout: Option<ChildStdout>,
+    stderr: Option<ChildStderr>,
 }
 
 impl CodexProcess {
     /// Spawn a codex-acp process with the provided config.
-    pub async fn spawn(app: Option<&AppHandle>, cfg: CodexProcessConfig) -> Result<Self> {
+    pub async fn spawn<R: Runtime>(
+        app: Option<&AppHandle<R>>,
+        cfg: CodexProcessConfig,
+    ) -> Result<Self> {
         let codex_home = cfg.codex_home_or_default(app)?;
         let mode = cfg
             .mode
+            .or_else(CodexAcpLaunchMode::from_dev_env)
             .unwrap_or_else(CodexAcpLaunchMode::default_for_build);
-        let binary = CodexAcpBinary::resolve_with_mode(mode, app)?;
+        let mut binary = CodexAcpBinary::resolve_with_mode(mode, app)?;
+        if let Some(spec) = cfg.npx_spec.as_deref() {
+            binary = binary.with_npx_spec(spec);
+        }
 
         tracing::info!(message = %binary.diagnostics_line(), "codex-acp diagnostics");
 
@@ -70,6 +81,8 @@ impl CodexProcess {
         for (k, v) in cfg.env {
             cmd.env(k, v);
         }
+        // Captured into the agent log instead of going to the app's terminal.
+        cmd.stderr(Stdio::piped());
 
         let mut child = cmd.spawn().context("failed to spawn codex-acp")?;
         let stdin = child
@@ -80,11 +93,13 @@ impl CodexProcess {
             .stdout
             .take()
             .ok_or_else(|| anyhow!("codex-acp stdout unavailable"))?;
+        let stderr = child.stderr.take();
 
         Ok(Self {
             child,
             stdin: Some(stdin),
             stdout: Some(stdout),
+            stderr,
         })
     }
 
@@ -95,6 +110,11 @@ impl CodexProcess {
         Ok((stdin, stdout))
     }
 
+    /// Take ownership of the child stderr handle.
+    pub fn take_stderr(&mut self) -> Option<ChildStderr> {
+        self.stderr.take()
+    }
+
     /// Return true if the child process is still running.
     pub fn is_alive(&mut self) -> bool {
         match self.child.try_wait() {
@@ -139,7 +159,7 @@ mod tests {
             mode: Some(CodexAcpLaunchMode::Npx),
             ..Default::default()
         };
-        let mut process = CodexProcess::spawn(None, cfg).await?;
+        let mut process = CodexProcess::spawn(None::<&AppHandle>, cfg).await?;
         assert!(process.is_alive());
         process.kill().await?;
         assert!(!process.is_alive());
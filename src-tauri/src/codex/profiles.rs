@@ -0,0 +1,465 @@
+//! Named environment profiles for local codex-acp launches.
+//!
+//! A profile bundles the env vars, `CODEX_HOME`, launch mode and npx spec a codex-acp
+//! process is spawned with, so sessions can run against e.g. a proxy or a staging
+//! provider side by side. Profiles are kept in `env-profiles.json`; the values of
+//! secret variables live apart in `env-profile-secrets.json` (owner-only on Unix) and
+//! are replaced by a placeholder whenever profiles are listed.
+
+use crate::codex::util::{load_json, save_json, unique_id};
+use crate::codex::{
+    binary::CodexAcpLaunchMode, process::CodexProcessConfig, recorder::is_secret_name,
+};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::PathBuf,
+    sync::{Mutex, MutexGuard},
+};
+
+/// Value shown instead of a stored secret. Saving it back keeps the stored value.
+pub const SECRET_PLACEHOLDER: &str = "********";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// A named set of launch settings for codex-acp.
+pub struct EnvProfile {
+    /// Stable profile identifier; generated when empty.
+    #[serde(default)]
+    pub id: String,
+    /// Human-readable name, unique among profiles.
+    pub name: String,
+    /// Environment variables set on the agent process.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Variables stored as secrets, besides those whose name looks secret.
+    #[serde(default)]
+    pub secret_keys: BTreeSet<String>,
+    /// `CODEX_HOME` for the agent; the app default when unset.
+    #[serde(default)]
+    pub codex_home: Option<String>,
+    /// Launch mode; `CODEX_DESKTOP_ACP_MODE` or the build default when unset.
+    #[serde(default)]
+    pub mode: Option<CodexAcpLaunchMode>,
+    /// npx package spec used in npx mode.
+    #[serde(default)]
+    pub npx_spec: Option<String>,
+}
+
+impl EnvProfile {
+    fn is_secret(&self, key: &str) -> bool {
+        self.secret_keys.contains(key) || is_secret_name(key)
+    }
+
+    /// Apply the profile on top of an agent launch configuration.
+    pub fn apply(&self, cfg: &mut CodexProcessConfig) {
+        for (key, value) in &self.env {
+            cfg.set_env(key.as_str(), value.as_str());
+        }
+        if let Some(home) = self.codex_home.as_deref() {
+            cfg.codex_home = Some(PathBuf::from(home));
+        }
+        if self.mode.is_some() {
+            cfg.mode = self.mode;
+        }
+        if self.npx_spec.is_some() {
+            cfg.npx_spec = self.npx_spec.clone();
+        }
+    }
+
+    /// Values of the profile's secret variables.
+    pub fn secret_values(&self) -> impl Iterator<Item = &str> {
+        self.env
+            .iter()
+            .filter(|(key, _)| self.secret_keys.contains(*key))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Default)]
+struct Profiles {
+    /// Profiles as persisted, without secret values.
+    profiles: Vec<EnvProfile>,
+    /// Secret values by profile id and variable name.
+    secrets: BTreeMap<String, BTreeMap<String, String>>,
+    /// Bumped on every change so connections spawned from an older version are replaced.
+    revisions: HashMap<String, u64>,
+    next_revision: u64,
+}
+
+impl Profiles {
+    fn bump(&mut self, id: &str) {
+        self.next_revision += 1;
+        self.revisions.insert(id.to_string(), self.next_revision);
+    }
+}
+
+/// Persistent environment profiles and their secrets.
+pub struct ProfileStore {
+    profiles_path: PathBuf,
+    secrets_path: PathBuf,
+    inner: Mutex<Profiles>,
+}
+
+impl ProfileStore {
+    /// Load profiles and secrets; unreadable files are logged and start empty.
+    pub fn new(profiles_path: PathBuf, secrets_path: PathBuf) -> Self {
+        let profiles: Vec<EnvProfile> = load_json(&profiles_path)
+            .unwrap_or_else(|err| {
+                tracing::warn!(error = %err, "failed to load environment profiles");
+                None
+            })
+            .unwrap_or_default();
+        let secrets = load_json(&secrets_path)
+            .unwrap_or_else(|err| {
+                tracing::warn!(error = %err, "failed to load environment profile secrets");
+                None
+            })
+            .unwrap_or_default();
+        let mut inner = Profiles {
+            profiles,
+            secrets,
+            ..Profiles::default()
+        };
+        let ids: Vec<String> = inner.profiles.iter().map(|p| p.id.clone()).collect();
+        for id in ids {
+            inner.bump(&id);
+        }
+        Self {
+            profiles_path,
+            secrets_path,
+            inner: Mutex::new(inner),
+        }
+    }
+
+    /// Return all profiles with secret values redacted.
+    pub fn list(&self) -> Vec<EnvProfile> {
+        self.lock().profiles.iter().map(redacted).collect()
+    }
+
+    /// Insert or replace a profile by id and return it redacted.
+    ///
+    /// Secret variables given as [`SECRET_PLACEHOLDER`] keep their stored value.
+    pub fn upsert(&self, mut profile: EnvProfile) -> Result<EnvProfile> {
+        profile.name = profile.name.trim().to_string();
+        if profile.name.is_empty() {
+            return Err(anyhow!("profile name is empty"));
+        }
+        if let Some(key) = profile.env.keys().find(|key| !valid_env_name(key)) {
+            return Err(anyhow!("invalid environment variable name: {key:?}"));
+        }
+        profile.codex_home = non_empty(profile.codex_home);
+        profile.npx_spec = non_empty(profile.npx_spec);
+
+        let mut guard = self.lock();
+        if profile.id.trim().is_empty() {
+            profile.id = unique_id("profile", |id| {
+                guard.profiles.iter().any(|existing| existing.id == id)
+            });
+        }
+        if guard
+            .profiles
+            .iter()
+            .any(|existing| existing.id != profile.id && existing.name == profile.name)
+        {
+            return Err(anyhow!("a profile named {:?} already exists", profile.name));
+        }
+
+        let stored = guard.secrets.get(&profile.id).cloned().unwrap_or_default();
+        let mut secrets = BTreeMap::new();
+        let mut env = BTreeMap::new();
+        for (key, value) in std::mem::take(&mut profile.env) {
+            if !profile.is_secret(&key) {
+                env.insert(key, value);
+                continue;
+            }
+            let value = if value == SECRET_PLACEHOLDER {
+                stored
+                    .get(&key)
+                    .cloned()
+                    .ok_or_else(|| anyhow!("no stored value for secret {key}"))?
+            } else {
+                value
+            };
+            secrets.insert(key, value);
+        }
+        profile.secret_keys = secrets.keys().cloned().collect();
+        profile.env = env;
+
+        let mut profiles = guard.profiles.clone();
+        match profiles
+            .iter()
+            .position(|existing| existing.id == profile.id)
+        {
+            Some(pos) => profiles[pos] = profile.clone(),
+            None => profiles.push(profile.clone()),
+        }
+        let mut all_secrets = guard.secrets.clone();
+        if secrets.is_empty() {
+            all_secrets.remove(&profile.id);
+        } else {
+            all_secrets.insert(profile.id.clone(), secrets);
+        }
+        // The change only takes effect once it is saved.
+        self.persist(&profiles, &all_secrets)?;
+        guard.profiles = profiles;
+        guard.secrets = all_secrets;
+        guard.bump(&profile.id);
+        Ok(redacted(&profile))
+    }
+
+    /// Delete a profile and its secrets.
+    pub fn remove(&self, id: &str) -> Result<()> {
+        let mut guard = self.lock();
+        let mut profiles = guard.profiles.clone();
+        profiles.retain(|profile| profile.id != id);
+        if profiles.len() == guard.profiles.len() {
+            return Err(anyhow!("unknown environment profile: {id}"));
+        }
+        let mut secrets = guard.secrets.clone();
+        secrets.remove(id);
+        self.persist(&profiles, &secrets)?;
+        guard.profiles = profiles;
+        guard.secrets = secrets;
+        guard.revisions.remove(id);
+        Ok(())
+    }
+
+    /// Current revision of a profile, or `None` if it does not exist.
+    pub fn revision(&self, id: &str) -> Option<u64> {
+        self.lock().revisions.get(id).copied()
+    }
+
+    /// Return a profile with its secret values filled in, plus its revision.
+    pub fn resolve(&self, id: &str) -> Result<(EnvProfile, u64)> {
+        let guard = self.lock();
+        let mut profile = guard
+            .profiles
+            .iter()
+            .find(|profile| profile.id == id)
+            .cloned()
+            .ok_or_else(|| anyhow!("unknown environment profile: {id}"))?;
+        if let Some(secrets) = guard.secrets.get(id) {
+            profile.env.extend(secrets.clone());
+        }
+        let revision = guard.revisions.get(id).copied().unwrap_or_default();
+        Ok((profile, revision))
+    }
+
+    fn persist(
+        &self,
+        profiles: &[EnvProfile],
+        secrets: &BTreeMap<String, BTreeMap<String, String>>,
+    ) -> Result<()> {
+        save_json(&self.profiles_path, &profiles, false)?;
+        save_json(&self.secrets_path, secrets, true)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Profiles> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Copy of a persisted profile with a placeholder for every secret variable.
+fn redacted(profile: &EnvProfile) -> EnvProfile {
+    let mut profile = profile.clone();
+    for key in &profile.secret_keys {
+        profile
+            .env
+            .insert(key.clone(), SECRET_PLACEHOLDER.to_string());
+    }
+    profile
+}
+
+fn valid_env_name(key: &str) -> bool {
+    !key.is_empty() && !key.contains(['=', '\0'])
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codex::util::now_millis;
+    use std::{fs, path::Path};
+
+    fn store(dir: &Path) -> ProfileStore {
+        ProfileStore::new(dir.join("profiles.json"), dir.join("secrets.json"))
+    }
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("codex-profiles-test-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn staging() -> EnvProfile {
+        EnvProfile {
+            name: "staging".to_string(),
+            env: BTreeMap::from([
+                ("HTTPS_PROXY".to_string(), "http://proxy:3128".to_string()),
+                ("OPENAI_API_KEY".to_string(), "sk-staging".to_string()),
+                ("ORG".to_string(), "acme".to_string()),
+            ]),
+            secret_keys: BTreeSet::from(["ORG".to_string()]),
+            mode: Some(CodexAcpLaunchMode::Npx),
+            npx_spec: Some("@zed-industries/codex-acp@0.9.1".to_string()),
+            ..EnvProfile::default()
+        }
+    }
+
+    #[test]
+    fn test_secrets_are_stored_apart_and_redacted() {
+        let dir = test_dir("secrets");
+        let store = store(&dir);
+        let saved = store.upsert(staging()).unwrap();
+        assert!(saved.id.starts_with("profile-"));
+        assert_eq!(saved.env["OPENAI_API_KEY"], SECRET_PLACEHOLDER);
+        assert_eq!(saved.env["ORG"], SECRET_PLACEHOLDER);
+        assert_eq!(saved.env["HTTPS_PROXY"], "http://proxy:3128");
+
+        let on_disk = fs::read_to_string(dir.join("profiles.json")).unwrap();
+        assert!(!on_disk.contains("sk-staging") && !on_disk.contains("acme"));
+        let secrets = fs::read_to_string(dir.join("secrets.json")).unwrap();
+        assert!(secrets.contains("sk-staging"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(dir.join("secrets.json"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o077, 0);
+        }
+
+        let (resolved, revision) = store.resolve(&saved.id).unwrap();
+        assert_eq!(resolved.env["OPENAI_API_KEY"], "sk-staging");
+        assert_eq!(resolved.env["ORG"], "acme");
+        let mut secrets: Vec<&str> = resolved.secret_values().collect();
+        secrets.sort();
+        assert_eq!(secrets, vec!["acme", "sk-staging"]);
+
+        // Saving the listing back keeps the secrets and starts a new revision.
+        let mut edited = store.list().remove(0);
+        edited.env.insert("ORG".to_string(), "globex".to_string());
+        store.upsert(edited).unwrap();
+        let (resolved, next) = store.resolve(&saved.id).unwrap();
+        assert_eq!(resolved.env["OPENAI_API_KEY"], "sk-staging");
+        assert_eq!(resolved.env["ORG"], "globex");
+        assert!(next > revision);
+
+        let reloaded = self::store(&dir);
+        assert_eq!(reloaded.list(), store.list());
+        assert_eq!(reloaded.resolve(&saved.id).unwrap().0, resolved);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rejects_invalid_profiles() {
+        let dir = test_dir("invalid");
+        let store = store(&dir);
+        let saved = store.upsert(staging()).unwrap();
+
+        let duplicate = EnvProfile {
+            name: "staging".to_string(),
+            ..EnvProfile::default()
+        };
+        assert!(store.upsert(duplicate).is_err());
+
+        let mut bad_key = staging();
+        bad_key.id = saved.id.clone();
+        bad_key.env.insert("A=B".to_string(), "x".to_string());
+        assert!(store.upsert(bad_key).is_err());
+
+        let unknown_secret = EnvProfile {
+            name: "other".to_string(),
+            env: BTreeMap::from([("API_KEY".to_string(), SECRET_PLACEHOLDER.to_string())]),
+            ..EnvProfile::default()
+        };
+        assert!(store.upsert(unknown_secret).is_err());
+
+        store.remove(&saved.id).unwrap();
+        assert!(store.list().is_empty());
+        assert!(store.resolve(&saved.id).is_err());
+        assert!(store.remove(&saved.id).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_ids_stay_unique_within_a_millisecond() {
+        let dir = test_dir("ids");
+        let store = store(&dir);
+        // Taken ids for the next few milliseconds force a collision with the clock.
+        let now = now_millis();
+        for offset in 0..3 {
+            store
+                .upsert(EnvProfile {
+                    id: format!("profile-{}", now + offset),
+                    name: format!("taken {offset}"),
+                    ..EnvProfile::default()
+                })
+                .unwrap();
+        }
+        let first = store.upsert(staging()).unwrap();
+        let mut second = staging();
+        second.name = "production".to_string();
+        let second = store.upsert(second).unwrap();
+        let mut ids: Vec<String> = store.list().into_iter().map(|p| p.id).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), 5);
+        assert_ne!(first.id, second.id);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_failed_save_leaves_profiles_unchanged() {
+        let dir = test_dir("readonly");
+        let plain = EnvProfile {
+            name: "plain".to_string(),
+            env: BTreeMap::from([("ORG".to_string(), "acme".to_string())]),
+            ..EnvProfile::default()
+        };
+        let saved = store(&dir).upsert(plain).unwrap();
+        // A file in place of the directory makes every save fail.
+        let blocked = dir.join("blocked");
+        fs::write(&blocked, "").unwrap();
+        let store = ProfileStore::new(dir.join("profiles.json"), blocked.join("secrets.json"));
+
+        let revision = store.revision(&saved.id);
+        let mut edited = store.list().remove(0);
+        edited.env.insert("ORG".to_string(), "globex".to_string());
+        assert!(store.upsert(edited).is_err());
+        assert!(store.upsert(staging()).is_err());
+        assert!(store.remove(&saved.id).is_err());
+        assert_eq!(store.list(), vec![saved.clone()]);
+        assert_eq!(store.revision(&saved.id), revision);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_apply_to_launch_config() {
+        let mut cfg = CodexProcessConfig::default();
+        cfg.set_env("ORG", "default");
+        let profile = EnvProfile {
+            codex_home: Some("/tmp/codex-staging".to_string()),
+            ..staging()
+        };
+        profile.apply(&mut cfg);
+        assert_eq!(cfg.env.get(std::ffi::OsStr::new("ORG")).unwrap(), "acme");
+        assert_eq!(cfg.codex_home, Some(PathBuf::from("/tmp/codex-staging")));
+        assert_eq!(cfg.mode, Some(CodexAcpLaunchMode::Npx));
+        assert_eq!(
+            cfg.npx_spec.as_deref(),
+            Some("@zed-industries/codex-acp@0.9.1")
+        );
+    }
+}
@@ -0,0 +1,252 @@
+//! Conversion of frontend prompt parts into ACP content blocks.
+
+use crate::codex::types::PromptPart;
+use agent_client_protocol::{
+    BlobResourceContents, ContentBlock, EmbeddedResource, EmbeddedResourceResource, ImageContent,
+    PromptCapabilities, ResourceLink, TextContent, TextResourceContents,
+};
+use anyhow::{anyhow, Context, Result};
+use base64::Engine;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Largest file embedded into a prompt as a resource.
+const MAX_EMBEDDED_BYTES: u64 = 1024 * 1024;
+/// Largest image attached to a prompt.
+const MAX_IMAGE_BYTES: u64 = 20 * 1024 * 1024;
+
+/// Where the files referenced by a prompt live.
+#[derive(Debug, Clone)]
+pub struct PromptContext<'a> {
+    /// Agent-side session working directory, used to resolve relative paths.
+    pub cwd: &'a Path,
+    /// True when the session runs on a remote host and its files are not readable locally.
+    pub remote: bool,
+}
+
+/// Build ACP content blocks from prompt parts, rejecting parts the agent cannot accept.
+pub fn build_prompt(
+    parts: Vec<PromptPart>,
+    ctx: &PromptContext<'_>,
+    caps: &PromptCapabilities,
+) -> Result<Vec<ContentBlock>> {
+    if parts.is_empty() {
+        return Err(anyhow!("prompt is empty"));
+    }
+    parts
+        .into_iter()
+        .map(|part| build_block(part, ctx, caps))
+        .collect()
+}
+
+fn build_block(
+    part: PromptPart,
+    ctx: &PromptContext<'_>,
+    caps: &PromptCapabilities,
+) -> Result<ContentBlock> {
+    match part {
+        PromptPart::Text { text } => Ok(ContentBlock::Text(TextContent::new(text))),
+        PromptPart::Image {
+            path,
+            data,
+            mime_type,
+        } => {
+            if !caps.image {
+                return Err(anyhow!("agent does not accept image prompts"));
+            }
+            let (data, mime_type, uri) = match (path, data) {
+                (_, Some(data)) => {
+                    let mime_type =
+                        mime_type.ok_or_else(|| anyhow!("base64 image requires a mime type"))?;
+                    (data, mime_type, None)
+                }
+                (Some(path), None) => {
+                    // Screenshots and pasted images always come from the local machine.
+                    let path = PathBuf::from(path);
+                    let mime_type = match mime_type {
+                        Some(mime_type) => mime_type,
+                        None => image_mime_type(&path)
+                            .ok_or_else(|| anyhow!("unsupported image type: {}", path.display()))?
+                            .to_string(),
+                    };
+                    let bytes = read_limited(&path, MAX_IMAGE_BYTES)?;
+                    let data = base64::engine::general_purpose::STANDARD.encode(bytes);
+                    (data, mime_type, Some(file_uri(&path)))
+                }
+                (None, None) => return Err(anyhow!("image part requires a path or data")),
+            };
+            Ok(ContentBlock::Image(
+                ImageContent::new(data, mime_type).uri(uri),
+            ))
+        }
+        PromptPart::ResourceLink {
+            path,
+            name,
+            mime_type,
+        } => {
+            let path = resolve(ctx, &path);
+            let name = name
+                .or_else(|| {
+                    path.file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                })
+                .unwrap_or_else(|| path.display().to_string());
+            let size = if ctx.remote {
+                None
+            } else {
+                fs::metadata(&path)
+                    .ok()
+                    .and_then(|meta| i64::try_from(meta.len()).ok())
+            };
+            Ok(ContentBlock::ResourceLink(
+                ResourceLink::new(name, file_uri(&path))
+                    .mime_type(mime_type)
+                    .size(size),
+            ))
+        }
+        PromptPart::Resource {
+            path,
+            text,
+            mime_type,
+        } => {
+            if !caps.embedded_context {
+                return Err(anyhow!("agent does not accept embedded resources"));
+            }
+            let path = resolve(ctx, &path);
+            let uri = file_uri(&path);
+            let resource = match text {
+                Some(text) => EmbeddedResourceResource::TextResourceContents(
+                    TextResourceContents::new(text, uri).mime_type(mime_type),
+                ),
+                None if ctx.remote => {
+                    return Err(anyhow!(
+                        "remote file contents must be supplied with the resource: {}",
+                        path.display()
+                    ))
+                }
+                None => {
+                    let bytes = read_limited(&path, MAX_EMBEDDED_BYTES)?;
+                    match String::from_utf8(bytes) {
+                        Ok(text) => EmbeddedResourceResource::TextResourceContents(
+                            TextResourceContents::new(text, uri).mime_type(mime_type),
+                        ),
+                        Err(err) => EmbeddedResourceResource::BlobResourceContents(
+                            BlobResourceContents::new(
+                                base64::engine::general_purpose::STANDARD.encode(err.into_bytes()),
+                                uri,
+                            )
+                            .mime_type(mime_type),
+                        ),
+                    }
+                }
+            };
+            Ok(ContentBlock::Resource(EmbeddedResource::new(resource)))
+        }
+    }
+}
+
+fn resolve(ctx: &PromptContext<'_>, path: &str) -> PathBuf {
+    let path = Path::new(path);
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        ctx.cwd.join(path)
+    }
+}
+
+fn read_limited(path: &Path, limit: u64) -> Result<Vec<u8>> {
+    let meta = fs::metadata(path).with_context(|| format!("cannot read {}", path.display()))?;
+    if meta.len() > limit {
+        return Err(anyhow!(
+            "{} is too large to attach ({} bytes, limit {})",
+            path.display(),
+            meta.len(),
+            limit
+        ));
+    }
+    fs::read(path).with_context(|| format!("cannot read {}", path.display()))
+}
+
+/// Build a `file://` URI for a path.
+pub fn file_uri(path: &Path) -> String {
+    url::Url::from_file_path(path)
+        .map(String::from)
+        .unwrap_or_else(|_| format!("file://{}", path.display()))
+}
+
+fn image_mime_type(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    match ext.as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx(cwd: &Path) -> PromptContext<'_> {
+        PromptContext { cwd, remote: false }
+    }
+
+    #[test]
+    fn test_build_prompt_checks_capabilities() {
+        let cwd = Path::new("/repo");
+        let caps = PromptCapabilities::new();
+        let image = PromptPart::Image {
+            path: None,
+            data: Some("aGVsbG8=".to_string()),
+            mime_type: Some("image/png".to_string()),
+        };
+        assert!(build_prompt(vec![image.clone()], &ctx(cwd), &caps).is_err());
+
+        let caps = PromptCapabilities::new().image(true);
+        let blocks = build_prompt(
+            vec![
+                PromptPart::Text {
+                    text: "look".to_string(),
+                },
+                image,
+            ],
+            &ctx(cwd),
+            &caps,
+        )
+        .unwrap();
+        assert!(matches!(blocks[1], ContentBlock::Image(_)));
+
+        let embedded = PromptPart::Resource {
+            path: "notes.md".to_string(),
+            text: Some("hi".to_string()),
+            mime_type: None,
+        };
+        assert!(build_prompt(vec![embedded], &ctx(cwd), &caps).is_err());
+    }
+
+    #[test]
+    fn test_resource_link_resolves_relative_path() {
+        let cwd = Path::new("/repo");
+        let blocks = build_prompt(
+            vec![PromptPart::ResourceLink {
+                path: "src/main.rs".to_string(),
+                name: None,
+                mime_type: None,
+            }],
+            &ctx(cwd),
+            &PromptCapabilities::new(),
+        )
+        .unwrap();
+        match &blocks[0] {
+            ContentBlock::ResourceLink(link) => {
+                assert_eq!(link.name, "main.rs");
+                assert_eq!(link.uri, "file:///repo/src/main.rs");
+            }
+            other => panic!("unexpected block: {other:?}"),
+        }
+    }
+}
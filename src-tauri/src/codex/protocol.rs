@@ -1,24 +1,44 @@
 //! ACP client wiring and permission handling.
 
+use crate::codex::util::now_millis;
 use crate::codex::{
+    agent_log::AgentLog,
+    coalesce::{emit_text_chunk, flush_chunks, ChunkKind},
     debug::DebugState,
     events::*,
+    fs::FsState,
+    history::SessionStore,
+    policy::{ApprovalPolicy, PermissionSubject},
     process::{CodexProcess, CodexProcessConfig},
+    recorder::{RecordedReader, RecordedWriter},
     thoughts::emit_thought_chunks,
-    types::ApprovalDecision,
+    types::{ApprovalDecision, ApprovalTimeout},
     unified_process::UnifiedProcess,
+    usage::UsageStore,
     util::content_block_text,
 };
+use crate::terminal::{AgentCommand, CommandExit, TerminalManager};
 use agent_client_protocol::{
-    Client, ClientSideConnection, ExtNotification, PermissionOptionId, PermissionOptionKind,
-    RequestPermissionOutcome, RequestPermissionRequest, RequestPermissionResponse,
-    SelectedPermissionOutcome, SessionNotification, SessionUpdate,
+    Client, ClientSideConnection, CreateTerminalRequest, CreateTerminalResponse, ExtNotification,
+    KillTerminalCommandRequest, KillTerminalCommandResponse, PermissionOptionId,
+    PermissionOptionKind, ReadTextFileRequest, ReadTextFileResponse, ReleaseTerminalRequest,
+    ReleaseTerminalResponse, RequestPermissionOutcome, RequestPermissionRequest,
+    RequestPermissionResponse, SelectedPermissionOutcome, SessionNotification, SessionUpdate,
+    TerminalExitStatus, TerminalId, TerminalOutputRequest, TerminalOutputResponse, ToolCallContent,
+    WaitForTerminalExitRequest, WaitForTerminalExitResponse, WriteTextFileRequest,
+    WriteTextFileResponse,
 };
 use anyhow::{anyhow, Context, Result};
 use serde::Serialize;
 use serde_json::json;
-use std::{collections::HashMap, sync::Arc};
-use tauri::{AppHandle, Emitter};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+};
+use tauri::{AppHandle, Manager, Runtime, Wry};
 use tokio::sync::oneshot;
 use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
 
@@ -41,21 +61,53 @@ impl ApprovalKey {
     }
 }
 
+/// A permission request waiting for the user, as re-rendered by a reloaded UI.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingApprovalInfo {
+    /// ACP session id.
+    pub session_id: String,
+    /// Tool call id, used as the approval request id.
+    pub request_id: String,
+    /// Tool call the permission is requested for.
+    pub tool_call: agent_client_protocol::ToolCallUpdate,
+    /// Options offered by the agent.
+    pub options: Vec<agent_client_protocol::PermissionOption>,
+    /// Request time in milliseconds since the Unix epoch.
+    pub created_at: u64,
+    /// Time at which the default outcome applies, if a timeout is configured.
+    pub expires_at: Option<u64>,
+}
+
 struct PendingApproval {
-    options: Vec<PermissionOptionChoice>,
+    connection_id: u64,
+    info: PendingApprovalInfo,
     tx: oneshot::Sender<PermissionOptionId>,
 }
 
-#[derive(Clone)]
-struct PermissionOptionChoice {
-    option_id: PermissionOptionId,
-    kind: PermissionOptionKind,
+impl PendingApproval {
+    /// Pick the option matching `decision`. There is no fallback to another kind, so a
+    /// reject decision can never select an allow option.
+    fn select(&self, decision: ApprovalDecision) -> Option<PermissionOptionId> {
+        let kind = match decision {
+            ApprovalDecision::AllowAlways => PermissionOptionKind::AllowAlways,
+            ApprovalDecision::AllowOnce => PermissionOptionKind::AllowOnce,
+            ApprovalDecision::RejectAlways => PermissionOptionKind::RejectAlways,
+            ApprovalDecision::RejectOnce => PermissionOptionKind::RejectOnce,
+        };
+        self.info
+            .options
+            .iter()
+            .find(|o| o.kind == kind)
+            .map(|o| o.option_id.clone())
+    }
 }
 
 #[derive(Default)]
 /// Shared state for pending approval requests.
 pub struct ApprovalState {
     pending: std::sync::Mutex<HashMap<ApprovalKey, PendingApproval>>,
+    timeout: std::sync::Mutex<Option<ApprovalTimeout>>,
 }
 
 impl ApprovalState {
@@ -63,11 +115,19 @@ impl ApprovalState {
     fn insert(
         &self,
         key: ApprovalKey,
-        options: Vec<PermissionOptionChoice>,
+        connection_id: u64,
+        info: PendingApprovalInfo,
         tx: oneshot::Sender<PermissionOptionId>,
     ) {
         let mut guard = self.lock_pending();
-        guard.insert(key, PendingApproval { options, tx });
+        guard.insert(
+            key,
+            PendingApproval {
+                connection_id,
+                info,
+                tx,
+            },
+        );
     }
 
     /// Resolve a pending approval request with a decision or explicit option id.
@@ -87,92 +147,146 @@ impl ApprovalState {
         let selected = if let Some(option_id) = option_id {
             PermissionOptionId::from(option_id)
         } else {
-            let desired_kind = decision.unwrap_or(ApprovalDecision::AllowOnce);
-            let kind = match desired_kind {
-                ApprovalDecision::AllowAlways => PermissionOptionKind::AllowAlways,
-                ApprovalDecision::AllowOnce => PermissionOptionKind::AllowOnce,
-                ApprovalDecision::RejectAlways => PermissionOptionKind::RejectAlways,
-                ApprovalDecision::RejectOnce => PermissionOptionKind::RejectOnce,
-            };
-
+            // Without a matching option the request is dropped, which cancels it.
             pending
-                .options
-                .iter()
-                .find(|o| o.kind == kind)
-                .or_else(|| pending.options.first())
-                .map(|o| o.option_id.clone())
-                .ok_or_else(|| anyhow!("approval request had no options"))?
+                .select(decision.unwrap_or(ApprovalDecision::AllowOnce))
+                .ok_or_else(|| anyhow!("no option matches the decision; request cancelled"))?
         };
 
         let _ = pending.tx.send(selected);
         Ok(())
     }
 
+    /// Apply the default outcome to a request whose timeout elapsed.
+    ///
+    /// Returns `None` if the request was already answered, otherwise the selected option
+    /// (`Some(None)` means the request is cancelled).
+    fn expire(
+        &self,
+        key: &ApprovalKey,
+        decision: Option<ApprovalDecision>,
+    ) -> Option<Option<PermissionOptionId>> {
+        let pending = self.lock_pending().remove(key)?;
+        Some(decision.and_then(|decision| pending.select(decision)))
+    }
+
+    /// Drop every pending request for a session; the agent sees them as cancelled.
+    pub fn purge_session(&self, session_id: &str) -> Vec<ApprovalKey> {
+        self.purge(|key, _| key.session_id.as_ref() == session_id)
+    }
+
+    /// Drop every pending request raised by a connection; the agent sees them as cancelled.
+    pub fn purge_connection(&self, connection_id: u64) -> Vec<ApprovalKey> {
+        self.purge(|_, pending| pending.connection_id == connection_id)
+    }
+
+    fn purge(&self, matches: impl Fn(&ApprovalKey, &PendingApproval) -> bool) -> Vec<ApprovalKey> {
+        let mut guard = self.lock_pending();
+        let keys: Vec<ApprovalKey> = guard
+            .iter()
+            .filter(|(key, pending)| matches(key, pending))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &keys {
+            guard.remove(key);
+        }
+        keys
+    }
+
+    /// List pending requests, optionally for a single session, oldest first.
+    pub fn list(&self, session_id: Option<&str>) -> Vec<PendingApprovalInfo> {
+        let mut pending: Vec<PendingApprovalInfo> = self
+            .lock_pending()
+            .values()
+            .filter(|pending| session_id.map_or(true, |id| pending.info.session_id == id))
+            .map(|pending| pending.info.clone())
+            .collect();
+        pending.sort_by_key(|info| info.created_at);
+        pending
+    }
+
+    /// Configure how long requests wait for the user before the default outcome applies.
+    pub fn set_timeout(&self, timeout: Option<ApprovalTimeout>) {
+        *self.lock_timeout() = timeout;
+    }
+
+    fn timeout(&self) -> Option<ApprovalTimeout> {
+        self.lock_timeout().clone()
+    }
+
     fn lock_pending(&self) -> std::sync::MutexGuard<'_, HashMap<ApprovalKey, PendingApproval>> {
         self.pending
             .lock()
             .unwrap_or_else(|poisoned| poisoned.into_inner())
     }
-}
 
-#[derive(Clone)]
-struct AcpClient {
-    app: AppHandle,
-    approvals: Arc<ApprovalState>,
-    debug: Arc<DebugState>,
+    fn lock_timeout(&self) -> std::sync::MutexGuard<'_, Option<ApprovalTimeout>> {
+        self.timeout
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
 }
 
-#[derive(Clone, Serialize)]
-#[serde(rename_all = "camelCase")]
-struct TextChunkPayload<'a> {
-    session_id: &'a str,
-    text: &'a str,
+/// Tell the UI that approval prompts were resolved without the user.
+pub fn emit_approvals_cleared<R: tauri::Runtime>(
+    app: &AppHandle<R>,
+    keys: &[ApprovalKey],
+    reason: &str,
+) {
+    for key in keys {
+        CodexEvent::ApprovalCleared(ApprovalClearedPayload {
+            session_id: key.session_id.as_ref(),
+            request_id: key.tool_call_id.as_ref(),
+            reason,
+            option_id: None,
+        })
+        .emit(app);
+    }
 }
 
-#[derive(Clone, Serialize)]
-#[serde(rename_all = "camelCase")]
-struct ToolCallPayload<'a> {
-    session_id: &'a str,
-    tool_call: &'a agent_client_protocol::ToolCall,
-}
+static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(1);
 
-#[derive(Clone, Serialize)]
-#[serde(rename_all = "camelCase")]
-struct ToolCallUpdatePayload<'a> {
-    session_id: &'a str,
-    update: &'a agent_client_protocol::ToolCallUpdate,
-}
+/// How long to wait for a dead agent's remaining stderr before reporting an error.
+const STDERR_DRAIN_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(250);
 
-#[derive(Clone, Serialize)]
-#[serde(rename_all = "camelCase")]
-struct PlanPayload<'a> {
-    session_id: &'a str,
-    plan: &'a agent_client_protocol::Plan,
+#[derive(Default)]
+/// Sessions whose history is currently being replayed by `session/load`.
+pub struct ReplayState {
+    sessions: std::sync::Mutex<HashSet<String>>,
 }
 
-#[derive(Clone, Serialize)]
-#[serde(rename_all = "camelCase")]
-struct AvailableCommandsPayload<'a> {
-    session_id: &'a str,
-    update: &'a agent_client_protocol::AvailableCommandsUpdate,
-}
+impl ReplayState {
+    fn begin(&self, session_id: &str) {
+        self.lock_sessions().insert(session_id.to_string());
+    }
 
-#[derive(Clone, Serialize)]
-#[serde(rename_all = "camelCase")]
-struct CurrentModePayload<'a> {
-    session_id: &'a str,
-    update: &'a agent_client_protocol::CurrentModeUpdate,
+    fn end(&self, session_id: &str) {
+        self.lock_sessions().remove(session_id);
+    }
+
+    fn contains(&self, session_id: &str) -> bool {
+        self.lock_sessions().contains(session_id)
+    }
+
+    fn lock_sessions(&self) -> std::sync::MutexGuard<'_, HashSet<String>> {
+        self.sessions
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
 }
 
-#[derive(Clone, Serialize)]
-#[serde(rename_all = "camelCase")]
-struct ConfigOptionPayload<'a> {
-    session_id: &'a str,
-    update: &'a agent_client_protocol::ConfigOptionUpdate,
+#[derive(Clone)]
+struct AcpClient<R: Runtime> {
+    app: AppHandle<R>,
+    connection_id: u64,
+    approvals: Arc<ApprovalState>,
+    debug: Arc<DebugState>,
+    fs: Arc<FsState>,
+    replay: Arc<ReplayState>,
 }
 
 #[async_trait::async_trait(?Send)]
-impl Client for AcpClient {
+impl<R: Runtime> Client for AcpClient<R> {
     async fn request_permission(
         &self,
         args: RequestPermissionRequest,
@@ -190,30 +304,121 @@ impl Client for AcpClient {
             json!({ "toolCallId": tool_call_id.as_ref() }),
         );
 
-        let (tx, rx) = oneshot::channel();
-        let option_choices = args
-            .options
-            .iter()
-            .map(|option| PermissionOptionChoice {
-                option_id: option.option_id.clone(),
-                kind: option.kind,
-            })
-            .collect();
-        self.approvals.insert(key, option_choices, tx);
+        let cwd = self.fs.session_root(session_id.as_ref());
+        let subject =
+            PermissionSubject::from_tool_call(session_id.as_ref(), cwd.as_deref(), &args.tool_call);
+        let policy = self.app.try_state::<ApprovalPolicy>();
+        if let Some((policy, decision)) = policy.as_ref().and_then(|policy| {
+            policy
+                .evaluate(&subject, &args.options)
+                .map(|decision| (policy, decision))
+        }) {
+            let entry = policy.audit(&subject, tool_call_id.as_ref(), &decision);
+            let timing = self.debug.mark_event(session_id.as_ref());
+            self.debug.emit(
+                &self.app,
+                Some(session_id.as_ref()),
+                "approval_auto_decided",
+                timing,
+                json!({
+                    "toolCallId": tool_call_id.as_ref(),
+                    "ruleId": &decision.rule_id,
+                    "optionId": decision.option.option_id.0.as_ref(),
+                }),
+            );
+            CodexEvent::ApprovalAutoDecided(&entry).emit(&self.app);
+            if let Some(store) = self.app.try_state::<SessionStore>() {
+                store.record_approval(
+                    session_id.as_ref(),
+                    tool_call_id.as_ref(),
+                    Some(decision.option.option_id.0.as_ref()),
+                    Some(decision.option.kind),
+                );
+            }
+            return Ok(RequestPermissionResponse::new(
+                RequestPermissionOutcome::Selected(SelectedPermissionOutcome::new(
+                    decision.option.option_id,
+                )),
+            ));
+        }
+
+        let timeout = self.approvals.timeout();
+        let created_at = now_millis();
+        let info = PendingApprovalInfo {
+            session_id: session_id.to_string(),
+            request_id: tool_call_id.to_string(),
+            tool_call: args.tool_call.clone(),
+            options: args.options.clone(),
+            created_at,
+            expires_at: timeout
+                .as_ref()
+                .map(|timeout| created_at.saturating_add(timeout.seconds.saturating_mul(1000))),
+        };
+        let (tx, mut rx) = oneshot::channel();
+        self.approvals
+            .insert(key.clone(), self.connection_id, info, tx);
 
-        let _ = self.app.emit(
-            EVENT_APPROVAL_REQUEST,
+        flush_chunks(&self.app, session_id.as_ref());
+        CodexEvent::ApprovalRequest(ApprovalRequestPayload {
+            session_id: session_id.as_ref(),
+            request_id: tool_call_id.as_ref(),
+            tool_call: &args.tool_call,
+            options: &args.options,
+        })
+        .emit(&self.app);
+
+        let selected = match timeout {
+            Some(timeout) => {
+                let wait = std::time::Duration::from_secs(timeout.seconds);
+                match tokio::time::timeout(wait, &mut rx).await {
+                    Ok(result) => result.ok(),
+                    Err(_) => match self.approvals.expire(&key, timeout.default_decision) {
+                        Some(selected) => {
+                            CodexEvent::ApprovalCleared(ApprovalClearedPayload {
+                                session_id: session_id.as_ref(),
+                                request_id: tool_call_id.as_ref(),
+                                reason: "timeout",
+                                option_id: selected.as_ref().map(|id| id.0.as_ref()),
+                            })
+                            .emit(&self.app);
+                            selected
+                        }
+                        // The user answered just as the timeout fired.
+                        None => rx.await.ok(),
+                    },
+                }
+            }
+            None => rx.await.ok(),
+        };
+        let timing = self.debug.mark_event(session_id.as_ref());
+        self.debug.emit(
+            &self.app,
+            Some(session_id.as_ref()),
+            "approval_resolved",
+            timing,
             json!({
-                "sessionId": session_id.as_ref(),
-                "requestId": tool_call_id.as_ref(),
-                "toolCall": args.tool_call,
-                "options": args.options,
+                "toolCallId": tool_call_id.as_ref(),
+                "optionId": selected.as_ref().map(|id| id.0.as_ref()),
             }),
         );
+        if let Some(store) = self.app.try_state::<SessionStore>() {
+            let option_kind = selected.as_ref().and_then(|selected| {
+                args.options
+                    .iter()
+                    .find(|option| &option.option_id == selected)
+                    .map(|option| option.kind)
+            });
+            store.record_approval(
+                session_id.as_ref(),
+                tool_call_id.as_ref(),
+                selected.as_ref().map(|option_id| option_id.0.as_ref()),
+                option_kind,
+            );
+        }
 
-        let selected = match rx.await {
-            Ok(option_id) => option_id,
-            Err(_) => {
+        let selected = match selected {
+            Some(option_id) => option_id,
+            None => {
                 return Ok(RequestPermissionResponse::new(
                     RequestPermissionOutcome::Cancelled,
                 ));
@@ -232,27 +437,242 @@ impl Client for AcpClient {
         let SessionNotification {
             session_id, update, ..
         } = args;
-        emit_session_update(&self.app, &self.debug, session_id.0.as_ref(), &update);
+        let session_id = session_id.0.as_ref();
+        let replay = self.replay.contains(session_id);
+        emit_session_update_with(&self.app, &self.debug, session_id, &update, replay);
         Ok(())
     }
 
+    async fn read_text_file(
+        &self,
+        args: ReadTextFileRequest,
+    ) -> agent_client_protocol::Result<ReadTextFileResponse> {
+        let session_id = args.session_id.0.as_ref();
+        let content = self
+            .fs
+            .read_text_file(session_id, &args.path, args.line, args.limit)?;
+
+        let timing = self.debug.mark_event(session_id);
+        self.debug.emit(
+            &self.app,
+            Some(session_id),
+            "read_text_file",
+            timing,
+            json!({ "path": args.path.display().to_string(), "len": content.len() }),
+        );
+
+        Ok(ReadTextFileResponse::new(content))
+    }
+
+    async fn write_text_file(
+        &self,
+        args: WriteTextFileRequest,
+    ) -> agent_client_protocol::Result<WriteTextFileResponse> {
+        let session_id = args.session_id.0.as_ref();
+        let outcome = self
+            .fs
+            .write_text_file(session_id, &args.path, &args.content)?;
+        let path = outcome.path.display().to_string();
+
+        let timing = self.debug.mark_event(session_id);
+        self.debug.emit(
+            &self.app,
+            Some(session_id),
+            "write_text_file",
+            timing,
+            json!({ "path": &path, "len": outcome.bytes }),
+        );
+        CodexEvent::FileWrite(FileWritePayload {
+            session_id,
+            path: &path,
+            created: outcome.created,
+            bytes: outcome.bytes,
+        })
+        .emit(&self.app);
+
+        Ok(WriteTextFileResponse::new())
+    }
+
+    async fn create_terminal(
+        &self,
+        args: CreateTerminalRequest,
+    ) -> agent_client_protocol::Result<CreateTerminalResponse> {
+        let session_id = args.session_id.0.as_ref();
+        let cwd = self.fs.terminal_cwd(session_id, args.cwd.as_deref())?;
+        let request = AgentCommand {
+            session_id: session_id.to_string(),
+            command: args.command.clone(),
+            args: args.args,
+            env: args
+                .env
+                .into_iter()
+                .map(|var| (var.name, var.value))
+                .collect(),
+            cwd: Some(cwd),
+            output_byte_limit: args.output_byte_limit,
+        };
+        let terminal_id = self
+            .terminals()?
+            .spawn_command(&self.app, request)
+            .map_err(terminal_error)?;
+
+        let timing = self.debug.mark_event(session_id);
+        self.debug.emit(
+            &self.app,
+            Some(session_id),
+            "create_terminal",
+            timing,
+            json!({ "terminalId": &terminal_id, "command": &args.command }),
+        );
+
+        Ok(CreateTerminalResponse::new(TerminalId::new(terminal_id)))
+    }
+
+    async fn terminal_output(
+        &self,
+        args: TerminalOutputRequest,
+    ) -> agent_client_protocol::Result<TerminalOutputResponse> {
+        let output = self
+            .terminals()?
+            .command_output(args.session_id.0.as_ref(), args.terminal_id.0.as_ref())
+            .map_err(terminal_error)?;
+        Ok(TerminalOutputResponse::new(output.output, output.truncated)
+            .exit_status(output.exit.map(terminal_exit_status)))
+    }
+
+    async fn release_terminal(
+        &self,
+        args: ReleaseTerminalRequest,
+    ) -> agent_client_protocol::Result<ReleaseTerminalResponse> {
+        self.terminals()?
+            .release_command(args.session_id.0.as_ref(), args.terminal_id.0.as_ref())
+            .map_err(terminal_error)?;
+        Ok(ReleaseTerminalResponse::new())
+    }
+
+    async fn wait_for_terminal_exit(
+        &self,
+        args: WaitForTerminalExitRequest,
+    ) -> agent_client_protocol::Result<WaitForTerminalExitResponse> {
+        let mut rx = self
+            .terminals()?
+            .command_exit(args.session_id.0.as_ref(), args.terminal_id.0.as_ref())
+            .map_err(terminal_error)?;
+        let exit = rx
+            .wait_for(|exit| exit.is_some())
+            .await
+            .map_err(|_| terminal_error("terminal released before exit".to_string()))?
+            .clone();
+        let exit = exit.ok_or_else(agent_client_protocol::Error::internal_error)?;
+        Ok(WaitForTerminalExitResponse::new(terminal_exit_status(exit)))
+    }
+
+    async fn kill_terminal_command(
+        &self,
+        args: KillTerminalCommandRequest,
+    ) -> agent_client_protocol::Result<KillTerminalCommandResponse> {
+        self.terminals()?
+            .kill_command(args.session_id.0.as_ref(), args.terminal_id.0.as_ref())
+            .map_err(terminal_error)?;
+        Ok(KillTerminalCommandResponse::new())
+    }
+
     async fn ext_notification(&self, args: ExtNotification) -> agent_client_protocol::Result<()> {
         if args.method.as_ref() == "codex/token-usage" {
             if let Ok(payload) = serde_json::from_str::<serde_json::Value>(args.params.get()) {
-                let _ = self.app.emit(EVENT_TOKEN_USAGE, payload);
+                if let Some(usage) = self.app.try_state::<UsageStore>() {
+                    usage.record(&self.app, &payload);
+                }
+                CodexEvent::TokenUsage(TokenUsagePayload { usage: &payload }).emit(&self.app);
             }
         }
         Ok(())
     }
 }
 
+impl<R: Runtime> AcpClient<R> {
+    fn terminals(&self) -> agent_client_protocol::Result<tauri::State<'_, TerminalManager>> {
+        self.app.try_state::<TerminalManager>().ok_or_else(|| {
+            agent_client_protocol::Error::internal_error()
+                .data(json!("terminal manager unavailable"))
+        })
+    }
+}
+
+fn terminal_error(message: String) -> agent_client_protocol::Error {
+    agent_client_protocol::Error::invalid_params().data(json!(message))
+}
+
+fn terminal_exit_status(exit: CommandExit) -> TerminalExitStatus {
+    TerminalExitStatus::new()
+        .exit_code(exit.exit_code)
+        .signal(exit.signal)
+}
+
+/// Emit a link between a tool call and any agent terminals embedded in its content.
+fn emit_terminal_links<R: tauri::Runtime>(
+    app: &AppHandle<R>,
+    session_id: &str,
+    tool_call_id: &str,
+    content: &[ToolCallContent],
+) {
+    for item in content {
+        if let ToolCallContent::Terminal(terminal) = item {
+            CodexEvent::TerminalAttached(TerminalAttachedPayload {
+                session_id,
+                tool_call_id,
+                terminal_id: terminal.terminal_id.0.as_ref(),
+            })
+            .emit(app);
+        }
+    }
+}
+
+/// Emit a live session update to the frontend.
 pub fn emit_session_update<R: tauri::Runtime>(
     app: &AppHandle<R>,
     debug: &DebugState,
     session_id: &str,
     update: &SessionUpdate,
 ) {
+    emit_session_update_with(app, debug, session_id, update, false);
+}
+
+/// Emit a session update, marking it as history replay when `replay` is set.
+///
+/// Live updates are also appended to the session journal; replayed history is already on disk.
+pub fn emit_session_update_with<R: tauri::Runtime>(
+    app: &AppHandle<R>,
+    debug: &DebugState,
+    session_id: &str,
+    update: &SessionUpdate,
+    replay: bool,
+) {
+    if !replay {
+        if let Some(store) = app.try_state::<SessionStore>() {
+            store.record_update(session_id, update);
+        }
+    }
+
+    // Pending chunks must reach the frontend before any other update of the session.
+    if !matches!(
+        update,
+        SessionUpdate::AgentMessageChunk(_) | SessionUpdate::AgentThoughtChunk(_)
+    ) {
+        flush_chunks(app, session_id);
+    }
+
     match update {
+        SessionUpdate::UserMessageChunk(chunk) => {
+            if let Some(text) = content_block_text(&chunk.content) {
+                CodexEvent::UserMessageChunk(TextChunkPayload {
+                    session_id,
+                    text,
+                    replay,
+                })
+                .emit(app);
+            }
+        }
         SessionUpdate::AgentMessageChunk(chunk) => {
             if let Some(text) = content_block_text(&chunk.content) {
                 let timing = debug.mark_event(session_id);
@@ -263,7 +683,7 @@ pub fn emit_session_update<R: tauri::Runtime>(
                     timing,
                     json!({ "textLen": text.len() }),
                 );
-                let _ = app.emit(EVENT_MESSAGE_CHUNK, TextChunkPayload { session_id, text });
+                emit_text_chunk(app, session_id, ChunkKind::Message, text, replay);
             }
         }
         SessionUpdate::AgentThoughtChunk(chunk) => {
@@ -277,7 +697,7 @@ pub fn emit_session_update<R: tauri::Runtime>(
                         timing,
                         json!({ "textLen": text.len() }),
                     );
-                    let _ = app.emit(EVENT_THOUGHT_CHUNK, TextChunkPayload { session_id, text });
+                    emit_text_chunk(app, session_id, ChunkKind::Thought, text, replay);
                 }
             }
         }
@@ -292,15 +712,16 @@ pub fn emit_session_update<R: tauri::Runtime>(
                 json!({
                     "toolCallId": tool_call_id,
                     "title": tool_call.title.as_str(),
+                    "status": tool_call.status,
                 }),
             );
-            let _ = app.emit(
-                EVENT_TOOL_CALL,
-                ToolCallPayload {
-                    session_id,
-                    tool_call,
-                },
-            );
+            CodexEvent::ToolCall(ToolCallPayload {
+                session_id,
+                tool_call,
+                replay,
+            })
+            .emit(app);
+            emit_terminal_links(app, session_id, tool_call_id, &tool_call.content);
         }
         SessionUpdate::ToolCallUpdate(update) => {
             let tool_call_id = update.tool_call_id.0.as_ref();
@@ -310,12 +731,17 @@ pub fn emit_session_update<R: tauri::Runtime>(
                 Some(session_id),
                 "tool_call_update",
                 timing,
-                json!({ "toolCallId": tool_call_id }),
-            );
-            let _ = app.emit(
-                EVENT_TOOL_CALL_UPDATE,
-                ToolCallUpdatePayload { session_id, update },
+                json!({ "toolCallId": tool_call_id, "status": update.fields.status }),
             );
+            CodexEvent::ToolCallUpdate(ToolCallUpdatePayload {
+                session_id,
+                update,
+                replay,
+            })
+            .emit(app);
+            if let Some(content) = update.fields.content.as_deref() {
+                emit_terminal_links(app, session_id, tool_call_id, content);
+            }
         }
         SessionUpdate::Plan(plan) => {
             let timing = debug.mark_event(session_id);
@@ -326,7 +752,12 @@ pub fn emit_session_update<R: tauri::Runtime>(
                 timing,
                 json!({ "entries": plan.entries.len() }),
             );
-            let _ = app.emit(EVENT_PLAN, PlanPayload { session_id, plan });
+            CodexEvent::Plan(PlanPayload {
+                session_id,
+                plan,
+                replay,
+            })
+            .emit(app);
         }
         SessionUpdate::AvailableCommandsUpdate(update) => {
             let timing = debug.mark_event(session_id);
@@ -337,10 +768,12 @@ pub fn emit_session_update<R: tauri::Runtime>(
                 timing,
                 json!({ "count": update.available_commands.len() }),
             );
-            let _ = app.emit(
-                EVENT_AVAILABLE_COMMANDS,
-                AvailableCommandsPayload { session_id, update },
-            );
+            CodexEvent::AvailableCommands(AvailableCommandsPayload {
+                session_id,
+                update,
+                replay,
+            })
+            .emit(app);
         }
         SessionUpdate::CurrentModeUpdate(update) => {
             let timing = debug.mark_event(session_id);
@@ -351,10 +784,12 @@ pub fn emit_session_update<R: tauri::Runtime>(
                 timing,
                 json!({ "mode": update.current_mode_id.0.as_ref() }),
             );
-            let _ = app.emit(
-                EVENT_CURRENT_MODE,
-                CurrentModePayload { session_id, update },
-            );
+            CodexEvent::CurrentMode(CurrentModePayload {
+                session_id,
+                update,
+                replay,
+            })
+            .emit(app);
         }
         SessionUpdate::ConfigOptionUpdate(update) => {
             let timing = debug.mark_event(session_id);
@@ -365,96 +800,88 @@ pub fn emit_session_update<R: tauri::Runtime>(
                 timing,
                 json!({ "count": update.config_options.len() }),
             );
-            let _ = app.emit(
-                EVENT_CONFIG_OPTION_UPDATE,
-                ConfigOptionPayload { session_id, update },
-            );
+            CodexEvent::ConfigOptionUpdate(ConfigOptionPayload {
+                session_id,
+                update,
+                replay,
+            })
+            .emit(app);
         }
         _ => {}
     }
 }
 
 /// Running ACP connection plus the managed child process.
-pub struct AcpConnection {
+pub struct AcpConnection<R: Runtime = Wry> {
     /// Shared connection handle for issuing ACP requests.
     pub conn: Arc<ClientSideConnection>,
+    id: u64,
+    app: AppHandle<R>,
+    approvals: Arc<ApprovalState>,
     process: tokio::sync::Mutex<UnifiedProcess>,
+    replay: Arc<ReplayState>,
+    closed: Arc<AtomicBool>,
+    log: Arc<AgentLog>,
+    stderr_task: std::sync::Mutex<Option<tokio::task::JoinHandle<()>>>,
 }
 
-impl AcpConnection {
+impl<R: Runtime> AcpConnection<R> {
     /// Spawn an ACP connection and background IO task.
     pub async fn spawn(
-        app: AppHandle,
+        app: AppHandle<R>,
         approvals: Arc<ApprovalState>,
         debug: Arc<DebugState>,
+        fs: Arc<FsState>,
+        log: Arc<AgentLog>,
         mut cfg: CodexProcessConfig,
     ) -> Result<Self> {
         cfg.set_env_if_missing("RUST_LOG", "warn");
 
-        let mut process = CodexProcess::spawn(Some(&app), cfg)
+        let process = CodexProcess::spawn(Some(&app), cfg)
             .await
             .context("failed to spawn codex-acp process")?;
-        let (stdin, stdout) = process.take_stdio()?;
-
-        let unified_process = UnifiedProcess::Local(process);
 
-        let client = AcpClient {
-            app: app.clone(),
+        Self::spawn_from_unified(
+            app,
             approvals,
-            debug: debug.clone(),
-        };
-
-        let (conn, io_task) = ClientSideConnection::new(
-            Arc::new(client),
-            stdin.compat_write(),
-            stdout.compat(),
-            |fut| {
-                tokio::task::spawn_local(fut);
-            },
-        );
-
-        let io_app = app.clone();
-        let io_debug = debug.clone();
-        tokio::task::spawn_local(async move {
-            if let Err(err) = io_task.await {
-                let err_message = err.to_string();
-                let timing = io_debug.mark_global();
-                io_debug.emit(
-                    &io_app,
-                    None,
-                    "io_error",
-                    timing,
-                    json!({ "error": &err_message }),
-                );
-                let _ = io_app.emit(EVENT_ERROR, json!({ "error": err_message }));
-            }
-        });
-
-        Ok(Self {
-            conn: Arc::new(conn),
-            process: tokio::sync::Mutex::new(unified_process),
-        })
+            debug,
+            fs,
+            log,
+            UnifiedProcess::Local(process),
+        )
+        .await
     }
 
     /// Spawn an ACP connection from a UnifiedProcess (local or remote)
     pub async fn spawn_from_unified(
-        app: AppHandle,
+        app: AppHandle<R>,
         approvals: Arc<ApprovalState>,
         debug: Arc<DebugState>,
+        fs: Arc<FsState>,
+        log: Arc<AgentLog>,
         mut process: UnifiedProcess,
     ) -> Result<Self> {
         let (stdin, stdout) = process.take_stdio()?;
+        let replay = Arc::new(ReplayState::default());
+        let id = NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed);
+        let stderr_task = process
+            .take_stderr()
+            .map(|stderr| log.capture(app.clone(), id, stderr));
 
         let client = AcpClient {
             app: app.clone(),
-            approvals,
+            connection_id: id,
+            approvals: approvals.clone(),
             debug: debug.clone(),
+            fs,
+            replay: replay.clone(),
         };
 
+        let recording = debug.recorder.start(id, log.server_id());
         let (conn, io_task) = ClientSideConnection::new(
             Arc::new(client),
-            stdin.compat_write(),
-            stdout.compat(),
+            RecordedWriter::new(stdin, recording.clone()).compat_write(),
+            RecordedReader::new(stdout, recording).compat(),
             |fut| {
                 tokio::task::spawn_local(fut);
             },
@@ -462,6 +889,9 @@ impl AcpConnection {
 
         let io_app = app.clone();
         let io_debug = debug.clone();
+        let io_approvals = approvals.clone();
+        let closed = Arc::new(AtomicBool::new(false));
+        let io_closed = closed.clone();
         tokio::task::spawn_local(async move {
             if let Err(err) = io_task.await {
                 let err_message = err.to_string();
@@ -473,19 +903,145 @@ impl AcpConnection {
                     timing,
                     json!({ "error": &err_message }),
                 );
-                let _ = io_app.emit(EVENT_ERROR, json!({ "error": err_message }));
+                CodexEvent::Error(ErrorPayload {
+                    error: &err_message,
+                })
+                .emit(&io_app);
             }
+            io_closed.store(true, Ordering::Release);
+            // Nobody can answer the agent any more; drop prompts the UI may still show.
+            let purged = io_approvals.purge_connection(id);
+            emit_approvals_cleared(&io_app, &purged, "disconnected");
         });
 
         Ok(Self {
             conn: Arc::new(conn),
+            id,
+            app,
+            approvals,
             process: tokio::sync::Mutex::new(process),
+            replay,
+            closed,
+            log,
+            stderr_task: std::sync::Mutex::new(stderr_task),
         })
     }
 
-    /// Terminate the underlying ACP process.
+    /// Mark session updates for `session_id` as history replay until `end_replay`.
+    pub fn begin_replay(&self, session_id: &str) {
+        self.replay.begin(session_id);
+    }
+
+    /// Stop marking session updates for `session_id` as history replay.
+    pub fn end_replay(&self, session_id: &str) {
+        self.replay.end(session_id);
+    }
+
+    /// Check that the agent process is running and its stdio stream is still open.
+    pub async fn is_alive(&self) -> bool {
+        if self.closed.load(Ordering::Acquire) {
+            return false;
+        }
+        self.process.lock().await.is_alive()
+    }
+
+    /// Last `count` stderr lines of this connection as text.
+    /// When the agent has exited, waits briefly so its final output is included.
+    pub async fn stderr_tail(&self, count: usize) -> String {
+        if !self.is_alive().await {
+            let task = self
+                .stderr_task
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .take();
+            if let Some(task) = task {
+                let _ = tokio::time::timeout(STDERR_DRAIN_TIMEOUT, task).await;
+            }
+        }
+        self.log.tail_text(self.id, count)
+    }
+
+    /// Terminate the underlying ACP process and cancel its pending approvals.
     pub async fn kill(&self) -> Result<()> {
+        let purged = self.approvals.purge_connection(self.id);
+        emit_approvals_cleared(&self.app, &purged, "disconnected");
         let mut guard = self.process.lock().await;
         guard.kill().await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agent_client_protocol::{PermissionOption, ToolCallUpdate, ToolCallUpdateFields};
+
+    fn pending(
+        state: &ApprovalState,
+        session_id: &str,
+        request_id: &str,
+        connection_id: u64,
+    ) -> oneshot::Receiver<PermissionOptionId> {
+        let (tx, rx) = oneshot::channel();
+        state.insert(
+            ApprovalKey::new(session_id, request_id),
+            connection_id,
+            PendingApprovalInfo {
+                session_id: session_id.to_string(),
+                request_id: request_id.to_string(),
+                tool_call: ToolCallUpdate::new(request_id.to_string(), ToolCallUpdateFields::new()),
+                options: vec![
+                    PermissionOption::new("allow", "Allow", PermissionOptionKind::AllowOnce),
+                    PermissionOption::new("reject", "Reject", PermissionOptionKind::RejectOnce),
+                ],
+                created_at: 0,
+                expires_at: None,
+            },
+            tx,
+        );
+        rx
+    }
+
+    #[test]
+    fn test_approval_expire_and_purge() {
+        let state = ApprovalState::default();
+        let _a = pending(&state, "s1", "call-1", 1);
+        let mut b = pending(&state, "s1", "call-2", 1);
+        let _c = pending(&state, "s2", "call-3", 2);
+        assert_eq!(state.list(None).len(), 3);
+        assert_eq!(state.list(Some("s2")).len(), 1);
+
+        let key = ApprovalKey::new("s1", "call-1");
+        let selected = state.expire(&key, Some(ApprovalDecision::RejectOnce));
+        assert_eq!(selected, Some(Some(PermissionOptionId::from("reject"))));
+        assert_eq!(state.expire(&key, None), None);
+
+        // Only allow options: a reject default cancels instead of allowing.
+        let (tx, _rx) = oneshot::channel();
+        let key = ApprovalKey::new("s1", "call-4");
+        state.insert(
+            key.clone(),
+            1,
+            PendingApprovalInfo {
+                session_id: "s1".to_string(),
+                request_id: "call-4".to_string(),
+                tool_call: ToolCallUpdate::new("call-4".to_string(), ToolCallUpdateFields::new()),
+                options: vec![
+                    PermissionOption::new("once", "Allow", PermissionOptionKind::AllowOnce),
+                    PermissionOption::new("always", "Always", PermissionOptionKind::AllowAlways),
+                ],
+                created_at: 0,
+                expires_at: None,
+            },
+            tx,
+        );
+        assert_eq!(
+            state.expire(&key, Some(ApprovalDecision::RejectOnce)),
+            Some(None)
+        );
+
+        assert_eq!(state.purge_session("s1").len(), 1);
+        assert!(b.try_recv().is_err());
+        assert_eq!(state.purge_connection(2).len(), 1);
+        assert!(state.list(None).is_empty());
+    }
+}
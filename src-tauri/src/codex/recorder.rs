@@ -0,0 +1,508 @@
+//! Opt-in recording of ACP JSON-RPC traffic and offline replay of recordings.
+//!
+//! When a recording directory is set, every connection writes one JSONL file with a
+//! line per frame in either direction. Known secrets (API keys passed to
+//! `authenticate`, secret-looking env overrides) and secret-looking JSON fields are
+//! redacted before anything reaches the disk.
+
+use crate::codex::util::now_millis;
+use crate::codex::{debug::DebugState, protocol::emit_session_update};
+use agent_client_protocol::SessionNotification;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Context as TaskContext, Poll},
+    time::Duration,
+};
+use tauri::{AppHandle, Runtime};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// Replacement written in place of redacted values.
+const REDACTED: &str = "[redacted]";
+
+/// Field names whose string values are always redacted (matched case-insensitively).
+const SECRET_FIELDS: [&str; 6] = [
+    "apikey",
+    "api_key",
+    "token",
+    "secret",
+    "password",
+    "authorization",
+];
+
+/// Direction of a recorded frame, seen from the desktop client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FrameDirection {
+    /// Client to agent (agent stdin).
+    Send,
+    /// Agent to client (agent stdout).
+    Recv,
+}
+
+/// One line of a recording file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedFrame {
+    /// Capture time in milliseconds since the Unix epoch.
+    pub ts: u64,
+    /// Direction of the frame.
+    pub dir: FrameDirection,
+    /// JSON-RPC message, or the raw line if it was not valid JSON.
+    pub frame: Value,
+}
+
+/// What a replay is about to emit.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplaySummary {
+    /// Frames in the recording.
+    pub frames: usize,
+    /// `session/update` notifications that will be emitted.
+    pub updates: usize,
+    /// Sessions the updates are emitted for.
+    pub session_ids: Vec<String>,
+    /// Playback length in milliseconds at the requested speed.
+    pub duration_ms: u64,
+}
+
+/// Recording settings and known secrets, shared by all connections.
+pub struct TrafficRecorder {
+    dir: Mutex<Option<PathBuf>>,
+    secrets: Arc<Mutex<Vec<String>>>,
+}
+
+impl std::fmt::Debug for TrafficRecorder {
+    // Secrets stay out of debug output.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TrafficRecorder")
+            .field("dir", &self.dir())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for TrafficRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TrafficRecorder {
+    /// Create a recorder, enabled when `CODEX_DESKTOP_ACP_RECORD_DIR` is set.
+    pub fn new() -> Self {
+        let dir = std::env::var_os("CODEX_DESKTOP_ACP_RECORD_DIR")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from);
+        Self {
+            dir: Mutex::new(dir),
+            secrets: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Directory new connections record into, if recording is enabled.
+    pub fn dir(&self) -> Option<PathBuf> {
+        self.dir
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Enable recording into `dir`, or disable it. Applies to connections spawned afterwards.
+    pub fn set_dir(&self, dir: Option<PathBuf>) {
+        *self
+            .dir
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = dir;
+    }
+
+    /// Remember a secret so it is redacted wherever it appears in recorded frames.
+    pub fn add_secret(&self, secret: &str) {
+        // Very short values would redact unrelated text.
+        if secret.len() < 8 {
+            return;
+        }
+        let mut secrets = lock_secrets(&self.secrets);
+        if !secrets.iter().any(|known| known == secret) {
+            secrets.push(secret.to_string());
+        }
+    }
+
+    /// Open a recording file for a new connection, if recording is enabled.
+    pub fn start(&self, connection_id: u64, server_id: Option<&str>) -> Option<Arc<Recording>> {
+        let dir = self.dir()?;
+        let target = server_id.map_or_else(|| "local".to_string(), sanitize_file_part);
+        let path = dir.join(format!(
+            "acp-{}-{target}-{connection_id}.jsonl",
+            now_millis()
+        ));
+        match Recording::create(&path, self.secrets.clone()) {
+            Ok(recording) => {
+                tracing::info!(path = %path.display(), "recording ACP traffic");
+                Some(Arc::new(recording))
+            }
+            Err(err) => {
+                tracing::warn!(error = %err, "failed to start ACP traffic recording");
+                None
+            }
+        }
+    }
+}
+
+/// An open recording file of one connection.
+pub struct Recording {
+    writer: Mutex<BufWriter<File>>,
+    secrets: Arc<Mutex<Vec<String>>>,
+}
+
+impl Recording {
+    fn create(path: &Path, secrets: Arc<Mutex<Vec<String>>>) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create {}", parent.display()))?;
+        }
+        let file =
+            File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+        Ok(Self {
+            writer: Mutex::new(BufWriter::new(file)),
+            secrets,
+        })
+    }
+
+    fn record_line(&self, dir: FrameDirection, line: &[u8]) {
+        let text = String::from_utf8_lossy(line);
+        let text = text.trim();
+        if text.is_empty() {
+            return;
+        }
+        let mut frame =
+            serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.to_string()));
+        redact(&mut frame, &lock_secrets(&self.secrets));
+        let record = RecordedFrame {
+            ts: now_millis(),
+            dir,
+            frame,
+        };
+        let Ok(line) = serde_json::to_string(&record) else {
+            return;
+        };
+        let mut writer = self
+            .writer
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        // Flushed per frame so the file is complete when the agent crashes.
+        let _ = writeln!(writer, "{line}");
+        let _ = writer.flush();
+    }
+}
+
+/// Splits a byte stream into newline-delimited frames for a recording.
+struct FrameTap {
+    recording: Option<Arc<Recording>>,
+    dir: FrameDirection,
+    pending: Vec<u8>,
+}
+
+impl FrameTap {
+    fn new(recording: Option<Arc<Recording>>, dir: FrameDirection) -> Self {
+        Self {
+            recording,
+            dir,
+            pending: Vec::new(),
+        }
+    }
+
+    fn feed(&mut self, bytes: &[u8]) {
+        let Some(recording) = self.recording.as_ref() else {
+            return;
+        };
+        self.pending.extend_from_slice(bytes);
+        while let Some(end) = self.pending.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            recording.record_line(self.dir, &line);
+        }
+    }
+}
+
+/// Agent stdout wrapper that records received frames.
+pub struct RecordedReader<T> {
+    inner: T,
+    tap: FrameTap,
+}
+
+impl<T> RecordedReader<T> {
+    /// Wrap `inner`; passes bytes through untouched when `recording` is `None`.
+    pub fn new(inner: T, recording: Option<Arc<Recording>>) -> Self {
+        Self {
+            inner,
+            tap: FrameTap::new(recording, FrameDirection::Recv),
+        }
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for RecordedReader<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        let before = buf.filled().len();
+        let poll = Pin::new(&mut this.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = poll {
+            this.tap.feed(&buf.filled()[before..]);
+        }
+        poll
+    }
+}
+
+/// Agent stdin wrapper that records sent frames.
+pub struct RecordedWriter<T> {
+    inner: T,
+    tap: FrameTap,
+}
+
+impl<T> RecordedWriter<T> {
+    /// Wrap `inner`; passes bytes through untouched when `recording` is `None`.
+    pub fn new(inner: T, recording: Option<Arc<Recording>>) -> Self {
+        Self {
+            inner,
+            tap: FrameTap::new(recording, FrameDirection::Send),
+        }
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for RecordedWriter<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        let poll = Pin::new(&mut this.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(written)) = poll {
+            this.tap.feed(&buf[..written]);
+        }
+        poll
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+/// Replace secrets in a frame: known secret strings anywhere, string values of
+/// secret-looking fields, and `{ "name": <secret-looking>, "value": .. }` env entries.
+pub fn redact(value: &mut Value, secrets: &[String]) {
+    match value {
+        Value::String(text) => {
+            for secret in secrets {
+                if text.contains(secret.as_str()) {
+                    *text = text.replace(secret.as_str(), REDACTED);
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                redact(item, secrets);
+            }
+        }
+        Value::Object(map) => {
+            let secret_env = map
+                .get("name")
+                .and_then(Value::as_str)
+                .is_some_and(is_secret_name);
+            for (key, item) in map.iter_mut() {
+                let secret_field = is_secret_name(key) || (secret_env && key == "value");
+                if secret_field && item.is_string() {
+                    *item = Value::String(REDACTED.to_string());
+                } else {
+                    redact(item, secrets);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Whether an env var or field name looks like it holds a credential.
+pub fn is_secret_name(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    SECRET_FIELDS.iter().any(|field| name.contains(field)) || name.ends_with("_key")
+}
+
+/// Read a recording file.
+pub fn load_recording(path: &Path) -> Result<Vec<RecordedFrame>> {
+    let raw = fs::read_to_string(path)
+        .with_context(|| format!("failed to read recording {}", path.display()))?;
+    raw.lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(index, line)| {
+            serde_json::from_str(line)
+                .with_context(|| format!("invalid frame on line {}", index + 1))
+        })
+        .collect()
+}
+
+/// `session/update` notifications sent by the agent, with their capture times.
+fn session_updates(frames: &[RecordedFrame]) -> Vec<(u64, SessionNotification)> {
+    frames
+        .iter()
+        .filter(|frame| frame.dir == FrameDirection::Recv)
+        .filter(|frame| frame.frame.get("method").and_then(Value::as_str) == Some("session/update"))
+        .filter_map(|frame| {
+            let params = frame.frame.get("params")?.clone();
+            match serde_json::from_value(params) {
+                Ok(notification) => Some((frame.ts, notification)),
+                Err(err) => {
+                    tracing::debug!(error = %err, "skipping unreadable session/update frame");
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Emit the agent's recorded session updates to the UI, keeping the original pacing
+/// scaled by `speed` (`0` emits everything at once). `session_id` redirects all
+/// updates to one session. Returns what will be emitted; playback runs in the background.
+pub fn replay_recording<R: Runtime>(
+    app: AppHandle<R>,
+    frames: &[RecordedFrame],
+    speed: f64,
+    session_id: Option<String>,
+) -> ReplaySummary {
+    let updates = session_updates(frames);
+    let delay = move |from: u64, to: u64| {
+        if speed > 0.0 {
+            // Absurdly slow speeds overflow; they wait forever rather than panic.
+            Duration::try_from_secs_f64(to.saturating_sub(from) as f64 / 1000.0 / speed)
+                .unwrap_or(Duration::MAX)
+        } else {
+            Duration::ZERO
+        }
+    };
+
+    let mut session_ids: Vec<String> = Vec::new();
+    for (_, notification) in &updates {
+        let id = session_id
+            .clone()
+            .unwrap_or_else(|| notification.session_id.0.to_string());
+        if !session_ids.contains(&id) {
+            session_ids.push(id);
+        }
+    }
+    let duration = match (updates.first(), updates.last()) {
+        (Some((first, _)), Some((last, _))) => delay(*first, *last),
+        _ => Duration::ZERO,
+    };
+    let summary = ReplaySummary {
+        frames: frames.len(),
+        updates: updates.len(),
+        session_ids,
+        duration_ms: duration.as_millis().try_into().unwrap_or(u64::MAX),
+    };
+
+    tauri::async_runtime::spawn(async move {
+        let debug = DebugState::new();
+        let mut last_ts = updates.first().map(|(ts, _)| *ts).unwrap_or_default();
+        for (ts, notification) in updates {
+            let wait = delay(last_ts, ts);
+            if !wait.is_zero() {
+                tokio::time::sleep(wait).await;
+            }
+            last_ts = ts;
+            let id = session_id
+                .clone()
+                .unwrap_or_else(|| notification.session_id.0.to_string());
+            emit_session_update(&app, &debug, &id, &notification.update);
+        }
+    });
+
+    summary
+}
+
+fn sanitize_file_part(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn lock_secrets(secrets: &Mutex<Vec<String>>) -> MutexGuard<'_, Vec<String>> {
+    secrets
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_redact_known_secrets_and_secret_fields() {
+        let secrets = vec!["sk-test-1234567890".to_string()];
+        let mut frame = json!({
+            "jsonrpc": "2.0",
+            "method": "session/new",
+            "params": {
+                "cwd": "/tmp",
+                "apiKey": "anything",
+                "mcpServers": [{
+                    "name": "github",
+                    "env": [
+                        { "name": "GITHUB_TOKEN", "value": "ghp_abc" },
+                        { "name": "LOG_LEVEL", "value": "debug" }
+                    ]
+                }],
+                "note": "key is sk-test-1234567890, keep it safe",
+                "usage": { "outputTokens": 12 }
+            }
+        });
+        redact(&mut frame, &secrets);
+        let params = &frame["params"];
+        assert_eq!(params["apiKey"], REDACTED);
+        assert_eq!(params["mcpServers"][0]["env"][0]["value"], REDACTED);
+        assert_eq!(params["mcpServers"][0]["env"][1]["value"], "debug");
+        assert_eq!(params["mcpServers"][0]["name"], "github");
+        assert_eq!(params["note"], "key is [redacted], keep it safe");
+        assert_eq!(params["usage"]["outputTokens"], 12);
+        assert_eq!(params["cwd"], "/tmp");
+    }
+
+    #[test]
+    fn test_frame_tap_records_complete_lines() {
+        let dir = std::env::temp_dir().join(format!("codex-recorder-test-{}", now_millis()));
+        let path = dir.join("frames.jsonl");
+        let secrets = Arc::new(Mutex::new(vec!["sk-secret-value".to_string()]));
+        let recording = Arc::new(Recording::create(&path, secrets).expect("create"));
+        let mut tap = FrameTap::new(Some(recording), FrameDirection::Send);
+        tap.feed(br#"{"jsonrpc":"2.0","id":1,"method":"authenticate","params":{"methodId":"sk-secret-value"}}"#);
+        tap.feed(b"\n{\"jsonrpc\":\"2.0\",\"method\":\"session/update\",");
+        tap.feed(b"\"params\":{}}\n");
+
+        let frames = load_recording(&path).expect("load");
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].dir, FrameDirection::Send);
+        assert_eq!(frames[0].frame["params"]["methodId"], REDACTED);
+        assert_eq!(frames[1].frame["method"], "session/update");
+        let _ = fs::remove_dir_all(dir);
+    }
+}
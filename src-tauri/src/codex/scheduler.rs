@@ -0,0 +1,270 @@
+//! Per-session turn scheduling.
+//!
+//! A session runs at most one prompt turn at a time; prompts sent while a turn is
+//! running wait in a per-session queue. Different sessions run their turns in parallel.
+
+use crate::codex::util::now_millis;
+use crate::codex::{
+    events::{CodexEvent, PromptQueuePayload},
+    types::{PromptInput, PromptPart, PromptResult},
+};
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{Mutex, MutexGuard},
+};
+use tauri::{AppHandle, Runtime};
+use tokio::sync::oneshot;
+
+/// Characters of prompt text shown in queue listings.
+const PREVIEW_CHARS: usize = 120;
+
+/// A prompt waiting for its session's running turn to finish.
+pub struct QueuedPrompt {
+    /// Queue entry identifier.
+    pub id: String,
+    /// Session the prompt belongs to.
+    pub session_id: String,
+    /// Prompt content as sent by the frontend.
+    pub content: PromptInput,
+    /// Enqueue time in milliseconds since the Unix epoch.
+    pub queued_at: u64,
+    /// Resolves the original `codex_prompt` call once the turn ran.
+    pub reply: oneshot::Sender<Result<PromptResult>>,
+}
+
+/// Queue entry as reported to the frontend.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedPromptInfo {
+    /// Queue entry identifier.
+    pub id: String,
+    /// Session the prompt belongs to.
+    pub session_id: String,
+    /// 1-based position in the session queue.
+    pub position: usize,
+    /// Beginning of the prompt text.
+    pub preview: String,
+    /// Enqueue time in milliseconds since the Unix epoch.
+    pub queued_at: u64,
+}
+
+#[derive(Default)]
+struct SchedulerInner {
+    running: HashSet<String>,
+    queues: HashMap<String, VecDeque<QueuedPrompt>>,
+    next_id: u64,
+}
+
+/// Tracks running turns and queued prompts for every session.
+#[derive(Default)]
+pub struct TurnScheduler {
+    inner: Mutex<SchedulerInner>,
+}
+
+impl TurnScheduler {
+    /// Mark a session as running a turn; returns false if one is already running.
+    pub fn try_start(&self, session_id: &str) -> bool {
+        self.lock_inner().running.insert(session_id.to_string())
+    }
+
+    /// Whether the session is running a turn.
+    pub fn is_running(&self, session_id: &str) -> bool {
+        self.lock_inner().running.contains(session_id)
+    }
+
+    /// Queue a prompt behind the running turn; `front` places it ahead of earlier prompts.
+    pub fn enqueue(
+        &self,
+        session_id: &str,
+        content: PromptInput,
+        reply: oneshot::Sender<Result<PromptResult>>,
+        front: bool,
+    ) -> String {
+        let mut inner = self.lock_inner();
+        inner.next_id += 1;
+        let id = format!("queued-{}", inner.next_id);
+        let prompt = QueuedPrompt {
+            id: id.clone(),
+            session_id: session_id.to_string(),
+            content,
+            queued_at: now_millis(),
+            reply,
+        };
+        let queue = inner.queues.entry(session_id.to_string()).or_default();
+        if front {
+            queue.push_front(prompt);
+        } else {
+            queue.push_back(prompt);
+        }
+        id
+    }
+
+    /// End the session's running turn and hand out the next queued prompt, if any.
+    /// The session stays marked as running while a next prompt is returned.
+    pub fn finish(&self, session_id: &str) -> Option<QueuedPrompt> {
+        let mut inner = self.lock_inner();
+        let next = inner
+            .queues
+            .get_mut(session_id)
+            .and_then(VecDeque::pop_front);
+        if inner.queues.get(session_id).is_some_and(VecDeque::is_empty) {
+            inner.queues.remove(session_id);
+        }
+        if next.is_none() {
+            inner.running.remove(session_id);
+        }
+        next
+    }
+
+    /// Remove a queued prompt by id.
+    pub fn remove(&self, id: &str) -> Option<QueuedPrompt> {
+        let mut inner = self.lock_inner();
+        let (session_id, index) = inner.queues.iter().find_map(|(session_id, queue)| {
+            queue
+                .iter()
+                .position(|prompt| prompt.id == id)
+                .map(|index| (session_id.clone(), index))
+        })?;
+        let queue = inner.queues.get_mut(&session_id)?;
+        let removed = queue.remove(index);
+        if queue.is_empty() {
+            inner.queues.remove(&session_id);
+        }
+        removed
+    }
+
+    /// Drop all queued prompts of a session that is gone and clear its running flag.
+    pub fn clear_session(&self, session_id: &str) -> Vec<QueuedPrompt> {
+        let mut inner = self.lock_inner();
+        inner.running.remove(session_id);
+        inner
+            .queues
+            .remove(session_id)
+            .map(Vec::from)
+            .unwrap_or_default()
+    }
+
+    /// List queued prompts, optionally for one session.
+    pub fn list(&self, session_id: Option<&str>) -> Vec<QueuedPromptInfo> {
+        let inner = self.lock_inner();
+        let mut out: Vec<QueuedPromptInfo> = inner
+            .queues
+            .iter()
+            .filter(|(id, _)| session_id.map_or(true, |filter| filter == id.as_str()))
+            .flat_map(|(_, queue)| queue.iter().enumerate().map(|(i, p)| info(p, i + 1)))
+            .collect();
+        out.sort_by(|a, b| {
+            a.session_id
+                .cmp(&b.session_id)
+                .then(a.position.cmp(&b.position))
+        });
+        out
+    }
+
+    /// Tell the frontend about the current queue of a session.
+    pub fn emit_queue<R: Runtime>(&self, app: &AppHandle<R>, session_id: &str) {
+        let payload = PromptQueuePayload {
+            session_id,
+            running: self.is_running(session_id),
+            queue: self.list(Some(session_id)),
+        };
+        CodexEvent::PromptQueue(payload).emit(app);
+    }
+
+    fn lock_inner(&self) -> MutexGuard<'_, SchedulerInner> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn info(prompt: &QueuedPrompt, position: usize) -> QueuedPromptInfo {
+    QueuedPromptInfo {
+        id: prompt.id.clone(),
+        session_id: prompt.session_id.clone(),
+        position,
+        preview: preview(&prompt.content),
+        queued_at: prompt.queued_at,
+    }
+}
+
+fn preview(content: &PromptInput) -> String {
+    let text = match content {
+        PromptInput::Text(text) => text.as_str(),
+        PromptInput::Parts(parts) => parts
+            .iter()
+            .find_map(|part| match part {
+                PromptPart::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .unwrap_or_default(),
+    };
+    let text = text.trim();
+    match text.char_indices().nth(PREVIEW_CHARS) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(value: &str) -> PromptInput {
+        PromptInput::Text(value.to_string())
+    }
+
+    #[test]
+    fn test_queue_order_steer_and_remove() {
+        let scheduler = TurnScheduler::default();
+        assert!(scheduler.try_start("s1"));
+        assert!(!scheduler.try_start("s1"));
+        assert!(scheduler.try_start("s2"));
+
+        let (tx, _rx1) = oneshot::channel();
+        let first = scheduler.enqueue("s1", text("first"), tx, false);
+        let (tx, _rx2) = oneshot::channel();
+        let second = scheduler.enqueue("s1", text("second"), tx, false);
+        let (tx, _rx3) = oneshot::channel();
+        let steer = scheduler.enqueue("s1", text("steer"), tx, true);
+
+        let listed: Vec<_> = scheduler
+            .list(Some("s1"))
+            .into_iter()
+            .map(|info| (info.id, info.position))
+            .collect();
+        assert_eq!(
+            listed,
+            vec![(steer.clone(), 1), (first.clone(), 2), (second.clone(), 3)]
+        );
+
+        assert!(scheduler.remove(&first).is_some());
+        assert!(scheduler.remove(&first).is_none());
+
+        assert_eq!(scheduler.finish("s1").map(|p| p.id), Some(steer));
+        assert_eq!(scheduler.finish("s1").map(|p| p.id), Some(second));
+        assert!(scheduler.is_running("s1"));
+        assert!(scheduler.finish("s1").is_none());
+        assert!(!scheduler.is_running("s1"));
+        assert!(scheduler.is_running("s2"));
+    }
+
+    #[test]
+    fn test_preview_uses_first_text_part() {
+        let parts = PromptInput::Parts(vec![
+            PromptPart::ResourceLink {
+                path: "a.rs".to_string(),
+                name: None,
+                mime_type: None,
+            },
+            PromptPart::Text {
+                text: "  fix it ".to_string(),
+            },
+        ]);
+        assert_eq!(preview(&parts), "fix it");
+        assert_eq!(preview(&text(&"x".repeat(200))).chars().count(), 121);
+    }
+}
@@ -1,19 +1,47 @@
 //! Background service for Codex ACP requests.
 
 use crate::codex::{
+    agent_log::{AgentLogLine, AgentLogs},
+    coalesce::flush_chunks,
     debug::DebugState,
+    events::{
+        CodexEvent, ConnectionState, ConnectionStatePayload, PromptAttachmentsPayload,
+        TurnCompletePayload,
+    },
+    fs::FsState,
+    history::{prompt_title, SessionStore},
+    mentions::{attach_mentions, MentionContext},
     process::{resolve_cwd, CodexProcessConfig},
-    protocol::{AcpConnection, ApprovalKey, ApprovalState},
-    types::{ApprovalDecision, InitializeResult, NewSessionResult, PromptResult},
+    profiles::ProfileStore,
+    prompt::{build_prompt, PromptContext},
+    protocol::{
+        emit_approvals_cleared, AcpConnection, ApprovalKey, ApprovalState, PendingApprovalInfo,
+    },
+    recorder::is_secret_name,
+    scheduler::{QueuedPromptInfo, TurnScheduler},
+    timeline::{DebugTraceExport, TraceFormat},
+    types::{
+        ApprovalDecision, ApprovalTimeout, InitializeResult, NewSessionResult, PromptDelivery,
+        PromptInput, PromptResult,
+    },
+    usage::UsageStore,
 };
+use crate::git::checkpoints::{checkpoints_enabled, create_checkpoint};
+use crate::terminal::TerminalManager;
 use agent_client_protocol::{
-    Agent, AuthenticateRequest, CancelNotification, ClientCapabilities, Implementation,
-    InitializeRequest, Meta, NewSessionRequest, PromptRequest, ProtocolVersion, SessionId,
-    SetSessionConfigOptionRequest, TextContent,
+    Agent, AuthenticateRequest, CancelNotification, ClientCapabilities, ContentBlock,
+    FileSystemCapability, Implementation, InitializeRequest, LoadSessionRequest, Meta,
+    NewSessionRequest, PromptRequest, ProtocolVersion, SessionId, SessionModelState,
+    SetSessionConfigOptionRequest,
 };
 use anyhow::{anyhow, Context, Result};
-use std::{collections::BTreeMap, path::PathBuf, sync::Arc, time::Instant};
-use tauri::{AppHandle, Emitter};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tauri::{AppHandle, Manager, Runtime};
 use tokio::sync::{mpsc, oneshot};
 
 #[derive(Clone)]
@@ -21,21 +49,28 @@ use tokio::sync::{mpsc, oneshot};
 pub struct CodexService {
     tx: mpsc::UnboundedSender<ServiceCommand>,
     approvals: Arc<ApprovalState>,
+    debug: Arc<DebugState>,
+    fs: Arc<FsState>,
+    logs: Arc<AgentLogs>,
+    scheduler: Arc<TurnScheduler>,
 }
 
 impl CodexService {
     /// Start a new Codex service worker bound to the Tauri app handle.
-    pub fn new(app: AppHandle) -> Self {
+    pub fn new<R: Runtime>(app: AppHandle<R>) -> Self {
         let approvals = Arc::new(ApprovalState::default());
         let debug = Arc::new(DebugState::new());
+        let fs = Arc::new(FsState::default());
+        let logs = Arc::new(AgentLogs::default());
+        let scheduler = Arc::new(TurnScheduler::default());
         let (tx, rx) = mpsc::unbounded_channel();
 
-        // Load remote server configurations
-        let remote_servers = Self::load_remote_servers(&app);
-
         std::thread::spawn({
             let approvals = approvals.clone();
             let debug = debug.clone();
+            let fs = fs.clone();
+            let logs = logs.clone();
+            let scheduler = scheduler.clone();
             move || {
                 let rt = match tokio::runtime::Builder::new_current_thread()
                     .enable_all()
@@ -50,30 +85,37 @@ impl CodexService {
 
                 rt.block_on(async move {
                     tokio::task::LocalSet::new()
-                        .run_until(worker_loop(app, approvals, debug, remote_servers, rx))
+                        .run_until(worker_loop(app, approvals, debug, fs, logs, scheduler, rx))
                         .await
                 });
             }
         });
 
-        Self { tx, approvals }
+        Self {
+            tx,
+            approvals,
+            debug,
+            fs,
+            logs,
+            scheduler,
+        }
     }
 
     /// Load remote server configurations from the manager
-    fn load_remote_servers(
-        app: &AppHandle,
-    ) -> std::collections::HashMap<String, crate::remote::RemoteServerConfig> {
+    fn load_remote_servers<R: Runtime>(
+        app: &AppHandle<R>,
+    ) -> HashMap<String, crate::remote::RemoteServerConfig> {
         use tauri::Manager;
 
         if let Some(manager) = app.try_state::<crate::remote::RemoteServerManager>() {
             let configs = manager.list();
-            let mut servers = std::collections::HashMap::with_capacity(configs.len());
+            let mut servers = HashMap::with_capacity(configs.len());
             for config in configs {
                 servers.insert(config.id.clone(), config);
             }
             servers
         } else {
-            std::collections::HashMap::new()
+            HashMap::new()
         }
     }
 
@@ -103,12 +145,40 @@ impl CodexService {
             .map_err(|_| anyhow!("codex service worker dropped response"))?
     }
 
-    /// Create a new ACP session rooted at the provided working directory.
-    pub async fn create_session(&self, cwd: PathBuf) -> Result<NewSessionResult> {
+    /// Create a new ACP session rooted at the provided working directory, launching
+    /// the agent with the given environment profile.
+    pub async fn create_session(
+        &self,
+        cwd: PathBuf,
+        profile: Option<String>,
+    ) -> Result<NewSessionResult> {
         let (reply_tx, reply_rx) = oneshot::channel();
         self.tx
             .send(ServiceCommand::NewSession {
                 cwd,
+                profile,
+                reply: reply_tx,
+            })
+            .map_err(|_| anyhow!("codex service worker stopped"))?;
+        reply_rx
+            .await
+            .map_err(|_| anyhow!("codex service worker dropped response"))?
+    }
+
+    /// Load an existing ACP session, replaying its history as session updates.
+    /// Without a profile, the one the session last ran with is used.
+    pub async fn load_session(
+        &self,
+        session_id: String,
+        cwd: PathBuf,
+        profile: Option<String>,
+    ) -> Result<NewSessionResult> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.tx
+            .send(ServiceCommand::LoadSession {
+                session_id,
+                cwd,
+                profile,
                 reply: reply_tx,
             })
             .map_err(|_| anyhow!("codex service worker stopped"))?;
@@ -118,12 +188,18 @@ impl CodexService {
     }
 
     /// Send a prompt to the ACP session.
-    pub async fn send_prompt(&self, session_id: String, content: String) -> Result<PromptResult> {
+    pub async fn send_prompt(
+        &self,
+        session_id: String,
+        content: PromptInput,
+        delivery: PromptDelivery,
+    ) -> Result<PromptResult> {
         let (reply_tx, reply_rx) = oneshot::channel();
         self.tx
             .send(ServiceCommand::Prompt {
                 session_id,
                 content,
+                delivery,
                 reply: reply_tx,
             })
             .map_err(|_| anyhow!("codex service worker stopped"))?;
@@ -146,6 +222,21 @@ impl CodexService {
             .map_err(|_| anyhow!("codex service worker dropped response"))?
     }
 
+    /// Close a live session: its running turn is cancelled and queued prompts fail.
+    /// Sessions that are not live are ignored.
+    pub async fn close_session(&self, session_id: String) -> Result<()> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.tx
+            .send(ServiceCommand::CloseSession {
+                session_id,
+                reply: reply_tx,
+            })
+            .map_err(|_| anyhow!("codex service worker stopped"))?;
+        reply_rx
+            .await
+            .map_err(|_| anyhow!("codex service worker dropped response"))?
+    }
+
     /// Update a session config option by id.
     pub async fn set_config_option(
         &self,
@@ -194,6 +285,82 @@ impl CodexService {
         self.approvals.respond(key, decision, option_id)
     }
 
+    /// List approval requests still waiting for the user.
+    pub fn pending_approvals(&self, session_id: Option<&str>) -> Vec<PendingApprovalInfo> {
+        self.approvals.list(session_id)
+    }
+
+    /// Configure the approval timeout and its default outcome.
+    pub fn set_approval_timeout(&self, timeout: Option<ApprovalTimeout>) {
+        self.approvals.set_timeout(timeout);
+    }
+
+    /// List prompts waiting for their session's running turn, optionally for one session.
+    pub fn queued_prompts(&self, session_id: Option<&str>) -> Vec<QueuedPromptInfo> {
+        self.scheduler.list(session_id)
+    }
+
+    /// Drop a queued prompt; its pending `send_prompt` call fails. Returns false if unknown.
+    pub fn remove_queued_prompt<R: Runtime>(&self, app: &AppHandle<R>, id: &str) -> bool {
+        let Some(prompt) = self.scheduler.remove(id) else {
+            return false;
+        };
+        let _ = prompt.reply.send(Err(anyhow!("prompt removed from queue")));
+        self.scheduler.emit_queue(app, &prompt.session_id);
+        true
+    }
+
+    /// Return the last `lines` stderr lines of the local agent or a remote server's agent.
+    pub fn agent_log(&self, server_id: Option<&str>, lines: usize) -> Vec<AgentLogLine> {
+        self.logs.tail(server_id, lines)
+    }
+
+    /// Record ACP traffic of connections spawned from now on into `dir`, or stop recording.
+    pub fn set_recording_dir(&self, dir: Option<PathBuf>) {
+        self.debug.recorder.set_dir(dir);
+    }
+
+    /// Directory ACP traffic is recorded into, if recording is enabled.
+    pub fn recording_dir(&self) -> Option<PathBuf> {
+        self.debug.recorder.dir()
+    }
+
+    /// Forget the debug timeline of a session.
+    pub fn clear_debug_timeline(&self, session_id: &str) {
+        self.debug.timeline.clear(session_id);
+    }
+
+    /// Export the debug timeline of one session, or of every session, optionally
+    /// writing it to `path`.
+    pub fn export_debug_trace(
+        &self,
+        session_id: Option<&str>,
+        format: TraceFormat,
+        path: Option<PathBuf>,
+    ) -> Result<DebugTraceExport> {
+        let mut export = self.debug.timeline.export(session_id, format);
+        if let Some(path) = path {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("failed to create {}", parent.display()))?;
+            }
+            std::fs::write(&path, &export.content)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+            export.path = Some(path.display().to_string());
+        }
+        Ok(export)
+    }
+
+    /// Expose unsaved editor contents to agent `fs/read_text_file` requests.
+    pub fn set_file_buffer(&self, path: PathBuf, content: String) {
+        self.fs.set_buffer(&path, content);
+    }
+
+    /// Stop overriding a file with unsaved editor contents.
+    pub fn clear_file_buffer(&self, path: PathBuf) {
+        self.fs.clear_buffer(&path);
+    }
+
     /// Warmup the ACP connection without creating a session.
     /// This pre-spawns the codex-acp process and initializes the protocol,
     /// reducing latency for the first actual session creation.
@@ -219,17 +386,29 @@ enum ServiceCommand {
     },
     NewSession {
         cwd: PathBuf,
+        profile: Option<String>,
+        reply: oneshot::Sender<Result<NewSessionResult>>,
+    },
+    LoadSession {
+        session_id: String,
+        cwd: PathBuf,
+        profile: Option<String>,
         reply: oneshot::Sender<Result<NewSessionResult>>,
     },
     Prompt {
         session_id: String,
-        content: String,
+        content: PromptInput,
+        delivery: PromptDelivery,
         reply: oneshot::Sender<Result<PromptResult>>,
     },
     Cancel {
         session_id: String,
         reply: oneshot::Sender<Result<()>>,
     },
+    CloseSession {
+        session_id: String,
+        reply: oneshot::Sender<Result<()>>,
+    },
     SetConfigOption {
         session_id: String,
         config_id: String,
@@ -246,95 +425,470 @@ enum ServiceCommand {
     },
 }
 
-struct WorkerState {
-    app: AppHandle,
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Where a codex-acp connection runs.
+enum ConnectionTarget {
+    Local,
+    Remote { server_id: String },
+}
+
+impl ConnectionTarget {
+    fn server_id(&self) -> Option<&str> {
+        match self {
+            ConnectionTarget::Local => None,
+            ConnectionTarget::Remote { server_id } => Some(server_id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Environment profile a connection was spawned with, at the revision it had then.
+struct ProfileRef {
+    id: String,
+    revision: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Pool key for a codex-acp connection: its target plus the env revision and profile
+/// it was spawned with.
+struct ConnectionKey {
+    target: ConnectionTarget,
+    env_revision: u64,
+    profile: Option<ProfileRef>,
+}
+
+/// A live codex-acp connection and its negotiated initialize result.
+struct ConnectionSlot<R: Runtime> {
+    conn: Arc<AcpConnection<R>>,
+    init: Option<InitializeResult>,
+}
+
+/// Connection and agent-side working directory of a live session.
+struct SessionRoute {
+    key: ConnectionKey,
+    cwd: PathBuf,
+    remote: bool,
+}
+
+/// Pending restart of a connection whose agent process died.
+struct RestartPlan {
+    attempt: u32,
+    next_at: Instant,
+    sessions: Vec<String>,
+    /// Whether an attempt is running as a local task.
+    in_flight: bool,
+}
+
+/// A connection brought back by a restart task.
+struct RestartedConnection<R: Runtime> {
+    /// The new connection, unless the sessions were reloaded on one that was
+    /// re-created while the restart was waiting.
+    spawned: Option<(Arc<AcpConnection<R>>, InitializeResult)>,
+    /// Sessions reloaded on the connection.
+    reloaded: Vec<String>,
+}
+
+/// Outcome of a restart attempt, sent back to the worker.
+struct RestartDone<R: Runtime> {
+    key: ConnectionKey,
+    result: Result<RestartedConnection<R>>,
+}
+
+/// How often live connections are checked for a dead agent process.
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// Restart attempts made before a crashed connection is given up.
+const MAX_RESTART_ATTEMPTS: u32 = 5;
+/// Stderr lines attached to initialize/new-session errors.
+const STDERR_TAIL_LINES: usize = 20;
+/// Longest delay between two restart attempts.
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Delay before the given (1-based) restart attempt: 1s, 2s, 4s, ... capped at 30s.
+fn restart_backoff(attempt: u32) -> Duration {
+    let exp = attempt.saturating_sub(1).min(16);
+    Duration::from_secs(1u64 << exp).min(MAX_RESTART_BACKOFF)
+}
+
+struct WorkerState<R: Runtime> {
+    app: AppHandle<R>,
     approvals: Arc<ApprovalState>,
     debug: Arc<DebugState>,
-    conn: Option<Arc<AcpConnection>>,
-    initialized: bool,
-    last_init: Option<InitializeResult>,
+    fs: Arc<FsState>,
+    logs: Arc<AgentLogs>,
+    scheduler: Arc<TurnScheduler>,
+    turn_done: mpsc::UnboundedSender<String>,
+    restart_done: mpsc::UnboundedSender<RestartDone<R>>,
+    connections: HashMap<ConnectionKey, ConnectionSlot<R>>,
+    sessions: HashMap<String, SessionRoute>,
+    env_revision: u64,
+    auth_method: Option<String>,
     api_key_env: Option<(String, String)>,
     env_overrides: BTreeMap<String, String>,
-    // Remote session support
-    remote_config: Option<crate::remote::RemoteSessionConfig>,
-    remote_servers: std::collections::HashMap<String, crate::remote::RemoteServerConfig>,
+    remote_servers: HashMap<String, crate::remote::RemoteServerConfig>,
+    restarts: HashMap<ConnectionKey, RestartPlan>,
 }
 
-async fn ensure_connection(state: &mut WorkerState) -> Result<()> {
-    if state.conn.is_some() {
-        return Ok(());
+impl<R: Runtime> WorkerState<R> {
+    fn key_for(&self, target: ConnectionTarget) -> ConnectionKey {
+        ConnectionKey {
+            target,
+            env_revision: self.env_revision,
+            profile: None,
+        }
     }
 
-    let conn = if let Some(remote) = &state.remote_config {
-        // Remote mode: spawn codex-acp on remote server via SSH
-        let server = state
-            .remote_servers
-            .get(&remote.server_id)
-            .context("Remote server configuration not found")?;
+    fn local_key(&self) -> ConnectionKey {
+        self.key_for(ConnectionTarget::Local)
+    }
 
-        let api_key = state
-            .api_key_env
+    fn spawn_context(&self) -> SpawnContext<R> {
+        SpawnContext {
+            app: self.app.clone(),
+            approvals: self.approvals.clone(),
+            debug: self.debug.clone(),
+            fs: self.fs.clone(),
+            logs: self.logs.clone(),
+            env_overrides: self.env_overrides.clone(),
+            api_key_env: self.api_key_env.clone(),
+            remote_servers: self.remote_servers.clone(),
+            auth_method: self.auth_method.clone(),
+        }
+    }
+
+    /// Look up the connection that owns a session.
+    fn session_conn(&self, session_id: &str) -> Result<Arc<AcpConnection<R>>> {
+        self.session_slot(session_id).map(|slot| slot.conn.clone())
+    }
+
+    fn session_slot(&self, session_id: &str) -> Result<&ConnectionSlot<R>> {
+        let route = self
+            .sessions
+            .get(session_id)
+            .ok_or_else(|| anyhow!("unknown session: {session_id}"))?;
+        self.connections
+            .get(&route.key)
+            .ok_or_else(|| anyhow!("connection for session {session_id} is closed"))
+    }
+
+    /// Describe where `@` mentions in a session's prompts are read from.
+    fn mention_context(&self, session_id: &str) -> Result<MentionContext> {
+        let route = self
+            .sessions
+            .get(session_id)
+            .ok_or_else(|| anyhow!("unknown session: {session_id}"))?;
+        let remote = match &route.key.target {
+            ConnectionTarget::Local => None,
+            ConnectionTarget::Remote { server_id } => Some(
+                self.remote_servers
+                    .get(server_id)
+                    .cloned()
+                    .context("Remote server configuration not found")?,
+            ),
+        };
+        let embed = self
+            .session_slot(session_id)?
+            .init
             .as_ref()
-            .map(|(key, value)| (key.as_str(), value.as_str()));
-        let local_codex_home =
-            crate::codex::binary::CodexAcpBinary::default_codex_home(Some(&state.app))?;
-
-        let process = crate::remote::RemoteSshProcess::spawn(
-            server,
-            &remote.remote_cwd,
-            local_codex_home.as_path(),
-            api_key,
-        )
-        .await?;
+            .is_some_and(|init| init.agent_capabilities.prompt_capabilities.embedded_context);
+        Ok(MentionContext {
+            cwd: route.cwd.clone(),
+            remote,
+            embed,
+        })
+    }
 
-        let unified_process = crate::codex::unified_process::UnifiedProcess::Remote(process);
+    /// Build the prompt blocks for a session from frontend input.
+    fn session_prompt(&self, session_id: &str, content: PromptInput) -> Result<Vec<ContentBlock>> {
+        let route = self
+            .sessions
+            .get(session_id)
+            .ok_or_else(|| anyhow!("unknown session: {session_id}"))?;
+        let caps = self
+            .session_slot(session_id)?
+            .init
+            .as_ref()
+            .map(|init| init.agent_capabilities.prompt_capabilities.clone())
+            .unwrap_or_default();
+        let ctx = PromptContext {
+            cwd: &route.cwd,
+            remote: route.remote,
+        };
+        build_prompt(content.into_parts(), &ctx, &caps)
+    }
 
-        AcpConnection::spawn_from_unified(
-            state.app.clone(),
-            state.approvals.clone(),
-            state.debug.clone(),
-            unified_process,
-        )
-        .await?
-    } else {
-        // Local mode: spawn local codex-acp process
-        let mut cfg = CodexProcessConfig::default();
+    /// Sessions currently routed to a connection.
+    fn sessions_on(&self, key: &ConnectionKey) -> Vec<String> {
+        let mut sessions: Vec<String> = self
+            .sessions
+            .iter()
+            .filter(|(_, route)| &route.key == key)
+            .map(|(id, _)| id.clone())
+            .collect();
+        sessions.sort();
+        sessions
+    }
 
-        for (key, value) in state.env_overrides.iter() {
-            cfg.set_env(key.as_str(), value.as_str());
+    /// Forget a session whose connection is gone for good.
+    fn drop_session(&mut self, session_id: &str) {
+        if self.sessions.remove(session_id).is_some() {
+            self.fs.unregister_session(session_id);
+            if let Some(terminals) = self.app.try_state::<TerminalManager>() {
+                terminals.release_session(session_id);
+            }
+            self.debug.timeline.clear(session_id);
+            if let Some(usage) = self.app.try_state::<UsageStore>() {
+                usage.end_session(session_id);
+            }
+            let purged = self.approvals.purge_session(session_id);
+            emit_approvals_cleared(&self.app, &purged, "disconnected");
+            for prompt in self.scheduler.clear_session(session_id) {
+                let _ = prompt
+                    .reply
+                    .send(Err(anyhow!("session {session_id} was closed")));
+            }
+            self.scheduler.emit_queue(&self.app, session_id);
         }
+    }
 
-        if let Some((key, value)) = state.api_key_env.as_ref() {
-            cfg.set_env(key.as_str(), value.as_str());
+    /// Whether a connection was spawned with the current env and profile settings.
+    fn is_current(&self, key: &ConnectionKey) -> bool {
+        key.env_revision == self.env_revision
+            && key.profile.as_ref().map_or(true, |profile| {
+                self.app
+                    .try_state::<ProfileStore>()
+                    .and_then(|store| store.revision(&profile.id))
+                    == Some(profile.revision)
+            })
+    }
+
+    /// Start a new env revision and drop idle connections spawned with the old env.
+    async fn bump_env_revision(&mut self) {
+        self.env_revision += 1;
+        self.drop_idle_outdated().await;
+    }
+
+    /// Drop idle connections spawned with an outdated env or profile.
+    /// Connections that still serve sessions keep running until those sessions end,
+    /// so this runs again whenever sessions are dropped or move to another connection.
+    async fn drop_idle_outdated(&mut self) {
+        let idle = idle_outdated(self.connections.keys(), &self.sessions, |key| {
+            self.is_current(key)
+        });
+        for key in idle {
+            if let Some(slot) = self.connections.remove(&key) {
+                let _ = slot.conn.kill().await;
+            }
         }
+    }
+}
 
-        AcpConnection::spawn(
-            state.app.clone(),
-            state.approvals.clone(),
-            state.debug.clone(),
-            cfg,
-        )
-        .await?
-    };
+/// Connections spawned with an outdated env or profile that no session is routed to.
+fn idle_outdated<'a>(
+    keys: impl Iterator<Item = &'a ConnectionKey>,
+    sessions: &HashMap<String, SessionRoute>,
+    is_current: impl Fn(&ConnectionKey) -> bool,
+) -> Vec<ConnectionKey> {
+    keys.filter(|key| !is_current(key) && !sessions.values().any(|route| &route.key == *key))
+        .cloned()
+        .collect()
+}
 
-    state.conn = Some(Arc::new(conn));
-    Ok(())
+/// Tell the UI about a connection lifecycle change.
+fn emit_connection_state<R: Runtime>(
+    app: &AppHandle<R>,
+    key: &ConnectionKey,
+    state: ConnectionState,
+    attempt: Option<u32>,
+    error: Option<String>,
+    sessions: &[String],
+) {
+    CodexEvent::ConnectionState(ConnectionStatePayload {
+        state,
+        server_id: key.target.server_id(),
+        attempt,
+        error: error.as_deref(),
+        sessions,
+    })
+    .emit(app);
 }
 
-async fn initialize_inner(state: &mut WorkerState) -> Result<InitializeResult> {
-    if state.initialized {
-        return state
-            .last_init
-            .clone()
-            .ok_or_else(|| anyhow!("initialized but missing cached InitializeResult"));
+/// Append the agent's recent stderr to an error; startup failures are usually explained there.
+async fn with_stderr_tail<R: Runtime>(
+    conn: &AcpConnection<R>,
+    err: anyhow::Error,
+) -> anyhow::Error {
+    let tail = conn.stderr_tail(STDERR_TAIL_LINES).await;
+    if tail.is_empty() {
+        return err;
     }
+    err.context(format!("agent stderr:\n{tail}"))
+}
 
-    ensure_connection(state).await?;
-    let conn = state.conn.as_ref().context("connection missing")?;
+/// What spawning a connection needs from the worker, copied out so restarts can
+/// spawn from a local task while the worker keeps serving commands.
+struct SpawnContext<R: Runtime> {
+    app: AppHandle<R>,
+    approvals: Arc<ApprovalState>,
+    debug: Arc<DebugState>,
+    fs: Arc<FsState>,
+    logs: Arc<AgentLogs>,
+    env_overrides: BTreeMap<String, String>,
+    api_key_env: Option<(String, String)>,
+    remote_servers: HashMap<String, crate::remote::RemoteServerConfig>,
+    auth_method: Option<String>,
+}
 
+async fn ensure_connection<R: Runtime>(
+    state: &mut WorkerState<R>,
+    key: &ConnectionKey,
+    remote_cwd: Option<&str>,
+) -> Result<Arc<AcpConnection<R>>> {
+    if let Some(slot) = state.connections.get(key) {
+        return Ok(slot.conn.clone());
+    }
+    // The restart task inserts the connection once it is back.
+    if state.restarts.get(key).is_some_and(|plan| plan.in_flight) {
+        return Err(anyhow!("codex-acp is restarting; try again shortly"));
+    }
+
+    let conn = Arc::new(spawn_connection(&state.spawn_context(), key, remote_cwd).await?);
+    state.connections.insert(
+        key.clone(),
+        ConnectionSlot {
+            conn: conn.clone(),
+            init: None,
+        },
+    );
+    Ok(conn)
+}
+
+async fn spawn_connection<R: Runtime>(
+    ctx: &SpawnContext<R>,
+    key: &ConnectionKey,
+    remote_cwd: Option<&str>,
+) -> Result<AcpConnection<R>> {
+    emit_connection_state(&ctx.app, key, ConnectionState::Connecting, None, None, &[]);
+    let conn = match &key.target {
+        ConnectionTarget::Remote { server_id } => {
+            // Remote mode: spawn codex-acp on remote server via SSH
+            let server = ctx
+                .remote_servers
+                .get(server_id)
+                .context("Remote server configuration not found")?;
+
+            let api_key = ctx
+                .api_key_env
+                .as_ref()
+                .map(|(key, value)| (key.as_str(), value.as_str()));
+            let local_codex_home =
+                crate::codex::binary::CodexAcpBinary::default_codex_home(Some(&ctx.app))?;
+
+            let process = crate::remote::RemoteSshProcess::spawn(
+                server,
+                remote_cwd.unwrap_or("~"),
+                local_codex_home.as_path(),
+                api_key,
+            )
+            .await?;
+
+            let unified_process = crate::codex::unified_process::UnifiedProcess::Remote(process);
+
+            AcpConnection::spawn_from_unified(
+                ctx.app.clone(),
+                ctx.approvals.clone(),
+                ctx.debug.clone(),
+                ctx.fs.clone(),
+                ctx.logs.for_target(key.target.server_id()),
+                unified_process,
+            )
+            .await?
+        }
+        ConnectionTarget::Local => {
+            // Local mode: spawn local codex-acp process
+            let mut cfg = CodexProcessConfig::default();
+
+            for (key, value) in ctx.env_overrides.iter() {
+                cfg.set_env(key.as_str(), value.as_str());
+            }
+
+            if let Some((key, value)) = ctx.api_key_env.as_ref() {
+                cfg.set_env(key.as_str(), value.as_str());
+            }
+
+            // The profile is read again on every spawn, so restarts pick up its latest values.
+            if let Some(profile) = &key.profile {
+                let (profile, _) = ctx
+                    .app
+                    .try_state::<ProfileStore>()
+                    .context("environment profiles are not available")?
+                    .resolve(&profile.id)?;
+                for secret in profile.secret_values() {
+                    ctx.debug.recorder.add_secret(secret);
+                }
+                profile.apply(&mut cfg);
+            }
+
+            AcpConnection::spawn(
+                ctx.app.clone(),
+                ctx.approvals.clone(),
+                ctx.debug.clone(),
+                ctx.fs.clone(),
+                ctx.logs.for_target(None),
+                cfg,
+            )
+            .await?
+        }
+    };
+    Ok(conn)
+}
+
+async fn initialize_inner<R: Runtime>(
+    state: &mut WorkerState<R>,
+    key: &ConnectionKey,
+    remote_cwd: Option<&str>,
+) -> Result<InitializeResult> {
+    if let Some(init) = state
+        .connections
+        .get(key)
+        .and_then(|slot| slot.init.clone())
+    {
+        return Ok(init);
+    }
+
+    let conn = ensure_connection(state, key, remote_cwd).await?;
+    let out = match handshake(&conn, key, state.auth_method.clone()).await {
+        Ok(out) => out,
+        Err(err) => {
+            // Do not keep a connection around that never finished the handshake.
+            if let Some(slot) = state.connections.remove(key) {
+                let _ = slot.conn.kill().await;
+            }
+            return Err(err);
+        }
+    };
+    if let Some(slot) = state.connections.get_mut(key) {
+        slot.init = Some(out.clone());
+    }
+    emit_connection_state(&state.app, key, ConnectionState::Ready, None, None, &[]);
+    Ok(out)
+}
+
+/// Run the initialize handshake on a new connection.
+async fn handshake<R: Runtime>(
+    conn: &AcpConnection<R>,
+    key: &ConnectionKey,
+    auth_method: Option<String>,
+) -> Result<InitializeResult> {
     let mut meta = Meta::default();
     meta.insert("terminal_output".to_owned(), serde_json::Value::Bool(true));
-    let client_capabilities = ClientCapabilities::new().meta(meta);
+    // Files and terminals are served from the local machine, so remote agents keep using their own.
+    let local = key.target == ConnectionTarget::Local;
+    let client_capabilities = ClientCapabilities::new()
+        .fs(FileSystemCapability::new()
+            .read_text_file(local)
+            .write_text_file(local))
+        .terminal(local)
+        .meta(meta);
 
     let init = conn
         .conn
@@ -346,143 +900,545 @@ async fn initialize_inner(state: &mut WorkerState) -> Result<InitializeResult> {
                 ))
                 .client_capabilities(client_capabilities),
         )
-        .await
-        .context("initialize failed")?;
+        .await;
+    let init = match init {
+        Ok(init) => init,
+        Err(err) => {
+            let err = anyhow::Error::new(err).context("initialize failed");
+            return Err(with_stderr_tail(conn, err).await);
+        }
+    };
 
-    let out: InitializeResult = init.into();
-    state.initialized = true;
-    state.last_init = Some(out.clone());
-    Ok(out)
+    // Connections spawned after the user authenticated need the same method applied.
+    if let Some(method_id) = auth_method {
+        if let Err(err) = conn
+            .conn
+            .authenticate(AuthenticateRequest::new(method_id))
+            .await
+        {
+            tracing::warn!(error = %err, "failed to re-apply authentication to new connection");
+        }
+    }
+
+    Ok(init.into())
+}
+
+/// Detect dead agent processes and start due restarts. Restarts run as local tasks
+/// and report back through `restart_done`, so a slow recovery does not hold up
+/// commands for other connections.
+async fn supervise<R: Runtime>(state: &mut WorkerState<R>) {
+    let mut dead = Vec::new();
+    for (key, slot) in &state.connections {
+        if !slot.conn.is_alive().await {
+            dead.push(key.clone());
+        }
+    }
+
+    for key in dead {
+        if let Some(slot) = state.connections.remove(&key) {
+            // Reaps the child and resolves its pending approvals.
+            let _ = slot.conn.kill().await;
+        }
+        let sessions = state.sessions_on(&key);
+        tracing::warn!(
+            ?key,
+            sessions = sessions.len(),
+            "codex-acp connection crashed"
+        );
+        // The agent that owned them is gone, even if its sessions come back.
+        if let Some(terminals) = state.app.try_state::<TerminalManager>() {
+            for id in &sessions {
+                terminals.release_session(id);
+            }
+        }
+        emit_connection_state(
+            &state.app,
+            &key,
+            ConnectionState::Crashed,
+            None,
+            None,
+            &sessions,
+        );
+        // Idle connections from an outdated env or profile are not worth bringing back.
+        if sessions.is_empty() && !state.is_current(&key) {
+            continue;
+        }
+        state.restarts.entry(key).or_insert_with(|| RestartPlan {
+            attempt: 1,
+            next_at: Instant::now() + restart_backoff(1),
+            sessions,
+            in_flight: false,
+        });
+    }
+
+    let now = Instant::now();
+    let due: Vec<ConnectionKey> = state
+        .restarts
+        .iter()
+        .filter(|(_, plan)| !plan.in_flight && plan.next_at <= now)
+        .map(|(key, _)| key.clone())
+        .collect();
+    for key in due {
+        let Some(plan) = state.restarts.get_mut(&key) else {
+            continue;
+        };
+        // Sessions may have been closed or re-created while waiting.
+        let sessions = &state.sessions;
+        plan.sessions
+            .retain(|id| sessions.get(id).is_some_and(|route| route.key == key));
+        plan.in_flight = true;
+        let (attempt, sessions) = (plan.attempt, plan.sessions.clone());
+        emit_connection_state(
+            &state.app,
+            &key,
+            ConnectionState::Restarting,
+            Some(attempt),
+            None,
+            &sessions,
+        );
+        start_restart(state, key, &sessions);
+    }
+}
+
+/// Run a restart attempt as a local task.
+fn start_restart<R: Runtime>(state: &WorkerState<R>, key: ConnectionKey, sessions: &[String]) {
+    let routes: Vec<(String, PathBuf)> = sessions
+        .iter()
+        .filter_map(|id| {
+            let route = state.sessions.get(id)?;
+            Some((id.clone(), route.cwd.clone()))
+        })
+        .collect();
+    let remote_cwd = sessions
+        .iter()
+        .filter_map(|id| state.sessions.get(id))
+        .find(|route| route.remote)
+        .map(|route| route.cwd.to_string_lossy().into_owned());
+    // Re-created meanwhile, e.g. by a new session; only the sessions need reloading.
+    let existing = state
+        .connections
+        .get(&key)
+        .and_then(|slot| Some((slot.conn.clone(), slot.init.clone()?)));
+    let ctx = state.spawn_context();
+    let done = state.restart_done.clone();
+    tokio::task::spawn_local(async move {
+        let result = restart_connection(ctx, &key, existing, remote_cwd, routes).await;
+        let _ = done.send(RestartDone { key, result });
+    });
 }
 
-async fn authenticate_inner(
-    state: &mut WorkerState,
+/// Respawn and re-initialize a crashed connection, then reload its sessions.
+/// The agent must support `session/load` for sessions to come back.
+async fn restart_connection<R: Runtime>(
+    ctx: SpawnContext<R>,
+    key: &ConnectionKey,
+    existing: Option<(Arc<AcpConnection<R>>, InitializeResult)>,
+    remote_cwd: Option<String>,
+    sessions: Vec<(String, PathBuf)>,
+) -> Result<RestartedConnection<R>> {
+    let (conn, init, spawned) = match existing {
+        Some((conn, init)) => (conn, init, false),
+        None => {
+            let conn = Arc::new(spawn_connection(&ctx, key, remote_cwd.as_deref()).await?);
+            // handshake re-applies the authentication method chosen by the user.
+            match handshake(&conn, key, ctx.auth_method.clone()).await {
+                Ok(init) => (conn, init, true),
+                Err(err) => {
+                    let _ = conn.kill().await;
+                    return Err(err);
+                }
+            }
+        }
+    };
+
+    let mut reloaded = Vec::with_capacity(sessions.len());
+    if init.agent_capabilities.load_session {
+        for (session_id, cwd) in sessions {
+            // The UI already shows the history; replayed updates are not journaled again.
+            conn.begin_replay(&session_id);
+            let loaded = conn
+                .conn
+                .load_session(LoadSessionRequest::new(
+                    SessionId::from(session_id.clone()),
+                    cwd,
+                ))
+                .await;
+            conn.end_replay(&session_id);
+            match loaded {
+                Ok(_) => reloaded.push(session_id),
+                Err(err) => {
+                    tracing::warn!(error = %err, session_id, "failed to reload session after restart")
+                }
+            }
+        }
+    }
+    Ok(RestartedConnection {
+        spawned: spawned.then_some((conn, init)),
+        reloaded,
+    })
+}
+
+/// Apply the outcome of a restart attempt: adopt the new connection, or schedule the
+/// next attempt until they run out.
+async fn finish_restart<R: Runtime>(state: &mut WorkerState<R>, done: RestartDone<R>) {
+    let RestartDone { key, result } = done;
+    let Some(mut plan) = state.restarts.remove(&key) else {
+        if let Ok(RestartedConnection {
+            spawned: Some((conn, _)),
+            ..
+        }) = result
+        {
+            let _ = conn.kill().await;
+        }
+        return;
+    };
+    // Sessions may have been closed or re-created while the restart ran.
+    plan.sessions
+        .retain(|id| state.sessions.get(id).is_some_and(|route| route.key == key));
+
+    match result {
+        Ok(mut restarted) => {
+            if let Some((conn, init)) = restarted.spawned {
+                if state.connections.contains_key(&key) {
+                    // Sessions were reloaded on a connection that cannot be routed to.
+                    let _ = conn.kill().await;
+                    restarted.reloaded.clear();
+                } else {
+                    state.connections.insert(
+                        key.clone(),
+                        ConnectionSlot {
+                            conn,
+                            init: Some(init),
+                        },
+                    );
+                }
+            }
+            let reloaded: Vec<String> = restarted
+                .reloaded
+                .into_iter()
+                .filter(|id| plan.sessions.contains(id))
+                .collect();
+            for id in plan.sessions.iter().filter(|id| !reloaded.contains(id)) {
+                state.drop_session(id);
+            }
+            state.drop_idle_outdated().await;
+            emit_connection_state(
+                &state.app,
+                &key,
+                ConnectionState::Ready,
+                None,
+                None,
+                &reloaded,
+            );
+        }
+        Err(err) if plan.attempt >= MAX_RESTART_ATTEMPTS => {
+            tracing::error!(error = %err, ?key, "giving up on codex-acp restart");
+            for id in &plan.sessions {
+                state.drop_session(id);
+            }
+            state.drop_idle_outdated().await;
+            emit_connection_state(
+                &state.app,
+                &key,
+                ConnectionState::Crashed,
+                Some(plan.attempt),
+                Some(format!("{err:#}")),
+                &plan.sessions,
+            );
+        }
+        Err(err) => {
+            tracing::warn!(error = %err, attempt = plan.attempt, "codex-acp restart failed");
+            emit_connection_state(
+                &state.app,
+                &key,
+                ConnectionState::Crashed,
+                Some(plan.attempt),
+                Some(format!("{err:#}")),
+                &plan.sessions,
+            );
+            plan.attempt += 1;
+            plan.next_at = Instant::now() + restart_backoff(plan.attempt);
+            plan.in_flight = false;
+            state.restarts.insert(key, plan);
+        }
+    }
+}
+
+async fn authenticate_inner<R: Runtime>(
+    state: &mut WorkerState<R>,
     method_id: String,
     api_key: Option<String>,
 ) -> Result<()> {
     if let Some(api_key) = api_key {
+        state.debug.recorder.add_secret(&api_key);
         let env_key = if method_id.to_ascii_lowercase().contains("codex") {
             "CODEX_API_KEY"
         } else {
             "OPENAI_API_KEY"
         };
         state.api_key_env = Some((env_key.to_string(), api_key));
-
-        if let Some(existing) = state.conn.take() {
-            existing.kill().await?;
-        }
-        state.initialized = false;
-        state.last_init = None;
+        state.bump_env_revision().await;
     }
 
-    let _ = initialize_inner(state).await?;
+    let key = state.local_key();
+    let _ = initialize_inner(state, &key, None).await?;
 
-    let conn = state.conn.as_ref().context("connection missing")?;
+    let conn = ensure_connection(state, &key, None).await?;
     conn.conn
-        .authenticate(AuthenticateRequest::new(method_id))
+        .authenticate(AuthenticateRequest::new(method_id.clone()))
         .await
         .context("authenticate failed")?;
+    state.auth_method = Some(method_id);
 
     Ok(())
 }
 
-async fn new_session_inner(state: &mut WorkerState, cwd: PathBuf) -> Result<NewSessionResult> {
+/// Resolve a session cwd (local or `remote://`) and environment profile into its
+/// connection key and agent-side path.
+fn resolve_session_target<R: Runtime>(
+    state: &WorkerState<R>,
+    cwd: &std::path::Path,
+    profile: Option<&str>,
+) -> Result<(ConnectionKey, PathBuf, bool)> {
     use crate::codex::remote_session::parse_remote_path;
 
     // Parse the cwd to check if it's a remote path
     let cwd_str = cwd.to_string_lossy();
     let (is_remote, server_id, actual_path) = parse_remote_path(&cwd_str)?;
 
-    if is_remote {
-        // Remote session: set up remote configuration
+    let (mut key, path) = if is_remote {
         let server_id =
             server_id.ok_or_else(|| anyhow::anyhow!("Remote path missing server ID"))?;
-        let remote_cwd = actual_path.to_string_lossy().into_owned();
+        (
+            state.key_for(ConnectionTarget::Remote { server_id }),
+            actual_path,
+        )
+    } else {
+        (state.local_key(), resolve_cwd(&actual_path)?)
+    };
 
-        // Set remote configuration
-        state.remote_config = Some(crate::remote::RemoteSessionConfig {
-            server_id,
-            remote_cwd,
+    if let Some(id) = profile {
+        if is_remote {
+            return Err(anyhow!("environment profiles only apply to local sessions"));
+        }
+        let revision = state
+            .app
+            .try_state::<ProfileStore>()
+            .and_then(|store| store.revision(id))
+            .ok_or_else(|| anyhow!("unknown environment profile: {id}"))?;
+        key.profile = Some(ProfileRef {
+            id: id.to_string(),
+            revision,
         });
+    }
+    Ok((key, path, is_remote))
+}
 
-        // Clear any existing connection to force remote connection
-        if let Some(existing) = state.conn.take() {
-            existing.kill().await?;
-        }
-        state.initialized = false;
-        state.last_init = None;
-    } else {
-        // Local session: clear remote configuration
-        if state.remote_config.take().is_some() {
-            // Was remote, now local - clear connection
-            if let Some(existing) = state.conn.take() {
-                existing.kill().await?;
-            }
-            state.initialized = false;
-            state.last_init = None;
-        }
+/// Record which connection owns a session and confine local file access to its cwd.
+fn register_session<R: Runtime>(
+    state: &mut WorkerState<R>,
+    session_id: &str,
+    key: ConnectionKey,
+    cwd: &std::path::Path,
+    is_remote: bool,
+) {
+    if !is_remote {
+        state.fs.register_session(session_id, cwd);
     }
+    state.sessions.insert(
+        session_id.to_string(),
+        SessionRoute {
+            key,
+            cwd: cwd.to_path_buf(),
+            remote: is_remote,
+        },
+    );
+}
 
-    let _ = initialize_inner(state).await?;
-    let conn = state.conn.as_ref().context("connection missing")?;
+async fn new_session_inner<R: Runtime>(
+    state: &mut WorkerState<R>,
+    cwd: PathBuf,
+    profile: Option<String>,
+) -> Result<NewSessionResult> {
+    let (key, resolved_cwd, is_remote) = resolve_session_target(state, &cwd, profile.as_deref())?;
+    state.drop_idle_outdated().await;
+    let remote_cwd = is_remote.then(|| resolved_cwd.to_string_lossy().into_owned());
 
-    let resolved_cwd = if is_remote {
-        actual_path
-    } else {
-        resolve_cwd(&actual_path)?
-    };
+    let _ = initialize_inner(state, &key, remote_cwd.as_deref()).await?;
+    let conn = ensure_connection(state, &key, remote_cwd.as_deref()).await?;
 
-    let session = conn
+    let session = match conn
         .conn
-        .new_session(NewSessionRequest::new(resolved_cwd))
+        .new_session(NewSessionRequest::new(resolved_cwd.clone()))
         .await
-        .context("new_session failed")?;
+    {
+        Ok(session) => session,
+        Err(err) => {
+            let err = anyhow::Error::new(err).context("new_session failed");
+            return Err(with_stderr_tail(&conn, err).await);
+        }
+    };
+
+    let session_id = session.session_id.0.as_ref().to_string();
+    register_session(state, &session_id, key, &resolved_cwd, is_remote);
+    if let Some(store) = state.app.try_state::<SessionStore>() {
+        store.start_session(&session_id, &cwd.to_string_lossy(), profile.as_deref());
+    }
+    if let Some(usage) = state.app.try_state::<UsageStore>() {
+        usage.start_session(&session_id, current_model(&session.models));
+    }
+
+    Ok(NewSessionResult {
+        session_id,
+        modes: session.modes,
+        models: session.models,
+        config_options: session.config_options,
+    })
+}
+
+/// Profile a stored session last ran with, if it still exists.
+fn stored_profile<R: Runtime>(state: &WorkerState<R>, session_id: &str) -> Option<String> {
+    let profile = state.app.try_state::<SessionStore>()?.profile(session_id)?;
+    let exists = state
+        .app
+        .try_state::<ProfileStore>()
+        .is_some_and(|store| store.revision(&profile).is_some());
+    if !exists {
+        tracing::warn!(
+            session_id,
+            profile,
+            "stored environment profile no longer exists"
+        );
+    }
+    exists.then_some(profile)
+}
+
+fn current_model(models: &Option<SessionModelState>) -> Option<String> {
+    models
+        .as_ref()
+        .map(|models| models.current_model_id.0.to_string())
+}
+
+async fn load_session_inner<R: Runtime>(
+    state: &mut WorkerState<R>,
+    session_id: String,
+    cwd: PathBuf,
+    profile: Option<String>,
+) -> Result<NewSessionResult> {
+    let profile = profile.or_else(|| stored_profile(state, &session_id));
+    let (key, resolved_cwd, is_remote) = resolve_session_target(state, &cwd, profile.as_deref())?;
+    state.drop_idle_outdated().await;
+    let remote_cwd = is_remote.then(|| resolved_cwd.to_string_lossy().into_owned());
+
+    let init = initialize_inner(state, &key, remote_cwd.as_deref()).await?;
+    if !init.agent_capabilities.load_session {
+        return Err(anyhow!("agent does not support loading sessions"));
+    }
+    let conn = ensure_connection(state, &key, remote_cwd.as_deref()).await?;
+
+    // The agent streams the stored history as session updates before answering the request.
+    conn.begin_replay(&session_id);
+    let loaded = conn
+        .conn
+        .load_session(LoadSessionRequest::new(
+            SessionId::from(session_id.clone()),
+            resolved_cwd.clone(),
+        ))
+        .await;
+    conn.end_replay(&session_id);
+    let session = loaded.context("load_session failed")?;
+
+    register_session(state, &session_id, key, &resolved_cwd, is_remote);
+    // The session may have moved off a connection spawned with an older env.
+    state.drop_idle_outdated().await;
+    if let Some(store) = state.app.try_state::<SessionStore>() {
+        store.resume_session(&session_id, &cwd.to_string_lossy(), profile.as_deref());
+    }
+    if let Some(usage) = state.app.try_state::<UsageStore>() {
+        usage.set_session_model(&session_id, current_model(&session.models));
+    }
 
     Ok(NewSessionResult {
-        session_id: session.session_id.0.as_ref().to_string(),
+        session_id,
         modes: session.modes,
         models: session.models,
         config_options: session.config_options,
     })
 }
 
-async fn prompt_inner(
-    conn: Arc<AcpConnection>,
-    app: AppHandle,
+async fn prompt_inner<R: Runtime>(
+    conn: Arc<AcpConnection<R>>,
+    app: AppHandle<R>,
     debug: Arc<DebugState>,
     session_id: String,
-    content: String,
+    blocks: Vec<ContentBlock>,
+    mentions: MentionContext,
 ) -> Result<PromptResult> {
     let session_id = Arc::<str>::from(session_id);
     let session_id_typed = SessionId::from(session_id.clone());
 
+    let (blocks, attachments) = attach_mentions(blocks, &mentions).await;
+    if !attachments.is_empty() {
+        CodexEvent::PromptAttachments(PromptAttachmentsPayload {
+            session_id: session_id.as_ref(),
+            attachments: &attachments,
+        })
+        .emit(&app);
+    }
+
     let timing = debug.mark_prompt(session_id.as_ref());
     debug.emit(
         &app,
         Some(session_id.as_ref()),
         "prompt_start",
         timing,
-        serde_json::json!({ "contentLen": content.len() }),
+        serde_json::json!({ "blocks": blocks.len() }),
     );
 
-    let request = PromptRequest::new(
-        session_id_typed,
-        vec![agent_client_protocol::ContentBlock::Text(TextContent::new(
-            content,
-        ))],
-    );
+    let store = app.try_state::<SessionStore>();
+    if let Some(store) = &store {
+        store.record_prompt(session_id.as_ref(), &blocks);
+    }
+    if mentions.remote.is_none() && checkpoints_enabled() {
+        let turn = store
+            .as_ref()
+            .and_then(|store| store.prompt_count(session_id.as_ref()));
+        take_checkpoint(&app, session_id.as_ref(), &mentions.cwd, turn, &blocks).await;
+    }
 
-    let resp = conn.conn.prompt(request).await.context("prompt failed")?;
+    let request = PromptRequest::new(session_id_typed, blocks);
+
+    let resp = conn.conn.prompt(request).await;
+    let usage = app
+        .try_state::<UsageStore>()
+        .map(|usage| usage.finish_turn(session_id.as_ref(), resp.is_ok()))
+        .filter(|turn| turn.tokens.total() > 0);
+    flush_chunks(&app, session_id.as_ref());
+    let resp = match resp.context("prompt failed") {
+        Ok(resp) => resp,
+        Err(err) => {
+            let timing = debug.mark_event(session_id.as_ref());
+            debug.emit(
+                &app,
+                Some(session_id.as_ref()),
+                "prompt_failed",
+                timing,
+                serde_json::json!({ "error": format!("{err:#}") }),
+            );
+            return Err(err);
+        }
+    };
+    if let Some(store) = &store {
+        store.record_stop(session_id.as_ref(), resp.stop_reason);
+    }
 
     let stop_reason_value =
         serde_json::to_value(resp.stop_reason).unwrap_or(serde_json::Value::Null);
-    let _ = app.emit(
-        crate::codex::events::EVENT_TURN_COMPLETE,
-        serde_json::json!({
-            "sessionId": session_id.as_ref(),
-            "stopReason": &stop_reason_value
-        }),
-    );
+    CodexEvent::TurnComplete(TurnCompletePayload {
+        session_id: session_id.as_ref(),
+        stop_reason: resp.stop_reason,
+    })
+    .emit(&app);
 
     let timing = debug.mark_event(session_id.as_ref());
     debug.emit(
@@ -495,59 +1451,161 @@ async fn prompt_inner(
 
     Ok(PromptResult {
         stop_reason: stop_reason_value,
+        attachments,
+        usage,
     })
 }
 
-async fn cancel_inner(state: &mut WorkerState, session_id: String) -> Result<()> {
-    let _ = initialize_inner(state).await?;
-    let conn = state.conn.as_ref().context("connection missing")?;
+/// Snapshot the working tree of a local session before a turn so it can be rolled
+/// back. Failures only cost the checkpoint, never the turn.
+async fn take_checkpoint<R: Runtime>(
+    app: &AppHandle<R>,
+    session_id: &str,
+    cwd: &Path,
+    turn: Option<u32>,
+    blocks: &[ContentBlock],
+) {
+    let label = prompt_title(blocks).unwrap_or_default();
+    let (cwd, session) = (cwd.to_path_buf(), session_id.to_string());
+    let result =
+        tokio::task::spawn_blocking(move || create_checkpoint(&cwd, &session, turn, &label)).await;
+    match result {
+        Ok(Ok(Some(checkpoint))) => CodexEvent::Checkpoint(&checkpoint).emit(app),
+        Ok(Ok(None)) => {}
+        Ok(Err(err)) => tracing::warn!(error = %err, session_id, "failed to create checkpoint"),
+        Err(err) => tracing::warn!(error = %err, session_id, "checkpoint task failed"),
+    }
+}
+
+/// Run a prompt turn for a session the scheduler has marked as running.
+/// Turns run as local tasks so other sessions and commands keep being served;
+/// the worker is told through `turn_done` when the session is free again.
+fn start_turn<R: Runtime>(
+    state: &WorkerState<R>,
+    session_id: String,
+    content: PromptInput,
+    reply: oneshot::Sender<Result<PromptResult>>,
+) {
+    state.scheduler.emit_queue(&state.app, &session_id);
+    let prepared = state.session_conn(&session_id).and_then(|conn| {
+        let blocks = state.session_prompt(&session_id, content)?;
+        let mentions = state.mention_context(&session_id)?;
+        Ok((conn, blocks, mentions))
+    });
+    match prepared {
+        Ok((conn, content, mentions)) => {
+            let app = state.app.clone();
+            let debug = state.debug.clone();
+            let done = state.turn_done.clone();
+            tokio::task::spawn_local(async move {
+                let res =
+                    prompt_inner(conn, app, debug, session_id.clone(), content, mentions).await;
+                let _ = reply.send(res);
+                let _ = done.send(session_id);
+            });
+        }
+        Err(err) => {
+            let _ = reply.send(Err(err));
+            let _ = state.turn_done.send(session_id);
+        }
+    }
+}
+
+/// Hand a finished session its next queued prompt.
+fn finish_turn<R: Runtime>(state: &WorkerState<R>, session_id: &str) {
+    match state.scheduler.finish(session_id) {
+        Some(next) => start_turn(state, next.session_id, next.content, next.reply),
+        None => state.scheduler.emit_queue(&state.app, session_id),
+    }
+}
+
+async fn cancel_inner<R: Runtime>(state: &mut WorkerState<R>, session_id: String) -> Result<()> {
+    let conn = state.session_conn(&session_id)?;
     conn.conn
-        .cancel(CancelNotification::new(SessionId::from(session_id)))
+        .cancel(CancelNotification::new(SessionId::from(session_id.clone())))
         .await
         .context("cancel failed")?;
+    // The turn is over; outstanding permission requests resolve as cancelled.
+    let purged = state.approvals.purge_session(&session_id);
+    emit_approvals_cleared(&state.app, &purged, "cancelled");
     Ok(())
 }
 
-async fn set_config_option_inner(
-    state: &mut WorkerState,
+async fn set_config_option_inner<R: Runtime>(
+    state: &mut WorkerState<R>,
     session_id: String,
     config_id: String,
     value_id: String,
 ) -> Result<()> {
-    let _ = initialize_inner(state).await?;
-    let conn = state.conn.as_ref().context("connection missing")?;
+    let conn = state.session_conn(&session_id)?;
+    let model = (config_id == "model").then(|| value_id.clone());
     conn.conn
         .set_session_config_option(SetSessionConfigOptionRequest::new(
-            SessionId::from(session_id),
+            SessionId::from(session_id.clone()),
             config_id,
             value_id,
         ))
         .await
         .context("set_session_config_option failed")?;
+    if let Some(usage) = state.app.try_state::<UsageStore>() {
+        usage.set_session_model(&session_id, model);
+    }
     Ok(())
 }
 
-async fn worker_loop(
-    app: AppHandle,
+async fn worker_loop<R: Runtime>(
+    app: AppHandle<R>,
     approvals: Arc<ApprovalState>,
     debug: Arc<DebugState>,
-    remote_servers: std::collections::HashMap<String, crate::remote::RemoteServerConfig>,
+    fs: Arc<FsState>,
+    logs: Arc<AgentLogs>,
+    scheduler: Arc<TurnScheduler>,
     mut rx: mpsc::UnboundedReceiver<ServiceCommand>,
 ) {
+    // Load remote server configurations
+    let remote_servers = CodexService::load_remote_servers(&app);
+    let (turn_done, mut turn_done_rx) = mpsc::unbounded_channel::<String>();
+    let (restart_done, mut restart_done_rx) = mpsc::unbounded_channel();
     let mut state = WorkerState {
         app,
         approvals,
         debug,
-        conn: None,
-        initialized: false,
-        last_init: None,
+        fs,
+        logs,
+        scheduler,
+        turn_done,
+        restart_done,
+        connections: HashMap::new(),
+        sessions: HashMap::new(),
+        env_revision: 0,
+        auth_method: None,
         api_key_env: None,
         env_overrides: BTreeMap::new(),
-        remote_config: None,
         remote_servers,
+        restarts: HashMap::new(),
     };
 
-    while let Some(cmd) = rx.recv().await {
+    let mut health = tokio::time::interval(HEALTH_CHECK_INTERVAL);
+    health.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    loop {
+        let cmd = tokio::select! {
+            cmd = rx.recv() => match cmd {
+                Some(cmd) => cmd,
+                None => break,
+            },
+            Some(session_id) = turn_done_rx.recv() => {
+                finish_turn(&state, &session_id);
+                continue;
+            }
+            Some(done) = restart_done_rx.recv() => {
+                finish_restart(&mut state, done).await;
+                continue;
+            }
+            _ = health.tick() => {
+                supervise(&mut state).await;
+                continue;
+            }
+        };
         match cmd {
             ServiceCommand::Initialize { reply } => {
                 let timing = state.debug.mark_global();
@@ -560,7 +1618,8 @@ async fn worker_loop(
                 );
 
                 let start = Instant::now();
-                let result = initialize_inner(&mut state).await;
+                let key = state.local_key();
+                let result = initialize_inner(&mut state, &key, None).await;
                 let duration_ms = start.elapsed().as_millis().try_into().unwrap_or(u64::MAX);
 
                 let timing = state.debug.mark_global();
@@ -603,7 +1662,11 @@ async fn worker_loop(
 
                 let _ = reply.send(result);
             }
-            ServiceCommand::NewSession { cwd, reply } => {
+            ServiceCommand::NewSession {
+                cwd,
+                profile,
+                reply,
+            } => {
                 let timing = state.debug.mark_global();
                 let cwd_label = cwd.display().to_string();
                 state.debug.emit(
@@ -611,11 +1674,11 @@ async fn worker_loop(
                     None,
                     "new_session_start",
                     timing,
-                    serde_json::json!({ "cwd": cwd_label }),
+                    serde_json::json!({ "cwd": cwd_label, "profile": &profile }),
                 );
 
                 let start = Instant::now();
-                let result = new_session_inner(&mut state, cwd).await;
+                let result = new_session_inner(&mut state, cwd, profile).await;
                 let duration_ms = start.elapsed().as_millis().try_into().unwrap_or(u64::MAX);
 
                 let timing = state.debug.mark_global();
@@ -629,28 +1692,57 @@ async fn worker_loop(
 
                 let _ = reply.send(result);
             }
+            ServiceCommand::LoadSession {
+                session_id,
+                cwd,
+                profile,
+                reply,
+            } => {
+                let timing = state.debug.mark_event(&session_id);
+                let cwd_label = cwd.display().to_string();
+                state.debug.emit(
+                    &state.app,
+                    Some(&session_id),
+                    "load_session_start",
+                    timing,
+                    serde_json::json!({ "cwd": cwd_label, "profile": &profile }),
+                );
+
+                let start = Instant::now();
+                let result = load_session_inner(&mut state, session_id.clone(), cwd, profile).await;
+                let duration_ms = start.elapsed().as_millis().try_into().unwrap_or(u64::MAX);
+
+                let timing = state.debug.mark_event(&session_id);
+                state.debug.emit(
+                    &state.app,
+                    Some(&session_id),
+                    "load_session_end",
+                    timing,
+                    serde_json::json!({ "ok": result.is_ok(), "durationMs": duration_ms }),
+                );
+
+                let _ = reply.send(result);
+            }
             ServiceCommand::Prompt {
                 session_id,
                 content,
+                delivery,
                 reply,
             } => {
-                // Ensure Initialize has happened once for this connection, then run prompt in a
-                // separate local task so cancellation/config updates can still be processed.
-                match initialize_inner(&mut state).await {
-                    Ok(_) => {
-                        let Some(conn) = state.conn.clone() else {
-                            let _ = reply.send(Err(anyhow!("initialized but connection missing")));
-                            continue;
-                        };
-                        let app = state.app.clone();
-                        let debug = state.debug.clone();
-                        tokio::task::spawn_local(async move {
-                            let res = prompt_inner(conn, app, debug, session_id, content).await;
-                            let _ = reply.send(res);
-                        });
-                    }
-                    Err(err) => {
-                        let _ = reply.send(Err(err));
+                if !state.sessions.contains_key(&session_id) {
+                    let _ = reply.send(Err(anyhow!("unknown session: {session_id}")));
+                } else if state.scheduler.try_start(&session_id) {
+                    start_turn(&state, session_id, content, reply);
+                } else {
+                    // The session is busy: wait behind the running turn, or steer by
+                    // cancelling it and going first once it has stopped.
+                    let steer = delivery == PromptDelivery::Steer;
+                    state.scheduler.enqueue(&session_id, content, reply, steer);
+                    state.scheduler.emit_queue(&state.app, &session_id);
+                    if steer {
+                        if let Err(err) = cancel_inner(&mut state, session_id).await {
+                            tracing::warn!(error = %err, "failed to cancel turn for steering");
+                        }
                     }
                 }
             }
@@ -679,6 +1771,18 @@ async fn worker_loop(
 
                 let _ = reply.send(result);
             }
+            ServiceCommand::CloseSession { session_id, reply } => {
+                if state.sessions.contains_key(&session_id) {
+                    if state.scheduler.is_running(&session_id) {
+                        if let Err(err) = cancel_inner(&mut state, session_id.clone()).await {
+                            tracing::warn!(error = %err, session_id, "failed to cancel closed session");
+                        }
+                    }
+                    state.drop_session(&session_id);
+                    state.drop_idle_outdated().await;
+                }
+                let _ = reply.send(Ok(()));
+            }
             ServiceCommand::SetConfigOption {
                 session_id,
                 config_id,
@@ -712,14 +1816,13 @@ async fn worker_loop(
                 let _ = reply.send(result);
             }
             ServiceCommand::SetEnv { key, value, reply } => {
+                if is_secret_name(&key) {
+                    state.debug.recorder.add_secret(&value);
+                }
                 let existing = state.env_overrides.get(&key).cloned();
                 if existing.as_deref() != Some(&value) {
                     state.env_overrides.insert(key, value);
-                    if let Some(conn) = state.conn.take() {
-                        let _ = conn.kill().await;
-                    }
-                    state.initialized = false;
-                    state.last_init = None;
+                    state.bump_env_revision().await;
                 }
                 let _ = reply.send(Ok(()));
             }
@@ -735,7 +1838,8 @@ async fn worker_loop(
 
                 let start = Instant::now();
                 // Warmup just ensures connection is established and initialized
-                let result = initialize_inner(&mut state).await.map(|_| ());
+                let key = state.local_key();
+                let result = initialize_inner(&mut state, &key, None).await.map(|_| ());
                 let duration_ms = start.elapsed().as_millis().try_into().unwrap_or(u64::MAX);
 
                 let timing = state.debug.mark_global();
@@ -752,3 +1856,54 @@ async fn worker_loop(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(env_revision: u64) -> ConnectionKey {
+        ConnectionKey {
+            target: ConnectionTarget::Local,
+            env_revision,
+            profile: None,
+        }
+    }
+
+    fn route(key: ConnectionKey) -> SessionRoute {
+        SessionRoute {
+            key,
+            cwd: PathBuf::from("/repo"),
+            remote: false,
+        }
+    }
+
+    #[test]
+    fn test_idle_outdated_connections() {
+        let keys = [key(0), key(1), key(2)];
+        let is_current = |key: &ConnectionKey| key.env_revision == 2;
+        let mut sessions = HashMap::new();
+        sessions.insert("a".to_string(), route(key(0)));
+        sessions.insert("b".to_string(), route(key(2)));
+        assert_eq!(
+            idle_outdated(keys.iter(), &sessions, is_current),
+            vec![key(1)]
+        );
+
+        // Once its last session is gone or moved, the old connection is idle too.
+        sessions.insert("a".to_string(), route(key(2)));
+        assert_eq!(
+            idle_outdated(keys.iter(), &sessions, is_current),
+            vec![key(0), key(1)]
+        );
+        sessions.clear();
+        assert_eq!(idle_outdated(keys.iter(), &sessions, is_current).len(), 2);
+    }
+
+    #[test]
+    fn test_restart_backoff_doubles_and_caps() {
+        assert_eq!(restart_backoff(1), Duration::from_secs(1));
+        assert_eq!(restart_backoff(3), Duration::from_secs(4));
+        assert_eq!(restart_backoff(6), MAX_RESTART_BACKOFF);
+        assert_eq!(restart_backoff(u32::MAX), MAX_RESTART_BACKOFF);
+    }
+}
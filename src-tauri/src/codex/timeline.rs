@@ -0,0 +1,534 @@
+//! Bounded per-session timeline of debug events and its trace exports.
+//!
+//! Every event passed to `DebugState::emit` is kept here, up to a fixed number per
+//! session. The timeline can be exported as Chrome trace-event JSON (loadable in
+//! `chrome://tracing` or Perfetto) or as CSV, together with per-turn summaries.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Write as _,
+    sync::{Mutex, MutexGuard},
+};
+
+/// Events kept per session (and for global events).
+const TIMELINE_CAPACITY: usize = 5000;
+
+/// One recorded debug event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimelineEvent {
+    /// Session the event belongs to, or `None` for global events.
+    pub session_id: Option<String>,
+    /// Event label, e.g. `prompt_start` or `tool_call`.
+    pub label: String,
+    /// Absolute timestamp (ms since epoch).
+    pub ts_ms: u64,
+    /// Time since process start (ms).
+    pub dt_ms: u64,
+    /// Time since last prompt for the session (ms).
+    pub since_prompt_ms: Option<u64>,
+    /// Time since last event for the session (ms).
+    pub since_last_event_ms: Option<u64>,
+    /// Label-specific details.
+    pub extra: Value,
+}
+
+/// A tool call within a turn.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolSpan {
+    pub tool_call_id: String,
+    pub title: Option<String>,
+    /// Start relative to the turn start (ms).
+    pub start_ms: u64,
+    /// Time until the tool call completed or failed; `None` if it never finished.
+    pub duration_ms: Option<u64>,
+    pub status: Option<String>,
+}
+
+/// A permission request within a turn.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApprovalSpan {
+    pub tool_call_id: String,
+    /// Start relative to the turn start (ms).
+    pub start_ms: u64,
+    /// Time until the request was answered; `None` if it never was.
+    pub wait_ms: Option<u64>,
+    /// Whether an approval rule answered the request.
+    pub auto: bool,
+    pub option_id: Option<String>,
+}
+
+/// Timing summary of one prompt turn.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TurnSummary {
+    pub session_id: String,
+    /// Position of the turn in the session's timeline, starting at 1.
+    pub index: usize,
+    pub started_at_ms: u64,
+    /// Turn duration; `None` while the turn is still running.
+    pub duration_ms: Option<u64>,
+    pub stop_reason: Option<Value>,
+    pub error: Option<String>,
+    /// Time from the prompt to the first message or thought chunk.
+    pub time_to_first_token_ms: Option<u64>,
+    pub message_chunks: usize,
+    pub thought_chunks: usize,
+    pub tools: Vec<ToolSpan>,
+    pub approvals: Vec<ApprovalSpan>,
+    /// Total time spent waiting for approvals.
+    pub approval_wait_ms: u64,
+}
+
+/// Supported debug trace export formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TraceFormat {
+    /// Chrome trace-event JSON.
+    Chrome,
+    /// One row per event.
+    Csv,
+}
+
+/// Exported debug trace.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DebugTraceExport {
+    pub format: TraceFormat,
+    /// Trace file contents.
+    pub content: String,
+    /// File the trace was written to, if a path was given.
+    pub path: Option<String>,
+    pub events: usize,
+    pub turns: Vec<TurnSummary>,
+}
+
+/// Bounded debug event buffers keyed by session (`None` for global events).
+#[derive(Debug, Default)]
+pub struct DebugTimeline {
+    events: Mutex<HashMap<Option<String>, VecDeque<TimelineEvent>>>,
+}
+
+impl DebugTimeline {
+    fn lock_events(&self) -> MutexGuard<'_, HashMap<Option<String>, VecDeque<TimelineEvent>>> {
+        self.events
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Append an event, dropping the oldest one of its session when full.
+    pub fn push(&self, event: TimelineEvent) {
+        let mut events = self.lock_events();
+        let buffer = events.entry(event.session_id.clone()).or_default();
+        if buffer.len() == TIMELINE_CAPACITY {
+            buffer.pop_front();
+        }
+        buffer.push_back(event);
+    }
+
+    /// Events of one session, or of every session and global events, in time order.
+    pub fn events(&self, session_id: Option<&str>) -> Vec<TimelineEvent> {
+        let events = self.lock_events();
+        let mut out: Vec<TimelineEvent> = match session_id {
+            Some(session_id) => events
+                .get(&Some(session_id.to_string()))
+                .map(|buffer| buffer.iter().cloned().collect())
+                .unwrap_or_default(),
+            None => events.values().flatten().cloned().collect(),
+        };
+        out.sort_by_key(|event| event.dt_ms);
+        out
+    }
+
+    /// Drop the timeline of a session.
+    pub fn clear(&self, session_id: &str) {
+        self.lock_events().remove(&Some(session_id.to_string()));
+    }
+
+    /// Export the timeline of one session, or of everything, in `format`.
+    pub fn export(&self, session_id: Option<&str>, format: TraceFormat) -> DebugTraceExport {
+        let events = self.events(session_id);
+        let turns = summarize_turns(&events);
+        let content = match format {
+            TraceFormat::Chrome => chrome_trace(&events, &turns).to_string(),
+            TraceFormat::Csv => csv_trace(&events),
+        };
+        DebugTraceExport {
+            format,
+            content,
+            path: None,
+            events: events.len(),
+            turns,
+        }
+    }
+}
+
+fn extra_str(event: &TimelineEvent, key: &str) -> Option<String> {
+    event
+        .extra
+        .get(key)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
+
+fn is_finished(status: Option<&str>) -> bool {
+    matches!(status, Some("completed" | "failed"))
+}
+
+/// Split events into prompt turns and compute their timings.
+///
+/// A turn runs from `prompt_start` to `prompt_done` or `prompt_failed` of the same
+/// session; events outside a turn are ignored.
+pub fn summarize_turns(events: &[TimelineEvent]) -> Vec<TurnSummary> {
+    let mut done = Vec::new();
+    let mut open: HashMap<&str, TurnSummary> = HashMap::new();
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+
+    for event in events {
+        let Some(session_id) = event.session_id.as_deref() else {
+            continue;
+        };
+        if event.label == "prompt_start" {
+            if let Some(turn) = open.remove(session_id) {
+                done.push(turn);
+            }
+            let index = counts.entry(session_id).or_default();
+            *index += 1;
+            open.insert(
+                session_id,
+                TurnSummary {
+                    session_id: session_id.to_string(),
+                    index: *index,
+                    started_at_ms: event.ts_ms,
+                    duration_ms: None,
+                    stop_reason: None,
+                    error: None,
+                    time_to_first_token_ms: None,
+                    message_chunks: 0,
+                    thought_chunks: 0,
+                    tools: Vec::new(),
+                    approvals: Vec::new(),
+                    approval_wait_ms: 0,
+                },
+            );
+            continue;
+        }
+        let Some(turn) = open.get_mut(session_id) else {
+            continue;
+        };
+        let offset = event.ts_ms.saturating_sub(turn.started_at_ms);
+        match event.label.as_str() {
+            "agent_message_chunk" | "agent_thought_chunk" => {
+                if event.label == "agent_message_chunk" {
+                    turn.message_chunks += 1;
+                } else {
+                    turn.thought_chunks += 1;
+                }
+                turn.time_to_first_token_ms.get_or_insert(offset);
+            }
+            "tool_call" => {
+                let status = extra_str(event, "status");
+                turn.tools.push(ToolSpan {
+                    tool_call_id: extra_str(event, "toolCallId").unwrap_or_default(),
+                    title: extra_str(event, "title"),
+                    start_ms: offset,
+                    duration_ms: is_finished(status.as_deref()).then_some(0),
+                    status,
+                });
+            }
+            "tool_call_update" => {
+                let id = extra_str(event, "toolCallId").unwrap_or_default();
+                let status = extra_str(event, "status");
+                if let Some(tool) = turn
+                    .tools
+                    .iter_mut()
+                    .rev()
+                    .find(|tool| tool.tool_call_id == id)
+                {
+                    if status.is_some() {
+                        tool.status = status;
+                    }
+                    if tool.duration_ms.is_none() && is_finished(tool.status.as_deref()) {
+                        tool.duration_ms = Some(offset.saturating_sub(tool.start_ms));
+                    }
+                }
+            }
+            "request_permission" => turn.approvals.push(ApprovalSpan {
+                tool_call_id: extra_str(event, "toolCallId").unwrap_or_default(),
+                start_ms: offset,
+                wait_ms: None,
+                auto: false,
+                option_id: None,
+            }),
+            "approval_auto_decided" | "approval_resolved" => {
+                let id = extra_str(event, "toolCallId").unwrap_or_default();
+                if let Some(approval) = turn
+                    .approvals
+                    .iter_mut()
+                    .rev()
+                    .find(|approval| approval.tool_call_id == id && approval.wait_ms.is_none())
+                {
+                    let wait = offset.saturating_sub(approval.start_ms);
+                    approval.wait_ms = Some(wait);
+                    approval.auto = event.label == "approval_auto_decided";
+                    approval.option_id = extra_str(event, "optionId");
+                    turn.approval_wait_ms += wait;
+                }
+            }
+            "prompt_done" | "prompt_failed" => {
+                turn.duration_ms = Some(offset);
+                turn.stop_reason = event.extra.get("stopReason").cloned();
+                turn.error = extra_str(event, "error");
+                done.extend(open.remove(session_id));
+            }
+            _ => {}
+        }
+    }
+
+    done.extend(open.into_values());
+    done.sort_by_key(|turn| turn.started_at_ms);
+    done
+}
+
+/// Build a Chrome trace-event document: one thread per session, turns as complete
+/// events, tool calls and approvals as async spans and everything else as instants.
+pub fn chrome_trace(events: &[TimelineEvent], turns: &[TurnSummary]) -> Value {
+    const PID: u64 = 1;
+    let mut threads: Vec<Option<&str>> = Vec::new();
+    for event in events {
+        if !threads.contains(&event.session_id.as_deref()) {
+            threads.push(event.session_id.as_deref());
+        }
+    }
+    let tid = |session_id: Option<&str>| {
+        threads
+            .iter()
+            .position(|thread| *thread == session_id)
+            .unwrap_or_default() as u64
+    };
+    let us = |ms: u64| ms.saturating_mul(1000);
+
+    let mut trace: Vec<Value> = threads
+        .iter()
+        .map(|thread| {
+            json!({
+                "ph": "M",
+                "name": "thread_name",
+                "pid": PID,
+                "tid": tid(*thread),
+                "args": { "name": thread.unwrap_or("global") },
+            })
+        })
+        .collect();
+
+    for event in events {
+        trace.push(json!({
+            "ph": "i",
+            "s": "t",
+            "name": &event.label,
+            "cat": "event",
+            "pid": PID,
+            "tid": tid(event.session_id.as_deref()),
+            "ts": us(event.ts_ms),
+            "args": &event.extra,
+        }));
+    }
+
+    for turn in turns {
+        let thread = tid(Some(&turn.session_id));
+        let mut span = |cat: &str, name: &str, id: String, start: u64, duration: Option<u64>| {
+            let Some(duration) = duration else {
+                return;
+            };
+            let ts = us(turn.started_at_ms + start);
+            for (ph, ts) in [("b", ts), ("e", ts + us(duration))] {
+                trace.push(json!({
+                    "ph": ph, "name": name, "cat": cat, "id": &id,
+                    "pid": PID, "tid": thread, "ts": ts,
+                }));
+            }
+        };
+        for tool in &turn.tools {
+            let name = tool.title.as_deref().unwrap_or(&tool.tool_call_id);
+            let id = format!("{}/{}", turn.session_id, tool.tool_call_id);
+            span("tool", name, id, tool.start_ms, tool.duration_ms);
+        }
+        for approval in &turn.approvals {
+            let id = format!("{}/approval/{}", turn.session_id, approval.tool_call_id);
+            span(
+                "approval",
+                "approval",
+                id,
+                approval.start_ms,
+                approval.wait_ms,
+            );
+        }
+        if let Some(duration) = turn.duration_ms {
+            trace.push(json!({
+                "ph": "X",
+                "name": format!("turn {}", turn.index),
+                "cat": "turn",
+                "pid": PID,
+                "tid": thread,
+                "ts": us(turn.started_at_ms),
+                "dur": us(duration),
+                "args": {
+                    "stopReason": &turn.stop_reason,
+                    "error": &turn.error,
+                    "timeToFirstTokenMs": turn.time_to_first_token_ms,
+                    "approvalWaitMs": turn.approval_wait_ms,
+                },
+            }));
+        }
+    }
+
+    json!({
+        "traceEvents": trace,
+        "displayTimeUnit": "ms",
+        "otherData": { "turns": turns },
+    })
+}
+
+/// Render events as CSV with a header row; `extra` is embedded as JSON.
+pub fn csv_trace(events: &[TimelineEvent]) -> String {
+    let mut out =
+        String::from("session_id,label,ts_ms,dt_ms,since_prompt_ms,since_last_event_ms,extra\n");
+    let opt = |value: Option<u64>| value.map(|v| v.to_string()).unwrap_or_default();
+    for event in events {
+        let _ = writeln!(
+            out,
+            "{},{},{},{},{},{},{}",
+            csv_field(event.session_id.as_deref().unwrap_or_default()),
+            csv_field(&event.label),
+            event.ts_ms,
+            event.dt_ms,
+            opt(event.since_prompt_ms),
+            opt(event.since_last_event_ms),
+            csv_field(&event.extra.to_string()),
+        );
+    }
+    out
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(label: &str, ts_ms: u64, extra: Value) -> TimelineEvent {
+        TimelineEvent {
+            session_id: Some("s1".to_string()),
+            label: label.to_string(),
+            ts_ms,
+            dt_ms: ts_ms,
+            since_prompt_ms: None,
+            since_last_event_ms: None,
+            extra,
+        }
+    }
+
+    fn sample() -> Vec<TimelineEvent> {
+        vec![
+            event("agent_message_chunk", 50, json!({})),
+            event("prompt_start", 100, json!({ "blocks": 1 })),
+            event("agent_thought_chunk", 130, json!({ "textLen": 3 })),
+            event("agent_message_chunk", 160, json!({ "textLen": 5 })),
+            event(
+                "tool_call",
+                200,
+                json!({ "toolCallId": "t1", "title": "Run tests", "status": "pending" }),
+            ),
+            event("request_permission", 210, json!({ "toolCallId": "t1" })),
+            event(
+                "approval_resolved",
+                710,
+                json!({ "toolCallId": "t1", "optionId": "allow" }),
+            ),
+            event(
+                "tool_call_update",
+                720,
+                json!({ "toolCallId": "t1", "status": "in_progress" }),
+            ),
+            event(
+                "tool_call_update",
+                900,
+                json!({ "toolCallId": "t1", "status": "completed" }),
+            ),
+            event("prompt_done", 1000, json!({ "stopReason": "end_turn" })),
+            event("prompt_start", 1100, json!({ "blocks": 1 })),
+        ]
+    }
+
+    #[test]
+    fn test_summarize_turns() {
+        let turns = summarize_turns(&sample());
+        assert_eq!(turns.len(), 2);
+
+        let turn = &turns[0];
+        assert_eq!(turn.index, 1);
+        assert_eq!(turn.duration_ms, Some(900));
+        assert_eq!(turn.stop_reason, Some(json!("end_turn")));
+        assert_eq!(turn.time_to_first_token_ms, Some(30));
+        assert_eq!((turn.message_chunks, turn.thought_chunks), (1, 1));
+        assert_eq!(turn.tools[0].start_ms, 100);
+        assert_eq!(turn.tools[0].duration_ms, Some(700));
+        assert_eq!(turn.tools[0].status.as_deref(), Some("completed"));
+        assert_eq!(turn.approvals[0].wait_ms, Some(500));
+        assert_eq!(turn.approvals[0].option_id.as_deref(), Some("allow"));
+        assert_eq!(turn.approval_wait_ms, 500);
+
+        assert_eq!(turns[1].index, 2);
+        assert_eq!(turns[1].duration_ms, None);
+    }
+
+    #[test]
+    fn test_timeline_exports() {
+        let timeline = DebugTimeline::default();
+        for event in sample() {
+            timeline.push(event);
+        }
+        timeline.push(TimelineEvent {
+            session_id: None,
+            ..event("initialize_end", 10, json!({ "ok": true }))
+        });
+
+        let export = timeline.export(Some("s1"), TraceFormat::Csv);
+        assert_eq!(export.events, 11);
+        let mut lines = export.content.lines();
+        assert!(lines.next().unwrap().starts_with("session_id,label,"));
+        assert_eq!(
+            lines.nth(1).unwrap(),
+            "s1,prompt_start,100,100,,,\"{\"\"blocks\"\":1}\""
+        );
+
+        let export = timeline.export(None, TraceFormat::Chrome);
+        assert_eq!(export.events, 12);
+        let trace: Value = serde_json::from_str(&export.content).unwrap();
+        let trace_events = trace["traceEvents"].as_array().unwrap();
+        let turn = trace_events
+            .iter()
+            .find(|event| event["ph"] == "X")
+            .unwrap();
+        assert_eq!(turn["ts"], 100_000);
+        assert_eq!(turn["dur"], 900_000);
+        assert_eq!(
+            trace_events
+                .iter()
+                .filter(|event| event["cat"] == "tool")
+                .count(),
+            2
+        );
+        assert_eq!(trace["otherData"]["turns"][0]["approvalWaitMs"], 500);
+    }
+}
@@ -1,7 +1,9 @@
 //! Serde-friendly data types used between backend and frontend.
 
+use crate::codex::{mentions::MentionAttachment, usage::TurnUsage};
 use agent_client_protocol::{
-    AuthMethod, InitializeResponse, SessionConfigOption, SessionModeState, SessionModelState,
+    AgentCapabilities, AuthMethod, InitializeResponse, SessionConfigOption, SessionModeState,
+    SessionModelState,
 };
 use serde::{Deserialize, Serialize};
 
@@ -19,6 +21,16 @@ pub enum ApprovalDecision {
     RejectOnce,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// How long approval requests wait for the user before a default outcome applies.
+pub struct ApprovalTimeout {
+    /// Seconds to wait for the user.
+    pub seconds: u64,
+    /// Decision applied on timeout; `None` cancels the request.
+    pub default_decision: Option<ApprovalDecision>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 /// JSON-friendly result returned from initialize.
@@ -29,6 +41,8 @@ pub struct InitializeResult {
     pub auth_methods: Vec<AuthMethod>,
     /// Negotiated protocol version as JSON.
     pub protocol_version: serde_json::Value,
+    /// Capabilities advertised by the agent.
+    pub agent_capabilities: AgentCapabilities,
 }
 
 impl From<InitializeResponse> for InitializeResult {
@@ -39,13 +53,14 @@ impl From<InitializeResponse> for InitializeResult {
             auth_methods: value.auth_methods,
             protocol_version: serde_json::to_value(value.protocol_version)
                 .unwrap_or(serde_json::Value::Null),
+            agent_capabilities: value.agent_capabilities,
         }
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-/// JSON-friendly session creation result.
+/// JSON-friendly session creation (or load) result.
 pub struct NewSessionResult {
     /// ACP session identifier.
     pub session_id: String,
@@ -57,12 +72,90 @@ pub struct NewSessionResult {
     pub config_options: Option<Vec<SessionConfigOption>>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+/// Prompt input accepted from the frontend: plain text or a list of content parts.
+pub enum PromptInput {
+    /// A single text block.
+    Text(String),
+    /// Mixed text, image and resource parts.
+    Parts(Vec<PromptPart>),
+}
+
+impl PromptInput {
+    /// Normalize the input into a list of parts.
+    pub fn into_parts(self) -> Vec<PromptPart> {
+        match self {
+            PromptInput::Text(text) => vec![PromptPart::Text { text }],
+            PromptInput::Parts(parts) => parts,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+/// What to do with a prompt sent while the session is already running a turn.
+pub enum PromptDelivery {
+    /// Wait until the running turn and any earlier queued prompts finish.
+    #[default]
+    Queue,
+    /// Cancel the running turn and send this prompt next, ahead of the queue.
+    Steer,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+/// A single prompt content part.
+pub enum PromptPart {
+    /// Plain text.
+    Text {
+        /// Text content.
+        text: String,
+    },
+    /// Image from a local file or inline base64 data.
+    #[serde(rename_all = "camelCase")]
+    Image {
+        /// Local image path.
+        path: Option<String>,
+        /// Base64-encoded image data.
+        data: Option<String>,
+        /// Image mime type; inferred from the extension for paths.
+        mime_type: Option<String>,
+    },
+    /// Link to a workspace file the agent can read itself.
+    #[serde(rename_all = "camelCase")]
+    ResourceLink {
+        /// File path, absolute or relative to the session cwd.
+        path: String,
+        /// Display name; defaults to the file name.
+        name: Option<String>,
+        /// Optional mime type.
+        mime_type: Option<String>,
+    },
+    /// File content embedded directly in the prompt.
+    #[serde(rename_all = "camelCase")]
+    Resource {
+        /// File path, absolute or relative to the session cwd.
+        path: String,
+        /// File content; read from disk when omitted.
+        text: Option<String>,
+        /// Optional mime type.
+        mime_type: Option<String>,
+    },
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 /// JSON-friendly prompt completion result.
 pub struct PromptResult {
     /// ACP stop reason value.
     pub stop_reason: serde_json::Value,
+    /// Files attached from `@` mentions in the prompt.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<MentionAttachment>,
+    /// Tokens and cost of the turn, when the agent reported usage.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<TurnUsage>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
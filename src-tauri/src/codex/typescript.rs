@@ -0,0 +1,276 @@
+//! Rendering of JSON Schemas (as produced by `schemars`) into TypeScript declarations.
+//!
+//! Only the subset `schemars` emits for serde types is understood: `$ref`, `type`
+//! (including lists with `null`), `properties`/`required`, `additionalProperties`,
+//! `items`, `prefixItems`, `enum`, `const`, `oneOf`, `anyOf` and `allOf`. Sibling
+//! keywords are combined as an intersection, like JSON Schema 2020-12 does.
+
+use serde_json::{Map, Value};
+
+/// Render `export interface` or `export type` for a named schema definition.
+pub fn declaration(name: &str, schema: &Value) -> String {
+    let mut out = doc_comment(schema, "");
+    let plain_object = schema.as_object().filter(|object| {
+        object.contains_key("properties")
+            && !["$ref", "oneOf", "anyOf", "allOf", "enum", "const"]
+                .iter()
+                .any(|key| object.contains_key(*key))
+            && type_names(object) == ["object"]
+    });
+    if let Some(object) = plain_object {
+        out.push_str(&format!("export interface {name} {{\n"));
+        out.push_str(&object_members(object, "  "));
+        out.push_str("}\n");
+    } else {
+        out.push_str(&format!("export type {name} = {};\n", type_expr(schema)));
+    }
+    out
+}
+
+/// Render a schema as an inline TypeScript type expression.
+pub fn type_expr(schema: &Value) -> String {
+    let object = match schema {
+        Value::Bool(true) => return "unknown".to_string(),
+        Value::Bool(false) => return "never".to_string(),
+        Value::Object(object) => object,
+        _ => return "unknown".to_string(),
+    };
+
+    let types = type_names(object);
+    let mut parts = Vec::new();
+    if let Some(reference) = object.get("$ref").and_then(Value::as_str) {
+        parts.push(
+            reference
+                .rsplit('/')
+                .next()
+                .unwrap_or(reference)
+                .to_string(),
+        );
+    }
+    if let Some(constant) = object.get("const") {
+        parts.push(literal(constant));
+    } else if let Some(values) = object.get("enum").and_then(Value::as_array) {
+        parts.push(union(values.iter().map(literal).collect()));
+    }
+    for key in ["oneOf", "anyOf"] {
+        if let Some(variants) = object.get(key).and_then(Value::as_array) {
+            parts.push(union(variants.iter().map(type_expr).collect()));
+        }
+    }
+    if let Some(all) = object.get("allOf").and_then(Value::as_array) {
+        parts.extend(all.iter().map(type_expr));
+    }
+    let has_members = object.contains_key("properties");
+    if has_members {
+        let members = object_members(object, "");
+        parts.push(format!("{{ {} }}", members.trim_end().replace('\n', " ")));
+    }
+
+    // Primitive alternatives from `type`, unless the keywords above already describe them.
+    let mut alternatives = Vec::new();
+    let typed_by_keywords = !parts.is_empty();
+    for name in &types {
+        let rendered = match name.as_str() {
+            "null" => continue,
+            "object" if has_members || typed_by_keywords => continue,
+            "object" => match object.get("additionalProperties") {
+                Some(Value::Bool(false)) => "Record<string, never>".to_string(),
+                Some(Value::Bool(true)) | None => "Record<string, unknown>".to_string(),
+                Some(values) => format!("Record<string, {}>", type_expr(values)),
+            },
+            "array" => array_expr(object),
+            "string" | "number" | "boolean" if typed_by_keywords => continue,
+            "integer" | "number" if typed_by_keywords => continue,
+            "integer" => "number".to_string(),
+            other => other.to_string(),
+        };
+        alternatives.push(rendered);
+    }
+
+    if parts.len() > 1 {
+        for part in &mut parts {
+            if part.contains(" | ") {
+                *part = format!("({part})");
+            }
+        }
+    }
+    if !parts.is_empty() {
+        alternatives.push(parts.join(" & "));
+    }
+    if types.iter().any(|name| name == "null") {
+        alternatives.push("null".to_string());
+    }
+    if alternatives.is_empty() {
+        "unknown".to_string()
+    } else {
+        alternatives.join(" | ")
+    }
+}
+
+fn type_names(object: &Map<String, Value>) -> Vec<String> {
+    match object.get("type") {
+        Some(Value::String(name)) => vec![name.clone()],
+        Some(Value::Array(names)) => names
+            .iter()
+            .filter_map(Value::as_str)
+            .map(str::to_string)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn array_expr(object: &Map<String, Value>) -> String {
+    if let Some(items) = object.get("prefixItems").and_then(Value::as_array) {
+        let items: Vec<String> = items.iter().map(type_expr).collect();
+        return format!("[{}]", items.join(", "));
+    }
+    match object.get("items") {
+        Some(items) => {
+            let item = type_expr(items);
+            if item.contains(['|', '&', ' ']) {
+                format!("Array<{item}>")
+            } else {
+                format!("{item}[]")
+            }
+        }
+        None => "unknown[]".to_string(),
+    }
+}
+
+fn union(variants: Vec<String>) -> String {
+    if variants.is_empty() {
+        "never".to_string()
+    } else {
+        variants.join(" | ")
+    }
+}
+
+fn literal(value: &Value) -> String {
+    match value {
+        Value::String(text) => format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'")),
+        other => other.to_string(),
+    }
+}
+
+fn object_members(object: &Map<String, Value>, indent: &str) -> String {
+    let required: Vec<&str> = object
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|names| names.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    let mut out = String::new();
+    if let Some(properties) = object.get("properties").and_then(Value::as_object) {
+        for (name, schema) in properties {
+            if !indent.is_empty() {
+                out.push_str(&doc_comment(schema, indent));
+            }
+            let optional = if required.contains(&name.as_str()) {
+                ""
+            } else {
+                "?"
+            };
+            out.push_str(&format!(
+                "{indent}{}{optional}: {};\n",
+                property_name(name),
+                type_expr(schema)
+            ));
+        }
+    }
+    match object.get("additionalProperties") {
+        Some(Value::Bool(false)) | None => {}
+        Some(_) => out.push_str(&format!("{indent}[key: string]: unknown;\n")),
+    }
+    out
+}
+
+fn property_name(name: &str) -> String {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    if valid {
+        name.to_string()
+    } else {
+        literal(&Value::String(name.to_string()))
+    }
+}
+
+fn doc_comment(schema: &Value, indent: &str) -> String {
+    let Some(description) = schema.get("description").and_then(Value::as_str) else {
+        return String::new();
+    };
+    let description = description.replace("*/", "*\\/");
+    let lines: Vec<&str> = description.lines().collect();
+    if lines.len() == 1 {
+        return format!("{indent}/** {} */\n", lines[0]);
+    }
+    let mut out = format!("{indent}/**\n");
+    for line in lines {
+        if line.is_empty() {
+            out.push_str(&format!("{indent} *\n"));
+        } else {
+            out.push_str(&format!("{indent} * {line}\n"));
+        }
+    }
+    out.push_str(&format!("{indent} */\n"));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_type_expressions() {
+        assert_eq!(type_expr(&json!(true)), "unknown");
+        assert_eq!(
+            type_expr(&json!({ "type": ["string", "null"] })),
+            "string | null"
+        );
+        assert_eq!(
+            type_expr(&json!({ "type": "array", "items": { "$ref": "#/$defs/Plan" } })),
+            "Plan[]"
+        );
+        assert_eq!(
+            type_expr(&json!({ "anyOf": [{ "$ref": "#/$defs/A" }, { "type": "null" }] })),
+            "A | null"
+        );
+        assert_eq!(
+            type_expr(&json!({
+                "$ref": "#/$defs/TextContent",
+                "type": "object",
+                "properties": { "type": { "const": "text", "type": "string" } },
+                "required": ["type"],
+            })),
+            "TextContent & { type: 'text'; }"
+        );
+        assert_eq!(
+            type_expr(&json!({ "type": "object", "additionalProperties": { "type": "integer" } })),
+            "Record<string, number>"
+        );
+        assert_eq!(
+            type_expr(&json!({ "type": "string", "enum": ["a", "b"] })),
+            "'a' | 'b'"
+        );
+    }
+
+    #[test]
+    fn test_interface_declaration() {
+        let schema = json!({
+            "description": "A chunk.",
+            "type": "object",
+            "properties": {
+                "sessionId": { "type": "string" },
+                "replay": { "description": "Replayed.", "type": "boolean" },
+                "kebab-key": { "type": ["integer", "null"] },
+            },
+            "required": ["sessionId"],
+        });
+        assert_eq!(
+            declaration("Chunk", &schema),
+            "/** A chunk. */\nexport interface Chunk {\n  'kebab-key'?: number | null;\n  /** Replayed. */\n  replay?: boolean;\n  sessionId: string;\n}\n"
+        );
+    }
+}
@@ -3,7 +3,7 @@
 use crate::codex::process::CodexProcess;
 use crate::remote::RemoteSshProcess;
 use anyhow::Result;
-use tokio::process::{ChildStdin, ChildStdout};
+use tokio::process::{ChildStderr, ChildStdin, ChildStdout};
 
 /// Unified process wrapper that can be either a local or remote codex-acp process
 pub enum UnifiedProcess {
@@ -20,6 +20,22 @@ impl UnifiedProcess {
         }
     }
 
+    /// Take ownership of the child stderr handle
+    pub fn take_stderr(&mut self) -> Option<ChildStderr> {
+        match self {
+            UnifiedProcess::Local(process) => process.take_stderr(),
+            UnifiedProcess::Remote(process) => process.take_stderr(),
+        }
+    }
+
+    /// Check whether the child process is still running
+    pub fn is_alive(&mut self) -> bool {
+        match self {
+            UnifiedProcess::Local(process) => process.is_alive(),
+            UnifiedProcess::Remote(process) => process.is_alive(),
+        }
+    }
+
     /// Terminate the process
     pub async fn kill(&mut self) -> Result<()> {
         match self {
@@ -0,0 +1,765 @@
+//! Token usage and cost accounting.
+//!
+//! `codex/token-usage` notifications are parsed into typed counts, added up per turn,
+//! per session and per local calendar day, priced with a user-editable table and
+//! persisted so totals survive restarts.
+
+use crate::codex::events::CodexEvent;
+use crate::codex::util::{load_json, now_millis, save_json};
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::PathBuf,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+use tauri::{AppHandle, Runtime};
+
+/// Minimum time between ledger writes while a turn is running; the ledger is always
+/// written when a turn ends.
+const LEDGER_SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Token counts in the shape Codex reports them.
+///
+/// `cached_input` is the part of `input` served from the prompt cache and
+/// `reasoning` the part of `output` spent on reasoning.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenCounts {
+    pub input: u64,
+    pub cached_input: u64,
+    pub output: u64,
+    pub reasoning: u64,
+}
+
+impl TokenCounts {
+    /// Billed tokens: input plus output.
+    pub fn total(&self) -> u64 {
+        self.input.saturating_add(self.output)
+    }
+
+    fn is_zero(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn add(&mut self, other: &Self) {
+        self.input = self.input.saturating_add(other.input);
+        self.cached_input = self.cached_input.saturating_add(other.cached_input);
+        self.output = self.output.saturating_add(other.output);
+        self.reasoning = self.reasoning.saturating_add(other.reasoning);
+    }
+
+    fn saturating_sub(&self, other: &Self) -> Self {
+        Self {
+            input: self.input.saturating_sub(other.input),
+            cached_input: self.cached_input.saturating_sub(other.cached_input),
+            output: self.output.saturating_sub(other.output),
+            reasoning: self.reasoning.saturating_sub(other.reasoning),
+        }
+    }
+}
+
+/// A parsed `codex/token-usage` notification.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TokenUsageUpdate {
+    pub session_id: String,
+    pub model: Option<String>,
+    /// Cumulative usage of the session, when reported.
+    pub total: Option<TokenCounts>,
+    /// Usage of the latest model request, when reported.
+    pub last: Option<TokenCounts>,
+    pub context_window: Option<u64>,
+}
+
+/// Parse a token usage payload, accepting camelCase and snake_case field names and
+/// Codex's nested `info.total_token_usage` / `info.last_token_usage` layout.
+pub fn parse_token_usage(payload: &Value) -> Option<TokenUsageUpdate> {
+    let session_id = string_field(payload, &["sessionId", "session_id"])?;
+    let info = payload
+        .get("info")
+        .filter(|info| info.is_object())
+        .unwrap_or(payload);
+    let total = [
+        "totalTokenUsage",
+        "total_token_usage",
+        "totalUsage",
+        "total",
+    ]
+    .iter()
+    .find_map(|key| info.get(*key).and_then(parse_counts));
+    let last = ["lastTokenUsage", "last_token_usage", "lastUsage", "last"]
+        .iter()
+        .find_map(|key| info.get(*key).and_then(parse_counts));
+    // Flat payloads carry the counts of the latest request at the top level.
+    let last = last.or_else(|| total.is_none().then(|| parse_counts(info)).flatten());
+    if total.is_none() && last.is_none() {
+        return None;
+    }
+    Some(TokenUsageUpdate {
+        session_id,
+        model: string_field(payload, &["model", "modelId", "model_id"])
+            .or_else(|| string_field(info, &["model", "modelId", "model_id"])),
+        total,
+        last,
+        context_window: u64_field(
+            info,
+            &[
+                "modelContextWindow",
+                "model_context_window",
+                "contextWindow",
+            ],
+        )
+        .or_else(|| u64_field(payload, &["contextWindow", "context_window"])),
+    })
+}
+
+fn parse_counts(value: &Value) -> Option<TokenCounts> {
+    let input = u64_field(value, &["inputTokens", "input_tokens", "input"]);
+    let output = u64_field(value, &["outputTokens", "output_tokens", "output"]);
+    if input.is_none() && output.is_none() {
+        return None;
+    }
+    Some(TokenCounts {
+        input: input.unwrap_or_default(),
+        cached_input: u64_field(
+            value,
+            &[
+                "cachedInputTokens",
+                "cached_input_tokens",
+                "cachedTokens",
+                "cached",
+            ],
+        )
+        .unwrap_or_default(),
+        output: output.unwrap_or_default(),
+        reasoning: u64_field(
+            value,
+            &[
+                "reasoningOutputTokens",
+                "reasoning_output_tokens",
+                "reasoningTokens",
+                "reasoning",
+            ],
+        )
+        .unwrap_or_default(),
+    })
+}
+
+fn string_field(value: &Value, keys: &[&str]) -> Option<String> {
+    keys.iter()
+        .find_map(|key| value.get(*key)?.as_str())
+        .map(str::to_string)
+}
+
+fn u64_field(value: &Value, keys: &[&str]) -> Option<u64> {
+    keys.iter().find_map(|key| value.get(*key)?.as_u64())
+}
+
+/// Price of a model in USD per million tokens.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelPrice {
+    pub input_per_mtok: f64,
+    /// Price of cached input; defaults to the input price.
+    #[serde(default)]
+    pub cached_input_per_mtok: Option<f64>,
+    pub output_per_mtok: f64,
+}
+
+impl ModelPrice {
+    fn cost(&self, tokens: &TokenCounts) -> f64 {
+        let cached = tokens.cached_input.min(tokens.input);
+        let uncached = tokens.input - cached;
+        let cached_price = self.cached_input_per_mtok.unwrap_or(self.input_per_mtok);
+        (uncached as f64 * self.input_per_mtok
+            + cached as f64 * cached_price
+            + tokens.output as f64 * self.output_per_mtok)
+            / 1_000_000.0
+    }
+}
+
+/// Spending limits that raise usage alerts when crossed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageBudget {
+    /// Limit for all sessions on one calendar day.
+    pub daily_usd: Option<f64>,
+    /// Limit for a single session.
+    pub session_usd: Option<f64>,
+}
+
+/// User-editable pricing and budget configuration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageSettings {
+    /// Prices keyed by model id; the longest key that prefixes a model id applies.
+    #[serde(default)]
+    pub prices: BTreeMap<String, ModelPrice>,
+    #[serde(default)]
+    pub budget: UsageBudget,
+}
+
+impl Default for UsageSettings {
+    fn default() -> Self {
+        let price = |input, cached, output| ModelPrice {
+            input_per_mtok: input,
+            cached_input_per_mtok: Some(cached),
+            output_per_mtok: output,
+        };
+        Self {
+            prices: BTreeMap::from([
+                ("gpt-5".to_string(), price(1.25, 0.125, 10.0)),
+                ("gpt-5-codex".to_string(), price(1.25, 0.125, 10.0)),
+                ("gpt-5-mini".to_string(), price(0.25, 0.025, 2.0)),
+            ]),
+            budget: UsageBudget::default(),
+        }
+    }
+}
+
+impl UsageSettings {
+    fn price_for(&self, model: &str) -> Option<&ModelPrice> {
+        let model = model.to_ascii_lowercase();
+        self.prices
+            .iter()
+            .filter(|(key, _)| model.starts_with(&key.to_ascii_lowercase()))
+            .max_by_key(|(key, _)| key.len())
+            .map(|(_, price)| price)
+    }
+}
+
+/// Accumulated usage for a session or a day.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageTotals {
+    pub tokens: TokenCounts,
+    pub cost_usd: f64,
+    pub turns: u64,
+    /// Tokens spent on models missing from the price table.
+    #[serde(default)]
+    pub unpriced_tokens: u64,
+}
+
+impl UsageTotals {
+    fn add(&mut self, tokens: &TokenCounts, cost: Option<f64>) {
+        self.tokens.add(tokens);
+        match cost {
+            Some(cost) => self.cost_usd += cost,
+            None => self.unpriced_tokens = self.unpriced_tokens.saturating_add(tokens.total()),
+        }
+    }
+}
+
+/// Persisted usage of one session.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionUsage {
+    pub session_id: String,
+    pub model: Option<String>,
+    pub totals: UsageTotals,
+    pub updated_at: u64,
+}
+
+/// Usage of one local calendar day.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DayUsage {
+    /// `YYYY-MM-DD` in local time.
+    pub date: String,
+    pub totals: UsageTotals,
+}
+
+/// Usage of a single prompt turn.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TurnUsage {
+    pub model: Option<String>,
+    pub tokens: TokenCounts,
+    pub cost_usd: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum BudgetScope {
+    Daily,
+    Session,
+}
+
+/// A budget that has been reached.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetAlert {
+    pub scope: BudgetScope,
+    pub session_id: Option<String>,
+    pub limit_usd: f64,
+    pub spent_usd: f64,
+}
+
+/// Result of `codex_usage_report`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageReport {
+    pub today: DayUsage,
+    /// Most recent days first.
+    pub days: Vec<DayUsage>,
+    /// Most recently active sessions first.
+    pub sessions: Vec<SessionUsage>,
+    pub budget: UsageBudget,
+    pub alerts: Vec<BudgetAlert>,
+    /// Models seen without an entry in the price table.
+    pub unpriced_models: Vec<String>,
+}
+
+/// Typed usage event emitted after every accounted notification.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageEvent {
+    pub session_id: String,
+    pub model: Option<String>,
+    pub delta: TokenCounts,
+    pub turn: TurnUsage,
+    pub session: UsageTotals,
+    pub today: UsageTotals,
+    pub context_window: Option<u64>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UsageLedger {
+    #[serde(default)]
+    sessions: BTreeMap<String, SessionUsage>,
+    #[serde(default)]
+    days: BTreeMap<String, UsageTotals>,
+    #[serde(default)]
+    unpriced_models: BTreeSet<String>,
+    /// Whether there are changes that are not written yet.
+    #[serde(skip)]
+    dirty: bool,
+    #[serde(skip)]
+    saved_at: Option<Instant>,
+}
+
+/// In-memory state of a session with a live connection.
+#[derive(Default)]
+struct LiveSession {
+    model: Option<String>,
+    last_total: Option<TokenCounts>,
+    turn: TurnUsage,
+}
+
+/// Usage ledger plus price table, stored on disk.
+pub struct UsageStore {
+    ledger_path: PathBuf,
+    settings_path: PathBuf,
+    ledger: Mutex<UsageLedger>,
+    settings: Mutex<UsageSettings>,
+    live: Mutex<HashMap<String, LiveSession>>,
+}
+
+impl UsageStore {
+    /// Load the ledger and settings; a missing settings file starts from default prices.
+    pub fn new(ledger_path: PathBuf, settings_path: PathBuf) -> Self {
+        let ledger = load_json(&ledger_path).unwrap_or_else(|err| {
+            tracing::warn!(error = %err, "failed to load usage ledger");
+            None
+        });
+        let settings = load_json(&settings_path).unwrap_or_else(|err| {
+            tracing::warn!(error = %err, "failed to load usage settings");
+            None
+        });
+        Self {
+            ledger_path,
+            settings_path,
+            ledger: Mutex::new(ledger.unwrap_or_default()),
+            settings: Mutex::new(settings.unwrap_or_default()),
+            live: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Track a session created by this app. Nothing was used before it started, so
+    /// its first cumulative total counts in full.
+    pub fn start_session(&self, session_id: &str, model: Option<String>) {
+        let mut live = self.lock_live();
+        live.insert(
+            session_id.to_string(),
+            LiveSession {
+                model,
+                last_total: Some(TokenCounts::default()),
+                ..LiveSession::default()
+            },
+        );
+    }
+
+    /// Remember the model a session runs with, for payloads that do not name it.
+    pub fn set_session_model(&self, session_id: &str, model: Option<String>) {
+        let mut live = self.lock_live();
+        let entry = live.entry(session_id.to_string()).or_default();
+        if model.is_some() {
+            entry.model = model;
+        }
+    }
+
+    /// Account a `codex/token-usage` payload and emit usage and budget events.
+    pub fn record<R: Runtime>(&self, app: &AppHandle<R>, payload: &Value) {
+        let Some(update) = parse_token_usage(payload) else {
+            return;
+        };
+        let Some((event, alerts)) = self.apply(update) else {
+            return;
+        };
+        CodexEvent::Usage(&event).emit(app);
+        for alert in alerts {
+            CodexEvent::UsageAlert(&alert).emit(app);
+        }
+    }
+
+    fn apply(&self, update: TokenUsageUpdate) -> Option<(UsageEvent, Vec<BudgetAlert>)> {
+        let settings = self.lock_settings().clone();
+        let mut live = self.lock_live();
+        let session = live.entry(update.session_id.clone()).or_default();
+        if update.model.is_some() {
+            session.model = update.model.clone();
+        }
+
+        // Prefer cumulative totals so repeated notifications are not counted twice.
+        let delta = match (update.total, session.last_total, update.last) {
+            (Some(total), Some(prev), _) if total.total() >= prev.total() => {
+                total.saturating_sub(&prev)
+            }
+            (_, _, Some(last)) => last,
+            // Without a previous total there is no telling what part of the first one
+            // is new for a loaded session, so it only becomes the baseline.
+            (Some(_), _, None) => TokenCounts::default(),
+            (None, _, None) => return None,
+        };
+        if update.total.is_some() {
+            session.last_total = update.total;
+        }
+        if delta.is_zero() {
+            return None;
+        }
+
+        let model = session.model.clone();
+        let cost = model
+            .as_deref()
+            .and_then(|model| settings.price_for(model))
+            .map(|price| price.cost(&delta));
+        session.turn.model = model.clone();
+        session.turn.tokens.add(&delta);
+        session.turn.cost_usd += cost.unwrap_or_default();
+        let turn = session.turn.clone();
+        drop(live);
+
+        let mut ledger = self.lock_ledger();
+        if cost.is_none() {
+            ledger
+                .unpriced_models
+                .insert(model.clone().unwrap_or_else(|| "unknown".to_string()));
+        }
+        let entry = ledger
+            .sessions
+            .entry(update.session_id.clone())
+            .or_insert_with(|| SessionUsage {
+                session_id: update.session_id.clone(),
+                ..SessionUsage::default()
+            });
+        let session_before = entry.totals.cost_usd;
+        entry.model = model.clone().or(entry.model.take());
+        entry.totals.add(&delta, cost);
+        entry.updated_at = now_millis();
+        let session_totals = entry.totals.clone();
+        let day = ledger.days.entry(today()).or_default();
+        let day_before = day.cost_usd;
+        day.add(&delta, cost);
+        let day_totals = day.clone();
+        self.save_ledger(&mut ledger, false);
+        drop(ledger);
+
+        let mut alerts = Vec::new();
+        let crossed = |limit: Option<f64>, before: f64, after: f64| {
+            limit.filter(|limit| before < *limit && after >= *limit)
+        };
+        if let Some(limit) = crossed(
+            settings.budget.session_usd,
+            session_before,
+            session_totals.cost_usd,
+        ) {
+            alerts.push(BudgetAlert {
+                scope: BudgetScope::Session,
+                session_id: Some(update.session_id.clone()),
+                limit_usd: limit,
+                spent_usd: session_totals.cost_usd,
+            });
+        }
+        if let Some(limit) = crossed(settings.budget.daily_usd, day_before, day_totals.cost_usd) {
+            alerts.push(BudgetAlert {
+                scope: BudgetScope::Daily,
+                session_id: None,
+                limit_usd: limit,
+                spent_usd: day_totals.cost_usd,
+            });
+        }
+
+        Some((
+            UsageEvent {
+                session_id: update.session_id,
+                model,
+                delta,
+                turn,
+                session: session_totals,
+                today: day_totals,
+                context_window: update.context_window,
+            },
+            alerts,
+        ))
+    }
+
+    /// Close the running turn of a session and return what it used. Only turns that
+    /// `completed` are counted.
+    pub fn finish_turn(&self, session_id: &str, completed: bool) -> TurnUsage {
+        let turn = self
+            .lock_live()
+            .get_mut(session_id)
+            .map(|session| std::mem::take(&mut session.turn))
+            .unwrap_or_default();
+        let mut ledger = self.lock_ledger();
+        if completed {
+            if let Some(entry) = ledger.sessions.get_mut(session_id) {
+                entry.totals.turns += 1;
+            }
+            ledger.days.entry(today()).or_default().turns += 1;
+            ledger.dirty = true;
+        }
+        if ledger.dirty {
+            self.save_ledger(&mut ledger, true);
+        }
+        turn
+    }
+
+    /// Forget the live counters of a session whose connection went away.
+    pub fn end_session(&self, session_id: &str) {
+        self.lock_live().remove(session_id);
+        let mut ledger = self.lock_ledger();
+        if ledger.dirty {
+            self.save_ledger(&mut ledger, true);
+        }
+    }
+
+    /// Build a usage report covering the last `days` days, optionally for one session.
+    pub fn report(&self, session_id: Option<&str>, days: usize) -> UsageReport {
+        let budget = self.lock_settings().budget.clone();
+        let ledger = self.lock_ledger();
+        let today_key = today();
+        let today = DayUsage {
+            date: today_key.clone(),
+            totals: ledger.days.get(&today_key).cloned().unwrap_or_default(),
+        };
+        let day_list = ledger
+            .days
+            .iter()
+            .rev()
+            .take(days)
+            .map(|(date, totals)| DayUsage {
+                date: date.clone(),
+                totals: totals.clone(),
+            })
+            .collect();
+        let mut sessions: Vec<SessionUsage> = ledger
+            .sessions
+            .values()
+            .filter(|usage| session_id.map_or(true, |id| usage.session_id == id))
+            .cloned()
+            .collect();
+        sessions.sort_by_key(|usage| std::cmp::Reverse(usage.updated_at));
+
+        let mut alerts = Vec::new();
+        if let Some(limit) = budget.daily_usd.filter(|l| today.totals.cost_usd >= *l) {
+            alerts.push(BudgetAlert {
+                scope: BudgetScope::Daily,
+                session_id: None,
+                limit_usd: limit,
+                spent_usd: today.totals.cost_usd,
+            });
+        }
+        if let Some(limit) = budget.session_usd {
+            alerts.extend(
+                sessions
+                    .iter()
+                    .filter(|usage| usage.totals.cost_usd >= limit)
+                    .map(|usage| BudgetAlert {
+                        scope: BudgetScope::Session,
+                        session_id: Some(usage.session_id.clone()),
+                        limit_usd: limit,
+                        spent_usd: usage.totals.cost_usd,
+                    }),
+            );
+        }
+
+        UsageReport {
+            today,
+            days: day_list,
+            sessions,
+            budget,
+            alerts,
+            unpriced_models: ledger.unpriced_models.iter().cloned().collect(),
+        }
+    }
+
+    /// Return the price table and budget.
+    pub fn settings(&self) -> UsageSettings {
+        self.lock_settings().clone()
+    }
+
+    /// Replace the price table and budget.
+    pub fn set_settings(&self, settings: UsageSettings) -> Result<()> {
+        save_json(&self.settings_path, &settings, false)?;
+        *self.lock_settings() = settings;
+        Ok(())
+    }
+
+    /// Write the ledger, at most once per `LEDGER_SAVE_INTERVAL` unless `force`d.
+    fn save_ledger(&self, ledger: &mut UsageLedger, force: bool) {
+        ledger.dirty = true;
+        if !force
+            && ledger
+                .saved_at
+                .is_some_and(|at| at.elapsed() < LEDGER_SAVE_INTERVAL)
+        {
+            return;
+        }
+        match save_json(&self.ledger_path, &*ledger, false) {
+            Ok(()) => {
+                ledger.dirty = false;
+                ledger.saved_at = Some(Instant::now());
+            }
+            Err(err) => tracing::warn!(error = %err, "failed to save usage ledger"),
+        }
+    }
+
+    fn lock_ledger(&self) -> MutexGuard<'_, UsageLedger> {
+        self.ledger
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn lock_settings(&self) -> MutexGuard<'_, UsageSettings> {
+        self.settings
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn lock_live(&self) -> MutexGuard<'_, HashMap<String, LiveSession>> {
+        self.live
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn today() -> String {
+    chrono::Local::now().format("%Y-%m-%d").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::fs;
+
+    fn counts(input: u64, cached: u64, output: u64, reasoning: u64) -> TokenCounts {
+        TokenCounts {
+            input,
+            cached_input: cached,
+            output,
+            reasoning,
+        }
+    }
+
+    #[test]
+    fn test_parse_token_usage_shapes() {
+        let nested = json!({
+            "session_id": "s1",
+            "info": {
+                "total_token_usage": {
+                    "input_tokens": 1200, "cached_input_tokens": 200,
+                    "output_tokens": 300, "reasoning_output_tokens": 100
+                },
+                "last_token_usage": { "input_tokens": 700, "output_tokens": 50 },
+                "model_context_window": 272000
+            }
+        });
+        let update = parse_token_usage(&nested).unwrap();
+        assert_eq!(update.session_id, "s1");
+        assert_eq!(update.total, Some(counts(1200, 200, 300, 100)));
+        assert_eq!(update.last, Some(counts(700, 0, 50, 0)));
+        assert_eq!(update.context_window, Some(272000));
+
+        let flat = json!({
+            "sessionId": "s2", "model": "gpt-5-codex",
+            "inputTokens": 10, "cachedInputTokens": 4, "outputTokens": 5
+        });
+        let update = parse_token_usage(&flat).unwrap();
+        assert_eq!(update.model.as_deref(), Some("gpt-5-codex"));
+        assert_eq!(update.total, None);
+        assert_eq!(update.last, Some(counts(10, 4, 5, 0)));
+
+        assert!(parse_token_usage(&json!({ "sessionId": "s3", "totalTokens": 5 })).is_none());
+    }
+
+    #[test]
+    fn test_usage_accounting_and_budget() {
+        let root = std::env::temp_dir().join(format!("codex-usage-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let store = UsageStore::new(root.join("usage.json"), root.join("usage-settings.json"));
+        let mut settings = store.settings();
+        settings.budget.session_usd = Some(0.01);
+        store.set_settings(settings).unwrap();
+        store.set_session_model("s1", Some("gpt-5-codex/high".to_string()));
+
+        let update = |total| TokenUsageUpdate {
+            session_id: "s1".to_string(),
+            total: Some(total),
+            ..TokenUsageUpdate::default()
+        };
+        // For a loaded session, the first total only sets the baseline.
+        assert!(store.apply(update(counts(1000, 0, 100, 0))).is_none());
+        let (event, alerts) = store.apply(update(counts(2000, 0, 200, 0))).unwrap();
+        assert_eq!(event.delta, counts(1000, 0, 100, 0));
+        assert!(alerts.is_empty());
+        // Repeated notifications with unchanged totals are not double counted.
+        assert!(store.apply(update(counts(2000, 0, 200, 0))).is_none());
+
+        let (event, alerts) = store.apply(update(counts(10000, 4000, 700, 200))).unwrap();
+        assert_eq!(event.delta, counts(8000, 4000, 500, 200));
+        assert_eq!(event.turn.tokens, counts(9000, 4000, 600, 200));
+        // 5000 * 1.25 + 4000 * 0.125 + 600 * 10 per million tokens.
+        assert!((event.session.cost_usd - 0.01275).abs() < 1e-9);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].scope, BudgetScope::Session);
+
+        let turn = store.finish_turn("s1", true);
+        assert_eq!(turn.tokens.total(), 9600);
+        // A failed prompt is not counted as a turn.
+        store.finish_turn("s1", false);
+        let reloaded = UsageStore::new(root.join("usage.json"), root.join("usage-settings.json"));
+        let report = reloaded.report(None, 7);
+        assert_eq!(report.sessions[0].totals.turns, 1);
+        assert_eq!(report.today.totals.tokens.total(), 9600);
+        assert_eq!(report.alerts.len(), 1);
+
+        // A session started here counts its first total in full.
+        store.start_session("s2", Some("gpt-5-codex/high".to_string()));
+        let (event, _) = store
+            .apply(TokenUsageUpdate {
+                session_id: "s2".to_string(),
+                total: Some(counts(1000, 0, 100, 0)),
+                ..TokenUsageUpdate::default()
+            })
+            .unwrap();
+        assert_eq!(event.delta, counts(1000, 0, 100, 0));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
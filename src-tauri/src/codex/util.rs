@@ -1,6 +1,12 @@
-//! Utility helpers for ACP content extraction.
+//! Helpers shared by the codex modules: ACP content extraction, timestamps and JSON
+//! files.
 
 use agent_client_protocol::ContentBlock;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Extract the text payload from a content block when available.
 pub fn content_block_text(block: &ContentBlock) -> Option<&str> {
@@ -9,3 +15,55 @@ pub fn content_block_text(block: &ContentBlock) -> Option<&str> {
         _ => None,
     }
 }
+
+/// Milliseconds since the Unix epoch.
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis().try_into().unwrap_or(u64::MAX))
+        .unwrap_or(0)
+}
+
+/// `<prefix>-<millis>` for a new record, skipping ids for which `taken` is true.
+pub fn unique_id(prefix: &str, taken: impl Fn(&str) -> bool) -> String {
+    // Records saved within the same millisecond must not share an id.
+    let mut millis = now_millis();
+    loop {
+        let id = format!("{prefix}-{millis}");
+        if !taken(&id) {
+            return id;
+        }
+        millis += 1;
+    }
+}
+
+/// Read a JSON file, or `None` if it does not exist.
+pub fn load_json<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<Option<T>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let raw = fs::read_to_string(path)?;
+    Ok(Some(serde_json::from_str(&raw)?))
+}
+
+/// Write `value` through a temporary file; `private` files are readable by the owner only.
+pub fn save_json<T: Serialize>(path: &Path, value: &T, private: bool) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    if private {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    #[cfg(not(unix))]
+    let _ = private;
+    let file = options
+        .open(&tmp)
+        .with_context(|| format!("failed to write {}", tmp.display()))?;
+    serde_json::to_writer_pretty(file, value)?;
+    fs::rename(&tmp, path).with_context(|| format!("failed to write {}", path.display()))
+}
@@ -0,0 +1,330 @@
+//! Sessions isolated in their own git worktree.
+//!
+//! A worktree session works on a fresh branch checked out under
+//! `<data dir>/codex-desktop/worktrees`, so the agent's edits stay out of the main
+//! checkout until they are merged back. The worktree is recorded in the session
+//! journal until its branch is deleted, and removed together with the stored session.
+
+use crate::codex::history::{SessionSummary, SessionWorktree};
+use crate::codex::remote_session::parse_remote_path;
+use crate::codex::types::NewSessionResult;
+use crate::git::commands::head_commit;
+use crate::git::types::GitCommit;
+use crate::git::worktrees::{
+    add_worktree, branch_exists, branch_head, check_new_branch, commits_ahead, delete_branch,
+    is_dirty, is_merged, main_worktree_root, merge_branch, remove_worktree,
+};
+use anyhow::{anyhow, bail, Result};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Prefix of generated session branch names.
+const BRANCH_PREFIX: &str = "codex/";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+/// A session created in a new worktree.
+pub struct NewWorktreeSessionResult {
+    #[serde(flatten)]
+    /// The created session.
+    pub session: NewSessionResult,
+    /// The worktree the session runs in.
+    pub worktree: SessionWorktree,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+/// A stored session that runs in a worktree, with the worktree's current state.
+pub struct WorktreeSessionInfo {
+    #[serde(flatten)]
+    /// Stored session summary.
+    pub summary: SessionSummary,
+    /// Whether the worktree directory still exists.
+    pub exists: bool,
+    /// Whether the worktree has uncommitted changes.
+    pub dirty: bool,
+    /// Commits on the session branch that HEAD of the main checkout does not have.
+    pub ahead: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+/// Outcome of removing a session worktree.
+pub struct WorktreeCleanup {
+    /// Whether the session branch is gone as well. Without `force`, a branch that is
+    /// not merged is kept.
+    pub branch_deleted: bool,
+}
+
+#[derive(Clone)]
+/// Directory new session worktrees are created in.
+pub struct WorktreeSessions {
+    dir: PathBuf,
+}
+
+impl WorktreeSessions {
+    /// Create worktrees under `dir`.
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Create a worktree for a new session in the repository containing `cwd`, on a
+    /// new branch starting from `base` (HEAD by default). Without a branch name, one
+    /// is generated from the current time.
+    pub fn create(
+        &self,
+        cwd: &str,
+        base: Option<&str>,
+        branch: Option<&str>,
+    ) -> Result<SessionWorktree> {
+        if parse_remote_path(cwd)?.0 {
+            bail!("worktree sessions need a local repository");
+        }
+        let root = main_worktree_root(Path::new(cwd)).map_err(|err| anyhow!(err))?;
+        let repo = root
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "repo".to_string());
+
+        let (branch, path) = match branch.map(str::trim).filter(|branch| !branch.is_empty()) {
+            Some(branch) => {
+                check_new_branch(&root, branch).map_err(|err| anyhow!(err))?;
+                (branch.to_string(), self.unused_path(&repo, branch))
+            }
+            None => self.generated_branch(&root, &repo),
+        };
+        fs::create_dir_all(&self.dir)?;
+        let base = add_worktree(&root, &path, &branch, base).map_err(|err| anyhow!(err))?;
+        Ok(SessionWorktree {
+            repo_root: root.to_string_lossy().into_owned(),
+            path: path.to_string_lossy().into_owned(),
+            branch,
+            base,
+            merged: None,
+        })
+    }
+
+    /// `<dir>/<repo>-<branch>`, numbered when a directory of that name exists.
+    fn unused_path(&self, repo: &str, branch: &str) -> PathBuf {
+        let slug: String = format!("{}-{}", repo, branch)
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                    c
+                } else {
+                    '-'
+                }
+            })
+            .collect();
+        let mut path = self.dir.join(&slug);
+        let mut n = 2;
+        while path.exists() {
+            path = self.dir.join(format!("{}-{}", slug, n));
+            n += 1;
+        }
+        path
+    }
+
+    /// `codex/<date>-<time>`, numbered when the branch exists.
+    fn generated_branch(&self, root: &Path, repo: &str) -> (String, PathBuf) {
+        let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+        let mut branch = format!("{}{}", BRANCH_PREFIX, stamp);
+        let mut n = 2;
+        while branch_exists(root, &branch) {
+            branch = format!("{}{}-{}", BRANCH_PREFIX, stamp, n);
+            n += 1;
+        }
+        let path = self.unused_path(repo, &branch);
+        (branch, path)
+    }
+}
+
+/// Current state of a stored session's worktree, for sessions that have one.
+pub fn worktree_info(summary: SessionSummary) -> Option<WorktreeSessionInfo> {
+    let worktree = summary.worktree.as_ref()?;
+    let root = Path::new(&worktree.repo_root);
+    let path = Path::new(&worktree.path);
+    let exists = path.is_dir();
+    let dirty = exists && is_dirty(path).unwrap_or(false);
+    let ahead = commits_ahead(root, "HEAD", &worktree.branch).unwrap_or(0);
+    Some(WorktreeSessionInfo {
+        summary,
+        exists,
+        dirty,
+        ahead,
+    })
+}
+
+/// Merge the session branch into the branch checked out in the main working tree,
+/// note the merged commit in `worktree` and return the resulting HEAD commit. Changes
+/// that are not committed in the worktree would be left behind, so they have to be
+/// committed first.
+pub fn merge_worktree(
+    worktree: &mut SessionWorktree,
+    squash: bool,
+    message: Option<&str>,
+) -> Result<GitCommit> {
+    let path = Path::new(&worktree.path);
+    if path.is_dir() && is_dirty(path).map_err(|err| anyhow!(err))? {
+        bail!(
+            "worktree {} has uncommitted changes; commit them before merging",
+            worktree.path
+        );
+    }
+    let root = Path::new(&worktree.repo_root);
+    let merged = branch_head(root, &worktree.branch).map_err(|err| anyhow!(err))?;
+    merge_branch(root, &worktree.branch, squash, message).map_err(|err| anyhow!(err))?;
+    worktree.merged = Some(merged);
+    head_commit(root).map_err(|err| anyhow!(err))
+}
+
+/// Fail unless [`remove_session_worktree`] without `force` would remove both the
+/// worktree and its branch.
+pub fn check_removable(worktree: &SessionWorktree) -> Result<()> {
+    let path = Path::new(&worktree.path);
+    if path.is_dir() && is_dirty(path).map_err(|err| anyhow!(err))? {
+        bail!(
+            "worktree {} has uncommitted changes; clean it up with force first",
+            worktree.path
+        );
+    }
+    let root = Path::new(&worktree.repo_root);
+    if branch_exists(root, &worktree.branch)
+        && !is_merged(root, &worktree.branch)
+        && branch_head(root, &worktree.branch).ok() != worktree.merged
+    {
+        bail!(
+            "branch {} is not merged; clean up the worktree with force first",
+            worktree.branch
+        );
+    }
+    Ok(())
+}
+
+/// Remove a session worktree and its branch. Without `force`, uncommitted changes
+/// keep the worktree and a branch is kept unless it is merged, by git's account or
+/// by [`merge_worktree`] with no commits since.
+pub fn remove_session_worktree(worktree: &SessionWorktree, force: bool) -> Result<WorktreeCleanup> {
+    let root = Path::new(&worktree.repo_root);
+    let mut branch_deleted =
+        remove_worktree(root, Path::new(&worktree.path), &worktree.branch, force)
+            .map_err(|err| anyhow!(err))?;
+    if !branch_deleted && worktree.merged.is_some() {
+        let head = branch_head(root, &worktree.branch).ok();
+        if head == worktree.merged {
+            branch_deleted = delete_branch(root, &worktree.branch, true);
+        }
+    }
+    Ok(WorktreeCleanup { branch_deleted })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::run_git;
+    use crate::git::test_support::{commit_file, init_repo, test_dir};
+
+    #[test]
+    fn test_create_names_branches_and_paths() {
+        let dir = test_dir("session-create");
+        let repo = init_repo(dir.join("repo"));
+        commit_file(&repo, "a.txt", "one\n");
+        let sessions = WorktreeSessions::new(dir.join("worktrees"));
+        let head = run_git(&repo, &["rev-parse", "HEAD"]).unwrap();
+
+        let worktree = sessions
+            .create(&repo.to_string_lossy(), None, Some(" feature/x "))
+            .unwrap();
+        assert_eq!(worktree.branch, "feature/x");
+        assert_eq!(worktree.base, head.trim());
+        assert_eq!(worktree.merged, None);
+        assert_eq!(
+            Path::new(&worktree.path),
+            dir.join("worktrees").join("repo-feature-x")
+        );
+        assert!(Path::new(&worktree.path).join("a.txt").exists());
+        assert!(sessions
+            .create(&repo.to_string_lossy(), None, Some("feature/x"))
+            .is_err());
+        assert!(sessions
+            .create("remote://server/repo", None, Some("feature/y"))
+            .is_err());
+
+        // Paths taken by something else are numbered.
+        assert_eq!(
+            sessions.unused_path("repo", "feature/x"),
+            dir.join("worktrees").join("repo-feature-x-2")
+        );
+        fs::create_dir_all(dir.join("worktrees").join("repo-feature-x-2")).unwrap();
+        assert_eq!(
+            sessions.unused_path("repo", "feature/x"),
+            dir.join("worktrees").join("repo-feature-x-3")
+        );
+
+        let (first, path) = sessions.generated_branch(&repo, "repo");
+        assert!(first.starts_with(BRANCH_PREFIX));
+        assert!(!path.exists());
+        run_git(&repo, &["branch", &first]).unwrap();
+        let (second, _) = sessions.generated_branch(&repo, "repo");
+        assert_ne!(second, first);
+        assert!(second.starts_with(BRANCH_PREFIX));
+
+        let generated = sessions
+            .create(&repo.to_string_lossy(), Some("HEAD"), None)
+            .unwrap();
+        assert!(generated.branch.starts_with(BRANCH_PREFIX));
+        assert!(Path::new(&generated.path).is_dir());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_squashed_branch_is_deleted_until_it_moves() {
+        let dir = test_dir("session-squash");
+        let repo = init_repo(dir.join("repo"));
+        commit_file(&repo, "a.txt", "one\n");
+        let sessions = WorktreeSessions::new(dir.join("worktrees"));
+        let create = |branch| {
+            sessions
+                .create(&repo.to_string_lossy(), None, Some(branch))
+                .unwrap()
+        };
+
+        let mut merged = create("codex/merged");
+        commit_file(Path::new(&merged.path), "b.txt", "two\n");
+        assert!(check_removable(&merged).is_err());
+        merge_worktree(&mut merged, true, Some("Squashed")).unwrap();
+        assert!(merged.merged.is_some());
+        check_removable(&merged).unwrap();
+        fs::write(Path::new(&merged.path).join("dirty.txt"), "x\n").unwrap();
+        assert!(check_removable(&merged).is_err());
+        fs::remove_file(Path::new(&merged.path).join("dirty.txt")).unwrap();
+        assert!(
+            remove_session_worktree(&merged, false)
+                .unwrap()
+                .branch_deleted
+        );
+        assert!(!branch_exists(&repo, "codex/merged"));
+
+        // Commits after the merge keep the branch.
+        let mut moved = create("codex/moved");
+        commit_file(Path::new(&moved.path), "c.txt", "three\n");
+        merge_worktree(&mut moved, true, None).unwrap();
+        commit_file(Path::new(&moved.path), "d.txt", "four\n");
+        assert!(check_removable(&moved).is_err());
+        assert!(
+            !remove_session_worktree(&moved, false)
+                .unwrap()
+                .branch_deleted
+        );
+        assert!(branch_exists(&repo, "codex/moved"));
+        assert!(
+            remove_session_worktree(&moved, true)
+                .unwrap()
+                .branch_deleted
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
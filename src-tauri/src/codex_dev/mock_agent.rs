@@ -0,0 +1,472 @@
+//! Scriptable ACP agent that replays scenario files, for offline end-to-end tests.
+//!
+//! A scenario is a JSON file with a list of turns. Each prompt runs the first turn
+//! whose `prompt` pattern is contained in the prompt text (a turn without a pattern
+//! matches any prompt); its steps are sent to the client in order and the turn ends
+//! with `stopReason`.
+//!
+//! ```json
+//! {
+//!   "turns": [
+//!     { "prompt": "hello", "steps": [{ "type": "message", "text": "hi" }] },
+//!     {
+//!       "prompt": "edit",
+//!       "steps": [
+//!         { "type": "permission", "toolCallId": "t1", "title": "Edit a.rs", "kind": "edit" },
+//!         { "type": "sleep", "ms": 5000 }
+//!       ],
+//!       "stopReason": "end_turn"
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! The outcome of a `permission` step is echoed as an agent message
+//! (`permission <toolCallId>: <optionId>` or `permission <toolCallId>: cancelled`).
+//! A cancelled turn stops at the next step boundary (or mid-`sleep`) with `cancelled`.
+
+use agent_client_protocol::{
+    Agent, AgentCapabilities, AgentSideConnection, AuthenticateRequest, AuthenticateResponse,
+    CancelNotification, Client, ContentBlock, ContentChunk, Error, ExtNotification,
+    InitializeRequest, InitializeResponse, LoadSessionRequest, LoadSessionResponse,
+    NewSessionRequest, NewSessionResponse, PermissionOption, PermissionOptionKind, Plan, PlanEntry,
+    PromptRequest, PromptResponse, RequestPermissionOutcome, RequestPermissionRequest,
+    SessionNotification, SessionUpdate, StopReason, TextContent, ToolCall, ToolCallContent,
+    ToolCallId, ToolCallStatus, ToolCallUpdate, ToolCallUpdateFields, ToolKind,
+};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::{
+    cell::{Cell, OnceCell, RefCell},
+    collections::HashMap,
+    path::Path,
+    rc::Rc,
+    time::Duration,
+};
+use tokio::sync::watch;
+use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// Scripted behaviour of the mock agent.
+pub struct Scenario {
+    /// Turns matched against incoming prompts, first match wins.
+    #[serde(default)]
+    pub turns: Vec<ScenarioTurn>,
+    /// Advertise `session/load` support (defaults to true).
+    #[serde(default = "default_true")]
+    pub load_session: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// One scripted prompt turn.
+pub struct ScenarioTurn {
+    /// Substring the prompt text must contain; `None` matches any prompt.
+    #[serde(default)]
+    pub prompt: Option<String>,
+    /// Steps replayed in order.
+    #[serde(default)]
+    pub steps: Vec<ScenarioStep>,
+    /// Stop reason returned when all steps ran.
+    #[serde(default = "default_stop_reason")]
+    pub stop_reason: StopReason,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "snake_case",
+    rename_all_fields = "camelCase"
+)]
+/// A single scripted agent action.
+pub enum ScenarioStep {
+    /// Stream an agent message chunk.
+    Message { text: String },
+    /// Stream an agent thought chunk.
+    Thought { text: String },
+    /// Announce a tool call.
+    ToolCall {
+        tool_call_id: String,
+        title: String,
+        #[serde(default)]
+        kind: Option<ToolKind>,
+        #[serde(default)]
+        status: Option<ToolCallStatus>,
+    },
+    /// Update a tool call's status and optionally replace its text output.
+    ToolUpdate {
+        tool_call_id: String,
+        #[serde(default)]
+        status: Option<ToolCallStatus>,
+        #[serde(default)]
+        output: Option<String>,
+    },
+    /// Replace the session plan.
+    Plan { entries: Vec<PlanEntry> },
+    /// Ask the client for permission and echo the outcome as a message.
+    Permission {
+        tool_call_id: String,
+        title: String,
+        #[serde(default)]
+        kind: Option<ToolKind>,
+        /// Offered options; allow-once/allow-always/reject-once by default.
+        #[serde(default)]
+        options: Option<Vec<PermissionOption>>,
+    },
+    /// Send an extension notification such as `codex/token-usage`.
+    ExtNotification {
+        method: String,
+        params: serde_json::Value,
+    },
+    /// Wait before the next step; interrupted by cancellation.
+    Sleep { ms: u64 },
+    /// Fail the prompt request with a JSON-RPC error.
+    Error {
+        #[serde(default = "default_error_code")]
+        code: i32,
+        message: String,
+    },
+    /// Terminate the agent process, simulating a crash.
+    Exit {
+        #[serde(default)]
+        code: i32,
+    },
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_stop_reason() -> StopReason {
+    StopReason::EndTurn
+}
+
+fn default_error_code() -> i32 {
+    -32603
+}
+
+impl Scenario {
+    /// Load a scenario from a JSON file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read scenario: {}", path.display()))?;
+        serde_json::from_str(&raw)
+            .with_context(|| format!("failed to parse scenario: {}", path.display()))
+    }
+
+    /// Find the turn scripted for a prompt.
+    pub fn turn_for(&self, prompt: &str) -> Option<&ScenarioTurn> {
+        self.turns.iter().find(|turn| {
+            turn.prompt
+                .as_deref()
+                .map_or(true, |pattern| prompt.contains(pattern))
+        })
+    }
+}
+
+/// In-process `Agent` implementation replaying a [`Scenario`].
+pub struct MockAgent {
+    scenario: Scenario,
+    conn: Rc<OnceCell<AgentSideConnection>>,
+    next_session: Cell<u64>,
+    cancels: RefCell<HashMap<String, watch::Sender<bool>>>,
+}
+
+impl MockAgent {
+    /// Create an agent; `conn` is filled once the connection to the client exists.
+    pub fn new(scenario: Scenario, conn: Rc<OnceCell<AgentSideConnection>>) -> Self {
+        Self {
+            scenario,
+            conn,
+            next_session: Cell::new(0),
+            cancels: RefCell::new(HashMap::new()),
+        }
+    }
+
+    fn client(&self) -> agent_client_protocol::Result<&AgentSideConnection> {
+        self.conn
+            .get()
+            .ok_or_else(|| Error::internal_error().data("mock agent connection not ready"))
+    }
+
+    async fn notify(
+        &self,
+        session_id: &agent_client_protocol::SessionId,
+        update: SessionUpdate,
+    ) -> agent_client_protocol::Result<()> {
+        self.client()?
+            .session_notification(SessionNotification::new(session_id.clone(), update))
+            .await
+    }
+
+    /// Run one step; returns `Some(stop_reason)` when the turn ends early.
+    async fn run_step(
+        &self,
+        session_id: &agent_client_protocol::SessionId,
+        step: &ScenarioStep,
+        cancelled: &mut watch::Receiver<bool>,
+    ) -> agent_client_protocol::Result<Option<StopReason>> {
+        match step {
+            ScenarioStep::Message { text } => {
+                self.notify(
+                    session_id,
+                    SessionUpdate::AgentMessageChunk(text_chunk(text)),
+                )
+                .await?;
+            }
+            ScenarioStep::Thought { text } => {
+                self.notify(
+                    session_id,
+                    SessionUpdate::AgentThoughtChunk(text_chunk(text)),
+                )
+                .await?;
+            }
+            ScenarioStep::ToolCall {
+                tool_call_id,
+                title,
+                kind,
+                status,
+            } => {
+                let mut tool_call = ToolCall::new(ToolCallId::new(tool_call_id.as_str()), title);
+                if let Some(kind) = kind {
+                    tool_call = tool_call.kind(*kind);
+                }
+                if let Some(status) = status {
+                    tool_call = tool_call.status(*status);
+                }
+                self.notify(session_id, SessionUpdate::ToolCall(tool_call))
+                    .await?;
+            }
+            ScenarioStep::ToolUpdate {
+                tool_call_id,
+                status,
+                output,
+            } => {
+                let mut fields = ToolCallUpdateFields::new();
+                if let Some(status) = status {
+                    fields = fields.status(*status);
+                }
+                if let Some(output) = output {
+                    fields = fields.content(vec![ToolCallContent::from(ContentBlock::Text(
+                        TextContent::new(output.as_str()),
+                    ))]);
+                }
+                let update = ToolCallUpdate::new(ToolCallId::new(tool_call_id.as_str()), fields);
+                self.notify(session_id, SessionUpdate::ToolCallUpdate(update))
+                    .await?;
+            }
+            ScenarioStep::Plan { entries } => {
+                self.notify(session_id, SessionUpdate::Plan(Plan::new(entries.clone())))
+                    .await?;
+            }
+            ScenarioStep::Permission {
+                tool_call_id,
+                title,
+                kind,
+                options,
+            } => {
+                let mut fields = ToolCallUpdateFields::new().title(title.as_str());
+                if let Some(kind) = kind {
+                    fields = fields.kind(*kind);
+                }
+                let options = options.clone().unwrap_or_else(default_permission_options);
+                let request = RequestPermissionRequest::new(
+                    session_id.clone(),
+                    ToolCallUpdate::new(ToolCallId::new(tool_call_id.as_str()), fields),
+                    options,
+                );
+                let response = self.client()?.request_permission(request).await?;
+                let outcome = match response.outcome {
+                    RequestPermissionOutcome::Selected(selected) => selected.option_id.to_string(),
+                    _ => "cancelled".to_string(),
+                };
+                self.notify(
+                    session_id,
+                    SessionUpdate::AgentMessageChunk(text_chunk(&format!(
+                        "permission {tool_call_id}: {outcome}"
+                    ))),
+                )
+                .await?;
+            }
+            ScenarioStep::ExtNotification { method, params } => {
+                let params = serde_json::value::to_raw_value(params)
+                    .map_err(|err| Error::internal_error().data(err.to_string()))?;
+                self.client()?
+                    .ext_notification(ExtNotification::new(method.as_str(), params.into()))
+                    .await?;
+            }
+            ScenarioStep::Sleep { ms } => {
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_millis(*ms)) => {}
+                    _ = cancelled.wait_for(|cancelled| *cancelled) => {
+                        return Ok(Some(StopReason::Cancelled));
+                    }
+                }
+            }
+            ScenarioStep::Error { code, message } => {
+                return Err(Error::new(*code, message.as_str()));
+            }
+            ScenarioStep::Exit { code } => std::process::exit(*code),
+        }
+        Ok(None)
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl Agent for MockAgent {
+    async fn initialize(
+        &self,
+        args: InitializeRequest,
+    ) -> agent_client_protocol::Result<InitializeResponse> {
+        Ok(InitializeResponse::new(args.protocol_version)
+            .agent_capabilities(AgentCapabilities::new().load_session(self.scenario.load_session)))
+    }
+
+    async fn authenticate(
+        &self,
+        _args: AuthenticateRequest,
+    ) -> agent_client_protocol::Result<AuthenticateResponse> {
+        Ok(AuthenticateResponse::new())
+    }
+
+    async fn new_session(
+        &self,
+        _args: NewSessionRequest,
+    ) -> agent_client_protocol::Result<NewSessionResponse> {
+        let id = self.next_session.get() + 1;
+        self.next_session.set(id);
+        Ok(NewSessionResponse::new(format!("mock-session-{id}")))
+    }
+
+    async fn load_session(
+        &self,
+        _args: LoadSessionRequest,
+    ) -> agent_client_protocol::Result<LoadSessionResponse> {
+        if !self.scenario.load_session {
+            return Err(Error::method_not_found());
+        }
+        Ok(LoadSessionResponse::new())
+    }
+
+    async fn prompt(&self, args: PromptRequest) -> agent_client_protocol::Result<PromptResponse> {
+        let text = args
+            .prompt
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text(text) => Some(text.text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let Some(turn) = self.scenario.turn_for(&text) else {
+            return Ok(PromptResponse::new(StopReason::EndTurn));
+        };
+
+        let (cancel_tx, mut cancelled) = watch::channel(false);
+        self.cancels
+            .borrow_mut()
+            .insert(args.session_id.0.to_string(), cancel_tx);
+
+        let mut stop_reason = turn.stop_reason;
+        for step in &turn.steps {
+            if *cancelled.borrow() {
+                stop_reason = StopReason::Cancelled;
+                break;
+            }
+            let result = self.run_step(&args.session_id, step, &mut cancelled).await;
+            match result {
+                Ok(Some(reason)) => {
+                    stop_reason = reason;
+                    break;
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    self.cancels.borrow_mut().remove(args.session_id.0.as_ref());
+                    return Err(err);
+                }
+            }
+        }
+        if *cancelled.borrow() {
+            stop_reason = StopReason::Cancelled;
+        }
+        self.cancels.borrow_mut().remove(args.session_id.0.as_ref());
+        Ok(PromptResponse::new(stop_reason))
+    }
+
+    async fn cancel(&self, args: CancelNotification) -> agent_client_protocol::Result<()> {
+        if let Some(tx) = self.cancels.borrow().get(args.session_id.0.as_ref()) {
+            let _ = tx.send(true);
+        }
+        Ok(())
+    }
+}
+
+fn text_chunk(text: &str) -> ContentChunk {
+    ContentChunk::new(ContentBlock::Text(TextContent::new(text)))
+}
+
+fn default_permission_options() -> Vec<PermissionOption> {
+    vec![
+        PermissionOption::new("allow", "Allow", PermissionOptionKind::AllowOnce),
+        PermissionOption::new(
+            "allow-always",
+            "Always allow",
+            PermissionOptionKind::AllowAlways,
+        ),
+        PermissionOption::new("reject", "Reject", PermissionOptionKind::RejectOnce),
+    ]
+}
+
+/// Serve the mock agent over stdin/stdout until the client disconnects.
+///
+/// Must run inside a `tokio::task::LocalSet`.
+pub async fn serve_stdio(scenario: Scenario) -> Result<()> {
+    let conn = Rc::new(OnceCell::new());
+    let agent = MockAgent::new(scenario, conn.clone());
+    let (client, io_task) = AgentSideConnection::new(
+        agent,
+        tokio::io::stdout().compat_write(),
+        tokio::io::stdin().compat(),
+        |fut| {
+            tokio::task::spawn_local(fut);
+        },
+    );
+    let _ = conn.set(client);
+    io_task.await.context("mock agent connection failed")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scenario_parse_and_turn_matching() {
+        let scenario: Scenario = serde_json::from_str(
+            r#"{
+                "turns": [
+                    { "prompt": "edit", "steps": [
+                        { "type": "permission", "toolCallId": "t1", "title": "Edit", "kind": "edit" },
+                        { "type": "tool_update", "toolCallId": "t1", "status": "completed" }
+                    ], "stopReason": "max_tokens" },
+                    { "steps": [{ "type": "message", "text": "fallback" }] }
+                ]
+            }"#,
+        )
+        .expect("scenario parses");
+
+        assert!(scenario.load_session);
+        let edit = scenario.turn_for("please edit a.rs").expect("edit turn");
+        assert_eq!(edit.stop_reason, StopReason::MaxTokens);
+        assert!(matches!(
+            edit.steps[0],
+            ScenarioStep::Permission {
+                kind: Some(ToolKind::Edit),
+                ..
+            }
+        ));
+        let fallback = scenario.turn_for("anything").expect("fallback turn");
+        assert_eq!(fallback.stop_reason, StopReason::EndTurn);
+        assert!(matches!(fallback.steps[0], ScenarioStep::Message { .. }));
+    }
+}
@@ -1,3 +1,4 @@
 //! Development-only helpers for running Codex ACP flows.
 
 pub mod config;
+pub mod mock_agent;
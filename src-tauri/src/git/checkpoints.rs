@@ -0,0 +1,342 @@
+//! Per-turn snapshots ("checkpoints") of the working tree.
+//!
+//! Before each local prompt turn the service records the working tree, including
+//! untracked files that are not ignored, as a commit under
+//! `refs/codex-desktop/checkpoints/<session>/<turn>`, so checkpoint N is the state
+//! before prompt N. Restoring first saves the current state under
+//! `.../<session>/restore-<n>`, outside the turn numbering. Snapshots are built in a scratch
+//! index, so the real index, HEAD and branches are left alone. Like `git stash`, a
+//! snapshot's first parent is HEAD at the time (when there is one) and its last parent
+//! is a commit of the index, so restoring brings both back.
+
+use crate::git::diff::{diff_stats, parse_patch, PATCH_ARGS};
+use crate::git::types::{GitCheckpoint, GitDiffResult};
+use crate::git::{run_git, run_git_env, run_git_owned, run_git_with_input};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Ref namespace holding the snapshots; refs keep them safe from `git gc`.
+pub const CHECKPOINT_REF_PREFIX: &str = "refs/codex-desktop/checkpoints";
+/// Environment switch that turns automatic checkpoints off when set to `0`.
+pub const CHECKPOINTS_ENV: &str = "CODEX_DESKTOP_CHECKPOINTS";
+
+/// Fixed identity for snapshot commits, which works without a configured user.
+const IDENTITY: [(&str, &str); 4] = [
+    ("GIT_AUTHOR_NAME", "Codex Desktop"),
+    ("GIT_AUTHOR_EMAIL", "codex-desktop@localhost"),
+    ("GIT_COMMITTER_NAME", "Codex Desktop"),
+    ("GIT_COMMITTER_EMAIL", "codex-desktop@localhost"),
+];
+
+/// Whether checkpoints are taken before prompt turns.
+pub fn checkpoints_enabled() -> bool {
+    std::env::var(CHECKPOINTS_ENV).map_or(true, |value| value.trim() != "0")
+}
+
+/// A temporary index file, removed on drop.
+struct ScratchIndex {
+    path: PathBuf,
+}
+
+impl ScratchIndex {
+    /// Start from a copy of the repository index so unchanged files keep their stat
+    /// data and only modified files are hashed again.
+    fn copy_of(root: &Path) -> Result<Self, String> {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "codex-desktop-index-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let index = root.join(run_git(root, &["rev-parse", "--git-path", "index"])?.trim());
+        if index.exists() {
+            fs::copy(&index, &path)
+                .map_err(|err| format!("Failed to copy the git index: {}", err))?;
+        }
+        Ok(Self { path })
+    }
+
+    fn run(&self, root: &Path, args: &[&str]) -> Result<String, String> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let index = self.path.to_string_lossy();
+        run_git_env(root, &args, &[("GIT_INDEX_FILE", index.as_ref())])
+    }
+}
+
+impl Drop for ScratchIndex {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Trees of the index and of the working tree. Afterwards the scratch index matches
+/// the working tree.
+fn snapshot_trees(root: &Path, scratch: &ScratchIndex) -> Result<(String, String), String> {
+    let index_tree = scratch.run(root, &["write-tree"])?.trim().to_string();
+    scratch.run(root, &["add", "-A", "--", "."])?;
+    let worktree_tree = scratch.run(root, &["write-tree"])?.trim().to_string();
+    Ok((index_tree, worktree_tree))
+}
+
+fn commit_tree(
+    root: &Path,
+    tree: &str,
+    parents: &[String],
+    message: &str,
+) -> Result<String, String> {
+    let mut args = vec![
+        "commit-tree".to_string(),
+        "--no-gpg-sign".to_string(),
+        tree.to_string(),
+    ];
+    for parent in parents {
+        args.push("-p".to_string());
+        args.push(parent.clone());
+    }
+    args.push("-m".to_string());
+    args.push(message.to_string());
+    Ok(run_git_env(root, &args, &IDENTITY)?.trim().to_string())
+}
+
+/// `refs/codex-desktop/checkpoints/<session>`. Bytes other than ASCII letters, digits
+/// and `-` are written as `_` and two hex digits, so distinct ids never share a ref.
+fn session_ref(session_id: &str) -> String {
+    let mut session = String::with_capacity(session_id.len());
+    for byte in session_id.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'-' {
+            session.push(byte as char);
+        } else {
+            session.push_str(&format!("_{:02X}", byte));
+        }
+    }
+    format!("{}/{}", CHECKPOINT_REF_PREFIX, session)
+}
+
+/// Ref of the checkpoint before `turn`, or of the `turn`th snapshot saved before a
+/// restore.
+fn checkpoint_ref(session_id: &str, turn: u32, restore: bool) -> String {
+    if restore {
+        format!("{}/restore-{}", session_ref(session_id), turn)
+    } else {
+        format!("{}/{}", session_ref(session_id), turn)
+    }
+}
+
+fn parse_checkpoint(line: &str, prefix: &str, session_id: &str) -> Option<GitCheckpoint> {
+    let fields: Vec<&str> = line.split('\x1f').collect();
+    if fields.len() < 5 {
+        return None;
+    }
+    let name = fields[0].strip_prefix(prefix)?;
+    let (restore, turn) = match name.strip_prefix("restore-") {
+        Some(number) => (true, number),
+        None => (false, name),
+    };
+    let turn = turn.parse().ok()?;
+    let parents: Vec<&str> = fields[2].split_whitespace().collect();
+    let label = fields[4]
+        .split_once(": ")
+        .map(|(_, label)| label)
+        .unwrap_or_default();
+    Some(GitCheckpoint {
+        session_id: session_id.to_string(),
+        turn,
+        restore,
+        commit: fields[1].to_string(),
+        head: (parents.len() > 1).then(|| parents[0].to_string()),
+        created_at: fields[3].to_string(),
+        label: label.to_string(),
+    })
+}
+
+/// Checkpoints of a session in the repository at `root`, oldest turn first, followed
+/// by the snapshots saved before restores.
+pub fn list_checkpoints(root: &Path, session_id: &str) -> Result<Vec<GitCheckpoint>, String> {
+    let session = session_ref(session_id);
+    let output = run_git(
+        root,
+        &[
+            "for-each-ref",
+            "--format=%(refname)%1f%(objectname)%1f%(parent)%1f%(committerdate:iso-strict)%1f%(subject)",
+            &session,
+        ],
+    )?;
+    let prefix = format!("{}/", session);
+    let mut checkpoints: Vec<GitCheckpoint> = output
+        .lines()
+        .filter_map(|line| parse_checkpoint(line, &prefix, session_id))
+        .collect();
+    checkpoints.sort_by_key(|checkpoint| (checkpoint.restore, checkpoint.turn));
+    Ok(checkpoints)
+}
+
+fn find_checkpoint(
+    root: &Path,
+    session_id: &str,
+    turn: u32,
+    restore: bool,
+) -> Result<GitCheckpoint, String> {
+    list_checkpoints(root, session_id)?
+        .into_iter()
+        .find(|checkpoint| checkpoint.turn == turn && checkpoint.restore == restore)
+        .ok_or_else(|| {
+            if restore {
+                format!("No snapshot for restore {} of session {}", turn, session_id)
+            } else {
+                format!("No checkpoint for turn {} of session {}", turn, session_id)
+            }
+        })
+}
+
+/// Number after the last turn checkpoint, or after the last pre-restore snapshot.
+fn next_number(root: &Path, session_id: &str, restore: bool) -> Result<u32, String> {
+    Ok(list_checkpoints(root, session_id)?
+        .iter()
+        .rev()
+        .find(|checkpoint| checkpoint.restore == restore)
+        .map_or(1, |checkpoint| checkpoint.turn + 1))
+}
+
+/// Snapshot the repository containing `cwd` before a turn of `session_id`.
+///
+/// Returns `None` outside a git repository. `turn` is raised past the session's
+/// existing checkpoints, so a snapshot never replaces an earlier one.
+pub fn create_checkpoint(
+    cwd: &Path,
+    session_id: &str,
+    turn: Option<u32>,
+    label: &str,
+) -> Result<Option<GitCheckpoint>, String> {
+    let Ok(root) = run_git(cwd, &["rev-parse", "--show-toplevel"]) else {
+        return Ok(None);
+    };
+    let root = PathBuf::from(root.trim());
+    let next = next_number(&root, session_id, false)?;
+    let turn = turn.map_or(next, |turn| turn.max(next));
+    save_snapshot(&root, session_id, turn, false, label).map(Some)
+}
+
+/// Commit the working tree and index as checkpoint `turn`, or as pre-restore snapshot
+/// `turn` when `restore` is set.
+fn save_snapshot(
+    root: &Path,
+    session_id: &str,
+    turn: u32,
+    restore: bool,
+    label: &str,
+) -> Result<GitCheckpoint, String> {
+    let kind = if restore { "Restore" } else { "Turn" };
+    let scratch = ScratchIndex::copy_of(root)?;
+    let (index_tree, worktree_tree) = snapshot_trees(root, &scratch)?;
+    let mut parents = Vec::new();
+    if let Ok(head) = run_git(root, &["rev-parse", "-q", "--verify", "HEAD^{commit}"]) {
+        parents.push(head.trim().to_string());
+    }
+    let index = commit_tree(
+        root,
+        &index_tree,
+        &parents,
+        &format!("Index at {} {}", kind.to_lowercase(), turn),
+    )?;
+    parents.push(index);
+    let label = label.lines().next().unwrap_or_default().trim();
+    let message = if label.is_empty() {
+        format!("{} {}", kind, turn)
+    } else {
+        format!("{} {}: {}", kind, turn, label)
+    };
+    let commit = commit_tree(root, &worktree_tree, &parents, &message)?;
+    // The empty old value makes the update fail instead of replacing a checkpoint.
+    let name = checkpoint_ref(session_id, turn, restore);
+    run_git(root, &["update-ref", &name, &commit, ""])?;
+    find_checkpoint(root, session_id, turn, restore)
+}
+
+/// Bring the working tree and index back to a checkpoint. Files created since are
+/// deleted, ignored files are left alone and HEAD does not move. The current state is
+/// saved as a pre-restore snapshot first, so a restore can be undone by restoring
+/// that snapshot (`restore`).
+pub fn restore_checkpoint(
+    root: &Path,
+    session_id: &str,
+    turn: u32,
+    restore: bool,
+) -> Result<GitCheckpoint, String> {
+    let checkpoint = find_checkpoint(root, session_id, turn, restore)?;
+    let label = if restore {
+        format!("Before restoring snapshot {}", turn)
+    } else {
+        format!("Before restoring turn {}", turn)
+    };
+    let number = next_number(root, session_id, true)?;
+    save_snapshot(root, session_id, number, true, &label)?;
+    let scratch = ScratchIndex::copy_of(root)?;
+    let (_, current) = snapshot_trees(root, &scratch)?;
+    let target = format!("{}^{{tree}}", checkpoint.commit);
+    scratch.run(root, &["read-tree", "-m", "-u", &current, &target])?;
+
+    let index_parent = if checkpoint.head.is_some() { 2 } else { 1 };
+    let index_tree = format!("{}^{}^{{tree}}", checkpoint.commit, index_parent);
+    run_git(root, &["read-tree", &index_tree])?;
+    // Only refreshes stat data; reports files that differ, which is expected.
+    let _ = run_git(root, &["update-index", "-q", "--refresh"]);
+    Ok(checkpoint)
+}
+
+/// Delete the checkpoints of a session from the repository containing `cwd`. Does
+/// nothing outside a git repository.
+pub fn delete_checkpoints(cwd: &Path, session_id: &str) -> Result<(), String> {
+    let Ok(root) = run_git(cwd, &["rev-parse", "--show-toplevel"]) else {
+        return Ok(());
+    };
+    let root = PathBuf::from(root.trim());
+    let session = session_ref(session_id);
+    let refs = run_git(&root, &["for-each-ref", "--format=%(refname)", &session])?;
+    let commands: String = refs
+        .lines()
+        .filter(|name| !name.is_empty())
+        .map(|name| format!("delete {}\n", name))
+        .collect();
+    if commands.is_empty() {
+        return Ok(());
+    }
+    let args = ["update-ref".to_string(), "--stdin".to_string()];
+    run_git_with_input(&root, &args, commands.as_bytes()).map(|_| ())
+}
+
+/// Diff a checkpoint, or a pre-restore snapshot (`restore`), against the checkpoint
+/// of a later turn or against the current working tree including untracked files.
+pub fn diff_checkpoint(
+    root: &Path,
+    session_id: &str,
+    turn: u32,
+    restore: bool,
+    to: Option<u32>,
+    context_lines: Option<u32>,
+) -> Result<GitDiffResult, String> {
+    let from = find_checkpoint(root, session_id, turn, restore)?;
+    let to_tree = match to {
+        Some(to) => format!(
+            "{}^{{tree}}",
+            find_checkpoint(root, session_id, to, false)?.commit
+        ),
+        None => {
+            let scratch = ScratchIndex::copy_of(root)?;
+            snapshot_trees(root, &scratch)?.1
+        }
+    };
+
+    let mut args = vec!["-c".to_string(), "core.quotePath=false".to_string()];
+    args.push("diff".to_string());
+    args.extend(PATCH_ARGS.iter().map(|arg| arg.to_string()));
+    if let Some(lines) = context_lines {
+        args.push(format!("--unified={}", lines));
+    }
+    args.push(format!("{}^{{tree}}", from.commit));
+    args.push(to_tree);
+    let output = run_git_owned(root, &args)?;
+    let files = parse_patch(&output);
+    let stats = diff_stats(&files);
+    Ok(GitDiffResult { files, stats })
+}
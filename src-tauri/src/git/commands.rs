@@ -1,8 +1,17 @@
 //! Tauri commands for Git integration.
 
-use crate::git::types::{GitCommit, GitStatusEntry, GitStatusResult};
-use crate::git::{run_git, run_git_owned};
+use crate::codex::events::CodexEvent;
+use crate::git::checkpoints::{diff_checkpoint, list_checkpoints, restore_checkpoint};
+use crate::git::diff::{diff_stats, parse_patch, select_hunks, PATCH_ARGS};
+use crate::git::network::run_git_progress;
+use crate::git::types::{
+    GitBranch, GitBranchesResult, GitCheckpoint, GitCommandError, GitCommit, GitCommitDetails,
+    GitDiffResult, GitDiscardResult, GitNetworkOperation, GitProgressEvent, GitStatusEntry,
+    GitStatusResult,
+};
+use crate::git::{run_git, run_git_bytes, run_git_owned, run_git_with_input};
 use std::path::{Path, PathBuf};
+use tauri::AppHandle;
 
 fn normalize_cwd(cwd: &str) -> Result<PathBuf, String> {
     let trimmed = cwd.trim();
@@ -25,22 +34,14 @@ fn repo_root(cwd: &Path) -> Option<PathBuf> {
 }
 
 fn parse_branch_line(line: &str) -> (Option<String>, usize, usize) {
-    let mut ahead = 0;
-    let mut behind = 0;
+    let (mut ahead, mut behind) = (0, 0);
     let mut branch_part = line.trim_start_matches("## ").trim();
 
     if let Some(bracket_idx) = branch_part.find(" [") {
         let (head, tail) = branch_part.split_at(bracket_idx);
         branch_part = head.trim();
         let meta = tail.trim_start_matches(" [").trim_end_matches(']');
-        for token in meta.split(',') {
-            let token = token.trim();
-            if let Some(value) = token.strip_prefix("ahead ") {
-                ahead = value.trim().parse::<usize>().unwrap_or(0);
-            } else if let Some(value) = token.strip_prefix("behind ") {
-                behind = value.trim().parse::<usize>().unwrap_or(0);
-            }
-        }
+        (ahead, behind) = parse_tracking(meta);
     }
 
     let branch_name = if let Some(dots) = branch_part.find("...") {
@@ -64,6 +65,21 @@ fn parse_branch_line(line: &str) -> (Option<String>, usize, usize) {
     (branch, ahead, behind)
 }
 
+/// Parse `ahead 1, behind 2` as printed by `git status` and `%(upstream:track)`.
+fn parse_tracking(meta: &str) -> (usize, usize) {
+    let mut ahead = 0;
+    let mut behind = 0;
+    for token in meta.split(',') {
+        let token = token.trim();
+        if let Some(value) = token.strip_prefix("ahead ") {
+            ahead = value.trim().parse::<usize>().unwrap_or(0);
+        } else if let Some(value) = token.strip_prefix("behind ") {
+            behind = value.trim().parse::<usize>().unwrap_or(0);
+        }
+    }
+    (ahead, behind)
+}
+
 fn is_conflicted_status(status: &str) -> bool {
     matches!(status, "DD" | "AU" | "UD" | "UA" | "DU" | "AA" | "UU")
 }
@@ -175,6 +191,55 @@ pub fn git_status(cwd: String) -> Result<GitStatusResult, String> {
     })
 }
 
+/// `git log` fields shared by history and commit details.
+const COMMIT_FORMAT: &str = "%H%x1f%P%x1f%an%x1f%ad%x1f%D%x1f%s";
+
+fn parse_commit_fields(fields: &[&str]) -> Option<GitCommit> {
+    if fields.len() < 6 {
+        return None;
+    }
+    let parents = if fields[1].trim().is_empty() {
+        Vec::new()
+    } else {
+        fields[1]
+            .split_whitespace()
+            .map(|s| s.to_string())
+            .collect()
+    };
+    let refs = if fields[4].trim().is_empty() {
+        Vec::new()
+    } else {
+        fields[4]
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect()
+    };
+
+    Some(GitCommit {
+        id: fields[0].to_string(),
+        parents,
+        author: fields[2].to_string(),
+        date: fields[3].to_string(),
+        refs,
+        summary: fields[5].to_string(),
+    })
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// Reject revisions git would parse as options.
+fn check_revision(revision: &str) -> Result<(), String> {
+    if revision.starts_with('-') {
+        return Err(format!("Invalid revision: {}", revision));
+    }
+    Ok(())
+}
+
 #[tauri::command]
 pub fn git_history(
     cwd: String,
@@ -184,7 +249,7 @@ pub fn git_history(
     let cwd = normalize_cwd(&cwd)?;
     let mut args = vec![
         "log".to_string(),
-        "--pretty=format:%H%x1f%P%x1f%an%x1f%ad%x1f%D%x1f%s%x1e".to_string(),
+        format!("--pretty=format:{}%x1e", COMMIT_FORMAT),
         "--date=iso-strict".to_string(),
         "--date-order".to_string(),
     ];
@@ -210,35 +275,9 @@ pub fn git_history(
             continue;
         }
         let fields: Vec<&str> = record.split('\x1f').collect();
-        if fields.len() < 6 {
-            continue;
+        if let Some(commit) = parse_commit_fields(&fields) {
+            commits.push(commit);
         }
-        let parents = if fields[1].trim().is_empty() {
-            Vec::new()
-        } else {
-            fields[1]
-                .split_whitespace()
-                .map(|s| s.to_string())
-                .collect()
-        };
-        let refs = if fields[4].trim().is_empty() {
-            Vec::new()
-        } else {
-            fields[4]
-                .split(',')
-                .map(|s| s.trim().to_string())
-                .filter(|s| !s.is_empty())
-                .collect()
-        };
-
-        commits.push(GitCommit {
-            id: fields[0].to_string(),
-            parents,
-            author: fields[2].to_string(),
-            date: fields[3].to_string(),
-            refs,
-            summary: fields[5].to_string(),
-        });
     }
 
     Ok(commits)
@@ -276,3 +315,959 @@ pub fn git_reset(cwd: String, commit: String, mode: Option<String>) -> Result<()
     let args = vec!["reset".to_string(), mode_flag.to_string(), commit];
     run_git_owned(&cwd, &args).map(|_| ())
 }
+
+/// Diff the working tree or index. Without revisions this shows unstaged changes,
+/// or staged ones with `staged`; `from` compares against that commit and `from` plus
+/// `to` compares two commits.
+#[tauri::command]
+pub fn git_diff(
+    cwd: String,
+    staged: Option<bool>,
+    from: Option<String>,
+    to: Option<String>,
+    paths: Option<Vec<String>>,
+    context_lines: Option<u32>,
+) -> Result<GitDiffResult, String> {
+    let cwd = normalize_cwd(&cwd)?;
+    let from = non_empty(from);
+    let to = non_empty(to);
+
+    let mut args = vec!["-c".to_string(), "core.quotePath=false".to_string()];
+    args.push("diff".to_string());
+    args.extend(PATCH_ARGS.iter().map(|arg| arg.to_string()));
+    if let Some(lines) = context_lines {
+        args.push(format!("--unified={}", lines));
+    }
+    match (&from, &to) {
+        (None, None) => {
+            if staged.unwrap_or(false) {
+                args.push("--cached".to_string());
+            }
+        }
+        (Some(from), None) => {
+            check_revision(from)?;
+            if staged.unwrap_or(false) {
+                args.push("--cached".to_string());
+            }
+            args.push(from.clone());
+        }
+        (Some(from), Some(to)) => {
+            check_revision(from)?;
+            check_revision(to)?;
+            if staged.unwrap_or(false) {
+                return Err("A diff between two commits cannot be staged".to_string());
+            }
+            args.push(from.clone());
+            args.push(to.clone());
+        }
+        (None, Some(_)) => return Err("A diff with `to` also needs `from`".to_string()),
+    }
+    args.push("--".to_string());
+    args.extend(paths.unwrap_or_default());
+
+    let output = run_git_owned(&cwd, &args)?;
+    let files = parse_patch(&output);
+    let stats = diff_stats(&files);
+    Ok(GitDiffResult { files, stats })
+}
+
+/// Show a commit with its message and the diff against its first parent.
+#[tauri::command]
+pub fn git_show_commit(
+    cwd: String,
+    commit: String,
+    context_lines: Option<u32>,
+) -> Result<GitCommitDetails, String> {
+    let cwd = normalize_cwd(&cwd)?;
+    let commit = commit.trim().to_string();
+    if commit.is_empty() {
+        return Err("Commit is empty".to_string());
+    }
+    check_revision(&commit)?;
+
+    let mut args = vec!["-c".to_string(), "core.quotePath=false".to_string()];
+    args.push("show".to_string());
+    args.extend(PATCH_ARGS.iter().map(|arg| arg.to_string()));
+    args.push("-m".to_string());
+    args.push("--first-parent".to_string());
+    args.push(format!("--format={}%x1f%b%x1e", COMMIT_FORMAT));
+    args.push("--date=iso-strict".to_string());
+    if let Some(lines) = context_lines {
+        args.push(format!("--unified={}", lines));
+    }
+    args.push(commit.clone());
+    args.push("--".to_string());
+
+    let output = run_git_owned(&cwd, &args)?;
+    let (header, patch) = output
+        .split_once('\x1e')
+        .ok_or_else(|| format!("Unexpected git show output for {}", commit))?;
+    let fields: Vec<&str> = header.split('\x1f').collect();
+    let details = parse_commit_fields(&fields)
+        .ok_or_else(|| format!("Unexpected git show output for {}", commit))?;
+    let body = fields.get(6).map(|body| body.trim()).unwrap_or_default();
+    let files = parse_patch(patch);
+    let stats = diff_stats(&files);
+    Ok(GitCommitDetails {
+        commit: details,
+        body: body.to_string(),
+        files,
+        stats,
+    })
+}
+
+/// Root of the repository containing `cwd`. Staging commands take paths relative to
+/// it, as reported by `git_status`.
+fn worktree_root(cwd: &str) -> Result<PathBuf, String> {
+    let cwd = normalize_cwd(cwd)?;
+    repo_root(&cwd).ok_or_else(|| format!("Not a git repository: {}", cwd.display()))
+}
+
+/// `git --literal-pathspecs <command...> -- <paths>`, so paths with glob characters
+/// only match themselves.
+fn pathspec_args(command: &[&str], paths: &[String]) -> Vec<String> {
+    let mut args = vec!["--literal-pathspecs".to_string()];
+    args.extend(command.iter().map(|arg| arg.to_string()));
+    args.push("--".to_string());
+    args.extend(paths.iter().cloned());
+    args
+}
+
+/// Stage `paths` including deletions and untracked files; everything when empty.
+#[tauri::command]
+pub fn git_stage(cwd: String, paths: Vec<String>) -> Result<(), String> {
+    let root = worktree_root(&cwd)?;
+    run_git_owned(&root, &pathspec_args(&["add", "-A"], &paths)).map(|_| ())
+}
+
+/// Unstage `paths`, keeping the working tree; everything when empty.
+#[tauri::command]
+pub fn git_unstage(cwd: String, paths: Vec<String>) -> Result<(), String> {
+    let root = worktree_root(&cwd)?;
+    run_git_owned(&root, &pathspec_args(&["reset", "-q"], &paths)).map(|_| ())
+}
+
+/// Stage the unstaged hunks of `path` with the given `@@` headers. `context_lines`
+/// must match the diff the headers were taken from.
+#[tauri::command]
+pub fn git_stage_hunks(
+    cwd: String,
+    path: String,
+    hunks: Vec<String>,
+    context_lines: Option<u32>,
+) -> Result<(), String> {
+    apply_hunks(&cwd, &path, &hunks, context_lines, false)
+}
+
+/// Unstage the staged hunks of `path` with the given `@@` headers.
+#[tauri::command]
+pub fn git_unstage_hunks(
+    cwd: String,
+    path: String,
+    hunks: Vec<String>,
+    context_lines: Option<u32>,
+) -> Result<(), String> {
+    apply_hunks(&cwd, &path, &hunks, context_lines, true)
+}
+
+fn apply_hunks(
+    cwd: &str,
+    path: &str,
+    hunks: &[String],
+    context_lines: Option<u32>,
+    staged: bool,
+) -> Result<(), String> {
+    let root = worktree_root(cwd)?;
+    let mut diff = vec!["diff"];
+    diff.extend(PATCH_ARGS);
+    let unified = context_lines.map(|lines| format!("--unified={}", lines));
+    if let Some(unified) = &unified {
+        diff.push(unified);
+    }
+    if staged {
+        diff.push("--cached");
+    }
+    let patch = run_git_bytes(&root, &pathspec_args(&diff, &[path.to_string()]))?;
+    let patch = select_hunks(&patch, hunks)?;
+
+    let mut apply = vec!["apply", "--cached", "--whitespace=nowarn"];
+    if staged {
+        apply.push("--reverse");
+    }
+    if context_lines == Some(0) {
+        apply.push("--unidiff-zero");
+    }
+    apply.push("-");
+    let args: Vec<String> = apply.iter().map(|arg| arg.to_string()).collect();
+    run_git_with_input(&root, &args, &patch).map(|_| ())
+}
+
+/// Discard the unstaged changes of `paths`: tracked files are restored from the
+/// index and untracked files are deleted. Without `token` nothing is touched; the
+/// result lists the affected changes and the token that confirms exactly them.
+#[tauri::command]
+pub fn git_discard(
+    cwd: String,
+    paths: Vec<String>,
+    token: Option<String>,
+) -> Result<GitDiscardResult, String> {
+    let root = worktree_root(&cwd)?;
+    if paths.is_empty() {
+        return Err("No paths to discard".to_string());
+    }
+    let status = run_git_owned(
+        &root,
+        &pathspec_args(
+            &["status", "--porcelain", "-z", "--untracked-files=all"],
+            &paths,
+        ),
+    )?;
+    let (_, _, _, changes, _) = parse_status_output(&status);
+    if changes.is_empty() {
+        return Err("No unstaged changes to discard".to_string());
+    }
+    let untracked: Vec<String> = changes
+        .iter()
+        .filter(|entry| entry.untracked)
+        .map(|entry| entry.path.clone())
+        .collect();
+    let tracked: Vec<String> = changes
+        .iter()
+        .filter(|entry| !entry.untracked)
+        .map(|entry| entry.path.clone())
+        .collect();
+    let current = discard_token(&root, &paths, &status, &untracked)?;
+
+    match token.as_deref().map(str::trim) {
+        None | Some("") => Ok(GitDiscardResult {
+            discarded: false,
+            token: current,
+            changes,
+        }),
+        Some(token) if token != current => {
+            Err("The changes have been modified since the discard was confirmed".to_string())
+        }
+        Some(_) => {
+            if !tracked.is_empty() {
+                run_git_owned(&root, &pathspec_args(&["checkout", "-q"], &tracked))?;
+            }
+            if !untracked.is_empty() {
+                run_git_owned(&root, &pathspec_args(&["clean", "-f", "-q"], &untracked))?;
+            }
+            Ok(GitDiscardResult {
+                discarded: true,
+                token: current,
+                changes,
+            })
+        }
+    }
+}
+
+/// Object id of the state a discard would destroy: the status, the unstaged patch
+/// and the contents of untracked files.
+fn discard_token(
+    root: &Path,
+    paths: &[String],
+    status: &str,
+    untracked: &[String],
+) -> Result<String, String> {
+    let patch = run_git_owned(
+        root,
+        &pathspec_args(
+            &["diff", "--binary", "--no-ext-diff", "--no-textconv"],
+            paths,
+        ),
+    )?;
+    let contents = if untracked.is_empty() {
+        String::new()
+    } else {
+        run_git_owned(
+            root,
+            &pathspec_args(&["hash-object", "--no-filters"], untracked),
+        )?
+    };
+    let state = format!("{}\0{}\0{}", status, patch, contents);
+    let args = ["hash-object".to_string(), "--stdin".to_string()];
+    Ok(run_git_with_input(root, &args, state.as_bytes())?
+        .trim()
+        .to_string())
+}
+
+/// Commit the index and return the new commit. An empty message is only allowed
+/// with `amend`, which then keeps the previous message.
+#[tauri::command]
+pub fn git_commit(
+    cwd: String,
+    message: String,
+    amend: Option<bool>,
+    sign_off: Option<bool>,
+) -> Result<GitCommit, String> {
+    let cwd = normalize_cwd(&cwd)?;
+    let amend = amend.unwrap_or(false);
+    let message = message.trim();
+    let mut args = vec!["commit".to_string(), "-q".to_string()];
+    if amend {
+        args.push("--amend".to_string());
+    }
+    if sign_off.unwrap_or(false) {
+        args.push("--signoff".to_string());
+    }
+    if message.is_empty() {
+        if !amend {
+            return Err("Commit message is empty".to_string());
+        }
+        args.push("--no-edit".to_string());
+    } else {
+        args.push("--file=-".to_string());
+    }
+    run_git_with_input(&cwd, &args, message.as_bytes())?;
+    head_commit(&cwd)
+}
+
+/// The commit HEAD points at.
+pub fn head_commit(cwd: &Path) -> Result<GitCommit, String> {
+    let output = run_git(
+        cwd,
+        &[
+            "log",
+            "-1",
+            &format!("--format={}", COMMIT_FORMAT),
+            "--date=iso-strict",
+            "HEAD",
+        ],
+    )?;
+    let fields: Vec<&str> = output.trim_end_matches('\n').split('\x1f').collect();
+    parse_commit_fields(&fields).ok_or_else(|| "Unexpected git log output".to_string())
+}
+
+const BRANCH_FORMAT: &str = "%(refname)%1f%(refname:short)%1f%(HEAD)%1f%(symref)%1f%(upstream:short)%1f%(upstream:track,nobracket)%1f%(objectname)%1f%(parent)%1f%(authorname)%1f%(authordate:iso-strict)%1f%(subject)";
+
+fn parse_branch(line: &str, remotes: &[&str]) -> Option<GitBranch> {
+    let fields: Vec<&str> = line.split('\x1f').collect();
+    if fields.len() < 11 || !fields[3].is_empty() {
+        // Symbolic refs such as `origin/HEAD` only point at another branch.
+        return None;
+    }
+    let remote = fields[0].strip_prefix("refs/remotes/").and_then(|rest| {
+        remotes
+            .iter()
+            .filter(|remote| {
+                rest.strip_prefix(**remote)
+                    .is_some_and(|branch| branch.starts_with('/'))
+            })
+            .max_by_key(|remote| remote.len())
+            .map(|remote| remote.to_string())
+    });
+    let (ahead, behind) = parse_tracking(fields[5]);
+    let last_commit =
+        parse_commit_fields(&[fields[6], fields[7], fields[8], fields[9], "", fields[10]])?;
+    Some(GitBranch {
+        name: fields[1].to_string(),
+        remote,
+        current: fields[2] == "*",
+        upstream: (!fields[4].is_empty()).then(|| fields[4].to_string()),
+        upstream_gone: fields[5] == "gone",
+        ahead,
+        behind,
+        last_commit,
+    })
+}
+
+/// Local and remote-tracking branches with upstream, ahead/behind and last commit.
+#[tauri::command]
+pub fn git_branches(cwd: String) -> Result<GitBranchesResult, String> {
+    let cwd = normalize_cwd(&cwd)?;
+    let remotes = run_git(&cwd, &["remote"])?;
+    let remotes: Vec<&str> = remotes.lines().map(str::trim).collect();
+    let output = run_git(
+        &cwd,
+        &[
+            "for-each-ref",
+            &format!("--format={}", BRANCH_FORMAT),
+            "--sort=refname",
+            "refs/heads",
+            "refs/remotes",
+        ],
+    )?;
+
+    let mut result = GitBranchesResult {
+        local: Vec::new(),
+        remote: Vec::new(),
+    };
+    for line in output.lines() {
+        let is_remote = line.starts_with("refs/remotes/");
+        if let Some(branch) = parse_branch(line, &remotes) {
+            if is_remote {
+                result.remote.push(branch);
+            } else {
+                result.local.push(branch);
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Delete a local branch; unmerged branches need `force`.
+#[tauri::command]
+pub fn git_delete_branch(cwd: String, name: String, force: Option<bool>) -> Result<(), String> {
+    let cwd = normalize_cwd(&cwd)?;
+    check_revision(&name)?;
+    let flag = if force.unwrap_or(false) { "-D" } else { "-d" };
+    run_git(&cwd, &["branch", flag, &name]).map(|_| ())
+}
+
+#[tauri::command]
+pub fn git_rename_branch(cwd: String, name: String, new_name: String) -> Result<(), String> {
+    let cwd = normalize_cwd(&cwd)?;
+    check_revision(&name)?;
+    check_revision(&new_name)?;
+    run_git(&cwd, &["branch", "-m", &name, &new_name]).map(|_| ())
+}
+
+fn fetch_args(remote: Option<String>, prune: Option<bool>) -> Result<Vec<String>, String> {
+    let mut args = vec!["fetch".to_string(), "--progress".to_string()];
+    if prune.unwrap_or(false) {
+        args.push("--prune".to_string());
+    }
+    if let Some(remote) = non_empty(remote) {
+        check_revision(&remote)?;
+        args.push(remote);
+    }
+    Ok(args)
+}
+
+/// `mode` is `rebase` or, by default, `ff-only`.
+fn pull_args(
+    remote: Option<String>,
+    branch: Option<String>,
+    mode: Option<String>,
+) -> Result<Vec<String>, String> {
+    let mode_flag = match mode.as_deref() {
+        Some("rebase") => "--rebase",
+        None | Some("ff-only") | Some("ffOnly") => "--ff-only",
+        Some(other) => return Err(format!("Unknown pull mode: {}", other)),
+    };
+    let mut args = vec![
+        "pull".to_string(),
+        "--progress".to_string(),
+        mode_flag.to_string(),
+    ];
+    args.extend(remote_and_branch(remote, branch)?);
+    Ok(args)
+}
+
+/// With `set_upstream` the remote defaults to `origin` and the branch to the current one.
+fn push_args(
+    remote: Option<String>,
+    branch: Option<String>,
+    set_upstream: Option<bool>,
+    force_with_lease: Option<bool>,
+) -> Result<Vec<String>, String> {
+    let mut args = vec!["push".to_string(), "--progress".to_string()];
+    let mut remote = non_empty(remote);
+    let mut branch = non_empty(branch);
+    if set_upstream.unwrap_or(false) {
+        args.push("--set-upstream".to_string());
+        remote.get_or_insert_with(|| "origin".to_string());
+        branch.get_or_insert_with(|| "HEAD".to_string());
+    }
+    if force_with_lease.unwrap_or(false) {
+        args.push("--force-with-lease".to_string());
+    }
+    args.extend(remote_and_branch(remote, branch)?);
+    Ok(args)
+}
+
+fn remote_and_branch(
+    remote: Option<String>,
+    branch: Option<String>,
+) -> Result<Vec<String>, String> {
+    match (non_empty(remote), non_empty(branch)) {
+        (None, None) => Ok(Vec::new()),
+        (Some(remote), None) => {
+            check_revision(&remote)?;
+            Ok(vec![remote])
+        }
+        (Some(remote), Some(branch)) => {
+            check_revision(&remote)?;
+            check_revision(&branch)?;
+            Ok(vec![remote, branch])
+        }
+        (None, Some(_)) => Err("A branch also needs a remote".to_string()),
+    }
+}
+
+/// Run a fetch, pull or push off the main thread, emitting `git:progress` events.
+async fn run_network(
+    app: AppHandle,
+    cwd: String,
+    operation: GitNetworkOperation,
+    args: Vec<String>,
+) -> Result<(), GitCommandError> {
+    let dir = normalize_cwd(&cwd)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        run_git_progress(&dir, &args, |progress| {
+            CodexEvent::GitProgress(GitProgressEvent {
+                cwd: &cwd,
+                operation,
+                progress,
+            })
+            .emit(&app);
+        })
+    })
+    .await
+    .map_err(|err| GitCommandError::from(format!("Git task failed: {}", err)))?
+    .map(|_| ())
+}
+
+#[tauri::command]
+pub async fn git_fetch(
+    app: AppHandle,
+    cwd: String,
+    remote: Option<String>,
+    prune: Option<bool>,
+) -> Result<(), GitCommandError> {
+    let args = fetch_args(remote, prune)?;
+    run_network(app, cwd, GitNetworkOperation::Fetch, args).await
+}
+
+#[tauri::command]
+pub async fn git_pull(
+    app: AppHandle,
+    cwd: String,
+    remote: Option<String>,
+    branch: Option<String>,
+    mode: Option<String>,
+) -> Result<(), GitCommandError> {
+    let args = pull_args(remote, branch, mode)?;
+    run_network(app, cwd, GitNetworkOperation::Pull, args).await
+}
+
+#[tauri::command]
+pub async fn git_push(
+    app: AppHandle,
+    cwd: String,
+    remote: Option<String>,
+    branch: Option<String>,
+    set_upstream: Option<bool>,
+    force_with_lease: Option<bool>,
+) -> Result<(), GitCommandError> {
+    let args = push_args(remote, branch, set_upstream, force_with_lease)?;
+    run_network(app, cwd, GitNetworkOperation::Push, args).await
+}
+
+fn check_session(session_id: &str) -> Result<(), String> {
+    if session_id.trim().is_empty() {
+        return Err("Session id is empty".to_string());
+    }
+    Ok(())
+}
+
+/// Checkpoints taken before the turns of a session, oldest first.
+#[tauri::command]
+pub fn git_list_checkpoints(cwd: String, session_id: String) -> Result<Vec<GitCheckpoint>, String> {
+    check_session(&session_id)?;
+    let root = worktree_root(&cwd)?;
+    list_checkpoints(&root, &session_id)
+}
+
+/// Restore the working tree and index to the state before `turn`, or to the
+/// pre-restore snapshot `turn` when `restore` is set.
+#[tauri::command]
+pub fn git_restore_checkpoint(
+    cwd: String,
+    session_id: String,
+    turn: u32,
+    restore: Option<bool>,
+) -> Result<GitCheckpoint, String> {
+    check_session(&session_id)?;
+    let root = worktree_root(&cwd)?;
+    restore_checkpoint(&root, &session_id, turn, restore.unwrap_or(false))
+}
+
+/// Changes since the checkpoint of `turn` (a pre-restore snapshot with `restore`):
+/// up to the checkpoint of turn `to`, or up to the current working tree.
+#[tauri::command]
+pub fn git_diff_checkpoint(
+    cwd: String,
+    session_id: String,
+    turn: u32,
+    restore: Option<bool>,
+    to: Option<u32>,
+    context_lines: Option<u32>,
+) -> Result<GitDiffResult, String> {
+    check_session(&session_id)?;
+    let root = worktree_root(&cwd)?;
+    diff_checkpoint(
+        &root,
+        &session_id,
+        turn,
+        restore.unwrap_or(false),
+        to,
+        context_lines,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_support::{commit_all, init_repo, test_dir};
+    use crate::git::types::GitDiffFileStatus;
+    use std::fs;
+
+    fn cwd(dir: &Path) -> String {
+        dir.display().to_string()
+    }
+
+    #[test]
+    fn test_diff_modes_and_show_commit() {
+        let dir = init_repo(test_dir("git-diff"));
+        fs::write(dir.join("a.txt"), "one\ntwo\nthree\n").unwrap();
+        fs::write(dir.join("old name.txt"), "keep\nme\nhere\n").unwrap();
+        let first = commit_all(&dir, "first");
+
+        fs::write(dir.join("a.txt"), "one\n2\nthree\n").unwrap();
+        run_git(&dir, &["mv", "old name.txt", "new name.txt"]).unwrap();
+        let second = commit_all(&dir, "second\n\nWith a body.");
+
+        fs::write(dir.join("a.txt"), "one\n2\nthree\nfour\n").unwrap();
+        run_git(&dir, &["add", "a.txt"]).unwrap();
+        fs::write(dir.join("a.txt"), "zero\none\n2\nthree\nfour\n").unwrap();
+
+        let unstaged = git_diff(cwd(&dir), None, None, None, None, None).unwrap();
+        assert_eq!(unstaged.files.len(), 1);
+        let line = &unstaged.files[0].hunks[0].lines[0];
+        assert_eq!((line.old_line, line.new_line), (None, Some(1)));
+        assert_eq!(line.content, "zero");
+
+        let staged = git_diff(cwd(&dir), Some(true), None, None, None, None).unwrap();
+        assert_eq!(staged.stats.additions, 1);
+        assert_eq!(
+            staged.files[0].hunks[0].lines.last().unwrap().content,
+            "four"
+        );
+
+        let against = git_diff(cwd(&dir), None, Some(first.clone()), None, None, Some(0)).unwrap();
+        assert_eq!(against.stats.files, 2);
+        let renamed = against
+            .files
+            .iter()
+            .find(|f| f.path == "new name.txt")
+            .unwrap();
+        assert_eq!(renamed.status, GitDiffFileStatus::Renamed);
+        assert_eq!(renamed.old_path.as_deref(), Some("old name.txt"));
+
+        let range = git_diff(
+            cwd(&dir),
+            None,
+            Some(first.clone()),
+            Some(second.clone()),
+            Some(vec!["a.txt".to_string()]),
+            None,
+        )
+        .unwrap();
+        assert_eq!(range.files.len(), 1);
+        assert_eq!((range.stats.additions, range.stats.deletions), (1, 1));
+        assert!(git_diff(cwd(&dir), None, None, Some(second.clone()), None, None).is_err());
+        assert!(git_diff(cwd(&dir), None, Some("--output=x".into()), None, None, None).is_err());
+
+        let details = git_show_commit(cwd(&dir), second.clone(), None).unwrap();
+        assert_eq!(details.commit.id, second);
+        assert_eq!(details.commit.parents, vec![first]);
+        assert_eq!(details.commit.summary, "second");
+        assert_eq!(details.body, "With a body.");
+        assert_eq!(details.stats.files, 2);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_stage_discard_and_commit() {
+        let dir = init_repo(test_dir("git-stage"));
+        let lines: Vec<String> = (1..=20).map(|n| n.to_string()).collect();
+        fs::write(dir.join("a.txt"), lines.join("\n") + "\n").unwrap();
+        let first = commit_all(&dir, "first");
+
+        let mut edited = lines.clone();
+        edited[0] = "one".to_string();
+        edited[19] = "twenty".to_string();
+        fs::write(dir.join("a.txt"), edited.join("\n") + "\n").unwrap();
+        fs::write(dir.join("new [1].txt"), "new\n").unwrap();
+
+        let diff = git_diff(cwd(&dir), None, None, None, None, None).unwrap();
+        let hunks: Vec<String> = diff.files[0]
+            .hunks
+            .iter()
+            .map(|hunk| hunk.header.clone())
+            .collect();
+        assert_eq!(hunks.len(), 2);
+        git_stage_hunks(cwd(&dir), "a.txt".into(), vec![hunks[1].clone()], None).unwrap();
+        let staged = git_diff(cwd(&dir), Some(true), None, None, None, None).unwrap();
+        assert_eq!(staged.files[0].hunks.len(), 1);
+        assert_eq!(
+            staged.files[0].hunks[0].lines.last().unwrap().content,
+            "twenty"
+        );
+        assert!(git_stage_hunks(cwd(&dir), "a.txt".into(), vec![hunks[1].clone()], None).is_err());
+
+        git_unstage_hunks(
+            cwd(&dir),
+            "a.txt".into(),
+            vec![staged.files[0].hunks[0].header.clone()],
+            None,
+        )
+        .unwrap();
+        assert!(git_diff(cwd(&dir), Some(true), None, None, None, None)
+            .unwrap()
+            .files
+            .is_empty());
+
+        git_stage(cwd(&dir), vec!["new [1].txt".into()]).unwrap();
+        let status = git_status(cwd(&dir)).unwrap();
+        assert_eq!(status.staged_changes.len(), 1);
+        git_unstage(cwd(&dir), Vec::new()).unwrap();
+        assert!(git_status(cwd(&dir)).unwrap().staged_changes.is_empty());
+
+        let paths = vec!["a.txt".to_string(), "new [1].txt".to_string()];
+        let preview = git_discard(cwd(&dir), paths.clone(), None).unwrap();
+        assert!(!preview.discarded);
+        assert_eq!(preview.changes.len(), 2);
+        fs::write(dir.join("new [1].txt"), "newer\n").unwrap();
+        assert!(git_discard(cwd(&dir), paths.clone(), Some(preview.token)).is_err());
+        let preview = git_discard(cwd(&dir), paths.clone(), None).unwrap();
+        let done = git_discard(cwd(&dir), paths.clone(), Some(preview.token)).unwrap();
+        assert!(done.discarded);
+        assert!(!dir.join("new [1].txt").exists());
+        assert!(git_status(cwd(&dir)).unwrap().changes.is_empty());
+
+        assert!(git_commit(cwd(&dir), "  ".into(), None, None).is_err());
+        fs::write(dir.join("a.txt"), "changed\n").unwrap();
+        git_stage(cwd(&dir), Vec::new()).unwrap();
+        let second = git_commit(cwd(&dir), "second\n\nBody.".into(), None, Some(true)).unwrap();
+        assert_eq!(second.summary, "second");
+        assert_eq!(second.parents, vec![first.clone()]);
+        let body = git_show_commit(cwd(&dir), second.id.clone(), None)
+            .unwrap()
+            .body;
+        assert!(body.contains("Signed-off-by: Test <test@example.com>"));
+
+        fs::write(dir.join("b.txt"), "b\n").unwrap();
+        git_stage(cwd(&dir), vec!["b.txt".into()]).unwrap();
+        let amended = git_commit(cwd(&dir), String::new(), Some(true), None).unwrap();
+        assert_ne!(amended.id, second.id);
+        assert_eq!(amended.summary, "second");
+        assert_eq!(amended.parents, vec![first]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_stage_hunks_keeps_other_encodings() {
+        let dir = init_repo(test_dir("git-latin1"));
+        let mut lines: Vec<Vec<u8>> = (1..=20).map(|n| n.to_string().into_bytes()).collect();
+        lines[0] = b"caf\xe9".to_vec();
+        fs::write(dir.join("a.txt"), lines.join(&b'\n')).unwrap();
+        commit_all(&dir, "first");
+
+        lines[0] = b"caf\xe8".to_vec();
+        lines[19] = b"vingt".to_vec();
+        let edited = lines.join(&b'\n');
+        fs::write(dir.join("a.txt"), &edited).unwrap();
+        let diff = git_diff(cwd(&dir), None, None, None, None, None).unwrap();
+        let first = diff.files[0].hunks[0].header.clone();
+        git_stage_hunks(cwd(&dir), "a.txt".into(), vec![first], None).unwrap();
+
+        let staged = run_git_bytes(&dir, &["show".to_string(), ":a.txt".to_string()]).unwrap();
+        assert!(staged.starts_with(b"caf\xe8\n"));
+        assert!(staged.ends_with(b"\n20"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_checkpoints_restore_and_diff() {
+        use crate::git::checkpoints::{
+            create_checkpoint, delete_checkpoints, CHECKPOINT_REF_PREFIX,
+        };
+
+        let dir = init_repo(test_dir("git-checkpoints"));
+        fs::write(dir.join("a.txt"), "one\n").unwrap();
+        fs::write(dir.join("gone.txt"), "gone\n").unwrap();
+        fs::write(dir.join(".gitignore"), "ignored.txt\n").unwrap();
+        let head = commit_all(&dir, "first");
+        fs::write(dir.join("a.txt"), "staged\n").unwrap();
+        run_git(&dir, &["add", "a.txt"]).unwrap();
+        fs::write(dir.join("a.txt"), "worktree\n").unwrap();
+        fs::write(dir.join("notes.txt"), "untracked\n").unwrap();
+        let status_before = run_git(&dir, &["status", "--porcelain"]).unwrap();
+
+        let first = create_checkpoint(&dir, "session/1", None, "Fix the bug\nmore")
+            .unwrap()
+            .unwrap();
+        assert_eq!((first.turn, first.label.as_str()), (1, "Fix the bug"));
+        assert_eq!(first.head.as_deref(), Some(head.as_str()));
+        assert_eq!(
+            run_git(&dir, &["status", "--porcelain"]).unwrap(),
+            status_before
+        );
+        assert!(run_git(&dir, &["stash", "list"]).unwrap().is_empty());
+
+        // The agent's turn: edits, deletes, creates and writes an ignored file.
+        fs::write(dir.join("a.txt"), "agent\n").unwrap();
+        fs::remove_file(dir.join("gone.txt")).unwrap();
+        fs::create_dir_all(dir.join("new")).unwrap();
+        fs::write(dir.join("new/file.txt"), "new\n").unwrap();
+        fs::write(dir.join("ignored.txt"), "keep\n").unwrap();
+        run_git(&dir, &["add", "-A"]).unwrap();
+
+        let second = create_checkpoint(&dir, "session/1", Some(1), "")
+            .unwrap()
+            .unwrap();
+        assert_eq!((second.turn, second.label.as_str()), (2, ""));
+        let listed = git_list_checkpoints(cwd(&dir), "session/1".into()).unwrap();
+        assert_eq!(listed.len(), 2);
+        assert!(git_list_checkpoints(cwd(&dir), "other".into())
+            .unwrap()
+            .is_empty());
+        // Ids that only differ in characters a ref cannot hold are kept apart.
+        assert!(git_list_checkpoints(cwd(&dir), "session_1".into())
+            .unwrap()
+            .is_empty());
+
+        let turn =
+            git_diff_checkpoint(cwd(&dir), "session/1".into(), 1, None, Some(2), None).unwrap();
+        let mut paths: Vec<&str> = turn.files.iter().map(|f| f.path.as_str()).collect();
+        paths.sort();
+        assert_eq!(paths, vec!["a.txt", "gone.txt", "new/file.txt"]);
+        let since =
+            git_diff_checkpoint(cwd(&dir), "session/1".into(), 2, None, None, None).unwrap();
+        assert!(since.files.is_empty());
+
+        let restored = git_restore_checkpoint(cwd(&dir), "session/1".into(), 1, None).unwrap();
+        assert_eq!(restored.commit, first.commit);
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "worktree\n");
+        assert_eq!(fs::read_to_string(dir.join("gone.txt")).unwrap(), "gone\n");
+        assert!(!dir.join("new/file.txt").exists());
+        assert!(dir.join("ignored.txt").exists());
+        assert_eq!(
+            run_git(&dir, &["status", "--porcelain"]).unwrap(),
+            status_before
+        );
+        assert_eq!(run_git(&dir, &["rev-parse", "HEAD"]).unwrap().trim(), head);
+        assert!(git_restore_checkpoint(cwd(&dir), "session/1".into(), 9, None).is_err());
+
+        // The state before the restore was saved and can be brought back.
+        let listed = git_list_checkpoints(cwd(&dir), "session/1".into()).unwrap();
+        assert_eq!(listed.len(), 3);
+        assert_eq!(
+            (listed[2].turn, listed[2].restore, listed[2].label.as_str()),
+            (1, true, "Before restoring turn 1")
+        );
+        let undo = git_diff_checkpoint(cwd(&dir), "session/1".into(), 1, Some(true), None, None);
+        assert!(!undo.unwrap().files.is_empty());
+        git_restore_checkpoint(cwd(&dir), "session/1".into(), 1, Some(true)).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "agent\n");
+        assert!(dir.join("new/file.txt").exists());
+
+        // Pre-restore snapshots stay out of the turn numbering.
+        let third = create_checkpoint(&dir, "session/1", Some(3), "Next prompt")
+            .unwrap()
+            .unwrap();
+        assert_eq!((third.turn, third.restore), (3, false));
+        let listed = git_list_checkpoints(cwd(&dir), "session/1".into()).unwrap();
+        let turns: Vec<(u32, bool)> = listed.iter().map(|c| (c.turn, c.restore)).collect();
+        assert_eq!(
+            turns,
+            vec![(1, false), (2, false), (3, false), (1, true), (2, true)]
+        );
+
+        delete_checkpoints(&dir, "session/1").unwrap();
+        assert!(git_list_checkpoints(cwd(&dir), "session/1".into())
+            .unwrap()
+            .is_empty());
+        assert!(run_git(&dir, &["for-each-ref", CHECKPOINT_REF_PREFIX])
+            .unwrap()
+            .is_empty());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_branches_and_remote_sync() {
+        use crate::git::network::run_git_progress;
+        use crate::git::types::GitErrorKind;
+
+        let a = init_repo(test_dir("git-remote-a"));
+        let base = test_dir("git-remote");
+        run_git(&base, &["init", "-q", "--bare", "origin.git"]).unwrap();
+        let origin = cwd(&base.join("origin.git"));
+        run_git(&a, &["remote", "add", "origin", &origin]).unwrap();
+        fs::write(a.join("a.txt"), "a\n").unwrap();
+        commit_all(&a, "first");
+        let branch = run_git(&a, &["rev-parse", "--abbrev-ref", "HEAD"])
+            .unwrap()
+            .trim()
+            .to_string();
+
+        let sync = |dir: &Path, args: Result<Vec<String>, String>| {
+            let mut phases = Vec::new();
+            run_git_progress(dir, &args.unwrap(), |progress| phases.push(progress.phase))
+                .map(|_| phases)
+        };
+        let phases = sync(&a, push_args(None, None, Some(true), None)).unwrap();
+        assert!(!phases.is_empty());
+
+        let branches = git_branches(cwd(&a)).unwrap();
+        assert_eq!(branches.local.len(), 1);
+        let local = &branches.local[0];
+        assert!(local.current);
+        assert_eq!(local.upstream, Some(format!("origin/{}", branch)));
+        assert_eq!((local.ahead, local.behind), (0, 0));
+        assert_eq!(local.last_commit.summary, "first");
+        assert_eq!(branches.remote[0].remote.as_deref(), Some("origin"));
+        assert_eq!(branches.remote[0].name, format!("origin/{}", branch));
+
+        // Another clone pushes first, so the branches diverge.
+        run_git(&base, &["clone", "-q", &origin, "b"]).unwrap();
+        let b = base.join("b");
+        for args in [
+            ["config", "user.name", "B"],
+            ["config", "user.email", "b@example.com"],
+        ] {
+            run_git(&b, &args).unwrap();
+        }
+        fs::write(b.join("b.txt"), "b\n").unwrap();
+        commit_all(&b, "from b");
+        sync(&b, push_args(None, None, None, None)).unwrap();
+        fs::write(a.join("a.txt"), "a2\n").unwrap();
+        commit_all(&a, "from a");
+
+        let rejected = sync(&a, push_args(None, None, None, None)).unwrap_err();
+        assert_eq!(rejected.kind, GitErrorKind::Rejected);
+        sync(&a, fetch_args(Some("origin".into()), Some(true))).unwrap();
+        let local = git_branches(cwd(&a)).unwrap().local.remove(0);
+        assert_eq!((local.ahead, local.behind), (1, 1));
+        let diverged = sync(&a, pull_args(None, None, None)).unwrap_err();
+        assert_eq!(diverged.kind, GitErrorKind::Rejected);
+        sync(&a, pull_args(None, None, Some("rebase".into()))).unwrap();
+        let local = git_branches(cwd(&a)).unwrap().local.remove(0);
+        assert_eq!((local.ahead, local.behind), (1, 0));
+        assert!(pull_args(None, None, Some("merge".into())).is_err());
+        assert!(push_args(None, Some("main".into()), None, None).is_err());
+
+        run_git(&a, &["branch", "topic"]).unwrap();
+        git_rename_branch(cwd(&a), "topic".into(), "renamed".into()).unwrap();
+        let names: Vec<String> = git_branches(cwd(&a))
+            .unwrap()
+            .local
+            .into_iter()
+            .map(|branch| branch.name)
+            .collect();
+        assert!(names.contains(&"renamed".to_string()) && !names.contains(&"topic".to_string()));
+        git_delete_branch(cwd(&a), "renamed".into(), None).unwrap();
+        assert_eq!(git_branches(cwd(&a)).unwrap().local.len(), 1);
+        assert!(git_delete_branch(cwd(&a), "--all".into(), None).is_err());
+
+        let _ = fs::remove_dir_all(&a);
+        let _ = fs::remove_dir_all(&base);
+    }
+}
@@ -0,0 +1,459 @@
+//! Parser for `git diff --patch` output.
+
+use crate::git::types::{
+    GitDiffFile, GitDiffFileStatus, GitDiffHunk, GitDiffLine, GitDiffLineKind, GitDiffStats,
+};
+
+/// Arguments that make patch output independent of user configuration.
+pub const PATCH_ARGS: [&str; 8] = [
+    "--patch",
+    "--no-color",
+    "--no-ext-diff",
+    "--no-textconv",
+    "--find-renames",
+    "--src-prefix=a/",
+    "--dst-prefix=b/",
+    "--submodule=short",
+];
+
+/// Parse the files, hunks and lines of a patch. Text before the first
+/// `diff --git` header (e.g. a commit message) is ignored.
+pub fn parse_patch(output: &str) -> Vec<GitDiffFile> {
+    let mut files: Vec<GitDiffFile> = Vec::new();
+    let mut old_line = 0;
+    let mut new_line = 0;
+
+    for line in output.lines() {
+        if let Some(header) = line.strip_prefix("diff --git ") {
+            let (old_path, path) = parse_header_paths(header).unwrap_or_default();
+            files.push(GitDiffFile {
+                old_path: (old_path != path).then_some(old_path),
+                path,
+                status: GitDiffFileStatus::Modified,
+                old_mode: None,
+                new_mode: None,
+                binary: false,
+                additions: 0,
+                deletions: 0,
+                hunks: Vec::new(),
+            });
+            continue;
+        }
+        let Some(file) = files.last_mut() else {
+            continue;
+        };
+
+        if line.starts_with("@@ ") {
+            if let Some(hunk) = parse_hunk_header(line) {
+                old_line = hunk.old_start;
+                new_line = hunk.new_start;
+                file.hunks.push(hunk);
+            }
+            continue;
+        }
+        if let Some(hunk) = file.hunks.last_mut() {
+            let (kind, content) = match line.as_bytes().first() {
+                Some(b' ') => (GitDiffLineKind::Context, &line[1..]),
+                Some(b'+') => (GitDiffLineKind::Added, &line[1..]),
+                Some(b'-') => (GitDiffLineKind::Removed, &line[1..]),
+                Some(b'\\') => {
+                    if let Some(last) = hunk.lines.last_mut() {
+                        last.no_newline = true;
+                    }
+                    continue;
+                }
+                // Some tools strip the space of empty context lines.
+                None => (GitDiffLineKind::Context, ""),
+                _ => continue,
+            };
+            if hunk_complete(hunk, old_line, new_line) {
+                // Trailing text after the last hunk, e.g. the next commit of a log.
+                continue;
+            }
+            let (old, new) = match kind {
+                GitDiffLineKind::Context => (Some(old_line), Some(new_line)),
+                GitDiffLineKind::Added => (None, Some(new_line)),
+                GitDiffLineKind::Removed => (Some(old_line), None),
+            };
+            if old.is_some() {
+                old_line += 1;
+            }
+            if new.is_some() {
+                new_line += 1;
+            }
+            match kind {
+                GitDiffLineKind::Added => file.additions += 1,
+                GitDiffLineKind::Removed => file.deletions += 1,
+                GitDiffLineKind::Context => {}
+            }
+            hunk.lines.push(GitDiffLine {
+                kind,
+                content: content.to_string(),
+                old_line: old,
+                new_line: new,
+                no_newline: false,
+            });
+            continue;
+        }
+
+        // Extended header lines between `diff --git` and the first hunk.
+        if let Some(mode) = line.strip_prefix("new file mode ") {
+            file.status = GitDiffFileStatus::Added;
+            file.new_mode = Some(mode.to_string());
+        } else if let Some(mode) = line.strip_prefix("deleted file mode ") {
+            file.status = GitDiffFileStatus::Deleted;
+            file.old_mode = Some(mode.to_string());
+        } else if let Some(mode) = line.strip_prefix("old mode ") {
+            file.old_mode = Some(mode.to_string());
+        } else if let Some(mode) = line.strip_prefix("new mode ") {
+            file.new_mode = Some(mode.to_string());
+        } else if let Some(path) = line.strip_prefix("rename from ") {
+            file.status = GitDiffFileStatus::Renamed;
+            file.old_path = Some(unquote(path));
+        } else if let Some(path) = line.strip_prefix("rename to ") {
+            file.path = unquote(path);
+        } else if let Some(path) = line.strip_prefix("copy from ") {
+            file.status = GitDiffFileStatus::Copied;
+            file.old_path = Some(unquote(path));
+        } else if let Some(path) = line.strip_prefix("copy to ") {
+            file.path = unquote(path);
+        } else if line.starts_with("Binary files ") || line == "GIT binary patch" {
+            file.binary = true;
+        }
+    }
+    files
+}
+
+/// Totals over a set of parsed files.
+pub fn diff_stats(files: &[GitDiffFile]) -> GitDiffStats {
+    GitDiffStats {
+        files: files.len(),
+        additions: files.iter().map(|file| file.additions).sum(),
+        deletions: files.iter().map(|file| file.deletions).sum(),
+    }
+}
+
+/// Reduce a single-file patch to its header and the hunks whose `@@` line is one of
+/// `headers`, for applying part of a change. Fails if a header is not in the patch,
+/// which means the file changed since the hunks were shown.
+///
+/// The patch is handled as bytes so lines in other encodings are applied unchanged.
+pub fn select_hunks(patch: &[u8], headers: &[String]) -> Result<Vec<u8>, String> {
+    if headers.is_empty() {
+        return Err("No hunks selected".to_string());
+    }
+    let mut selected = Vec::new();
+    let mut files = 0;
+    let mut found = vec![false; headers.len()];
+    let mut keep = true;
+    for line in patch.split_inclusive(|byte| *byte == b'\n') {
+        if line.starts_with(b"diff --git ") {
+            files += 1;
+            if files > 1 {
+                return Err("Hunks can only be selected from a single file".to_string());
+            }
+        } else if line.starts_with(b"@@ ") {
+            // Headers were shown from the lossily decoded patch.
+            let header = String::from_utf8_lossy(line);
+            let header = header.trim_end_matches(['\r', '\n']);
+            keep = false;
+            for (index, wanted) in headers.iter().enumerate() {
+                if wanted == header {
+                    found[index] = true;
+                    keep = true;
+                }
+            }
+        } else if files == 0 {
+            continue;
+        }
+        if keep {
+            selected.extend_from_slice(line);
+        }
+    }
+    if files == 0 {
+        return Err("No changes to apply".to_string());
+    }
+    if let Some(index) = found.iter().position(|found| !found) {
+        return Err(format!(
+            "Hunk no longer matches the file, refresh the diff: {}",
+            headers[index]
+        ));
+    }
+    Ok(selected)
+}
+
+fn hunk_complete(hunk: &GitDiffHunk, old_line: u32, new_line: u32) -> bool {
+    old_line >= hunk.old_start + hunk.old_lines && new_line >= hunk.new_start + hunk.new_lines
+}
+
+/// Parse `@@ -old_start,old_lines +new_start,new_lines @@ section`.
+fn parse_hunk_header(line: &str) -> Option<GitDiffHunk> {
+    let rest = line.strip_prefix("@@ -")?;
+    let (ranges, section) = rest.split_once(" @@")?;
+    let (old, new) = ranges.split_once(" +")?;
+    let (old_start, old_lines) = parse_range(old)?;
+    let (new_start, new_lines) = parse_range(new)?;
+    let section = section.trim();
+    Some(GitDiffHunk {
+        header: line.to_string(),
+        old_start,
+        old_lines,
+        new_start,
+        new_lines,
+        section: (!section.is_empty()).then(|| section.to_string()),
+        lines: Vec::new(),
+    })
+}
+
+fn parse_range(range: &str) -> Option<(u32, u32)> {
+    match range.split_once(',') {
+        Some((start, lines)) => Some((start.parse().ok()?, lines.parse().ok()?)),
+        None => Some((range.parse().ok()?, 1)),
+    }
+}
+
+/// Split `a/<old> b/<new>` into both paths. Unquoted paths may contain spaces, so
+/// they are only split where both sides name the same file; renames and copies get
+/// their paths from the extended header instead.
+fn parse_header_paths(header: &str) -> Option<(String, String)> {
+    if header.starts_with('"') {
+        let (old, rest) = take_quoted(header)?;
+        let rest = rest.strip_prefix(' ')?;
+        let new = if rest.starts_with('"') {
+            take_quoted(rest)?.0
+        } else {
+            rest.to_string()
+        };
+        return Some((strip_prefix(&old, "a/"), strip_prefix(&new, "b/")));
+    }
+    if let Some(index) = header.find(" \"b/") {
+        let (new, _) = take_quoted(&header[index + 1..])?;
+        return Some((
+            strip_prefix(&header[..index], "a/"),
+            strip_prefix(&new, "b/"),
+        ));
+    }
+    let half = header.len().checked_sub(1)? / 2;
+    if header.len() % 2 == 1 && header.is_char_boundary(half) {
+        let (old, new) = (&header[..half], &header[half + 1..]);
+        if old.strip_prefix("a/") == new.strip_prefix("b/") {
+            return Some((strip_prefix(old, "a/"), strip_prefix(new, "b/")));
+        }
+    }
+    let (old, new) = header.split_once(" b/")?;
+    Some((strip_prefix(old, "a/"), new.to_string()))
+}
+
+fn strip_prefix(path: &str, prefix: &str) -> String {
+    path.strip_prefix(prefix).unwrap_or(path).to_string()
+}
+
+/// Decode a path from an extended header line, which git quotes C-style when it
+/// contains special characters.
+fn unquote(path: &str) -> String {
+    let path = path.trim_end_matches('\t');
+    match take_quoted(path) {
+        Some((decoded, _)) if path.starts_with('"') => decoded,
+        _ => path.to_string(),
+    }
+}
+
+/// Decode a leading C-style quoted string, returning it and the remaining text.
+fn take_quoted(text: &str) -> Option<(String, &str)> {
+    let body = text.strip_prefix('"')?;
+    let mut bytes = Vec::new();
+    let mut iter = body.char_indices();
+    while let Some((index, c)) = iter.next() {
+        match c {
+            '"' => {
+                let decoded = String::from_utf8_lossy(&bytes).into_owned();
+                return Some((decoded, &body[index + 1..]));
+            }
+            '\\' => {
+                let (_, escaped) = iter.next()?;
+                match escaped {
+                    'a' => bytes.push(0x07),
+                    'b' => bytes.push(0x08),
+                    'f' => bytes.push(0x0c),
+                    'n' => bytes.push(b'\n'),
+                    'r' => bytes.push(b'\r'),
+                    't' => bytes.push(b'\t'),
+                    'v' => bytes.push(0x0b),
+                    '0'..='7' => {
+                        let mut value = escaped.to_digit(8)?;
+                        for _ in 0..2 {
+                            let (_, digit) = iter.next()?;
+                            value = value * 8 + digit.to_digit(8)?;
+                        }
+                        bytes.push(u8::try_from(value).ok()?);
+                    }
+                    other => {
+                        let mut buf = [0; 4];
+                        bytes.extend_from_slice(other.encode_utf8(&mut buf).as_bytes());
+                    }
+                }
+            }
+            other => {
+                let mut buf = [0; 4];
+                bytes.extend_from_slice(other.encode_utf8(&mut buf).as_bytes());
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PATCH: &str = "\
+diff --git a/src/main.rs b/src/main.rs
+index 83db48f..bf269f4 100644
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -1,4 +1,5 @@ fn main() {
+ use std::io;
+-use std::fs;
++use std::fs::File;
++use std::path::Path;
+
+ fn main() {
+@@ -10,2 +11,2 @@ fn helper() {
+-    old();
++    new();
+ }
+\\ No newline at end of file
+diff --git a/docs/old name.md b/docs/new name.md
+similarity index 90%
+rename from docs/old name.md
+rename to docs/new name.md
+index 1111111..2222222 100644
+--- a/docs/old name.md\t
++++ b/docs/new name.md\t
+@@ -3 +3 @@
+-a
++b
+diff --git a/logo.png b/logo.png
+new file mode 100644
+index 0000000..3333333
+Binary files /dev/null and b/logo.png differ
+diff --git \"a/caf\\303\\251.txt\" \"b/caf\\303\\251.txt\"
+deleted file mode 100755
+index 4444444..0000000
+--- \"a/caf\\303\\251.txt\"
++++ /dev/null
+@@ -1 +0,0 @@
+-bye
+";
+
+    #[test]
+    fn test_parse_patch_files_hunks_and_lines() {
+        let files = parse_patch(PATCH);
+        assert_eq!(files.len(), 4);
+
+        let main = &files[0];
+        assert_eq!(main.path, "src/main.rs");
+        assert_eq!(main.old_path, None);
+        assert_eq!(main.status, GitDiffFileStatus::Modified);
+        assert_eq!((main.additions, main.deletions), (3, 2));
+        assert_eq!(main.hunks.len(), 2);
+        let hunk = &main.hunks[0];
+        assert_eq!(
+            (
+                hunk.old_start,
+                hunk.old_lines,
+                hunk.new_start,
+                hunk.new_lines
+            ),
+            (1, 4, 1, 5)
+        );
+        assert_eq!(hunk.section.as_deref(), Some("fn main() {"));
+        let numbered: Vec<(GitDiffLineKind, Option<u32>, Option<u32>, &str)> = hunk
+            .lines
+            .iter()
+            .map(|line| {
+                (
+                    line.kind,
+                    line.old_line,
+                    line.new_line,
+                    line.content.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            numbered,
+            vec![
+                (GitDiffLineKind::Context, Some(1), Some(1), "use std::io;"),
+                (GitDiffLineKind::Removed, Some(2), None, "use std::fs;"),
+                (GitDiffLineKind::Added, None, Some(2), "use std::fs::File;"),
+                (
+                    GitDiffLineKind::Added,
+                    None,
+                    Some(3),
+                    "use std::path::Path;"
+                ),
+                (GitDiffLineKind::Context, Some(3), Some(4), ""),
+                (GitDiffLineKind::Context, Some(4), Some(5), "fn main() {"),
+            ]
+        );
+        let last = main.hunks[1].lines.last().unwrap();
+        assert_eq!((last.old_line, last.new_line), (Some(11), Some(12)));
+        assert!(last.no_newline);
+
+        let renamed = &files[1];
+        assert_eq!(renamed.status, GitDiffFileStatus::Renamed);
+        assert_eq!(renamed.old_path.as_deref(), Some("docs/old name.md"));
+        assert_eq!(renamed.path, "docs/new name.md");
+        assert_eq!(renamed.hunks[0].lines[1].new_line, Some(3));
+
+        let binary = &files[2];
+        assert_eq!(binary.status, GitDiffFileStatus::Added);
+        assert!(binary.binary && binary.hunks.is_empty());
+        assert_eq!(binary.new_mode.as_deref(), Some("100644"));
+
+        let deleted = &files[3];
+        assert_eq!(deleted.path, "café.txt");
+        assert_eq!(deleted.status, GitDiffFileStatus::Deleted);
+        assert_eq!(deleted.old_mode.as_deref(), Some("100755"));
+        assert_eq!(deleted.deletions, 1);
+
+        let stats = diff_stats(&files);
+        assert_eq!((stats.files, stats.additions, stats.deletions), (4, 4, 4));
+    }
+
+    #[test]
+    fn test_select_hunks() {
+        let main = &PATCH[..PATCH.find("diff --git a/docs").unwrap()];
+        let second = "@@ -10,2 +11,2 @@ fn helper() {".to_string();
+        let selected = select_hunks(main.as_bytes(), std::slice::from_ref(&second)).unwrap();
+        let selected = String::from_utf8(selected).unwrap();
+        assert!(selected.starts_with("diff --git a/src/main.rs b/src/main.rs\n"));
+        assert!(selected.contains("+++ b/src/main.rs\n@@ -10,2"));
+        assert!(!selected.contains("use std::io;"));
+        assert!(selected.ends_with("\\ No newline at end of file\n"));
+        assert_eq!(parse_patch(&selected)[0].hunks.len(), 1);
+
+        let header = "@@ -1 +1 @@".to_string();
+        assert!(select_hunks(main.as_bytes(), std::slice::from_ref(&header)).is_err());
+        assert!(select_hunks(PATCH.as_bytes(), &[second]).is_err());
+        assert!(select_hunks(b"", std::slice::from_ref(&header)).is_err());
+
+        // Lines that are not UTF-8 are kept byte for byte.
+        let latin1 = b"diff --git a/a.txt b/a.txt\n--- a/a.txt\n+++ b/a.txt\n@@ -1 +1 @@\n-caf\xe9\n+caf\xe8\n";
+        assert_eq!(select_hunks(latin1, &[header]).unwrap(), latin1.to_vec());
+    }
+
+    #[test]
+    fn test_header_paths() {
+        assert_eq!(
+            parse_header_paths("a/with space b/with space"),
+            Some(("with space".to_string(), "with space".to_string()))
+        );
+        assert_eq!(
+            parse_header_paths("a/x \"b/tab\\there\""),
+            Some(("x".to_string(), "tab\there".to_string()))
+        );
+        assert_eq!(unquote("plain name\t"), "plain name");
+        assert_eq!(unquote("\"quote\\\"d\""), "quote\"d");
+    }
+}
@@ -1,10 +1,15 @@
 //! Git integration helpers and Tauri commands.
 
+pub mod checkpoints;
 pub mod commands;
+pub mod diff;
+pub mod network;
 pub mod types;
+pub mod worktrees;
 
+use std::io::Write;
 use std::path::Path;
-use std::process::Command;
+use std::process::{Command, Output, Stdio};
 
 fn validate_cwd(cwd: &Path) -> Result<(), String> {
     if !cwd.exists() {
@@ -20,8 +25,16 @@ fn run_git_command(mut cmd: Command) -> Result<String, String> {
     let output = cmd
         .output()
         .map_err(|err| format!("Failed to execute git: {}", err))?;
+    git_output(output)
+}
+
+fn git_output(output: Output) -> Result<String, String> {
+    git_output_bytes(output).map(|stdout| String::from_utf8_lossy(&stdout).to_string())
+}
+
+fn git_output_bytes(output: Output) -> Result<Vec<u8>, String> {
     if output.status.success() {
-        return Ok(String::from_utf8_lossy(&output.stdout).to_string());
+        return Ok(output.stdout);
     }
     let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
     if stderr.is_empty() {
@@ -51,3 +64,101 @@ pub fn run_git_owned(cwd: &Path, args: &[String]) -> Result<String, String> {
     cmd.current_dir(cwd).args(args);
     run_git_command(cmd)
 }
+
+/// Run a git command and return its stdout as is, for output that is not
+/// necessarily UTF-8 such as patches.
+pub fn run_git_bytes(cwd: &Path, args: &[String]) -> Result<Vec<u8>, String> {
+    validate_cwd(cwd)?;
+    let output = Command::new("git")
+        .current_dir(cwd)
+        .args(args)
+        .output()
+        .map_err(|err| format!("Failed to execute git: {}", err))?;
+    git_output_bytes(output)
+}
+
+/// Run a git command with extra environment variables, e.g. `GIT_INDEX_FILE`.
+pub fn run_git_env(cwd: &Path, args: &[String], env: &[(&str, &str)]) -> Result<String, String> {
+    validate_cwd(cwd)?;
+    let mut cmd = Command::new("git");
+    cmd.current_dir(cwd).args(args).envs(env.iter().copied());
+    run_git_command(cmd)
+}
+
+/// Run a git command with `input` written to its stdin.
+pub fn run_git_with_input(cwd: &Path, args: &[String], input: &[u8]) -> Result<String, String> {
+    validate_cwd(cwd)?;
+    let mut child = Command::new("git")
+        .current_dir(cwd)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("Failed to execute git: {}", err))?;
+    // Write from another thread so a full stdout pipe cannot block the write.
+    let Some(mut stdin) = child.stdin.take() else {
+        let _ = child.kill();
+        let _ = child.wait();
+        return Err("Failed to open git stdin".to_string());
+    };
+    let input = input.to_vec();
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+    let output = child
+        .wait_with_output()
+        .map_err(|err| format!("Failed to execute git: {}", err))?;
+    let written = writer
+        .join()
+        .map_err(|_| "Failed to write git input".to_string())?;
+    let result = git_output(output);
+    if result.is_ok() {
+        written.map_err(|err| format!("Failed to write git input: {}", err))?;
+    }
+    result
+}
+
+/// Scratch repositories for tests.
+#[cfg(test)]
+pub mod test_support {
+    use super::run_git;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    /// Fresh empty directory `<tmp>/codex-test-<name>-<pid>`.
+    pub fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("codex-test-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Initialize a repository at `dir` with a test identity and unsigned commits.
+    pub fn init_repo(dir: PathBuf) -> PathBuf {
+        fs::create_dir_all(&dir).unwrap();
+        for args in [
+            vec!["init", "-q"],
+            vec!["config", "user.name", "Test"],
+            vec!["config", "user.email", "test@example.com"],
+            vec!["config", "commit.gpgsign", "false"],
+        ] {
+            run_git(&dir, &args).unwrap();
+        }
+        dir
+    }
+
+    /// Commit everything in the working tree and return the new HEAD.
+    pub fn commit_all(dir: &Path, message: &str) -> String {
+        run_git(dir, &["add", "-A"]).unwrap();
+        run_git(dir, &["commit", "-q", "-m", message]).unwrap();
+        run_git(dir, &["rev-parse", "HEAD"])
+            .unwrap()
+            .trim()
+            .to_string()
+    }
+
+    /// Write `file` and commit it, named after the file.
+    pub fn commit_file(dir: &Path, file: &str, content: &str) -> String {
+        fs::write(dir.join(file), content).unwrap();
+        commit_all(dir, file)
+    }
+}
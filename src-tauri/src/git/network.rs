@@ -0,0 +1,222 @@
+//! Fetch, pull and push with progress reporting and classified failures.
+
+use crate::git::types::{GitCommandError, GitErrorKind, GitProgress};
+use crate::git::{run_git, validate_cwd};
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Lowercase fragments of git, ssh and credential helper messages about missing or
+/// rejected credentials.
+const AUTH_MARKERS: [&str; 11] = [
+    "authentication failed",
+    "could not read username",
+    "could not read password",
+    "terminal prompts disabled",
+    "permission denied (publickey",
+    "invalid username or password",
+    "invalid credentials",
+    "bad credentials",
+    "http basic: access denied",
+    "the requested url returned error: 401",
+    "the requested url returned error: 403",
+];
+
+/// Lowercase fragments of refused updates: rejected pushes and diverged pulls.
+const REJECTED_MARKERS: [&str; 5] = [
+    "[rejected]",
+    "[remote rejected]",
+    "updates were rejected",
+    "not possible to fast-forward",
+    "non-fast-forward",
+];
+
+/// Classify the error output of a failed network command.
+pub fn classify_error(message: String) -> GitCommandError {
+    let lower = message.to_lowercase();
+    let kind = if AUTH_MARKERS.iter().any(|marker| lower.contains(marker)) {
+        GitErrorKind::Auth
+    } else if REJECTED_MARKERS.iter().any(|marker| lower.contains(marker)) {
+        GitErrorKind::Rejected
+    } else {
+        GitErrorKind::Other
+    };
+    GitCommandError { kind, message }
+}
+
+/// Parse a progress line such as `Receiving objects:  45% (9/20), 1.00 KiB | 1 MiB/s`
+/// or `remote: Counting objects: 100% (3/3), done.` into its phase and percentage.
+pub fn parse_progress(line: &str) -> Option<(String, Option<u32>)> {
+    let line = line.strip_prefix("remote: ").unwrap_or(line).trim();
+    let (phase, rest) = line.split_once(": ")?;
+    let rest = rest.trim_start();
+    if phase.is_empty() || !rest.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let percent = rest
+        .split_once('%')
+        .and_then(|(value, _)| value.trim().parse().ok());
+    Some((phase.to_string(), percent))
+}
+
+/// `GIT_SSH_COMMAND` that makes ssh fail instead of asking for a passphrase or a host
+/// key confirmation, unless the user set up an ssh command of their own.
+fn ssh_batch_mode(cwd: &Path) -> Option<(&'static str, &'static str)> {
+    let configured = ["GIT_SSH_COMMAND", "GIT_SSH"]
+        .iter()
+        .any(|key| std::env::var_os(key).is_some())
+        || run_git(cwd, &["config", "--get", "core.sshCommand"]).is_ok();
+    (!configured).then_some(("GIT_SSH_COMMAND", "ssh -o BatchMode=yes"))
+}
+
+/// Run a network git command without prompts, calling `on_progress` for every
+/// progress update on stderr. Other stderr lines make up the error message.
+pub fn run_git_progress(
+    cwd: &Path,
+    args: &[String],
+    mut on_progress: impl FnMut(GitProgress),
+) -> Result<String, GitCommandError> {
+    validate_cwd(cwd)?;
+    let mut child = Command::new("git")
+        .current_dir(cwd)
+        .args(args)
+        // Fail instead of waiting for credentials nobody can type, and keep messages
+        // in English so failures can be classified.
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env("LC_ALL", "C")
+        .envs(ssh_batch_mode(cwd))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("Failed to execute git: {}", err))?;
+
+    let (Some(mut stdout), Some(mut stderr)) = (child.stdout.take(), child.stderr.take()) else {
+        let _ = child.kill();
+        let _ = child.wait();
+        return Err("Failed to read git output".to_string().into());
+    };
+    let reader = std::thread::spawn(move || {
+        let mut output = Vec::new();
+        let _ = stdout.read_to_end(&mut output);
+        output
+    });
+
+    let mut messages = Vec::new();
+    let mut pending = Vec::new();
+    let mut buffer = [0u8; 4096];
+    let mut handle_line = |line: &[u8], messages: &mut Vec<String>| {
+        let line = String::from_utf8_lossy(line).trim_end().to_string();
+        if line.is_empty() {
+            return;
+        }
+        match parse_progress(&line) {
+            Some((phase, percent)) => on_progress(GitProgress {
+                phase,
+                percent,
+                message: line,
+            }),
+            None => messages.push(line),
+        }
+    };
+    loop {
+        match stderr.read(&mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(read) => {
+                for &byte in &buffer[..read] {
+                    // Progress lines are redrawn with `\r`.
+                    if byte == b'\r' || byte == b'\n' {
+                        handle_line(&pending, &mut messages);
+                        pending.clear();
+                    } else {
+                        pending.push(byte);
+                    }
+                }
+            }
+        }
+    }
+    handle_line(&pending, &mut messages);
+
+    let status = child
+        .wait()
+        .map_err(|err| format!("Failed to execute git: {}", err))?;
+    let output = String::from_utf8_lossy(&reader.join().unwrap_or_default()).to_string();
+    if status.success() {
+        return Ok(output);
+    }
+    let message = messages.join("\n");
+    if message.is_empty() {
+        return Err("Git command failed with no output".to_string().into());
+    }
+    Err(classify_error(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_lines() {
+        assert_eq!(
+            parse_progress("Receiving objects:  45% (9/20), 1.00 KiB | 1 MiB/s"),
+            Some(("Receiving objects".to_string(), Some(45)))
+        );
+        assert_eq!(
+            parse_progress("remote: Counting objects: 100% (3/3), done."),
+            Some(("Counting objects".to_string(), Some(100)))
+        );
+        assert_eq!(
+            parse_progress("Enumerating objects: 5, done."),
+            Some(("Enumerating objects".to_string(), None))
+        );
+        assert_eq!(parse_progress("fatal: Authentication failed for 'x'"), None);
+        assert_eq!(parse_progress("To github.com:acme/repo.git"), None);
+    }
+
+    #[test]
+    fn test_ssh_batch_mode_keeps_configured_command() {
+        let dir = std::env::temp_dir().join(format!("codex-git-ssh-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        run_git(&dir, &["init", "-q"]).unwrap();
+        if ["GIT_SSH_COMMAND", "GIT_SSH"]
+            .iter()
+            .all(|key| std::env::var_os(key).is_none())
+        {
+            assert_eq!(
+                ssh_batch_mode(&dir),
+                Some(("GIT_SSH_COMMAND", "ssh -o BatchMode=yes"))
+            );
+        }
+        run_git(&dir, &["config", "core.sshCommand", "ssh -i key"]).unwrap();
+        assert_eq!(ssh_batch_mode(&dir), None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_classify_errors() {
+        let kind = |message: &str| classify_error(message.to_string()).kind;
+        assert_eq!(
+            kind("fatal: could not read Username for 'https://github.com': terminal prompts disabled"),
+            GitErrorKind::Auth
+        );
+        assert_eq!(
+            kind("git@github.com: Permission denied (publickey).\nfatal: Could not read from remote repository."),
+            GitErrorKind::Auth
+        );
+        assert_eq!(
+            kind(
+                " ! [rejected]        main -> main (fetch first)\nerror: failed to push some refs"
+            ),
+            GitErrorKind::Rejected
+        );
+        assert_eq!(
+            kind("fatal: Not possible to fast-forward, aborting."),
+            GitErrorKind::Rejected
+        );
+        assert_eq!(
+            kind("fatal: 'nope' does not appear to be a git repository"),
+            GitErrorKind::Other
+        );
+    }
+}
@@ -1,5 +1,6 @@
 //! Serializable Git data structures returned to the frontend.
 
+use schemars::JsonSchema;
 use serde::Serialize;
 
 #[derive(Clone, Serialize)]
@@ -29,7 +30,7 @@ pub struct GitStatusResult {
     pub root: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GitCommit {
     pub id: String,
@@ -39,3 +40,184 @@ pub struct GitCommit {
     pub summary: String,
     pub refs: Vec<String>,
 }
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GitDiffFileStatus {
+    Added,
+    Deleted,
+    Modified,
+    Renamed,
+    Copied,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GitDiffLineKind {
+    Context,
+    Added,
+    Removed,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitDiffLine {
+    pub kind: GitDiffLineKind,
+    pub content: String,
+    pub old_line: Option<u32>,
+    pub new_line: Option<u32>,
+    pub no_newline: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitDiffHunk {
+    pub header: String,
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    pub section: Option<String>,
+    pub lines: Vec<GitDiffLine>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitDiffFile {
+    pub path: String,
+    pub old_path: Option<String>,
+    pub status: GitDiffFileStatus,
+    pub old_mode: Option<String>,
+    pub new_mode: Option<String>,
+    pub binary: bool,
+    pub additions: usize,
+    pub deletions: usize,
+    pub hunks: Vec<GitDiffHunk>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitDiffStats {
+    pub files: usize,
+    pub additions: usize,
+    pub deletions: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitDiffResult {
+    pub files: Vec<GitDiffFile>,
+    pub stats: GitDiffStats,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitCommitDetails {
+    pub commit: GitCommit,
+    pub body: String,
+    pub files: Vec<GitDiffFile>,
+    pub stats: GitDiffStats,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitDiscardResult {
+    pub discarded: bool,
+    pub token: String,
+    pub changes: Vec<GitStatusEntry>,
+}
+
+/// Snapshot of a working tree taken before a session turn.
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GitCheckpoint {
+    pub session_id: String,
+    /// Prompt turn the snapshot was taken before, or the number of a pre-restore
+    /// snapshot.
+    pub turn: u32,
+    /// Saved before restoring a checkpoint rather than before a prompt turn.
+    pub restore: bool,
+    pub commit: String,
+    /// HEAD when the snapshot was taken; restoring does not move HEAD back.
+    pub head: Option<String>,
+    pub created_at: String,
+    pub label: String,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitBranch {
+    /// Short name, e.g. `main` or `origin/main`.
+    pub name: String,
+    /// Remote of a remote-tracking branch.
+    pub remote: Option<String>,
+    pub current: bool,
+    pub upstream: Option<String>,
+    /// The upstream is configured but no longer exists.
+    pub upstream_gone: bool,
+    pub ahead: usize,
+    pub behind: usize,
+    pub last_commit: GitCommit,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitBranchesResult {
+    pub local: Vec<GitBranch>,
+    pub remote: Vec<GitBranch>,
+}
+
+/// A progress update of a fetch, pull or push.
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GitProgress {
+    pub phase: String,
+    pub percent: Option<u32>,
+    pub message: String,
+}
+
+/// Network operation reporting progress.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum GitNetworkOperation {
+    Fetch,
+    Pull,
+    Push,
+}
+
+/// Payload of the `git:progress` event.
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GitProgressEvent<'a> {
+    pub cwd: &'a str,
+    pub operation: GitNetworkOperation,
+    #[serde(flatten)]
+    pub progress: GitProgress,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GitErrorKind {
+    /// Credentials are missing or were refused.
+    Auth,
+    /// The remote refused a push, or a fast-forward-only pull is not possible.
+    Rejected,
+    Other,
+}
+
+/// Error of the network commands, which the frontend can branch on by `kind`.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitCommandError {
+    pub kind: GitErrorKind,
+    pub message: String,
+}
+
+impl From<String> for GitCommandError {
+    fn from(message: String) -> Self {
+        Self {
+            kind: GitErrorKind::Other,
+            message,
+        }
+    }
+}
@@ -0,0 +1,269 @@
+//! Linked worktrees on their own branch, used to isolate agent sessions.
+
+use crate::git::{run_git, run_git_owned, run_git_with_input};
+use std::path::{Path, PathBuf};
+
+/// Root of the main working tree of the repository containing `cwd`, even when `cwd`
+/// is inside a linked worktree.
+pub fn main_worktree_root(cwd: &Path) -> Result<PathBuf, String> {
+    let common = run_git(cwd, &["rev-parse", "--git-common-dir"])
+        .map_err(|_| format!("Not a git repository: {}", cwd.display()))?;
+    // Relative to `cwd` when run from the top of the main working tree.
+    let common = cwd
+        .join(common.trim())
+        .canonicalize()
+        .map_err(|err| format!("Failed to resolve the git directory: {}", err))?;
+    if common.file_name().is_some_and(|name| name == ".git") {
+        if let Some(root) = common.parent() {
+            return Ok(root.to_path_buf());
+        }
+    }
+    Err(format!(
+        "Worktrees need a repository with a working tree: {}",
+        cwd.display()
+    ))
+}
+
+/// Whether `name` is a valid and unused branch name.
+pub fn check_new_branch(root: &Path, name: &str) -> Result<(), String> {
+    if name.starts_with('-') {
+        return Err(format!("Invalid branch name: {}", name));
+    }
+    run_git(root, &["check-ref-format", "--branch", name])
+        .map_err(|_| format!("Invalid branch name: {}", name))?;
+    if branch_exists(root, name) {
+        return Err(format!("Branch already exists: {}", name));
+    }
+    Ok(())
+}
+
+pub fn branch_exists(root: &Path, name: &str) -> bool {
+    branch_head(root, name).is_ok()
+}
+
+/// Commit the branch `name` points at.
+pub fn branch_head(root: &Path, name: &str) -> Result<String, String> {
+    let reference = format!("refs/heads/{}", name);
+    let commit = run_git(root, &["rev-parse", "-q", "--verify", &reference])?;
+    Ok(commit.trim().to_string())
+}
+
+/// Create a worktree at `path` on the new branch `branch`, starting from `base` (HEAD
+/// by default). Returns the commit the branch starts from.
+pub fn add_worktree(
+    root: &Path,
+    path: &Path,
+    branch: &str,
+    base: Option<&str>,
+) -> Result<String, String> {
+    let base = base.map(str::trim).filter(|base| !base.is_empty());
+    if base.is_some_and(|base| base.starts_with('-')) {
+        return Err(format!("Invalid revision: {}", base.unwrap_or_default()));
+    }
+    let revision = format!("{}^{{commit}}", base.unwrap_or("HEAD"));
+    let commit = run_git(root, &["rev-parse", "-q", "--verify", &revision])
+        .map_err(|_| format!("Unknown base revision: {}", base.unwrap_or("HEAD")))?
+        .trim()
+        .to_string();
+    check_new_branch(root, branch)?;
+    let args = vec![
+        "worktree".to_string(),
+        "add".to_string(),
+        "-b".to_string(),
+        branch.to_string(),
+        path.to_string_lossy().into_owned(),
+        commit.clone(),
+    ];
+    run_git_owned(root, &args)?;
+    Ok(commit)
+}
+
+/// Whether the worktree at `path` has staged, unstaged or untracked changes.
+pub fn is_dirty(path: &Path) -> Result<bool, String> {
+    let output = run_git(path, &["status", "--porcelain", "--untracked-files=normal"])?;
+    Ok(!output.trim().is_empty())
+}
+
+/// Number of commits on `branch` that are not on `target`.
+pub fn commits_ahead(root: &Path, target: &str, branch: &str) -> Result<usize, String> {
+    let range = format!("{}..refs/heads/{}", target, branch);
+    let output = run_git(root, &["rev-list", "--count", &range])?;
+    output
+        .trim()
+        .parse()
+        .map_err(|_| "Unexpected git rev-list output".to_string())
+}
+
+/// Whether everything on `branch` is reachable from HEAD at `root`, which is what
+/// `git branch -d` asks for when the branch has no upstream.
+pub fn is_merged(root: &Path, branch: &str) -> bool {
+    let reference = format!("refs/heads/{}", branch);
+    run_git(root, &["merge-base", "--is-ancestor", &reference, "HEAD"]).is_ok()
+}
+
+/// Merge `branch` into the branch checked out at `root`, as a merge commit or as a
+/// single squashed commit. A failed merge is aborted so the checkout is left as it was.
+pub fn merge_branch(
+    root: &Path,
+    branch: &str,
+    squash: bool,
+    message: Option<&str>,
+) -> Result<(), String> {
+    let message = message.map(str::trim).filter(|message| !message.is_empty());
+    let reference = format!("refs/heads/{}", branch);
+    if !squash {
+        let mut args = vec!["merge".to_string(), "-q".to_string(), "--no-ff".to_string()];
+        match message {
+            // Unlike `commit`, `merge` cannot read the message from stdin.
+            Some(message) => args.extend(["-m".to_string(), message.to_string()]),
+            None => args.push("--no-edit".to_string()),
+        }
+        args.push(reference);
+        run_git_owned(root, &args).map_err(|err| {
+            let _ = run_git(root, &["merge", "--abort"]);
+            err
+        })?;
+        return Ok(());
+    }
+
+    // The squashed commit takes everything in the index, so it has to start clean.
+    if run_git(root, &["diff", "--cached", "--quiet"]).is_err() {
+        return Err("Commit or unstage the staged changes before merging".to_string());
+    }
+    if let Err(err) = run_git(root, &["merge", "-q", "--squash", &reference]) {
+        let _ = run_git(root, &["reset", "-q", "--merge"]);
+        return Err(err);
+    }
+    if run_git(root, &["diff", "--cached", "--quiet"]).is_ok() {
+        return Err(format!("Nothing to merge from {}", branch));
+    }
+    let mut args = vec!["commit".to_string(), "-q".to_string()];
+    match message {
+        Some(_) => args.push("--file=-".to_string()),
+        // Keeps the message git prepared, which lists the squashed commits.
+        None => args.push("--no-edit".to_string()),
+    }
+    run_git_with_input(root, &args, message.unwrap_or_default().as_bytes()).map_err(|err| {
+        let _ = run_git(root, &["reset", "-q", "--merge"]);
+        err
+    })?;
+    Ok(())
+}
+
+/// Remove the worktree at `path` and its branch. Without `force`, a worktree with
+/// changes is kept and a branch that is not merged survives the removal. Returns
+/// whether the branch is gone.
+pub fn remove_worktree(
+    root: &Path,
+    path: &Path,
+    branch: &str,
+    force: bool,
+) -> Result<bool, String> {
+    if path.exists() {
+        let mut args = vec!["worktree".to_string(), "remove".to_string()];
+        if force {
+            args.push("--force".to_string());
+        }
+        args.push(path.to_string_lossy().into_owned());
+        run_git_owned(root, &args)?;
+    } else {
+        // Deleted by hand; only git's bookkeeping is left.
+        run_git(root, &["worktree", "prune"])?;
+    }
+    Ok(!branch_exists(root, branch) || delete_branch(root, branch, force))
+}
+
+/// Delete `branch`; without `force`, only when it is merged. Returns whether it was
+/// deleted.
+pub fn delete_branch(root: &Path, branch: &str, force: bool) -> bool {
+    let delete = if force { "-D" } else { "-d" };
+    run_git(root, &["branch", "-q", delete, branch]).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_support::{commit_file, init_repo, test_dir};
+    use std::fs;
+
+    fn head_parents(dir: &Path) -> usize {
+        run_git(dir, &["rev-list", "--parents", "-n", "1", "HEAD"])
+            .unwrap()
+            .split_whitespace()
+            .count()
+            - 1
+    }
+
+    #[test]
+    fn test_worktree_merge_and_remove() {
+        let dir = test_dir("worktree-merge");
+        let repo = init_repo(dir.join("repo"));
+        commit_file(&repo, "a.txt", "one\n");
+        let tree = dir.join("tree");
+        let head = run_git(&repo, &["rev-parse", "HEAD"]).unwrap();
+
+        let base = add_worktree(&repo, &tree, "codex/one", None).unwrap();
+        assert_eq!(base, head.trim());
+        assert_eq!(
+            main_worktree_root(&tree).unwrap().canonicalize().unwrap(),
+            repo.canonicalize().unwrap()
+        );
+        assert!(check_new_branch(&repo, "codex/one").is_err());
+        assert!(check_new_branch(&repo, "bad..name").is_err());
+        assert!(add_worktree(&repo, &dir.join("other"), "codex/two", Some("nope")).is_err());
+
+        assert!(!is_dirty(&tree).unwrap());
+        fs::write(tree.join("b.txt"), "two\n").unwrap();
+        assert!(is_dirty(&tree).unwrap());
+        // Unmerged changes keep the worktree unless forced.
+        assert!(remove_worktree(&repo, &tree, "codex/one", false).is_err());
+
+        commit_file(&tree, "b.txt", "two\n");
+        assert_eq!(commits_ahead(&repo, "HEAD", "codex/one").unwrap(), 1);
+        merge_branch(&repo, "codex/one", false, Some("Merge the work")).unwrap();
+        assert_eq!(head_parents(&repo), 2);
+        let subject = run_git(&repo, &["log", "-1", "--format=%s"]).unwrap();
+        assert_eq!(subject.trim(), "Merge the work");
+        assert!(repo.join("b.txt").exists());
+        assert_eq!(commits_ahead(&repo, "HEAD", "codex/one").unwrap(), 0);
+
+        assert!(remove_worktree(&repo, &tree, "codex/one", false).unwrap());
+        assert!(!tree.exists());
+        assert!(!branch_exists(&repo, "codex/one"));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_worktree_squash_and_conflicts() {
+        let dir = test_dir("worktree-squash");
+        let repo = init_repo(dir.join("repo"));
+        commit_file(&repo, "a.txt", "one\n");
+        let tree = dir.join("tree");
+        add_worktree(&repo, &tree, "codex/squash", Some("HEAD")).unwrap();
+        commit_file(&tree, "b.txt", "two\n");
+        commit_file(&tree, "c.txt", "three\n");
+
+        merge_branch(&repo, "codex/squash", true, Some("Squashed work")).unwrap();
+        assert_eq!(head_parents(&repo), 1);
+        let subject = run_git(&repo, &["log", "-1", "--format=%s"]).unwrap();
+        assert_eq!(subject.trim(), "Squashed work");
+        assert!(repo.join("c.txt").exists());
+        assert!(merge_branch(&repo, "codex/squash", true, None).is_err());
+
+        // A conflicting merge is aborted.
+        commit_file(&tree, "a.txt", "tree\n");
+        commit_file(&repo, "a.txt", "repo\n");
+        assert!(merge_branch(&repo, "codex/squash", false, None).is_err());
+        assert!(!is_dirty(&repo).unwrap());
+
+        // A squashed branch is not merged as far as git can tell, and the path is
+        // already gone.
+        fs::remove_dir_all(&tree).unwrap();
+        assert!(!remove_worktree(&repo, &tree, "codex/squash", false).unwrap());
+        assert!(branch_exists(&repo, "codex/squash"));
+        assert!(remove_worktree(&repo, &tree, "codex/squash", true).unwrap());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
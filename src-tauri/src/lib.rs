@@ -1,6 +1,7 @@
 //! Tauri backend entrypoint and command wiring for Codex Desktop.
 
 use std::sync::Once;
+use tauri::{Manager, Runtime};
 
 static INIT_TRACING: Once = Once::new();
 
@@ -29,37 +30,135 @@ pub mod remote;
 /// Local terminal PTY integration.
 pub mod terminal;
 
-/// Start the Tauri application and register Codex commands.
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    init_tracing();
-
+/// Manage the backend state shared by the desktop app and the headless CLI.
+pub fn manage_state<R: Runtime, M: Manager<R>>(manager: &M) {
     // Remote server configuration storage path
     let remote_config_path = dirs::config_dir()
         .unwrap_or_default()
         .join("codex-desktop")
         .join("remote-servers.json");
 
+    // Approval rules live with the other settings; the audit log with session data
+    let approval_rules_path = dirs::config_dir()
+        .unwrap_or_default()
+        .join("codex-desktop")
+        .join("approval-rules.json");
+    let approval_audit_path = dirs::data_dir()
+        .unwrap_or_default()
+        .join("codex-desktop")
+        .join("approval-audit.jsonl");
+
+    // Environment profiles with the settings; their secret values in a separate file
+    let profiles_path = dirs::config_dir()
+        .unwrap_or_default()
+        .join("codex-desktop")
+        .join("env-profiles.json");
+    let profile_secrets_path = dirs::config_dir()
+        .unwrap_or_default()
+        .join("codex-desktop")
+        .join("env-profile-secrets.json");
+
+    // Token usage ledger with session data; prices and budget with the settings
+    let usage_ledger_path = dirs::data_dir()
+        .unwrap_or_default()
+        .join("codex-desktop")
+        .join("usage.json");
+    let usage_settings_path = dirs::config_dir()
+        .unwrap_or_default()
+        .join("codex-desktop")
+        .join("usage-settings.json");
+
+    // Session transcript journal directory
+    let sessions_dir = dirs::data_dir()
+        .unwrap_or_default()
+        .join("codex-desktop")
+        .join("sessions");
+
+    // Worktrees of isolated sessions, outside the repositories they belong to
+    let worktrees_dir = dirs::data_dir()
+        .unwrap_or_default()
+        .join("codex-desktop")
+        .join("worktrees");
+
+    manager.manage(codex::commands::CodexManager::default());
+    manager.manage(terminal::TerminalManager::default());
+    manager.manage(remote::RemoteServerManager::new(remote_config_path));
+    manager.manage(codex::history::SessionStore::new(sessions_dir));
+    manager.manage(codex::worktrees::WorktreeSessions::new(worktrees_dir));
+    manager.manage(codex::policy::ApprovalPolicy::new(
+        approval_rules_path,
+        approval_audit_path,
+    ));
+    manager.manage(codex::profiles::ProfileStore::new(
+        profiles_path,
+        profile_secrets_path,
+    ));
+    manager.manage(codex::coalesce::ChunkCoalescer::new(
+        codex::coalesce::CoalesceConfig::from_env(),
+    ));
+    manager.manage(codex::usage::UsageStore::new(
+        usage_ledger_path,
+        usage_settings_path,
+    ));
+}
+
+/// Start the Tauri application and register Codex commands.
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    init_tracing();
+
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_fs::init())
-        .manage(codex::commands::CodexManager::default())
-        .manage(terminal::TerminalManager::default())
-        .manage(remote::RemoteServerManager::new(remote_config_path))
+        .setup(|app| {
+            manage_state(app);
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             codex::commands::codex_init,
             codex::commands::codex_auth,
             codex::commands::codex_load_cli_config,
             codex::commands::codex_set_env,
             codex::commands::codex_new_session,
+            codex::commands::codex_new_worktree_session,
+            codex::commands::codex_load_session,
             codex::commands::codex_prompt,
             codex::commands::codex_cancel,
             codex::commands::codex_approve,
+            codex::commands::codex_list_pending_approvals,
+            codex::commands::codex_agent_log,
+            codex::commands::codex_set_acp_recording,
+            codex::commands::codex_replay_acp_recording,
+            codex::commands::codex_export_debug_trace,
+            codex::commands::codex_chunk_coalescing,
+            codex::commands::codex_set_chunk_coalescing,
+            codex::commands::codex_list_queued_prompts,
+            codex::commands::codex_remove_queued_prompt,
+            codex::commands::codex_set_approval_timeout,
             codex::commands::codex_set_mode,
             codex::commands::codex_set_model,
             codex::commands::codex_set_config_option,
             codex::commands::codex_warmup,
+            codex::commands::codex_set_file_buffer,
+            codex::commands::codex_clear_file_buffer,
+            codex::commands::codex_list_sessions,
+            codex::commands::codex_search_sessions,
+            codex::commands::codex_open_session,
+            codex::commands::codex_delete_session,
+            codex::commands::codex_list_worktree_sessions,
+            codex::commands::codex_merge_worktree_session,
+            codex::commands::codex_cleanup_worktree_session,
+            codex::commands::codex_list_approval_rules,
+            codex::commands::codex_save_approval_rule,
+            codex::commands::codex_delete_approval_rule,
+            codex::commands::codex_approval_audit,
+            codex::commands::codex_list_profiles,
+            codex::commands::codex_save_profile,
+            codex::commands::codex_delete_profile,
+            codex::commands::codex_usage_report,
+            codex::commands::codex_usage_settings,
+            codex::commands::codex_set_usage_settings,
             terminal::terminal_spawn,
             terminal::terminal_write,
             terminal::terminal_resize,
@@ -68,6 +167,23 @@ pub fn run() {
             git::commands::git_history,
             git::commands::git_checkout,
             git::commands::git_reset,
+            git::commands::git_diff,
+            git::commands::git_show_commit,
+            git::commands::git_stage,
+            git::commands::git_unstage,
+            git::commands::git_stage_hunks,
+            git::commands::git_unstage_hunks,
+            git::commands::git_discard,
+            git::commands::git_commit,
+            git::commands::git_branches,
+            git::commands::git_delete_branch,
+            git::commands::git_rename_branch,
+            git::commands::git_fetch,
+            git::commands::git_pull,
+            git::commands::git_push,
+            git::commands::git_list_checkpoints,
+            git::commands::git_restore_checkpoint,
+            git::commands::git_diff_checkpoint,
             remote::commands::remote_add_server,
             remote::commands::remote_remove_server,
             remote::commands::remote_list_servers,
@@ -4,13 +4,14 @@ use super::types::{RemoteServerConfig, SshAuth};
 use anyhow::{anyhow, Context, Result};
 use std::path::Path;
 use std::process::Stdio;
-use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::process::{Child, ChildStderr, ChildStdin, ChildStdout, Command};
 
 /// Process wrapper for running codex-acp remotely via SSH
 pub struct RemoteSshProcess {
     child: Child,
     stdin: Option<ChildStdin>,
     stdout: Option<ChildStdout>,
+    stderr: Option<ChildStderr>,
 }
 
 impl RemoteSshProcess {
@@ -40,7 +41,7 @@ impl RemoteSshProcess {
         // Configure stdio
         cmd.stdin(Stdio::piped())
             .stdout(Stdio::piped())
-            .stderr(Stdio::inherit()); // stderr displayed in local terminal for debugging
+            .stderr(Stdio::piped()); // ssh and remote codex-acp diagnostics go to the agent log
 
         tracing::info!(
             "Starting remote codex-acp on {}@{}:{}",
@@ -61,11 +62,13 @@ impl RemoteSshProcess {
             .stdout
             .take()
             .ok_or_else(|| anyhow!("Failed to get SSH stdout"))?;
+        let stderr = child.stderr.take();
 
         Ok(Self {
             child,
             stdin: Some(stdin),
             stdout: Some(stdout),
+            stderr,
         })
     }
 
@@ -107,6 +110,11 @@ impl RemoteSshProcess {
         Ok((stdin, stdout))
     }
 
+    /// Get stderr for log capture
+    pub fn take_stderr(&mut self) -> Option<ChildStderr> {
+        self.stderr.take()
+    }
+
     /// Check if process is alive
     pub fn is_alive(&mut self) -> bool {
         match self.child.try_wait() {
@@ -126,6 +134,37 @@ impl RemoteSshProcess {
     }
 }
 
+/// Read at most `max_bytes` from the start of a file on a remote server.
+pub async fn read_remote_file_head(
+    config: &RemoteServerConfig,
+    path: &str,
+    max_bytes: u64,
+) -> Result<Vec<u8>> {
+    let mut cmd = Command::new("ssh");
+    apply_ssh_options(&mut cmd, config, "-p")?;
+    cmd.arg(format!("{}@{}", config.username, config.host));
+    cmd.arg(format!(
+        "test -f {path} && head -c {max_bytes} -- {path}",
+        path = shell_escape(path)
+    ));
+    cmd.stdin(Stdio::null());
+
+    let output = cmd
+        .output()
+        .await
+        .context("Failed to run ssh, ensure ssh command is available")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stderr = stderr.trim();
+        return Err(if stderr.is_empty() {
+            anyhow!("remote file not found: {path}")
+        } else {
+            anyhow!("failed to read remote file {path}: {stderr}")
+        });
+    }
+    Ok(output.stdout)
+}
+
 /// Simple shell escaping
 fn shell_escape(s: &str) -> String {
     // Wrap with single quotes and escape internal single quotes.
@@ -1,11 +1,12 @@
-use portable_pty::{native_pty_system, CommandBuilder, MasterPty, PtySize};
+use portable_pty::{native_pty_system, ChildKiller, CommandBuilder, MasterPty, PtySize};
 use serde::Serialize;
 use std::collections::HashMap;
 use std::io::{Read, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
-use tauri::{AppHandle, Emitter, State};
+use tauri::{AppHandle, Emitter, Runtime, State};
+use tokio::sync::watch;
 
 const TERMINAL_OUTPUT_EVENT: &str = "terminal-output";
 const TERMINAL_EXIT_EVENT: &str = "terminal-exit";
@@ -21,7 +22,126 @@ pub struct TerminalManager {
 struct TerminalInstance {
     master: Box<dyn MasterPty + Send>,
     writer: Arc<Mutex<Box<dyn Write + Send>>>,
-    child: Arc<Mutex<Box<dyn portable_pty::Child + Send + Sync>>>,
+    killer: Mutex<Box<dyn ChildKiller + Send + Sync>>,
+    capture: Option<Arc<CommandCapture>>,
+}
+
+/// Command launched in a PTY on behalf of an ACP agent.
+pub struct AgentCommand {
+    pub session_id: String,
+    pub command: String,
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+    pub cwd: Option<PathBuf>,
+    pub output_byte_limit: Option<u64>,
+}
+
+/// Exit status of a command run through an agent terminal.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandExit {
+    pub exit_code: Option<u32>,
+    pub signal: Option<String>,
+}
+
+/// Snapshot of the output retained for an agent terminal.
+pub struct CommandOutput {
+    pub output: String,
+    pub truncated: bool,
+    pub exit: Option<CommandExit>,
+}
+
+struct CommandCapture {
+    session_id: String,
+    limit: Option<usize>,
+    output: Mutex<CapturedOutput>,
+    exit: watch::Sender<Option<CommandExit>>,
+}
+
+#[derive(Default)]
+struct CapturedOutput {
+    text: String,
+    truncated: bool,
+}
+
+impl CommandCapture {
+    fn append(&self, chunk: &str) {
+        let mut guard = self
+            .output
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        guard.text.push_str(chunk);
+        if let Some(limit) = self.limit {
+            if truncate_front(&mut guard.text, limit) {
+                guard.truncated = true;
+            }
+        }
+    }
+}
+
+/// Drop bytes from the front of `text` so it fits in `limit`, respecting char boundaries.
+fn truncate_front(text: &mut String, limit: usize) -> bool {
+    if text.len() <= limit {
+        return false;
+    }
+    let mut cut = text.len() - limit;
+    while !text.is_char_boundary(cut) {
+        cut += 1;
+    }
+    text.drain(..cut);
+    true
+}
+
+/// Decode the complete UTF-8 in `pending`, leaving an incomplete trailing character
+/// for the next read. Invalid sequences become U+FFFD.
+fn drain_utf8(pending: &mut Vec<u8>) -> String {
+    let mut text = String::new();
+    let mut rest = pending.as_slice();
+    loop {
+        match std::str::from_utf8(rest) {
+            Ok(valid) => {
+                text.push_str(valid);
+                rest = &[];
+                break;
+            }
+            Err(err) => {
+                let (valid, after) = rest.split_at(err.valid_up_to());
+                // Checked by from_utf8 above.
+                text.push_str(std::str::from_utf8(valid).unwrap_or_default());
+                match err.error_len() {
+                    Some(len) => {
+                        text.push(char::REPLACEMENT_CHARACTER);
+                        rest = &after[len..];
+                    }
+                    // Cut off by the read; the next one completes it.
+                    None => {
+                        rest = after;
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    let consumed = pending.len() - rest.len();
+    pending.drain(..consumed);
+    text
+}
+
+impl From<portable_pty::ExitStatus> for CommandExit {
+    fn from(status: portable_pty::ExitStatus) -> Self {
+        // portable-pty only exposes the signal name through Display.
+        let label = status.to_string();
+        match label.strip_prefix("Terminated by ") {
+            Some(signal) => Self {
+                exit_code: None,
+                signal: Some(signal.to_string()),
+            },
+            None => Self {
+                exit_code: Some(status.exit_code()),
+                signal: None,
+            },
+        }
+    }
 }
 
 #[derive(Clone, Serialize)]
@@ -42,6 +162,246 @@ impl TerminalManager {
         let id = self.counter.fetch_add(1, Ordering::Relaxed);
         format!("term-{}", id)
     }
+
+    fn spawn_pty<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        cmd: CommandBuilder,
+        cols: u16,
+        rows: u16,
+        capture: Option<Arc<CommandCapture>>,
+    ) -> Result<String, String> {
+        let pty_system = native_pty_system();
+        let pair = pty_system
+            .openpty(PtySize {
+                rows,
+                cols,
+                pixel_width: 0,
+                pixel_height: 0,
+            })
+            .map_err(|err| err.to_string())?;
+
+        let mut child = pair
+            .slave
+            .spawn_command(cmd)
+            .map_err(|err| err.to_string())?;
+        drop(pair.slave);
+
+        let mut reader = pair
+            .master
+            .try_clone_reader()
+            .map_err(|err| err.to_string())?;
+        let writer = pair.master.take_writer().map_err(|err| err.to_string())?;
+        let killer = child.clone_killer();
+
+        let id = self.next_id();
+        {
+            let mut terminals = self
+                .terminals
+                .lock()
+                .map_err(|_| "terminal manager poisoned".to_string())?;
+            terminals.insert(
+                id.clone(),
+                TerminalInstance {
+                    master: pair.master,
+                    writer: Arc::new(Mutex::new(writer)),
+                    killer: Mutex::new(killer),
+                    capture: capture.clone(),
+                },
+            );
+        }
+
+        let exit_capture = capture.clone();
+        std::thread::spawn(move || {
+            let status = child.wait();
+            if let Some(capture) = exit_capture {
+                let exit = match status {
+                    Ok(status) => CommandExit::from(status),
+                    Err(_) => CommandExit {
+                        exit_code: None,
+                        signal: None,
+                    },
+                };
+                capture.exit.send_replace(Some(exit));
+            }
+        });
+
+        let terminal_id = id.clone();
+        let app_handle = app.clone();
+        std::thread::spawn(move || {
+            let mut buffer = [0u8; 8192];
+            let mut pending = Vec::new();
+            loop {
+                match reader.read(&mut buffer) {
+                    Ok(0) => break,
+                    Ok(bytes) => {
+                        pending.extend_from_slice(&buffer[..bytes]);
+                        let text = drain_utf8(&mut pending);
+                        if text.is_empty() {
+                            continue;
+                        }
+                        if let Some(capture) = capture.as_ref() {
+                            capture.append(text.as_ref());
+                        }
+                        let _ = app_handle.emit(
+                            TERMINAL_OUTPUT_EVENT,
+                            TerminalOutput {
+                                terminal_id: terminal_id.as_str(),
+                                data: text.as_ref(),
+                            },
+                        );
+                    }
+                    Err(_) => break,
+                }
+            }
+
+            let _ = app_handle.emit(
+                TERMINAL_EXIT_EVENT,
+                TerminalExit {
+                    terminal_id: terminal_id.as_str(),
+                },
+            );
+        });
+
+        Ok(id)
+    }
+
+    /// Run an agent-requested command in a PTY and retain its output.
+    pub fn spawn_command<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        request: AgentCommand,
+    ) -> Result<String, String> {
+        let mut cmd = CommandBuilder::new(&request.command);
+        cmd.args(&request.args);
+        cmd.env("TERM", "xterm-256color");
+        for (key, value) in &request.env {
+            cmd.env(key, value);
+        }
+        if let Some(cwd) = request.cwd.as_ref() {
+            if !cwd.exists() {
+                return Err(format!(
+                    "Working directory does not exist: {}",
+                    cwd.display()
+                ));
+            }
+            cmd.cwd(cwd);
+        }
+
+        let (exit, _) = watch::channel(None);
+        let capture = Arc::new(CommandCapture {
+            session_id: request.session_id,
+            limit: request
+                .output_byte_limit
+                .map(|limit| usize::try_from(limit).unwrap_or(usize::MAX)),
+            output: Mutex::new(CapturedOutput::default()),
+            exit,
+        });
+
+        self.spawn_pty(app, cmd, DEFAULT_COLS, DEFAULT_ROWS, Some(capture))
+    }
+
+    /// Return the retained output and exit status of an agent terminal.
+    pub fn command_output(
+        &self,
+        session_id: &str,
+        terminal_id: &str,
+    ) -> Result<CommandOutput, String> {
+        let capture = self.capture(session_id, terminal_id)?;
+        let exit = capture.exit.borrow().clone();
+        let guard = capture
+            .output
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        Ok(CommandOutput {
+            output: guard.text.clone(),
+            truncated: guard.truncated,
+            exit,
+        })
+    }
+
+    /// Subscribe to the exit status of an agent terminal.
+    pub fn command_exit(
+        &self,
+        session_id: &str,
+        terminal_id: &str,
+    ) -> Result<watch::Receiver<Option<CommandExit>>, String> {
+        Ok(self.capture(session_id, terminal_id)?.exit.subscribe())
+    }
+
+    /// Kill the command in an agent terminal while keeping its output available.
+    pub fn kill_command(&self, session_id: &str, terminal_id: &str) -> Result<(), String> {
+        self.capture(session_id, terminal_id)?;
+        let terminals = self
+            .terminals
+            .lock()
+            .map_err(|_| "terminal manager poisoned".to_string())?;
+        if let Some(terminal) = terminals.get(terminal_id) {
+            if let Ok(mut killer) = terminal.killer.lock() {
+                let _ = killer.kill();
+            }
+        }
+        Ok(())
+    }
+
+    /// Kill the command if still running and forget the agent terminal.
+    pub fn release_command(&self, session_id: &str, terminal_id: &str) -> Result<(), String> {
+        self.capture(session_id, terminal_id)?;
+        let terminal = {
+            let mut terminals = self
+                .terminals
+                .lock()
+                .map_err(|_| "terminal manager poisoned".to_string())?;
+            terminals.remove(terminal_id)
+        };
+        if let Some(terminal) = terminal {
+            if let Ok(mut killer) = terminal.killer.lock() {
+                let _ = killer.kill();
+            }
+        }
+        Ok(())
+    }
+
+    /// Kill and forget every agent terminal of a session that is gone.
+    pub fn release_session(&self, session_id: &str) {
+        let released: Vec<TerminalInstance> = {
+            let mut terminals = self
+                .terminals
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let ids: Vec<String> = terminals
+                .iter()
+                .filter(|(_, terminal)| {
+                    terminal
+                        .capture
+                        .as_ref()
+                        .is_some_and(|capture| capture.session_id == session_id)
+                })
+                .map(|(id, _)| id.clone())
+                .collect();
+            ids.iter().filter_map(|id| terminals.remove(id)).collect()
+        };
+        for terminal in released {
+            if let Ok(mut killer) = terminal.killer.lock() {
+                let _ = killer.kill();
+            }
+        }
+    }
+
+    fn capture(&self, session_id: &str, terminal_id: &str) -> Result<Arc<CommandCapture>, String> {
+        let terminals = self
+            .terminals
+            .lock()
+            .map_err(|_| "terminal manager poisoned".to_string())?;
+        let capture = terminals
+            .get(terminal_id)
+            .and_then(|terminal| terminal.capture.clone())
+            .ok_or_else(|| "terminal not found".to_string())?;
+        if capture.session_id != session_id {
+            return Err("terminal belongs to another session".to_string());
+        }
+        Ok(capture)
+    }
 }
 
 fn default_shell() -> String {
@@ -62,15 +422,6 @@ pub fn terminal_spawn(
 ) -> Result<String, String> {
     let cols = cols.filter(|value| *value > 0).unwrap_or(DEFAULT_COLS);
     let rows = rows.filter(|value| *value > 0).unwrap_or(DEFAULT_ROWS);
-    let pty_system = native_pty_system();
-    let pair = pty_system
-        .openpty(PtySize {
-            rows,
-            cols,
-            pixel_width: 0,
-            pixel_height: 0,
-        })
-        .map_err(|err| err.to_string())?;
 
     let mut cmd = CommandBuilder::new(default_shell());
     cmd.env("TERM", "xterm-256color");
@@ -81,67 +432,7 @@ pub fn terminal_spawn(
         }
     }
 
-    let child = pair
-        .slave
-        .spawn_command(cmd)
-        .map_err(|err| err.to_string())?;
-    drop(pair.slave);
-
-    let mut reader = pair
-        .master
-        .try_clone_reader()
-        .map_err(|err| err.to_string())?;
-    let writer = pair.master.take_writer().map_err(|err| err.to_string())?;
-
-    let id = state.next_id();
-    let writer = Arc::new(Mutex::new(writer));
-    let child: Arc<Mutex<Box<dyn portable_pty::Child + Send + Sync>>> = Arc::new(Mutex::new(child));
-
-    {
-        let mut terminals = state
-            .terminals
-            .lock()
-            .map_err(|_| "terminal manager poisoned".to_string())?;
-        terminals.insert(
-            id.clone(),
-            TerminalInstance {
-                master: pair.master,
-                writer: Arc::clone(&writer),
-                child: Arc::clone(&child),
-            },
-        );
-    }
-
-    let terminal_id = id.clone();
-    let app_handle = app.clone();
-    std::thread::spawn(move || {
-        let mut buffer = [0u8; 8192];
-        loop {
-            match reader.read(&mut buffer) {
-                Ok(0) => break,
-                Ok(bytes) => {
-                    let text = String::from_utf8_lossy(&buffer[..bytes]);
-                    let _ = app_handle.emit(
-                        TERMINAL_OUTPUT_EVENT,
-                        TerminalOutput {
-                            terminal_id: terminal_id.as_str(),
-                            data: text.as_ref(),
-                        },
-                    );
-                }
-                Err(_) => break,
-            }
-        }
-
-        let _ = app_handle.emit(
-            TERMINAL_EXIT_EVENT,
-            TerminalExit {
-                terminal_id: terminal_id.as_str(),
-            },
-        );
-    });
-
-    Ok(id)
+    state.spawn_pty(&app, cmd, cols, rows, None)
 }
 
 #[tauri::command]
@@ -212,10 +503,42 @@ pub fn terminal_kill(state: State<'_, TerminalManager>, terminal_id: String) ->
     };
 
     if let Some(terminal) = terminal {
-        if let Ok(mut child) = terminal.child.lock() {
-            let _ = child.kill();
+        if let Ok(mut killer) = terminal.killer.lock() {
+            let _ = killer.kill();
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_front_respects_char_boundaries() {
+        let mut text = "abcdef".to_string();
+        assert!(!truncate_front(&mut text, 10));
+        assert!(truncate_front(&mut text, 4));
+        assert_eq!(text, "cdef");
+
+        let mut text = "é-ok".to_string();
+        assert!(truncate_front(&mut text, 4));
+        assert_eq!(text, "-ok");
+    }
+
+    #[test]
+    fn test_drain_utf8_keeps_split_characters() {
+        let bytes = "aé€".as_bytes();
+        let mut pending = bytes[..4].to_vec();
+        assert_eq!(drain_utf8(&mut pending), "aé");
+        assert_eq!(pending, &bytes[3..4]);
+        pending.extend_from_slice(&bytes[4..]);
+        assert_eq!(drain_utf8(&mut pending), "€");
+        assert!(pending.is_empty());
+
+        let mut pending = b"a\xffb".to_vec();
+        assert_eq!(drain_utf8(&mut pending), "a\u{fffd}b");
+        assert!(pending.is_empty());
+    }
+}
@@ -5,9 +5,20 @@ use agent_client_protocol::{
     PlanEntryStatus, SessionModeId, SessionUpdate, TextContent, ToolCall, ToolCallId,
     ToolCallStatus, ToolCallUpdate, ToolCallUpdateFields, ToolKind,
 };
-use codex_desktop_lib::codex::{debug::DebugState, protocol::emit_session_update};
-use std::time::Instant;
-use tauri::test::mock_app;
+use codex_desktop_lib::codex::{
+    coalesce::{ChunkCoalescer, CoalesceConfig},
+    debug::DebugState,
+    events::{EVENT_MESSAGE_CHUNK, EVENT_THOUGHT_CHUNK},
+    protocol::emit_session_update,
+};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tauri::{test::mock_app, Listener, Manager};
 
 const DEFAULT_COUNT: usize = 200_000;
 
@@ -22,21 +33,62 @@ enum Mode {
     CurrentMode,
 }
 
+/// Which emitters to profile: per-chunk events, coalesced chunks, or both for comparison.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Batching {
+    Off,
+    On,
+    Both,
+}
+
 struct Config {
     count: usize,
     mode: Mode,
+    batching: Batching,
+    coalesce: CoalesceConfig,
+}
+
+struct Run {
+    elapsed: Duration,
+    chunk_events: usize,
 }
 
 #[test]
 fn run_profile() {
     let cfg = parse_config();
+    let updates = build_updates();
+
+    let unbatched = matches!(cfg.batching, Batching::Off | Batching::Both)
+        .then(|| report(&cfg, "unbatched", profile(&cfg, &updates, None)));
+    let batched = matches!(cfg.batching, Batching::On | Batching::Both)
+        .then(|| report(&cfg, "batched", profile(&cfg, &updates, Some(cfg.coalesce))));
+
+    if let (Some(unbatched), Some(batched)) = (unbatched, batched) {
+        eprintln!(
+            "batched/unbatched speedup={:.2}x chunk_events {} -> {}",
+            unbatched.elapsed.as_secs_f64() / batched.elapsed.as_secs_f64().max(f64::EPSILON),
+            unbatched.chunk_events,
+            batched.chunk_events
+        );
+    }
+}
+
+fn profile(cfg: &Config, updates: &[SessionUpdate], coalesce: Option<CoalesceConfig>) -> Run {
     let app = mock_app();
     let handle = app.handle();
+    if let Some(coalesce) = coalesce {
+        app.manage(ChunkCoalescer::new(coalesce));
+    }
+    let chunk_events = Arc::new(AtomicUsize::new(0));
+    for name in [EVENT_MESSAGE_CHUNK, EVENT_THOUGHT_CHUNK] {
+        let chunk_events = chunk_events.clone();
+        app.listen_any(name, move |_| {
+            chunk_events.fetch_add(1, Ordering::Relaxed);
+        });
+    }
     let debug = DebugState::new();
     let session_id = "profile-session";
-
-    let updates = build_updates();
-    let update = pick_mode_update(cfg.mode, &updates);
+    let update = pick_mode_update(cfg.mode, updates);
 
     let start = Instant::now();
     for idx in 0..cfg.count {
@@ -46,15 +98,25 @@ fn run_profile() {
         };
         emit_session_update(handle, &debug, session_id, current);
     }
-    let elapsed = start.elapsed();
-    let elapsed_ms = elapsed.as_secs_f64() * 1000.0;
-    let per_us = (elapsed.as_secs_f64() * 1_000_000.0) / cfg.count as f64;
-    let rate = cfg.count as f64 / elapsed.as_secs_f64();
+    if let Some(coalescer) = app.try_state::<ChunkCoalescer>() {
+        coalescer.flush_all(handle);
+    }
+    Run {
+        elapsed: start.elapsed(),
+        chunk_events: chunk_events.load(Ordering::Relaxed),
+    }
+}
+
+fn report(cfg: &Config, label: &str, run: Run) -> Run {
+    let elapsed_ms = run.elapsed.as_secs_f64() * 1000.0;
+    let per_us = (run.elapsed.as_secs_f64() * 1_000_000.0) / cfg.count as f64;
+    let rate = cfg.count as f64 / run.elapsed.as_secs_f64();
 
     eprintln!(
-        "mode={:?} count={} elapsed_ms={:.2} per_event_us={:.2} rate={:.0}/s",
-        cfg.mode, cfg.count, elapsed_ms, per_us, rate
+        "mode={:?} emitter={} count={} elapsed_ms={:.2} per_event_us={:.2} rate={:.0}/s chunk_events={}",
+        cfg.mode, label, cfg.count, elapsed_ms, per_us, rate, run.chunk_events
     );
+    run
 }
 
 fn parse_config() -> Config {
@@ -69,7 +131,25 @@ fn parse_config() -> Config {
         .map(parse_mode)
         .unwrap_or(Mode::Mix);
 
-    Config { count, mode }
+    let batching = match std::env::var("CODEX_EVENT_PROFILE_BATCH").as_deref() {
+        Ok("off") => Batching::Off,
+        Ok("on") => Batching::On,
+        _ => Batching::Both,
+    };
+    let mut coalesce = CoalesceConfig::default();
+    if let Some(window_ms) = std::env::var("CODEX_EVENT_PROFILE_WINDOW_MS")
+        .ok()
+        .and_then(|value| value.parse::<u64>().ok())
+    {
+        coalesce.window_ms = window_ms;
+    }
+
+    Config {
+        count,
+        mode,
+        batching,
+        coalesce,
+    }
 }
 
 fn parse_mode(value: &str) -> Mode {
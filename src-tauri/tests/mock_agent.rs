@@ -0,0 +1,372 @@
+//! End-to-end tests of `CodexService` against the scripted `codex-acp-mock` agent.
+
+use codex_desktop_lib::codex::{
+    events::{
+        EVENT_APPROVAL_REQUEST, EVENT_CONNECTION_STATE, EVENT_MESSAGE_CHUNK, EVENT_PLAN,
+        EVENT_TOOL_CALL, EVENT_TOOL_CALL_UPDATE,
+    },
+    profiles::{EnvProfile, ProfileStore},
+    service::CodexService,
+    types::{ApprovalDecision, PromptDelivery, PromptInput, PromptResult},
+};
+use serde_json::Value;
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex, Once},
+    time::Duration,
+};
+use tauri::{
+    test::{mock_app, MockRuntime},
+    App, Listener, Manager,
+};
+
+static SETUP: Once = Once::new();
+
+/// Point the service at the mock agent; every test spawns its own agent process.
+fn setup() {
+    SETUP.call_once(|| {
+        let scenario = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join("mock_scenario.json");
+        std::env::set_var("CODEX_DESKTOP_ACP_MODE", "mock");
+        std::env::set_var(
+            "CODEX_DESKTOP_ACP_MOCK_BIN",
+            env!("CARGO_BIN_EXE_codex-acp-mock"),
+        );
+        std::env::set_var("CODEX_DESKTOP_ACP_MOCK_SCENARIO", scenario);
+        std::env::set_var("CODEX_DESKTOP_CODEX_HOME", std::env::temp_dir());
+        // Sessions run in this repository; keep turns from snapshotting it.
+        std::env::set_var("CODEX_DESKTOP_CHECKPOINTS", "0");
+    });
+}
+
+#[derive(Clone, Default)]
+struct Events(Arc<Mutex<Vec<(&'static str, Value)>>>);
+
+impl Events {
+    fn record(&self, app: &App<MockRuntime>, names: &[&'static str]) {
+        for &name in names {
+            let events = self.clone();
+            app.listen_any(name, move |event| {
+                let payload = serde_json::from_str(event.payload()).unwrap_or(Value::Null);
+                events.0.lock().unwrap().push((name, payload));
+            });
+        }
+    }
+
+    fn named(&self, name: &str) -> Vec<Value> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(event, _)| *event == name)
+            .map(|(_, payload)| payload.clone())
+            .collect()
+    }
+
+    fn message_text(&self) -> String {
+        self.named(EVENT_MESSAGE_CHUNK)
+            .iter()
+            .filter_map(|payload| payload["text"].as_str().map(str::to_string))
+            .collect()
+    }
+
+    /// Session notifications are handled concurrently with the prompt response, so the
+    /// last chunks of a turn may arrive after `send_prompt` returned.
+    async fn expect_message(&self, text: &str) {
+        self.wait_for(&format!("message {text:?}"), |events| {
+            events.message_text() == text
+        })
+        .await;
+    }
+
+    async fn wait_for(&self, what: &str, check: impl Fn(&Self) -> bool) {
+        for _ in 0..750 {
+            if check(self) {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("timed out waiting for {what}");
+    }
+}
+
+struct Harness {
+    app: App<MockRuntime>,
+    service: CodexService,
+    events: Events,
+    session_id: String,
+}
+
+async fn start(names: &[&'static str]) -> Harness {
+    setup();
+    let app = mock_app();
+    let events = Events::default();
+    events.record(&app, names);
+    let service = CodexService::new(app.handle().clone());
+    service.initialize().await.expect("initialize");
+    let session = service
+        .create_session(PathBuf::from(env!("CARGO_MANIFEST_DIR")), None)
+        .await
+        .expect("new session");
+    Harness {
+        app,
+        service,
+        events,
+        session_id: session.session_id,
+    }
+}
+
+impl Harness {
+    async fn prompt(&self, text: &str) -> anyhow::Result<PromptResult> {
+        self.service
+            .send_prompt(
+                self.session_id.clone(),
+                PromptInput::Text(text.to_string()),
+                PromptDelivery::Queue,
+            )
+            .await
+    }
+}
+
+#[tokio::test]
+async fn test_prompt_streams_scripted_updates() {
+    let h = start(&[
+        EVENT_MESSAGE_CHUNK,
+        EVENT_TOOL_CALL,
+        EVENT_TOOL_CALL_UPDATE,
+        EVENT_PLAN,
+    ])
+    .await;
+
+    let result = h.prompt("hello").await.expect("prompt");
+    assert_eq!(result.stop_reason, "end_turn");
+    h.events.expect_message("Hello world").await;
+
+    let tool_calls = h.events.named(EVENT_TOOL_CALL);
+    assert_eq!(tool_calls.len(), 1);
+    assert_eq!(tool_calls[0]["sessionId"], h.session_id.as_str());
+    assert_eq!(tool_calls[0]["toolCall"]["kind"], "read");
+    assert_eq!(
+        h.events.named(EVENT_TOOL_CALL_UPDATE)[0]["update"]["status"],
+        "completed"
+    );
+    assert_eq!(
+        h.events.named(EVENT_PLAN)[0]["plan"]["entries"]
+            .as_array()
+            .map(Vec::len),
+        Some(2)
+    );
+
+    let result = h.prompt("limit").await.expect("prompt");
+    assert_eq!(result.stop_reason, "max_tokens");
+}
+
+#[tokio::test]
+async fn test_approval_response_reaches_agent() {
+    let h = start(&[EVENT_MESSAGE_CHUNK]).await;
+    let service = h.service.clone();
+    h.app.listen_any(EVENT_APPROVAL_REQUEST, move |event| {
+        let payload: Value = serde_json::from_str(event.payload()).unwrap();
+        assert_eq!(payload["toolCall"]["kind"], "edit");
+        service
+            .respond_permission(
+                payload["sessionId"].as_str().unwrap().to_string(),
+                payload["requestId"].as_str().unwrap().to_string(),
+                Some(ApprovalDecision::RejectOnce),
+                None,
+            )
+            .expect("respond");
+    });
+
+    let result = h.prompt("approve please").await.expect("prompt");
+    assert_eq!(result.stop_reason, "end_turn");
+    h.events
+        .expect_message("permission edit-1: reject done")
+        .await;
+    assert!(h.service.pending_approvals(None).is_empty());
+}
+
+#[tokio::test]
+async fn test_cancel_resolves_pending_approval_and_stops_turn() {
+    let h = start(&[EVENT_MESSAGE_CHUNK, EVENT_APPROVAL_REQUEST]).await;
+
+    let service = h.service.clone();
+    let session_id = h.session_id.clone();
+    let turn = tokio::spawn(async move {
+        service
+            .send_prompt(
+                session_id,
+                PromptInput::Text("wait for it".to_string()),
+                PromptDelivery::Queue,
+            )
+            .await
+    });
+
+    h.events
+        .wait_for("approval request", |events| {
+            !events.named(EVENT_APPROVAL_REQUEST).is_empty()
+        })
+        .await;
+    assert_eq!(h.service.pending_approvals(Some(&h.session_id)).len(), 1);
+
+    h.service
+        .cancel(h.session_id.clone())
+        .await
+        .expect("cancel");
+    let result = turn.await.expect("join").expect("prompt");
+    assert_eq!(result.stop_reason, "cancelled");
+    assert!(h.service.pending_approvals(None).is_empty());
+    h.events
+        .expect_message("permission exec-1: cancelled")
+        .await;
+}
+
+#[tokio::test]
+async fn test_agent_error_fails_prompt() {
+    let h = start(&[EVENT_MESSAGE_CHUNK]).await;
+    let err = h.prompt("fail now").await.expect_err("prompt fails");
+    assert!(format!("{err:#}").contains("scripted failure"), "{err:#}");
+    h.events.expect_message("about to fail").await;
+
+    // The session stays usable after a failed turn.
+    let result = h.prompt("hello").await.expect("prompt");
+    assert_eq!(result.stop_reason, "end_turn");
+}
+
+#[tokio::test]
+async fn test_crashed_agent_restarts_and_reloads_session() {
+    let h = start(&[EVENT_MESSAGE_CHUNK, EVENT_CONNECTION_STATE]).await;
+    assert!(h.prompt("crash").await.is_err());
+
+    h.events
+        .wait_for("connection restart", |events| {
+            events
+                .named(EVENT_CONNECTION_STATE)
+                .iter()
+                .any(|payload| payload["state"] == "ready" && payload["sessions"][0].is_string())
+        })
+        .await;
+    let states: Vec<String> = h
+        .events
+        .named(EVENT_CONNECTION_STATE)
+        .iter()
+        .filter_map(|payload| payload["state"].as_str().map(str::to_string))
+        .collect();
+    assert!(states.contains(&"crashed".to_string()), "{states:?}");
+    assert!(states.contains(&"restarting".to_string()), "{states:?}");
+    let ready = h
+        .events
+        .named(EVENT_CONNECTION_STATE)
+        .into_iter()
+        .rfind(|payload| payload["state"] == "ready")
+        .unwrap();
+    assert_eq!(ready["sessions"][0], h.session_id.as_str());
+
+    let result = h.prompt("hello again").await.expect("prompt after restart");
+    assert_eq!(result.stop_reason, "end_turn");
+}
+
+#[tokio::test]
+async fn test_remote_paths_need_a_configured_server() {
+    let h = start(&[]).await;
+
+    let err = h
+        .service
+        .create_session(
+            PathBuf::from("remote://unknown-server/home/me/project"),
+            None,
+        )
+        .await
+        .expect_err("unknown server");
+    assert!(
+        format!("{err:#}").contains("Remote server configuration not found"),
+        "{err:#}"
+    );
+
+    let err = h
+        .service
+        .create_session(PathBuf::from("remote://no-path"), None)
+        .await
+        .expect_err("malformed remote path");
+    assert!(
+        format!("{err:#}").contains("Invalid remote path format"),
+        "{err:#}"
+    );
+}
+
+#[tokio::test]
+async fn test_sessions_start_against_a_profile() {
+    let h = start(&[EVENT_CONNECTION_STATE, EVENT_MESSAGE_CHUNK]).await;
+    let dir = std::env::temp_dir().join(format!("codex-profiles-e2e-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    h.app.manage(ProfileStore::new(
+        dir.join("env-profiles.json"),
+        dir.join("env-profile-secrets.json"),
+    ));
+    let profiles = h.app.state::<ProfileStore>();
+    let profile = profiles
+        .upsert(EnvProfile {
+            name: "staging".to_string(),
+            env: [("STAGING_API_KEY".to_string(), "sk-staging".to_string())].into(),
+            ..EnvProfile::default()
+        })
+        .expect("save profile");
+    let ready = |events: &Events| {
+        events
+            .named(EVENT_CONNECTION_STATE)
+            .iter()
+            .filter(|payload| payload["state"] == "ready")
+            .count()
+    };
+    let cwd = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    // A profiled session gets its own agent process next to the default one.
+    let session = h
+        .service
+        .create_session(cwd.clone(), Some(profile.id.clone()))
+        .await
+        .expect("profiled session");
+    assert_eq!(ready(&h.events), 2);
+    let result = h
+        .service
+        .send_prompt(
+            session.session_id,
+            PromptInput::Text("hello".to_string()),
+            PromptDelivery::Queue,
+        )
+        .await
+        .expect("prompt");
+    assert_eq!(result.stop_reason, "end_turn");
+
+    // Sessions share the connection until the profile changes.
+    h.service
+        .create_session(cwd.clone(), Some(profile.id.clone()))
+        .await
+        .expect("second profiled session");
+    assert_eq!(ready(&h.events), 2);
+    profiles.upsert(profile.clone()).expect("update profile");
+    h.service
+        .create_session(cwd.clone(), Some(profile.id.clone()))
+        .await
+        .expect("session after profile update");
+    assert_eq!(ready(&h.events), 3);
+
+    let err = h
+        .service
+        .create_session(cwd, Some("missing".to_string()))
+        .await
+        .expect_err("unknown profile");
+    assert!(format!("{err:#}").contains("unknown environment profile"));
+    let err = h
+        .service
+        .create_session(
+            PathBuf::from("remote://server/home/me/project"),
+            Some(profile.id),
+        )
+        .await
+        .expect_err("remote session with a profile");
+    assert!(format!("{err:#}").contains("only apply to local sessions"));
+    let _ = std::fs::remove_dir_all(&dir);
+}
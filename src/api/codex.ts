@@ -1,29 +1,96 @@
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+
+import type { UnlistenFn } from '@tauri-apps/api/event';
+import type { CodexEventMap, CodexEventName } from '../types/codexEvents.generated';
+import type { GitCommit } from '../types/git';
 
 import type {
+  AgentLogLine,
+  ApprovalAuditEntry,
   ApprovalDecision,
+  ApprovalRule,
+  ApprovalTimeout,
+  PendingApproval,
   CodexCliConfigInfo,
+  CoalesceConfig,
+  DebugTraceExport,
+  EnvProfile,
   NewSessionResult,
+  NewWorktreeSessionResult,
+  PromptDelivery,
+  PromptPart,
   PromptResult,
+  QueuedPrompt,
+  ReplaySummary,
+  UsageReport,
+  UsageSettings,
   InitializeResult,
+  SessionSearchHit,
+  SessionTranscript,
+  StoredSessionSummary,
+  TraceFormat,
+  WorktreeCleanup,
+  WorktreeSessionInfo,
 } from '../types/codex';
 
 export async function initCodex(): Promise<InitializeResult> {
   return invoke<InitializeResult>('codex_init');
 }
 
-export async function createSession(cwd: string): Promise<NewSessionResult> {
-  return invoke<NewSessionResult>('codex_new_session', { cwd });
+export async function createSession(cwd: string, profile?: string): Promise<NewSessionResult> {
+  return invoke<NewSessionResult>('codex_new_session', { cwd, profile: profile ?? null });
+}
+
+export async function createWorktreeSession(
+  cwd: string,
+  options: { base?: string; branch?: string; profile?: string } = {}
+): Promise<NewWorktreeSessionResult> {
+  return invoke<NewWorktreeSessionResult>('codex_new_worktree_session', {
+    cwd,
+    base: options.base ?? null,
+    branch: options.branch ?? null,
+    profile: options.profile ?? null,
+  });
 }
 
-export async function sendPrompt(sessionId: string, content: string): Promise<PromptResult> {
+export async function loadSession(
+  sessionId: string,
+  cwd: string,
+  profile?: string
+): Promise<NewSessionResult> {
+  return invoke<NewSessionResult>('codex_load_session', {
+    sessionId,
+    session_id: sessionId,
+    cwd,
+    profile: profile ?? null,
+  });
+}
+
+export async function sendPrompt(
+  sessionId: string,
+  content: string | PromptPart[],
+  delivery?: PromptDelivery
+): Promise<PromptResult> {
   return invoke<PromptResult>('codex_prompt', {
     sessionId,
     session_id: sessionId,
     content,
+    delivery: delivery ?? null,
   });
 }
 
+export async function listQueuedPrompts(sessionId?: string): Promise<QueuedPrompt[]> {
+  return invoke<QueuedPrompt[]>('codex_list_queued_prompts', {
+    sessionId: sessionId ?? null,
+    session_id: sessionId ?? null,
+  });
+}
+
+export async function removeQueuedPrompt(id: string): Promise<boolean> {
+  return invoke<boolean>('codex_remove_queued_prompt', { id });
+}
+
 export async function approveRequest(
   sessionId: string,
   requestId: string,
@@ -82,3 +149,166 @@ export async function setCodexEnv(key: string, value: string): Promise<void> {
 export async function warmupCodex(): Promise<void> {
   await invoke<void>('codex_warmup');
 }
+
+export async function setFileBuffer(path: string, content: string): Promise<void> {
+  await invoke<void>('codex_set_file_buffer', { path, content });
+}
+
+export async function clearFileBuffer(path: string): Promise<void> {
+  await invoke<void>('codex_clear_file_buffer', { path });
+}
+
+export async function listStoredSessions(): Promise<StoredSessionSummary[]> {
+  return invoke<StoredSessionSummary[]>('codex_list_sessions');
+}
+
+export async function searchStoredSessions(
+  query: string,
+  limit?: number
+): Promise<SessionSearchHit[]> {
+  return invoke<SessionSearchHit[]>('codex_search_sessions', { query, limit: limit ?? null });
+}
+
+export async function openStoredSession(sessionId: string): Promise<SessionTranscript> {
+  return invoke<SessionTranscript>('codex_open_session', { sessionId, session_id: sessionId });
+}
+
+export async function deleteStoredSession(sessionId: string): Promise<void> {
+  await invoke<void>('codex_delete_session', { sessionId, session_id: sessionId });
+}
+
+export async function listWorktreeSessions(): Promise<WorktreeSessionInfo[]> {
+  return invoke<WorktreeSessionInfo[]>('codex_list_worktree_sessions');
+}
+
+export async function mergeWorktreeSession(
+  sessionId: string,
+  options: { squash?: boolean; message?: string } = {}
+): Promise<GitCommit> {
+  return invoke<GitCommit>('codex_merge_worktree_session', {
+    sessionId,
+    session_id: sessionId,
+    squash: options.squash ?? null,
+    message: options.message ?? null,
+  });
+}
+
+export async function cleanupWorktreeSession(
+  sessionId: string,
+  force?: boolean
+): Promise<WorktreeCleanup> {
+  return invoke<WorktreeCleanup>('codex_cleanup_worktree_session', {
+    sessionId,
+    session_id: sessionId,
+    force: force ?? null,
+  });
+}
+
+export async function listApprovalRules(): Promise<ApprovalRule[]> {
+  return invoke<ApprovalRule[]>('codex_list_approval_rules');
+}
+
+export async function saveApprovalRule(rule: ApprovalRule, index?: number): Promise<ApprovalRule> {
+  return invoke<ApprovalRule>('codex_save_approval_rule', { rule, index: index ?? null });
+}
+
+export async function deleteApprovalRule(id: string): Promise<void> {
+  await invoke<void>('codex_delete_approval_rule', { id });
+}
+
+export async function listEnvProfiles(): Promise<EnvProfile[]> {
+  return invoke<EnvProfile[]>('codex_list_profiles');
+}
+
+export async function saveEnvProfile(profile: EnvProfile): Promise<EnvProfile> {
+  return invoke<EnvProfile>('codex_save_profile', { profile });
+}
+
+export async function deleteEnvProfile(id: string): Promise<void> {
+  await invoke<void>('codex_delete_profile', { id });
+}
+
+export async function readApprovalAudit(limit?: number): Promise<ApprovalAuditEntry[]> {
+  return invoke<ApprovalAuditEntry[]>('codex_approval_audit', { limit: limit ?? null });
+}
+
+export async function getUsageReport(sessionId?: string, days?: number): Promise<UsageReport> {
+  return invoke<UsageReport>('codex_usage_report', {
+    sessionId: sessionId ?? null,
+    session_id: sessionId ?? null,
+    days: days ?? null,
+  });
+}
+
+export async function getUsageSettings(): Promise<UsageSettings> {
+  return invoke<UsageSettings>('codex_usage_settings');
+}
+
+export async function setUsageSettings(settings: UsageSettings): Promise<void> {
+  await invoke<void>('codex_set_usage_settings', { settings });
+}
+
+export async function listPendingApprovals(sessionId?: string): Promise<PendingApproval[]> {
+  return invoke<PendingApproval[]>('codex_list_pending_approvals', {
+    sessionId: sessionId ?? null,
+    session_id: sessionId ?? null,
+  });
+}
+
+export async function getAgentLog(serverId?: string, lines?: number): Promise<AgentLogLine[]> {
+  return invoke<AgentLogLine[]>('codex_agent_log', {
+    serverId: serverId ?? null,
+    server_id: serverId ?? null,
+    lines: lines ?? null,
+  });
+}
+
+export async function setAcpRecording(enabled: boolean): Promise<string | null> {
+  return invoke<string | null>('codex_set_acp_recording', { enabled });
+}
+
+export async function replayAcpRecording(
+  path: string,
+  speed?: number,
+  sessionId?: string
+): Promise<ReplaySummary> {
+  return invoke<ReplaySummary>('codex_replay_acp_recording', {
+    path,
+    speed: speed ?? null,
+    sessionId: sessionId ?? null,
+    session_id: sessionId ?? null,
+  });
+}
+
+export async function exportDebugTrace(
+  format: TraceFormat,
+  sessionId?: string,
+  path?: string
+): Promise<DebugTraceExport> {
+  return invoke<DebugTraceExport>('codex_export_debug_trace', {
+    format,
+    sessionId: sessionId ?? null,
+    session_id: sessionId ?? null,
+    path: path ?? null,
+  });
+}
+
+export async function setApprovalTimeout(timeout: ApprovalTimeout | null): Promise<void> {
+  await invoke<void>('codex_set_approval_timeout', { timeout });
+}
+
+export async function getChunkCoalescing(): Promise<CoalesceConfig> {
+  return invoke<CoalesceConfig>('codex_chunk_coalescing');
+}
+
+export async function setChunkCoalescing(config: CoalesceConfig): Promise<void> {
+  await invoke<void>('codex_set_chunk_coalescing', { config });
+}
+
+/** Subscribe to a backend event with the payload type generated from the Rust definitions. */
+export async function listenCodexEvent<K extends CodexEventName>(
+  name: K,
+  handler: (payload: CodexEventMap[K]) => void
+): Promise<UnlistenFn> {
+  return listen<CodexEventMap[K]>(name, (event) => handler(event.payload));
+}
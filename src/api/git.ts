@@ -1,6 +1,24 @@
 import { invoke } from '@tauri-apps/api/core';
+import type { UnlistenFn } from '@tauri-apps/api/event';
 
-import type { GitStatusResult, GitCommit, RemoteGitHistoryResult } from '../types/git';
+import { listenCodexEvent } from './codex';
+
+import type {
+  GitBranchesResult,
+  GitCheckpoint,
+  GitCommandError,
+  GitStatusResult,
+  GitCommit,
+  GitCommitDetails,
+  GitDiffOptions,
+  GitCommitOptions,
+  GitDiffResult,
+  GitDiscardResult,
+  GitProgressEvent,
+  GitPullMode,
+  GitPushOptions,
+  RemoteGitHistoryResult,
+} from '../types/git';
 
 export async function gitStatus(cwd: string): Promise<GitStatusResult> {
   return invoke<GitStatusResult>('git_status', { cwd });
@@ -40,3 +58,187 @@ export async function gitReset(
 ): Promise<void> {
   await invoke('git_reset', { cwd, commit, mode });
 }
+
+export async function gitDiff(cwd: string, options: GitDiffOptions = {}): Promise<GitDiffResult> {
+  return invoke<GitDiffResult>('git_diff', {
+    cwd,
+    staged: options.staged,
+    from: options.from,
+    to: options.to,
+    paths: options.paths,
+    contextLines: options.contextLines,
+    context_lines: options.contextLines,
+  });
+}
+
+export async function gitShowCommit(
+  cwd: string,
+  commit: string,
+  contextLines?: number
+): Promise<GitCommitDetails> {
+  return invoke<GitCommitDetails>('git_show_commit', {
+    cwd,
+    commit,
+    contextLines,
+    context_lines: contextLines,
+  });
+}
+
+/** Paths are relative to the repository root, as in `GitStatusResult`. */
+export async function gitStage(cwd: string, paths: string[] = []): Promise<void> {
+  await invoke('git_stage', { cwd, paths });
+}
+
+export async function gitUnstage(cwd: string, paths: string[] = []): Promise<void> {
+  await invoke('git_unstage', { cwd, paths });
+}
+
+/** `hunks` are `GitDiffHunk.header` values from a diff with the same `contextLines`. */
+export async function gitStageHunks(
+  cwd: string,
+  path: string,
+  hunks: string[],
+  contextLines?: number
+): Promise<void> {
+  await invoke('git_stage_hunks', { cwd, path, hunks, contextLines, context_lines: contextLines });
+}
+
+export async function gitUnstageHunks(
+  cwd: string,
+  path: string,
+  hunks: string[],
+  contextLines?: number
+): Promise<void> {
+  await invoke('git_unstage_hunks', {
+    cwd,
+    path,
+    hunks,
+    contextLines,
+    context_lines: contextLines,
+  });
+}
+
+export async function gitDiscard(
+  cwd: string,
+  paths: string[],
+  token?: string
+): Promise<GitDiscardResult> {
+  return invoke<GitDiscardResult>('git_discard', { cwd, paths, token });
+}
+
+export async function gitCommit(
+  cwd: string,
+  message: string,
+  options: GitCommitOptions = {}
+): Promise<GitCommit> {
+  return invoke<GitCommit>('git_commit', {
+    cwd,
+    message,
+    amend: options.amend,
+    signOff: options.signOff,
+    sign_off: options.signOff,
+  });
+}
+
+export async function gitListCheckpoints(cwd: string, sessionId: string): Promise<GitCheckpoint[]> {
+  return invoke<GitCheckpoint[]>('git_list_checkpoints', {
+    cwd,
+    sessionId,
+    session_id: sessionId,
+  });
+}
+
+/**
+ * Restores the working tree and index as they were before `turn`, or to pre-restore snapshot
+ * `turn` with `restore`; HEAD stays put.
+ */
+export async function gitRestoreCheckpoint(
+  cwd: string,
+  sessionId: string,
+  turn: number,
+  restore?: boolean
+): Promise<GitCheckpoint> {
+  return invoke<GitCheckpoint>('git_restore_checkpoint', {
+    cwd,
+    sessionId,
+    session_id: sessionId,
+    turn,
+    restore,
+  });
+}
+
+/**
+ * Changes since the checkpoint of `turn` (a pre-restore snapshot with `restore`), up to turn
+ * `to` or the current working tree.
+ */
+export async function gitDiffCheckpoint(
+  cwd: string,
+  sessionId: string,
+  turn: number,
+  to?: number,
+  contextLines?: number,
+  restore?: boolean
+): Promise<GitDiffResult> {
+  return invoke<GitDiffResult>('git_diff_checkpoint', {
+    cwd,
+    sessionId,
+    session_id: sessionId,
+    turn,
+    restore,
+    to,
+    contextLines,
+    context_lines: contextLines,
+  });
+}
+
+export async function gitBranches(cwd: string): Promise<GitBranchesResult> {
+  return invoke<GitBranchesResult>('git_branches', { cwd });
+}
+
+export async function gitDeleteBranch(cwd: string, name: string, force?: boolean): Promise<void> {
+  await invoke('git_delete_branch', { cwd, name, force });
+}
+
+export async function gitRenameBranch(cwd: string, name: string, newName: string): Promise<void> {
+  await invoke('git_rename_branch', { cwd, name, newName, new_name: newName });
+}
+
+export async function gitFetch(cwd: string, remote?: string, prune?: boolean): Promise<void> {
+  await invoke('git_fetch', { cwd, remote, prune });
+}
+
+export async function gitPull(
+  cwd: string,
+  mode: GitPullMode = 'ff-only',
+  remote?: string,
+  branch?: string
+): Promise<void> {
+  await invoke('git_pull', { cwd, remote, branch, mode });
+}
+
+export async function gitPush(cwd: string, options: GitPushOptions = {}): Promise<void> {
+  await invoke('git_push', {
+    cwd,
+    remote: options.remote,
+    branch: options.branch,
+    setUpstream: options.setUpstream,
+    set_upstream: options.setUpstream,
+    forceWithLease: options.forceWithLease,
+    force_with_lease: options.forceWithLease,
+  });
+}
+
+export async function listenGitProgress(
+  handler: (event: GitProgressEvent) => void
+): Promise<UnlistenFn> {
+  return listenCodexEvent('git:progress', handler);
+}
+
+/** Whether a rejected `gitFetch`/`gitPull`/`gitPush` call failed for lack of credentials. */
+export function isGitAuthError(error: unknown): error is GitCommandError {
+  return (
+    typeof error === 'object' &&
+    error !== null &&
+    (error as Partial<GitCommandError>).kind === 'auth'
+  );
+}
@@ -8,13 +8,12 @@
  */
 
 import { useEffect, useMemo, useRef } from 'react';
-import { listen } from '@tauri-apps/api/event';
 
 import { devDebug } from '../utils/logger';
+import { listenCodexEvent } from '../api/codex';
 import {
   asRecord,
   extractSlashCommands,
-  getString,
   newMessageId,
   parseToolCall,
   resolveModelOptions,
@@ -28,7 +27,6 @@ import { DEFAULT_MODEL_ID, DEFAULT_MODE_ID } from '../constants/chat';
 
 import type { MutableRefObject } from 'react';
 import type { Message } from '../types/message';
-import type { ApprovalRequest } from '../types/codex';
 import type { PlanStep, PlanStatus } from '../types/plan';
 
 /**
@@ -180,36 +178,36 @@ export function useCodexEvents(callbacks?: CodexEventsCallbacks): void {
 
     const listenerToken = beginListeners();
     const unlistenPromises = [
-      listen<{ sessionId: string; text: string }>('codex:message', (event) => {
+      listenCodexEvent('codex:message', (payload) => {
         if (!isListenerActive()) return;
-        const sessionId = resolveChatSessionId(event.payload.sessionId);
+        const sessionId = resolveChatSessionId(payload.sessionId);
         if (!sessionId) return;
-        appendAssistantChunk(sessionId, event.payload.text);
+        appendAssistantChunk(sessionId, payload.text);
       }),
-      listen<{ sessionId: string; text: string }>('codex:thought', (event) => {
+      listenCodexEvent('codex:thought', (payload) => {
         if (!isListenerActive()) return;
         devDebug('[codex:thought] Received', {
-          sessionId: event.payload.sessionId,
-          textLen: event.payload.text.length,
+          sessionId: payload.sessionId,
+          textLen: payload.text.length,
         });
-        const sessionId = resolveChatSessionId(event.payload.sessionId);
+        const sessionId = resolveChatSessionId(payload.sessionId);
         if (!sessionId) return;
-        appendThoughtChunk(sessionId, event.payload.text);
+        appendThoughtChunk(sessionId, payload.text);
       }),
-      listen<{ sessionId: string; stopReason: unknown }>('codex:turn-complete', (event) => {
+      listenCodexEvent('codex:turn-complete', (payload) => {
         if (!isListenerActive()) return;
-        const sessionId = resolveChatSessionId(event.payload.sessionId);
+        const sessionId = resolveChatSessionId(payload.sessionId);
         if (!sessionId) return;
         finalizeStreamingMessages(sessionId);
         useSessionStore.getState().setIsGenerating(sessionId, false);
       }),
-      listen<{ error: string }>('codex:error', (event) => {
+      listenCodexEvent('codex:error', (payload) => {
         if (!isListenerActive()) return;
         const sessionId = activeSessionIdRef.current;
         const errMsg: Message = {
           id: newMessageId(),
           role: 'assistant',
-          content: i18n.t('errors.genericError', { error: event.payload.error }),
+          content: i18n.t('errors.genericError', { error: payload.error }),
           isStreaming: false,
           timestamp: new Date(),
         };
@@ -217,37 +215,39 @@ export function useCodexEvents(callbacks?: CodexEventsCallbacks): void {
         useSessionStore.getState().addMessage(sessionId, errMsg);
         useSessionStore.getState().setIsGenerating(sessionId, false);
       }),
-      listen<ApprovalRequest>('codex:approval-request', (event) => {
+      listenCodexEvent('codex:approval-request', (payload) => {
         if (!isListenerActive()) return;
-        useCodexStore.getState().registerApprovalRequest(event.payload);
+        // The store keeps the loosely typed shape the approval cards parse.
+        useCodexStore.getState().registerApprovalRequest({
+          sessionId: payload.sessionId,
+          requestId: payload.requestId,
+          toolCall: asRecord(payload.toolCall) ?? {},
+          options: payload.options.map((option) => asRecord(option) ?? {}),
+        });
       }),
-      listen<{ sessionId: string; update: unknown }>('codex:available-commands', (event) => {
+      listenCodexEvent('codex:available-commands', (payload) => {
         if (!isListenerActive()) return;
-        const sessionId = resolveChatSessionId(event.payload.sessionId);
+        const sessionId = resolveChatSessionId(payload.sessionId);
         if (!sessionId) return;
-        const commands = extractSlashCommands(event.payload.update);
+        const commands = extractSlashCommands(payload.update);
         if (commands.length === 0) return;
         useSessionStore
           .getState()
           .setSessionSlashCommands((prev) => ({ ...prev, [sessionId]: commands }));
       }),
-      listen<{ sessionId: string; update: unknown }>('codex:current-mode', (event) => {
+      listenCodexEvent('codex:current-mode', (payload) => {
         if (!isListenerActive()) return;
-        const sessionId = resolveChatSessionId(event.payload.sessionId);
+        const sessionId = resolveChatSessionId(payload.sessionId);
         if (!sessionId) return;
-        const update = asRecord(event.payload.update);
-        if (!update) return;
-        const modeId = getString(update.currentModeId ?? update.current_mode_id);
+        const modeId = payload.update.currentModeId;
         if (!modeId) return;
         useSessionStore.getState().updateSession(sessionId, { mode: modeId });
       }),
-      listen<{ sessionId: string; update: unknown }>('codex:config-option-update', (event) => {
+      listenCodexEvent('codex:config-option-update', (payload) => {
         if (!isListenerActive()) return;
-        const sessionId = resolveChatSessionId(event.payload.sessionId);
+        const sessionId = resolveChatSessionId(payload.sessionId);
         if (!sessionId) return;
-        const update = asRecord(event.payload.update);
-        if (!update) return;
-        const configOptions = update.configOptions ?? update.config_options ?? update.configOption;
+        const configOptions = payload.update.configOptions;
 
         const modeState = resolveModeOptions(undefined, configOptions);
         if (modeState?.options.length) {
@@ -281,39 +281,33 @@ export function useCodexEvents(callbacks?: CodexEventsCallbacks): void {
           useSessionStore.getState().updateSession(sessionId, { model: modelState.currentModelId });
         }
       }),
-      listen<{ sessionId: string; toolCall: unknown }>('codex:tool-call', (event) => {
+      listenCodexEvent('codex:tool-call', (payload) => {
         if (!isListenerActive()) return;
-        const sessionId = resolveChatSessionId(event.payload.sessionId);
+        const sessionId = resolveChatSessionId(payload.sessionId);
         if (!sessionId) return;
-        const toolCall = asRecord(event.payload.toolCall);
+        const toolCall = asRecord(payload.toolCall);
         if (!toolCall) return;
         const parsed = parseToolCall(toolCall);
         upsertToolCallMessage(sessionId, parsed);
       }),
-      listen<{ sessionId: string; update: unknown }>('codex:tool-call-update', (event) => {
+      listenCodexEvent('codex:tool-call-update', (payload) => {
         if (!isListenerActive()) return;
-        const sessionId = resolveChatSessionId(event.payload.sessionId);
+        const sessionId = resolveChatSessionId(payload.sessionId);
         if (!sessionId) return;
-        const update = asRecord(event.payload.update);
+        const update = asRecord(payload.update);
         if (!update) return;
         applyToolCallUpdateMessage(sessionId, update);
       }),
-      listen<{
-        sessionId: string;
-        plan: { entries: Array<{ content: string; status: string; priority: string }> };
-      }>('codex:plan', (event) => {
+      listenCodexEvent('codex:plan', (payload) => {
         if (!isListenerActive()) return;
-        const sessionId = resolveChatSessionId(event.payload.sessionId);
+        const sessionId = resolveChatSessionId(payload.sessionId);
         if (!sessionId) return;
-        const entries = event.payload.plan?.entries;
-        if (entries && Array.isArray(entries)) {
-          const steps: PlanStep[] = entries.map((entry, index) => ({
-            id: `plan-step-${index}`,
-            title: entry.content,
-            status: mapPlanStatus(entry.status),
-          }));
-          messageHandlers.updatePlan(sessionId, steps);
-        }
+        const steps: PlanStep[] = payload.plan.entries.map((entry, index) => ({
+          id: `plan-step-${index}`,
+          title: entry.content,
+          status: mapPlanStatus(entry.status),
+        }));
+        messageHandlers.updatePlan(sessionId, steps);
       }),
     ];
     commitListeners(listenerToken, unlistenPromises);
@@ -44,6 +44,7 @@ export interface InitializeResult {
   agentInfo: unknown;
   authMethods: AuthMethod[];
   protocolVersion: unknown;
+  agentCapabilities?: unknown;
 }
 
 export interface AuthMethod extends Record<string, unknown> {
@@ -59,8 +60,115 @@ export interface NewSessionResult {
   configOptions?: unknown;
 }
 
+export type PromptPart =
+  | { type: 'text'; text: string }
+  | { type: 'image'; path?: string; data?: string; mimeType?: string }
+  | { type: 'resource_link'; path: string; name?: string; mimeType?: string }
+  | { type: 'resource'; path: string; text?: string; mimeType?: string };
+
+export type PromptDelivery = 'queue' | 'steer';
+
+export interface QueuedPrompt {
+  id: string;
+  sessionId: string;
+  position: number;
+  preview: string;
+  queuedAt: number;
+}
+
+export interface PromptQueueEvent {
+  sessionId: string;
+  running: boolean;
+  queue: QueuedPrompt[];
+}
+
+export interface MentionAttachment {
+  token: string;
+  path: string;
+  lines?: [number, number] | null;
+  status: 'embedded' | 'linked' | 'binary' | 'missing';
+  bytes: number;
+  reason?: string | null;
+}
+
 export interface PromptResult {
   stopReason: unknown;
+  attachments?: MentionAttachment[];
+  usage?: TurnUsage;
+}
+
+export interface TokenCounts {
+  input: number;
+  cachedInput: number;
+  output: number;
+  reasoning: number;
+}
+
+export interface TurnUsage {
+  model?: string | null;
+  tokens: TokenCounts;
+  costUsd: number;
+}
+
+export interface UsageTotals {
+  tokens: TokenCounts;
+  costUsd: number;
+  turns: number;
+  unpricedTokens: number;
+}
+
+export interface ModelPrice {
+  inputPerMtok: number;
+  cachedInputPerMtok?: number | null;
+  outputPerMtok: number;
+}
+
+export interface UsageBudget {
+  dailyUsd?: number | null;
+  sessionUsd?: number | null;
+}
+
+export interface UsageSettings {
+  prices: Record<string, ModelPrice>;
+  budget: UsageBudget;
+}
+
+export interface BudgetAlert {
+  scope: 'daily' | 'session';
+  sessionId?: string | null;
+  limitUsd: number;
+  spentUsd: number;
+}
+
+export interface SessionUsage {
+  sessionId: string;
+  model?: string | null;
+  totals: UsageTotals;
+  updatedAt: number;
+}
+
+export interface DayUsage {
+  date: string;
+  totals: UsageTotals;
+}
+
+export interface UsageReport {
+  today: DayUsage;
+  days: DayUsage[];
+  sessions: SessionUsage[];
+  budget: UsageBudget;
+  alerts: BudgetAlert[];
+  unpricedModels: string[];
+}
+
+export interface UsageEvent {
+  sessionId: string;
+  model?: string | null;
+  delta: TokenCounts;
+  turn: TurnUsage;
+  session: UsageTotals;
+  today: UsageTotals;
+  contextWindow?: number | null;
 }
 
 export interface CodexCliConfigInfo {
@@ -80,3 +188,195 @@ export interface TokenUsageEvent {
   contextWindow?: number | null;
   percentRemaining?: number | null;
 }
+
+export interface StoredSessionSummary {
+  sessionId: string;
+  cwd: string;
+  title?: string | null;
+  createdAt: number;
+  updatedAt: number;
+  promptCount: number;
+  lastStopReason?: string | null;
+  profile?: string | null;
+  worktree?: SessionWorktree | null;
+}
+
+export interface SessionWorktree {
+  repoRoot: string;
+  path: string;
+  branch: string;
+  base: string;
+  merged?: string | null;
+}
+
+export interface NewWorktreeSessionResult extends NewSessionResult {
+  worktree: SessionWorktree;
+}
+
+export interface WorktreeSessionInfo extends StoredSessionSummary {
+  exists: boolean;
+  dirty: boolean;
+  ahead: number;
+}
+
+export interface WorktreeCleanup {
+  branchDeleted: boolean;
+}
+
+export type JournalEntry = { ts: number } & (
+  | { kind: 'started'; cwd: string; profile?: string }
+  | { kind: 'resumed'; cwd: string; profile?: string }
+  | { kind: 'prompt'; content: unknown[] }
+  | { kind: 'update'; update: Record<string, unknown> }
+  | { kind: 'approval'; toolCallId: string; optionId?: string | null; optionKind?: string | null }
+  | { kind: 'stop'; stopReason: string }
+  | { kind: 'worktree'; worktree: SessionWorktree | null }
+);
+
+export interface SessionTranscript {
+  summary: StoredSessionSummary;
+  entries: JournalEntry[];
+}
+
+export interface SessionSearchHit {
+  sessionId: string;
+  title?: string | null;
+  source: 'prompt' | 'message' | 'toolCall';
+  snippet: string;
+  ts: number;
+}
+
+export type AcpLaunchMode = 'npx' | 'sidecar' | 'mock';
+
+/** Placeholder the backend returns for secret values; saving it back keeps the stored value. */
+export const SECRET_PLACEHOLDER = '********';
+
+export interface EnvProfile {
+  /** Empty for a new profile; the backend assigns one. */
+  id: string;
+  name: string;
+  env: Record<string, string>;
+  secretKeys: string[];
+  codexHome?: string | null;
+  mode?: AcpLaunchMode | null;
+  npxSpec?: string | null;
+}
+
+export type ApprovalRuleAction = 'allow' | 'reject' | 'ask';
+
+export interface ApprovalRule {
+  id: string;
+  name?: string | null;
+  enabled: boolean;
+  toolKinds: string[];
+  titleRegex?: string | null;
+  commandRegex?: string | null;
+  pathGlobs: string[];
+  sessionId?: string | null;
+  action: ApprovalRuleAction;
+}
+
+export interface ApprovalAuditEntry {
+  ts: number;
+  sessionId: string;
+  toolCallId: string;
+  ruleId: string;
+  action: ApprovalRuleAction;
+  optionId: string;
+  optionKind: string;
+  toolKind?: string | null;
+  title?: string | null;
+  command?: string | null;
+  paths: string[];
+}
+
+export interface PendingApproval {
+  sessionId: string;
+  requestId: string;
+  toolCall: ToolCall;
+  options: PermissionOption[];
+  createdAt: number;
+  expiresAt?: number | null;
+}
+
+export interface ApprovalTimeout {
+  seconds: number;
+  defaultDecision?: ApprovalDecision | null;
+}
+
+export interface ApprovalClearedEvent {
+  sessionId: string;
+  requestId: string;
+  reason: 'timeout' | 'cancelled' | 'disconnected';
+  optionId?: string | null;
+}
+
+export interface ConnectionStateEvent {
+  state: 'connecting' | 'ready' | 'crashed' | 'restarting';
+  serverId?: string | null;
+  attempt?: number | null;
+  error?: string | null;
+  sessions: string[];
+}
+
+export interface AgentLogLine {
+  serverId?: string | null;
+  connectionId: number;
+  ts: number;
+  level?: 'error' | 'warn' | 'info' | 'debug' | 'trace' | null;
+  target?: string | null;
+  message: string;
+}
+
+export interface CoalesceConfig {
+  windowMs: number;
+  maxBytes: number;
+}
+
+export type TraceFormat = 'chrome' | 'csv';
+
+export interface ToolSpan {
+  toolCallId: string;
+  title?: string | null;
+  startMs: number;
+  durationMs?: number | null;
+  status?: string | null;
+}
+
+export interface ApprovalSpan {
+  toolCallId: string;
+  startMs: number;
+  waitMs?: number | null;
+  auto: boolean;
+  optionId?: string | null;
+}
+
+export interface TurnSummary {
+  sessionId: string;
+  index: number;
+  startedAtMs: number;
+  durationMs?: number | null;
+  stopReason?: unknown;
+  error?: string | null;
+  timeToFirstTokenMs?: number | null;
+  messageChunks: number;
+  thoughtChunks: number;
+  tools: ToolSpan[];
+  approvals: ApprovalSpan[];
+  approvalWaitMs: number;
+}
+
+export interface DebugTraceExport {
+  format: TraceFormat;
+  content: string;
+  path?: string | null;
+  events: number;
+  turns: TurnSummary[];
+}
+
+export interface ReplaySummary {
+  frames: number;
+  updates: number;
+  sessionIds: string[];
+  durationMs: number;
+}
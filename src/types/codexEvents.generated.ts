@@ -0,0 +1,989 @@
+// Generated from src-tauri/src/codex/events.rs. Do not edit by hand.
+// Regenerate with `CODEX_UPDATE_EVENT_TYPES=1 cargo test generated_event_types`
+// in src-tauri.
+
+export const CODEX_EVENT_SCHEMA_VERSION = 1;
+
+export type Versioned<T> = T & { schemaVersion: number };
+
+export interface CodexEventMap {
+  'codex:message': Versioned<TextChunkPayload>;
+  'codex:user-message': Versioned<TextChunkPayload>;
+  'codex:thought': Versioned<TextChunkPayload>;
+  'codex:tool-call': Versioned<ToolCallPayload>;
+  'codex:tool-call-update': Versioned<ToolCallUpdatePayload>;
+  'codex:approval-request': Versioned<ApprovalRequestPayload>;
+  'codex:approval-cleared': Versioned<ApprovalClearedPayload>;
+  'codex:approval-auto-decided': Versioned<ApprovalAuditEntry>;
+  'codex:plan': Versioned<PlanPayload>;
+  'codex:available-commands': Versioned<AvailableCommandsPayload>;
+  'codex:current-mode': Versioned<CurrentModePayload>;
+  'codex:config-option-update': Versioned<ConfigOptionPayload>;
+  'codex:prompt-attachments': Versioned<PromptAttachmentsPayload>;
+  'codex:prompt-queue': Versioned<PromptQueuePayload>;
+  'codex:turn-complete': Versioned<TurnCompletePayload>;
+  'codex:connection-state': Versioned<ConnectionStatePayload>;
+  'codex:agent-log': Versioned<AgentLogLine>;
+  'codex:error': Versioned<ErrorPayload>;
+  'codex:debug': Versioned<DebugPayload>;
+  'codex:token-usage': Versioned<TokenUsagePayload>;
+  'codex:usage': Versioned<UsageEvent>;
+  'codex:usage-alert': Versioned<BudgetAlert>;
+  'codex:file-write': Versioned<FileWritePayload>;
+  'codex:terminal-attached': Versioned<TerminalAttachedPayload>;
+  'codex:checkpoint': Versioned<GitCheckpoint>;
+  'git:progress': Versioned<GitProgressEvent>;
+}
+
+export type CodexEventName = keyof CodexEventMap;
+
+/** One stderr line from an agent process. */
+export interface AgentLogLine {
+  /** Connection that produced the line; changes when the agent is restarted. */
+  connectionId: number;
+  /** Parsed level, if the line looked like a log record. */
+  level: LogLevel | null;
+  /** Message text with the level/target prefix and ANSI colors removed. */
+  message: string;
+  /** Remote server the agent runs on, or `None` for the local agent. */
+  serverId: string | null;
+  /** Parsed module target, if present. */
+  target: string | null;
+  /** Capture time in milliseconds since the Unix epoch. */
+  ts: number;
+}
+
+/** Optional annotations for the client. The client can use annotations to inform how objects are used or displayed */
+export interface Annotations {
+  /**
+   * The _meta property is reserved by ACP to allow clients and agents to attach additional
+   * metadata to their interactions. Implementations MUST NOT make assumptions about values at
+   * these keys.
+   *
+   * See protocol docs: [Extensibility](https://agentclientprotocol.com/protocol/extensibility)
+   */
+  _meta?: Record<string, unknown> | null;
+  audience?: Role[] | null;
+  lastModified?: string | null;
+  priority?: number | null;
+}
+
+/** One line of the approval audit log. */
+export interface ApprovalAuditEntry {
+  /** Action taken. */
+  action: RuleAction;
+  /** Command line, for execute tools. */
+  command: string | null;
+  /** Option selected. */
+  optionId: string;
+  /** Kind of the selected option. */
+  optionKind: PermissionOptionKind;
+  /** Paths touched by the tool call. */
+  paths: string[];
+  /** Rule that decided. */
+  ruleId: string;
+  /** ACP session id. */
+  sessionId: string;
+  /** Tool call title. */
+  title: string | null;
+  /** Tool call id. */
+  toolCallId: string;
+  /** Tool kind of the request. */
+  toolKind: ToolKind | null;
+  /** Milliseconds since the Unix epoch. */
+  ts: number;
+}
+
+/** A pending approval resolved without the user. */
+export interface ApprovalClearedPayload {
+  /** Option selected on the user's behalf, if any. */
+  optionId: string | null;
+  /** `timeout`, `cancelled` or `disconnected`. */
+  reason: string;
+  requestId: string;
+  sessionId: string;
+}
+
+/** A permission request waiting for the user. */
+export interface ApprovalRequestPayload {
+  options: PermissionOption[];
+  /** Tool call id; pass it back to `codex_approve`. */
+  requestId: string;
+  sessionId: string;
+  toolCall: ToolCallUpdate;
+}
+
+/** How a mentioned file was attached. */
+export type AttachmentStatus = 'embedded' | 'linked' | 'binary' | 'missing';
+
+/** Audio provided to or from an LLM. */
+export interface AudioContent {
+  /**
+   * The _meta property is reserved by ACP to allow clients and agents to attach additional
+   * metadata to their interactions. Implementations MUST NOT make assumptions about values at
+   * these keys.
+   *
+   * See protocol docs: [Extensibility](https://agentclientprotocol.com/protocol/extensibility)
+   */
+  _meta?: Record<string, unknown> | null;
+  annotations?: Annotations | null;
+  data: string;
+  mimeType: string;
+}
+
+/** Information about a command. */
+export interface AvailableCommand {
+  /**
+   * The _meta property is reserved by ACP to allow clients and agents to attach additional
+   * metadata to their interactions. Implementations MUST NOT make assumptions about values at
+   * these keys.
+   *
+   * See protocol docs: [Extensibility](https://agentclientprotocol.com/protocol/extensibility)
+   */
+  _meta?: Record<string, unknown> | null;
+  /** Human-readable description of what the command does. */
+  description: string;
+  /** Input for the command if required */
+  input: AvailableCommandInput | null;
+  /** Command name (e.g., `create_plan`, `research_codebase`). */
+  name: string;
+}
+
+/** The input specification for a command. */
+export type AvailableCommandInput = UnstructuredCommandInput;
+
+/** Slash commands the agent offers. */
+export interface AvailableCommandsPayload {
+  replay?: boolean;
+  sessionId: string;
+  update: AvailableCommandsUpdate;
+}
+
+/** Available commands are ready or have changed */
+export interface AvailableCommandsUpdate {
+  /**
+   * The _meta property is reserved by ACP to allow clients and agents to attach additional
+   * metadata to their interactions. Implementations MUST NOT make assumptions about values at
+   * these keys.
+   *
+   * See protocol docs: [Extensibility](https://agentclientprotocol.com/protocol/extensibility)
+   */
+  _meta?: Record<string, unknown> | null;
+  /** Commands the agent can execute */
+  availableCommands: AvailableCommand[];
+}
+
+/** Binary resource contents. */
+export interface BlobResourceContents {
+  /**
+   * The _meta property is reserved by ACP to allow clients and agents to attach additional
+   * metadata to their interactions. Implementations MUST NOT make assumptions about values at
+   * these keys.
+   *
+   * See protocol docs: [Extensibility](https://agentclientprotocol.com/protocol/extensibility)
+   */
+  _meta?: Record<string, unknown> | null;
+  blob: string;
+  mimeType?: string | null;
+  uri: string;
+}
+
+/** A budget that has been reached. */
+export interface BudgetAlert {
+  limitUsd: number;
+  scope: BudgetScope;
+  sessionId: string | null;
+  spentUsd: number;
+}
+
+export type BudgetScope = 'daily' | 'session';
+
+/** Changed session configuration options. */
+export interface ConfigOptionPayload {
+  replay?: boolean;
+  sessionId: string;
+  update: ConfigOptionUpdate;
+}
+
+/**
+ * **UNSTABLE**
+ *
+ * This capability is not part of the spec yet, and may be removed or changed at any point.
+ *
+ * Session configuration options have been updated.
+ */
+export interface ConfigOptionUpdate {
+  /**
+   * The _meta property is reserved by ACP to allow clients and agents to attach additional
+   * metadata to their interactions. Implementations MUST NOT make assumptions about values at
+   * these keys.
+   *
+   * See protocol docs: [Extensibility](https://agentclientprotocol.com/protocol/extensibility)
+   */
+  _meta?: Record<string, unknown> | null;
+  /** The full set of configuration options and their current values. */
+  configOptions: SessionConfigOption[];
+}
+
+/** Lifecycle state of an agent connection. */
+export type ConnectionState = 'connecting' | 'ready' | 'crashed' | 'restarting';
+
+/** An agent connection lifecycle change. */
+export interface ConnectionStatePayload {
+  /** Restart attempt, while restarting. */
+  attempt: number | null;
+  error: string | null;
+  /** Remote server of the connection, or `None` for the local agent. */
+  serverId: string | null;
+  /** Sessions affected by a crash, or reloaded after a restart. */
+  sessions: string[];
+  state: ConnectionState;
+}
+
+/** Standard content block (text, images, resources). */
+export interface Content {
+  /**
+   * The _meta property is reserved by ACP to allow clients and agents to attach additional
+   * metadata to their interactions. Implementations MUST NOT make assumptions about values at
+   * these keys.
+   *
+   * See protocol docs: [Extensibility](https://agentclientprotocol.com/protocol/extensibility)
+   */
+  _meta?: Record<string, unknown> | null;
+  /** The actual content block. */
+  content: ContentBlock;
+}
+
+/**
+ * Content blocks represent displayable information in the Agent Client Protocol.
+ *
+ * They provide a structured way to handle various types of user-facing content—whether
+ * it's text from language models, images for analysis, or embedded resources for context.
+ *
+ * Content blocks appear in:
+ * - User prompts sent via `session/prompt`
+ * - Language model output streamed through `session/update` notifications
+ * - Progress updates and results from tool calls
+ *
+ * This structure is compatible with the Model Context Protocol (MCP), enabling
+ * agents to seamlessly forward content from MCP tool outputs without transformation.
+ *
+ * See protocol docs: [Content](https://agentclientprotocol.com/protocol/content)
+ */
+export type ContentBlock = TextContent & { type: 'text'; } | ImageContent & { type: 'image'; } | AudioContent & { type: 'audio'; } | ResourceLink & { type: 'resource_link'; } | EmbeddedResource & { type: 'resource'; };
+
+/** A session mode change. */
+export interface CurrentModePayload {
+  replay?: boolean;
+  sessionId: string;
+  update: CurrentModeUpdate;
+}
+
+/**
+ * The current mode of the session has changed
+ *
+ * See protocol docs: [Session Modes](https://agentclientprotocol.com/protocol/session-modes)
+ */
+export interface CurrentModeUpdate {
+  /**
+   * The _meta property is reserved by ACP to allow clients and agents to attach additional
+   * metadata to their interactions. Implementations MUST NOT make assumptions about values at
+   * these keys.
+   *
+   * See protocol docs: [Extensibility](https://agentclientprotocol.com/protocol/extensibility)
+   */
+  _meta?: Record<string, unknown> | null;
+  /** The ID of the current mode */
+  currentModeId: SessionModeId;
+}
+
+/** Debug timing of a backend event. */
+export interface DebugPayload {
+  dtMs: number;
+  extra: unknown;
+  label: string;
+  sessionId: string | null;
+  sinceLastEventMs: number | null;
+  sincePromptMs: number | null;
+  tsMs: number;
+}
+
+/**
+ * A diff representing file modifications.
+ *
+ * Shows changes to files in a format suitable for display in the client UI.
+ *
+ * See protocol docs: [Content](https://agentclientprotocol.com/protocol/tool-calls#content)
+ */
+export interface Diff {
+  /**
+   * The _meta property is reserved by ACP to allow clients and agents to attach additional
+   * metadata to their interactions. Implementations MUST NOT make assumptions about values at
+   * these keys.
+   *
+   * See protocol docs: [Extensibility](https://agentclientprotocol.com/protocol/extensibility)
+   */
+  _meta?: Record<string, unknown> | null;
+  /** The new content after modification. */
+  newText: string;
+  /** The original content (None for new files). */
+  oldText: string | null;
+  /** The file path being modified. */
+  path: string;
+}
+
+/** The contents of a resource, embedded into a prompt or tool call result. */
+export interface EmbeddedResource {
+  /**
+   * The _meta property is reserved by ACP to allow clients and agents to attach additional
+   * metadata to their interactions. Implementations MUST NOT make assumptions about values at
+   * these keys.
+   *
+   * See protocol docs: [Extensibility](https://agentclientprotocol.com/protocol/extensibility)
+   */
+  _meta?: Record<string, unknown> | null;
+  annotations?: Annotations | null;
+  resource: EmbeddedResourceResource;
+}
+
+/** Resource content that can be embedded in a message. */
+export type EmbeddedResourceResource = TextResourceContents | BlobResourceContents;
+
+/** A backend error not tied to a request. */
+export interface ErrorPayload {
+  error: string;
+}
+
+/** A file written by the agent through the client. */
+export interface FileWritePayload {
+  bytes: number;
+  created: boolean;
+  path: string;
+  sessionId: string;
+}
+
+/** Snapshot of a working tree taken before a session turn. */
+export interface GitCheckpoint {
+  commit: string;
+  createdAt: string;
+  /** HEAD when the snapshot was taken; restoring does not move HEAD back. */
+  head: string | null;
+  label: string;
+  /** Saved before restoring a checkpoint rather than before a prompt turn. */
+  restore: boolean;
+  sessionId: string;
+  /**
+   * Prompt turn the snapshot was taken before, or the number of a pre-restore
+   * snapshot.
+   */
+  turn: number;
+}
+
+/** Network operation reporting progress. */
+export type GitNetworkOperation = 'fetch' | 'pull' | 'push';
+
+/** Payload of the `git:progress` event. */
+export interface GitProgressEvent {
+  cwd: string;
+  message: string;
+  operation: GitNetworkOperation;
+  percent: number | null;
+  phase: string;
+}
+
+/** An image provided to or from an LLM. */
+export interface ImageContent {
+  /**
+   * The _meta property is reserved by ACP to allow clients and agents to attach additional
+   * metadata to their interactions. Implementations MUST NOT make assumptions about values at
+   * these keys.
+   *
+   * See protocol docs: [Extensibility](https://agentclientprotocol.com/protocol/extensibility)
+   */
+  _meta?: Record<string, unknown> | null;
+  annotations?: Annotations | null;
+  data: string;
+  mimeType: string;
+  uri?: string | null;
+}
+
+/** Severity parsed from an agent log line. */
+export type LogLevel = 'error' | 'warn' | 'info' | 'debug' | 'trace';
+
+/** Result of resolving one mention, reported back to the UI. */
+export interface MentionAttachment {
+  /** Embedded content size in bytes. */
+  bytes: number;
+  /** Inclusive 1-based line range, if one was requested. */
+  lines: [number, number] | null;
+  /** Resolved agent-side path. */
+  path: string;
+  /** Why the file was linked or skipped instead of embedded. */
+  reason: string | null;
+  /** How the file was attached. */
+  status: AttachmentStatus;
+  /** Token as typed, including the leading `@`. */
+  token: string;
+}
+
+/** An option presented to the user when requesting permission. */
+export interface PermissionOption {
+  /**
+   * The _meta property is reserved by ACP to allow clients and agents to attach additional
+   * metadata to their interactions. Implementations MUST NOT make assumptions about values at
+   * these keys.
+   *
+   * See protocol docs: [Extensibility](https://agentclientprotocol.com/protocol/extensibility)
+   */
+  _meta?: Record<string, unknown> | null;
+  /** Hint about the nature of this permission option. */
+  kind: PermissionOptionKind;
+  /** Human-readable label to display to the user. */
+  name: string;
+  /** Unique identifier for this permission option. */
+  optionId: PermissionOptionId;
+}
+
+/** Unique identifier for a permission option. */
+export type PermissionOptionId = string;
+
+/**
+ * The type of permission option being presented to the user.
+ *
+ * Helps clients choose appropriate icons and UI treatment.
+ */
+export type PermissionOptionKind = 'allow_once' | 'allow_always' | 'reject_once' | 'reject_always';
+
+/**
+ * An execution plan for accomplishing complex tasks.
+ *
+ * Plans consist of multiple entries representing individual tasks or goals.
+ * Agents report plans to clients to provide visibility into their execution strategy.
+ * Plans can evolve during execution as the agent discovers new requirements or completes tasks.
+ *
+ * See protocol docs: [Agent Plan](https://agentclientprotocol.com/protocol/agent-plan)
+ */
+export interface Plan {
+  /**
+   * The _meta property is reserved by ACP to allow clients and agents to attach additional
+   * metadata to their interactions. Implementations MUST NOT make assumptions about values at
+   * these keys.
+   *
+   * See protocol docs: [Extensibility](https://agentclientprotocol.com/protocol/extensibility)
+   */
+  _meta?: Record<string, unknown> | null;
+  /**
+   * The list of tasks to be accomplished.
+   *
+   * When updating a plan, the agent must send a complete list of all entries
+   * with their current status. The client replaces the entire plan with each update.
+   */
+  entries: PlanEntry[];
+}
+
+/**
+ * A single entry in the execution plan.
+ *
+ * Represents a task or goal that the assistant intends to accomplish
+ * as part of fulfilling the user's request.
+ * See protocol docs: [Plan Entries](https://agentclientprotocol.com/protocol/agent-plan#plan-entries)
+ */
+export interface PlanEntry {
+  /**
+   * The _meta property is reserved by ACP to allow clients and agents to attach additional
+   * metadata to their interactions. Implementations MUST NOT make assumptions about values at
+   * these keys.
+   *
+   * See protocol docs: [Extensibility](https://agentclientprotocol.com/protocol/extensibility)
+   */
+  _meta?: Record<string, unknown> | null;
+  /** Human-readable description of what this task aims to accomplish. */
+  content: string;
+  /**
+   * The relative importance of this task.
+   * Used to indicate which tasks are most critical to the overall goal.
+   */
+  priority: PlanEntryPriority;
+  /** Current execution status of this task. */
+  status: PlanEntryStatus;
+}
+
+/**
+ * Priority levels for plan entries.
+ *
+ * Used to indicate the relative importance or urgency of different
+ * tasks in the execution plan.
+ * See protocol docs: [Plan Entries](https://agentclientprotocol.com/protocol/agent-plan#plan-entries)
+ */
+export type PlanEntryPriority = 'high' | 'medium' | 'low';
+
+/**
+ * Status of a plan entry in the execution flow.
+ *
+ * Tracks the lifecycle of each task from planning through completion.
+ * See protocol docs: [Plan Entries](https://agentclientprotocol.com/protocol/agent-plan#plan-entries)
+ */
+export type PlanEntryStatus = 'pending' | 'in_progress' | 'completed';
+
+/** The agent's current plan. */
+export interface PlanPayload {
+  plan: Plan;
+  replay?: boolean;
+  sessionId: string;
+}
+
+/** Files attached to a prompt from its `@` mentions. */
+export interface PromptAttachmentsPayload {
+  attachments: MentionAttachment[];
+  sessionId: string;
+}
+
+/** Running turn and queued prompts of a session. */
+export interface PromptQueuePayload {
+  queue: QueuedPromptInfo[];
+  running: boolean;
+  sessionId: string;
+}
+
+/** Queue entry as reported to the frontend. */
+export interface QueuedPromptInfo {
+  /** Queue entry identifier. */
+  id: string;
+  /** 1-based position in the session queue. */
+  position: number;
+  /** Beginning of the prompt text. */
+  preview: string;
+  /** Enqueue time in milliseconds since the Unix epoch. */
+  queuedAt: number;
+  /** Session the prompt belongs to. */
+  sessionId: string;
+}
+
+/** A resource that the server is capable of reading, included in a prompt or tool call result. */
+export interface ResourceLink {
+  /**
+   * The _meta property is reserved by ACP to allow clients and agents to attach additional
+   * metadata to their interactions. Implementations MUST NOT make assumptions about values at
+   * these keys.
+   *
+   * See protocol docs: [Extensibility](https://agentclientprotocol.com/protocol/extensibility)
+   */
+  _meta?: Record<string, unknown> | null;
+  annotations?: Annotations | null;
+  description?: string | null;
+  mimeType?: string | null;
+  name: string;
+  size?: number | null;
+  title?: string | null;
+  uri: string;
+}
+
+/** The sender or recipient of messages and data in a conversation. */
+export type Role = 'assistant' | 'user';
+
+/** What to do with a permission request matched by a rule. */
+export type RuleAction = 'allow' | 'reject' | 'ask';
+
+/**
+ * **UNSTABLE**
+ *
+ * This capability is not part of the spec yet, and may be removed or changed at any point.
+ *
+ * Unique identifier for a session configuration option value group.
+ */
+export type SessionConfigGroupId = string;
+
+/**
+ * **UNSTABLE**
+ *
+ * This capability is not part of the spec yet, and may be removed or changed at any point.
+ *
+ * Unique identifier for a session configuration option.
+ */
+export type SessionConfigId = string;
+
+/**
+ * **UNSTABLE**
+ *
+ * This capability is not part of the spec yet, and may be removed or changed at any point.
+ *
+ * A session configuration option selector and its current state.
+ */
+export type SessionConfigOption = SessionConfigSelect & { type: 'select'; } & ({ _meta?: Record<string, unknown> | null; category?: SessionConfigOptionCategory | null; description?: string | null; id: SessionConfigId; name: string; });
+
+/**
+ * **UNSTABLE**
+ *
+ * This capability is not part of the spec yet, and may be removed or changed at any point.
+ *
+ * Semantic category for a session configuration option.
+ *
+ * This is intended to help Clients distinguish broadly common selectors (e.g. model selector vs
+ * session mode selector vs thought/reasoning level) for UX purposes (keyboard shortcuts, icons,
+ * placement). It MUST NOT be required for correctness. Clients MUST handle missing or unknown
+ * categories gracefully (treat as `Other`).
+ */
+export type SessionConfigOptionCategory = 'mode' | 'model' | 'thought_level' | 'other';
+
+/**
+ * **UNSTABLE**
+ *
+ * This capability is not part of the spec yet, and may be removed or changed at any point.
+ *
+ * A single-value selector (dropdown) session configuration option payload.
+ */
+export interface SessionConfigSelect {
+  /** The currently selected value. */
+  currentValue: SessionConfigValueId;
+  /** The set of selectable options. */
+  options: SessionConfigSelectOptions;
+}
+
+/**
+ * **UNSTABLE**
+ *
+ * This capability is not part of the spec yet, and may be removed or changed at any point.
+ *
+ * A group of possible values for a session configuration option.
+ */
+export interface SessionConfigSelectGroup {
+  /**
+   * The _meta property is reserved by ACP to allow clients and agents to attach additional
+   * metadata to their interactions. Implementations MUST NOT make assumptions about values at
+   * these keys.
+   *
+   * See protocol docs: [Extensibility](https://agentclientprotocol.com/protocol/extensibility)
+   */
+  _meta?: Record<string, unknown> | null;
+  /** Unique identifier for this group. */
+  group: SessionConfigGroupId;
+  /** Human-readable label for this group. */
+  name: string;
+  /** The set of option values in this group. */
+  options: SessionConfigSelectOption[];
+}
+
+/**
+ * **UNSTABLE**
+ *
+ * This capability is not part of the spec yet, and may be removed or changed at any point.
+ *
+ * A possible value for a session configuration option.
+ */
+export interface SessionConfigSelectOption {
+  /**
+   * The _meta property is reserved by ACP to allow clients and agents to attach additional
+   * metadata to their interactions. Implementations MUST NOT make assumptions about values at
+   * these keys.
+   *
+   * See protocol docs: [Extensibility](https://agentclientprotocol.com/protocol/extensibility)
+   */
+  _meta?: Record<string, unknown> | null;
+  /** Optional description for this option value. */
+  description?: string | null;
+  /** Human-readable label for this option value. */
+  name: string;
+  /** Unique identifier for this option value. */
+  value: SessionConfigValueId;
+}
+
+/**
+ * **UNSTABLE**
+ *
+ * This capability is not part of the spec yet, and may be removed or changed at any point.
+ *
+ * Possible values for a session configuration option.
+ */
+export type SessionConfigSelectOptions = SessionConfigSelectOption[] | SessionConfigSelectGroup[];
+
+/**
+ * **UNSTABLE**
+ *
+ * This capability is not part of the spec yet, and may be removed or changed at any point.
+ *
+ * Unique identifier for a session configuration option value.
+ */
+export type SessionConfigValueId = string;
+
+/** Unique identifier for a Session Mode. */
+export type SessionModeId = string;
+
+/**
+ * Reasons why an agent stops processing a prompt turn.
+ *
+ * See protocol docs: [Stop Reasons](https://agentclientprotocol.com/protocol/prompt-turn#stop-reasons)
+ */
+export type StopReason = 'end_turn' | 'max_tokens' | 'max_turn_requests' | 'refusal' | 'cancelled';
+
+/**
+ * Embed a terminal created with `terminal/create` by its id.
+ *
+ * The terminal must be added before calling `terminal/release`.
+ *
+ * See protocol docs: [Terminal](https://agentclientprotocol.com/protocol/terminals)
+ */
+export interface Terminal {
+  /**
+   * The _meta property is reserved by ACP to allow clients and agents to attach additional
+   * metadata to their interactions. Implementations MUST NOT make assumptions about values at
+   * these keys.
+   *
+   * See protocol docs: [Extensibility](https://agentclientprotocol.com/protocol/extensibility)
+   */
+  _meta?: Record<string, unknown> | null;
+  terminalId: string;
+}
+
+/** An agent terminal embedded in a tool call. */
+export interface TerminalAttachedPayload {
+  sessionId: string;
+  terminalId: string;
+  toolCallId: string;
+}
+
+/** A message, thought or replayed user message chunk. */
+export interface TextChunkPayload {
+  /** Set when the chunk is replayed from session history. */
+  replay?: boolean;
+  sessionId: string;
+  text: string;
+}
+
+/** Text provided to or from an LLM. */
+export interface TextContent {
+  /**
+   * The _meta property is reserved by ACP to allow clients and agents to attach additional
+   * metadata to their interactions. Implementations MUST NOT make assumptions about values at
+   * these keys.
+   *
+   * See protocol docs: [Extensibility](https://agentclientprotocol.com/protocol/extensibility)
+   */
+  _meta?: Record<string, unknown> | null;
+  annotations?: Annotations | null;
+  text: string;
+}
+
+/** Text-based resource contents. */
+export interface TextResourceContents {
+  /**
+   * The _meta property is reserved by ACP to allow clients and agents to attach additional
+   * metadata to their interactions. Implementations MUST NOT make assumptions about values at
+   * these keys.
+   *
+   * See protocol docs: [Extensibility](https://agentclientprotocol.com/protocol/extensibility)
+   */
+  _meta?: Record<string, unknown> | null;
+  mimeType?: string | null;
+  text: string;
+  uri: string;
+}
+
+/**
+ * Token counts in the shape Codex reports them.
+ *
+ * `cached_input` is the part of `input` served from the prompt cache and
+ * `reasoning` the part of `output` spent on reasoning.
+ */
+export interface TokenCounts {
+  cachedInput: number;
+  input: number;
+  output: number;
+  reasoning: number;
+}
+
+/** Parameters of a `codex/token-usage` notification, passed on as the agent sent them. */
+export interface TokenUsagePayload {
+  usage: unknown;
+}
+
+/**
+ * Represents a tool call that the language model has requested.
+ *
+ * Tool calls are actions that the agent executes on behalf of the language model,
+ * such as reading files, executing code, or fetching data from external sources.
+ *
+ * See protocol docs: [Tool Calls](https://agentclientprotocol.com/protocol/tool-calls)
+ */
+export interface ToolCall {
+  /**
+   * The _meta property is reserved by ACP to allow clients and agents to attach additional
+   * metadata to their interactions. Implementations MUST NOT make assumptions about values at
+   * these keys.
+   *
+   * See protocol docs: [Extensibility](https://agentclientprotocol.com/protocol/extensibility)
+   */
+  _meta?: Record<string, unknown> | null;
+  /** Content produced by the tool call. */
+  content?: ToolCallContent[];
+  /**
+   * The category of tool being invoked.
+   * Helps clients choose appropriate icons and UI treatment.
+   */
+  kind?: ToolKind;
+  /**
+   * File locations affected by this tool call.
+   * Enables "follow-along" features in clients.
+   */
+  locations?: ToolCallLocation[];
+  /** Raw input parameters sent to the tool. */
+  rawInput?: unknown;
+  /** Raw output returned by the tool. */
+  rawOutput?: unknown;
+  /** Current execution status of the tool call. */
+  status?: ToolCallStatus;
+  /** Human-readable title describing what the tool is doing. */
+  title: string;
+  /** Unique identifier for this tool call within the session. */
+  toolCallId: ToolCallId;
+}
+
+/**
+ * Content produced by a tool call.
+ *
+ * Tool calls can produce different types of content including
+ * standard content blocks (text, images) or file diffs.
+ *
+ * See protocol docs: [Content](https://agentclientprotocol.com/protocol/tool-calls#content)
+ */
+export type ToolCallContent = Content & { type: 'content'; } | Diff & { type: 'diff'; } | Terminal & { type: 'terminal'; };
+
+/** Unique identifier for a tool call within a session. */
+export type ToolCallId = string;
+
+/**
+ * A file location being accessed or modified by a tool.
+ *
+ * Enables clients to implement "follow-along" features that track
+ * which files the agent is working with in real-time.
+ *
+ * See protocol docs: [Following the Agent](https://agentclientprotocol.com/protocol/tool-calls#following-the-agent)
+ */
+export interface ToolCallLocation {
+  /**
+   * The _meta property is reserved by ACP to allow clients and agents to attach additional
+   * metadata to their interactions. Implementations MUST NOT make assumptions about values at
+   * these keys.
+   *
+   * See protocol docs: [Extensibility](https://agentclientprotocol.com/protocol/extensibility)
+   */
+  _meta?: Record<string, unknown> | null;
+  /** Optional line number within the file. */
+  line?: number | null;
+  /** The file path being accessed or modified. */
+  path: string;
+}
+
+/** A new tool call. */
+export interface ToolCallPayload {
+  replay?: boolean;
+  sessionId: string;
+  toolCall: ToolCall;
+}
+
+/**
+ * Execution status of a tool call.
+ *
+ * Tool calls progress through different statuses during their lifecycle.
+ *
+ * See protocol docs: [Status](https://agentclientprotocol.com/protocol/tool-calls#status)
+ */
+export type ToolCallStatus = 'pending' | 'in_progress' | 'completed' | 'failed';
+
+/**
+ * An update to an existing tool call.
+ *
+ * Used to report progress and results as tools execute. All fields except
+ * the tool call ID are optional - only changed fields need to be included.
+ *
+ * See protocol docs: [Updating](https://agentclientprotocol.com/protocol/tool-calls#updating)
+ */
+export interface ToolCallUpdate {
+  /**
+   * The _meta property is reserved by ACP to allow clients and agents to attach additional
+   * metadata to their interactions. Implementations MUST NOT make assumptions about values at
+   * these keys.
+   *
+   * See protocol docs: [Extensibility](https://agentclientprotocol.com/protocol/extensibility)
+   */
+  _meta?: Record<string, unknown> | null;
+  /** Replace the content collection. */
+  content?: ToolCallContent[] | null;
+  /** Update the tool kind. */
+  kind?: ToolKind | null;
+  /** Replace the locations collection. */
+  locations?: ToolCallLocation[] | null;
+  /** Update the raw input. */
+  rawInput?: unknown;
+  /** Update the raw output. */
+  rawOutput?: unknown;
+  /** Update the execution status. */
+  status?: ToolCallStatus | null;
+  /** Update the human-readable title. */
+  title?: string | null;
+  /** The ID of the tool call being updated. */
+  toolCallId: ToolCallId;
+}
+
+/** An update of an earlier tool call. */
+export interface ToolCallUpdatePayload {
+  replay?: boolean;
+  sessionId: string;
+  update: ToolCallUpdate;
+}
+
+/**
+ * Categories of tools that can be invoked.
+ *
+ * Tool kinds help clients choose appropriate icons and optimize how they
+ * display tool execution progress.
+ *
+ * See protocol docs: [Creating](https://agentclientprotocol.com/protocol/tool-calls#creating)
+ */
+export type ToolKind = 'read' | 'edit' | 'delete' | 'move' | 'search' | 'execute' | 'think' | 'fetch' | 'switch_mode' | 'other';
+
+/** A finished prompt turn. */
+export interface TurnCompletePayload {
+  sessionId: string;
+  stopReason: StopReason;
+}
+
+/** Usage of a single prompt turn. */
+export interface TurnUsage {
+  costUsd: number;
+  model: string | null;
+  tokens: TokenCounts;
+}
+
+/** All text that was typed after the command name is provided as input. */
+export interface UnstructuredCommandInput {
+  /**
+   * The _meta property is reserved by ACP to allow clients and agents to attach additional
+   * metadata to their interactions. Implementations MUST NOT make assumptions about values at
+   * these keys.
+   *
+   * See protocol docs: [Extensibility](https://agentclientprotocol.com/protocol/extensibility)
+   */
+  _meta?: Record<string, unknown> | null;
+  /** A hint to display when the input hasn't been provided yet */
+  hint: string;
+}
+
+/** Typed usage event emitted after every accounted notification. */
+export interface UsageEvent {
+  contextWindow: number | null;
+  delta: TokenCounts;
+  model: string | null;
+  session: UsageTotals;
+  sessionId: string;
+  today: UsageTotals;
+  turn: TurnUsage;
+}
+
+/** Accumulated usage for a session or a day. */
+export interface UsageTotals {
+  costUsd: number;
+  tokens: TokenCounts;
+  turns: number;
+  /** Tokens spent on models missing from the price table. */
+  unpricedTokens: number;
+}
@@ -1,3 +1,9 @@
+export type {
+  GitCheckpoint,
+  GitNetworkOperation,
+  GitProgressEvent,
+} from './codexEvents.generated';
+
 export type GitStatusEntry = {
   path: string;
   oldPath?: string | null;
@@ -34,3 +40,118 @@ export type RemoteGitHistoryResult = {
   isGitRepo: boolean;
   history: GitCommit[];
 };
+
+export type GitDiffFileStatus = 'added' | 'deleted' | 'modified' | 'renamed' | 'copied';
+
+export type GitDiffLineKind = 'context' | 'added' | 'removed';
+
+export type GitDiffLine = {
+  kind: GitDiffLineKind;
+  content: string;
+  oldLine?: number | null;
+  newLine?: number | null;
+  noNewline: boolean;
+};
+
+export type GitDiffHunk = {
+  header: string;
+  oldStart: number;
+  oldLines: number;
+  newStart: number;
+  newLines: number;
+  section?: string | null;
+  lines: GitDiffLine[];
+};
+
+export type GitDiffFile = {
+  path: string;
+  oldPath?: string | null;
+  status: GitDiffFileStatus;
+  oldMode?: string | null;
+  newMode?: string | null;
+  binary: boolean;
+  additions: number;
+  deletions: number;
+  hunks: GitDiffHunk[];
+};
+
+export type GitDiffStats = {
+  files: number;
+  additions: number;
+  deletions: number;
+};
+
+export type GitDiffResult = {
+  files: GitDiffFile[];
+  stats: GitDiffStats;
+};
+
+export type GitCommitDetails = {
+  commit: GitCommit;
+  body: string;
+  files: GitDiffFile[];
+  stats: GitDiffStats;
+};
+
+/**
+ * Which changes `gitDiff` compares. Without revisions: unstaged changes, or staged
+ * ones with `staged`. `from` compares against a commit, `from` + `to` two commits.
+ */
+export type GitDiffOptions = {
+  staged?: boolean;
+  from?: string;
+  to?: string;
+  paths?: string[];
+  contextLines?: number;
+};
+
+/**
+ * `gitDiscard` without a token only previews: `changes` are what would be discarded
+ * and `token` confirms exactly that state.
+ */
+export type GitDiscardResult = {
+  discarded: boolean;
+  token: string;
+  changes: GitStatusEntry[];
+};
+
+export type GitCommitOptions = {
+  amend?: boolean;
+  signOff?: boolean;
+};
+
+export type GitBranch = {
+  /** Short name, e.g. `main` or `origin/main`. */
+  name: string;
+  /** Remote of a remote-tracking branch. */
+  remote?: string | null;
+  current: boolean;
+  upstream?: string | null;
+  upstreamGone: boolean;
+  ahead: number;
+  behind: number;
+  lastCommit: GitCommit;
+};
+
+export type GitBranchesResult = {
+  local: GitBranch[];
+  remote: GitBranch[];
+};
+
+export type GitPullMode = 'ff-only' | 'rebase';
+
+export type GitPushOptions = {
+  remote?: string;
+  branch?: string;
+  setUpstream?: boolean;
+  forceWithLease?: boolean;
+};
+
+export type GitErrorKind = 'auth' | 'rejected' | 'other';
+
+/** Error thrown by `gitFetch`, `gitPull` and `gitPush`. */
+export type GitCommandError = {
+  kind: GitErrorKind;
+  message: string;
+};
+